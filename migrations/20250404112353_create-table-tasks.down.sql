@@ -1,2 +1,2 @@
-- Add down migration script here
+-- Add down migration script here
 DROP TABLE tasks;
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX task_labels_label_id_idx;
+DROP TABLE task_labels;
+DROP TABLE labels;
@@ -0,0 +1,19 @@
+-- Add up migration script here
+CREATE TABLE labels (
+    id SERIAL PRIMARY KEY,
+    pid UUID NOT NULL DEFAULT (uuid_generate_v4()),
+    user_pid UUID NOT NULL REFERENCES users (pid),
+    name VARCHAR(50) NOT NULL,
+    colour VARCHAR(7) NOT NULL DEFAULT '#808080',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT labels_user_pid_name_key UNIQUE (user_pid, name)
+);
+
+CREATE TABLE task_labels (
+    task_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    label_id INTEGER NOT NULL REFERENCES labels (id) ON DELETE CASCADE,
+    PRIMARY KEY (task_id, label_id)
+);
+
+CREATE INDEX task_labels_label_id_idx ON task_labels (label_id);
@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP INDEX tasks_project_id_position_idx;
+ALTER TABLE tasks DROP COLUMN position, DROP COLUMN project_id;
+DROP INDEX projects_user_pid_idx;
+DROP TABLE projects;
@@ -0,0 +1,26 @@
+-- Add up migration script here
+CREATE TABLE projects (
+    id SERIAL PRIMARY KEY,
+    pid UUID NOT NULL DEFAULT (uuid_generate_v4()),
+    user_pid UUID NOT NULL REFERENCES users (pid),
+    name VARCHAR(100) NOT NULL,
+    description TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX projects_user_pid_idx ON projects (user_pid);
+
+ALTER TABLE tasks
+    ADD COLUMN project_id INTEGER REFERENCES projects (id) ON DELETE SET NULL,
+    ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+-- Existing tasks all live in their owner's inbox, number them in creation order.
+UPDATE tasks t
+SET position = ordered.rn - 1
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY user_pid ORDER BY id) AS rn FROM tasks
+) ordered
+WHERE t.id = ordered.id;
+
+CREATE INDEX tasks_project_id_position_idx ON tasks (project_id, position);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX tasks_parent_id_idx;
+ALTER TABLE tasks DROP COLUMN parent_id;
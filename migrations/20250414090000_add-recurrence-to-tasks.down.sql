@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX tasks_series_id_idx;
+ALTER TABLE tasks DROP COLUMN series_id, DROP COLUMN recurrence, DROP COLUMN due_at;
@@ -0,0 +1,7 @@
+-- Add up migration script here
+ALTER TABLE tasks
+    ADD COLUMN due_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN recurrence TEXT,
+    ADD COLUMN series_id UUID;
+
+CREATE INDEX tasks_series_id_idx ON tasks (series_id);
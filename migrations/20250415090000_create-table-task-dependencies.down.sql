@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX task_dependencies_blocked_id_idx;
+DROP TABLE task_dependencies;
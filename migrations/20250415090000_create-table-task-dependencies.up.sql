@@ -0,0 +1,10 @@
+-- Add up migration script here
+CREATE TABLE task_dependencies (
+    blocker_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    blocked_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (blocker_id, blocked_id),
+    CONSTRAINT task_dependencies_no_self_block CHECK (blocker_id <> blocked_id)
+);
+
+CREATE INDEX task_dependencies_blocked_id_idx ON task_dependencies (blocked_id);
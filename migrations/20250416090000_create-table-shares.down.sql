@@ -0,0 +1,8 @@
+-- Add down migration script here
+DROP FUNCTION task_permission;
+DROP FUNCTION project_permission;
+DROP FUNCTION permission_rank;
+DROP INDEX project_shares_user_pid_idx;
+DROP TABLE project_shares;
+DROP INDEX task_shares_user_pid_idx;
+DROP TABLE task_shares;
@@ -0,0 +1,67 @@
+-- Add up migration script here
+CREATE TABLE task_shares (
+    task_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    user_pid UUID NOT NULL REFERENCES users (pid) ON DELETE CASCADE,
+    permission VARCHAR(10) NOT NULL CHECK (permission IN ('viewer', 'editor', 'owner')),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (task_id, user_pid)
+);
+
+CREATE INDEX task_shares_user_pid_idx ON task_shares (user_pid);
+
+CREATE TABLE project_shares (
+    project_id INTEGER NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    user_pid UUID NOT NULL REFERENCES users (pid) ON DELETE CASCADE,
+    permission VARCHAR(10) NOT NULL CHECK (permission IN ('viewer', 'editor', 'owner')),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (project_id, user_pid)
+);
+
+CREATE INDEX project_shares_user_pid_idx ON project_shares (user_pid);
+
+CREATE FUNCTION permission_rank(permission TEXT) RETURNS INTEGER
+LANGUAGE SQL IMMUTABLE AS $$
+    SELECT CASE permission
+        WHEN 'viewer' THEN 1
+        WHEN 'editor' THEN 2
+        WHEN 'owner' THEN 3
+        ELSE 0
+    END
+$$;
+
+-- Highest permission `member` holds on a project, NULL without access.
+CREATE FUNCTION project_permission(project INTEGER, member UUID) RETURNS TEXT
+LANGUAGE SQL STABLE AS $$
+    SELECT granted.permission FROM (
+        SELECT 'owner' AS permission FROM projects WHERE id = project AND user_pid = member
+        UNION ALL
+        SELECT permission FROM project_shares WHERE project_id = project AND user_pid = member
+    ) granted
+    ORDER BY permission_rank(granted.permission) DESC
+    LIMIT 1
+$$;
+
+-- Highest permission `member` holds on a task. Access to a parent task or to
+-- the project of the task or of any of its parents applies as well.
+CREATE FUNCTION task_permission(task INTEGER, member UUID) RETURNS TEXT
+LANGUAGE SQL STABLE AS $$
+    WITH RECURSIVE ancestors AS (
+        SELECT id, parent_id, user_pid, project_id FROM tasks WHERE id = task
+        UNION ALL
+        SELECT t.id, t.parent_id, t.user_pid, t.project_id
+        FROM tasks t JOIN ancestors a ON t.id = a.parent_id
+    )
+    SELECT granted.permission FROM (
+        SELECT 'owner' AS permission FROM ancestors WHERE user_pid = member
+        UNION ALL
+        SELECT s.permission FROM task_shares s
+        JOIN ancestors a ON a.id = s.task_id
+        WHERE s.user_pid = member
+        UNION ALL
+        SELECT project_permission(a.project_id, member) FROM ancestors a
+        WHERE a.project_id IS NOT NULL
+    ) granted
+    WHERE granted.permission IS NOT NULL
+    ORDER BY permission_rank(granted.permission) DESC
+    LIMIT 1
+$$;
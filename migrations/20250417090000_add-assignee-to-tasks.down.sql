@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX tasks_assignee_pid_idx;
+ALTER TABLE tasks DROP COLUMN assignee_pid;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE tasks ADD COLUMN assignee_pid UUID REFERENCES users (pid) ON DELETE SET NULL;
+
+CREATE INDEX tasks_assignee_pid_idx ON tasks (assignee_pid);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE comment_mentions;
+DROP INDEX comments_task_id_created_at_idx;
+DROP TABLE comments;
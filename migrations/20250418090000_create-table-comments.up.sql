@@ -0,0 +1,19 @@
+-- Add up migration script here
+CREATE TABLE comments (
+    id SERIAL PRIMARY KEY,
+    pid UUID NOT NULL DEFAULT (uuid_generate_v4()),
+    task_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    author_pid UUID NOT NULL REFERENCES users (pid) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    edited BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX comments_task_id_created_at_idx ON comments (task_id, created_at);
+
+CREATE TABLE comment_mentions (
+    comment_id INTEGER NOT NULL REFERENCES comments (id) ON DELETE CASCADE,
+    user_pid UUID NOT NULL REFERENCES users (pid) ON DELETE CASCADE,
+    PRIMARY KEY (comment_id, user_pid)
+);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX attachments_storage_key_idx;
+DROP INDEX attachments_task_id_idx;
+DROP TABLE attachments;
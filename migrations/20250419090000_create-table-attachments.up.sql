@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE attachments (
+    id SERIAL PRIMARY KEY,
+    pid UUID NOT NULL DEFAULT (uuid_generate_v4()),
+    task_id INTEGER NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    uploader_pid UUID NOT NULL REFERENCES users (pid) ON DELETE CASCADE,
+    filename VARCHAR(255) NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    size BIGINT NOT NULL,
+    storage_key VARCHAR(64) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX attachments_task_id_idx ON attachments (task_id);
+CREATE INDEX attachments_storage_key_idx ON attachments (storage_key);
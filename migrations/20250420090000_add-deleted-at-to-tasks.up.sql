@@ -0,0 +1,29 @@
+-- Add up migration script here
+ALTER TABLE tasks ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX tasks_deleted_at_idx ON tasks (deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Trashed tasks grant no access, only their owner can restore or purge them.
+CREATE OR REPLACE FUNCTION task_permission(task INTEGER, member UUID) RETURNS TEXT
+LANGUAGE SQL STABLE AS $$
+    WITH RECURSIVE ancestors AS (
+        SELECT id, parent_id, user_pid, project_id FROM tasks
+        WHERE id = task AND deleted_at IS NULL
+        UNION ALL
+        SELECT t.id, t.parent_id, t.user_pid, t.project_id
+        FROM tasks t JOIN ancestors a ON t.id = a.parent_id
+    )
+    SELECT granted.permission FROM (
+        SELECT 'owner' AS permission FROM ancestors WHERE user_pid = member
+        UNION ALL
+        SELECT s.permission FROM task_shares s
+        JOIN ancestors a ON a.id = s.task_id
+        WHERE s.user_pid = member
+        UNION ALL
+        SELECT project_permission(a.project_id, member) FROM ancestors a
+        WHERE a.project_id IS NOT NULL
+    ) granted
+    WHERE granted.permission IS NOT NULL
+    ORDER BY permission_rank(granted.permission) DESC
+    LIMIT 1
+$$;
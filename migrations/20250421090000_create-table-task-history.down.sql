@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS task_history;
+DROP FUNCTION IF EXISTS task_history_immutable();
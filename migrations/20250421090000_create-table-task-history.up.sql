@@ -0,0 +1,22 @@
+-- Add up migration script here
+CREATE TABLE task_history (
+    id SERIAL PRIMARY KEY,
+    -- No foreign key, so the history outlives the task
+    task_id INTEGER NOT NULL,
+    actor_pid UUID,
+    action VARCHAR(16) NOT NULL CHECK (action IN ('created', 'updated', 'deleted', 'restored', 'purged')),
+    changes JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX task_history_task_id_idx ON task_history (task_id, created_at);
+
+CREATE FUNCTION task_history_immutable() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'task_history rows cannot be changed';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_history_immutable
+BEFORE UPDATE OR DELETE ON task_history
+FOR EACH ROW EXECUTE FUNCTION task_history_immutable();
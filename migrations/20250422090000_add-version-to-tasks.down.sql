@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS tasks_bump_version ON tasks;
+DROP FUNCTION IF EXISTS bump_task_version();
+ALTER TABLE tasks DROP COLUMN IF EXISTS version;
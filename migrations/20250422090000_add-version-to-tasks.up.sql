@@ -0,0 +1,15 @@
+-- Add up migration script here
+ALTER TABLE tasks ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+
+CREATE FUNCTION bump_task_version() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW IS DISTINCT FROM OLD THEN
+        NEW.version := OLD.version + 1;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tasks_bump_version
+BEFORE UPDATE ON tasks
+FOR EACH ROW EXECUTE FUNCTION bump_task_version();
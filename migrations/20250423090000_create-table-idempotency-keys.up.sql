@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE idempotency_keys (
+    -- The nil UUID scopes keys sent without signing in
+    user_pid UUID NOT NULL,
+    key VARCHAR(255) NOT NULL,
+    request_hash VARCHAR(64) NOT NULL,
+    -- NULL until the first request with the key has been answered
+    status SMALLINT,
+    headers JSONB,
+    body BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_pid, key)
+);
+
+CREATE INDEX idempotency_keys_created_at_idx ON idempotency_keys (created_at);
@@ -0,0 +1,20 @@
+-- Add down migration script here
+DROP TABLE account_exports;
+
+CREATE OR REPLACE FUNCTION task_history_immutable() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'task_history rows cannot be changed';
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE projects
+    DROP CONSTRAINT projects_user_pid_fkey,
+    ADD CONSTRAINT projects_user_pid_fkey FOREIGN KEY (user_pid) REFERENCES users (pid);
+
+ALTER TABLE labels
+    DROP CONSTRAINT labels_user_pid_fkey,
+    ADD CONSTRAINT labels_user_pid_fkey FOREIGN KEY (user_pid) REFERENCES users (pid);
+
+ALTER TABLE tasks
+    DROP CONSTRAINT tasks_user_pid_fkey,
+    ADD CONSTRAINT tasks_user_pid_fkey FOREIGN KEY (user_pid) REFERENCES users (pid);
@@ -0,0 +1,42 @@
+-- Add up migration script here
+ALTER TABLE tasks
+    DROP CONSTRAINT tasks_user_pid_fkey,
+    ADD CONSTRAINT tasks_user_pid_fkey FOREIGN KEY (user_pid) REFERENCES users (pid) ON DELETE CASCADE;
+
+ALTER TABLE labels
+    DROP CONSTRAINT labels_user_pid_fkey,
+    ADD CONSTRAINT labels_user_pid_fkey FOREIGN KEY (user_pid) REFERENCES users (pid) ON DELETE CASCADE;
+
+ALTER TABLE projects
+    DROP CONSTRAINT projects_user_pid_fkey,
+    ADD CONSTRAINT projects_user_pid_fkey FOREIGN KEY (user_pid) REFERENCES users (pid) ON DELETE CASCADE;
+
+-- History stays immutable, except while a transaction erases an account and
+-- has set app.erasing_user to its pid.
+CREATE OR REPLACE FUNCTION task_history_immutable() RETURNS TRIGGER AS $$
+BEGIN
+    IF COALESCE(current_setting('app.erasing_user', true), '') <> '' THEN
+        IF TG_OP = 'DELETE' THEN
+            RETURN OLD;
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    RAISE EXCEPTION 'task_history rows cannot be changed';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TABLE account_exports (
+    id SERIAL PRIMARY KEY,
+    pid UUID NOT NULL DEFAULT (uuid_generate_v4()),
+    user_pid UUID NOT NULL REFERENCES users (pid) ON DELETE CASCADE,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'ready', 'failed')),
+    storage_key TEXT,
+    size BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX account_exports_user_pid_idx ON account_exports (user_pid);
+CREATE INDEX account_exports_expires_at_idx ON account_exports (expires_at);
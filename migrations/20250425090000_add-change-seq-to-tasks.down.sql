@@ -0,0 +1,17 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS tasks_bury ON tasks;
+DROP FUNCTION IF EXISTS bury_task();
+DROP TABLE IF EXISTS task_tombstones;
+
+DROP TRIGGER IF EXISTS task_dependencies_touch_blocker ON task_dependencies;
+DROP TRIGGER IF EXISTS task_dependencies_touch_blocked ON task_dependencies;
+DROP TRIGGER IF EXISTS task_labels_touch_task ON task_labels;
+DROP FUNCTION IF EXISTS touch_task();
+
+DROP TRIGGER IF EXISTS tasks_set_change_seq ON tasks;
+DROP FUNCTION IF EXISTS set_task_change_seq();
+DROP INDEX IF EXISTS tasks_user_pid_change_seq_idx;
+ALTER TABLE tasks DROP COLUMN IF EXISTS change_seq;
+
+DROP FUNCTION IF EXISTS settled_change_seq();
+DROP FUNCTION IF EXISTS current_change_seq();
@@ -0,0 +1,75 @@
+-- Add up migration script here
+
+-- Id of the transaction that last changed the task, or its labels or
+-- dependencies. Every transaction below pg_snapshot_xmin has finished, so
+-- the changes below it never change again and can be synced in order.
+CREATE FUNCTION current_change_seq() RETURNS BIGINT
+LANGUAGE SQL VOLATILE AS $$
+    SELECT pg_current_xact_id()::TEXT::BIGINT
+$$;
+
+CREATE FUNCTION settled_change_seq() RETURNS BIGINT
+LANGUAGE SQL STABLE AS $$
+    SELECT pg_snapshot_xmin(pg_current_snapshot())::TEXT::BIGINT
+$$;
+
+ALTER TABLE tasks ADD COLUMN change_seq BIGINT NOT NULL DEFAULT current_change_seq();
+
+CREATE INDEX tasks_user_pid_change_seq_idx ON tasks (user_pid, change_seq, id);
+
+CREATE FUNCTION set_task_change_seq() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.change_seq := current_change_seq();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tasks_set_change_seq
+BEFORE UPDATE ON tasks
+FOR EACH ROW EXECUTE FUNCTION set_task_change_seq();
+
+-- Marks the task whose id is in the column named by the first argument as
+-- changed
+CREATE FUNCTION touch_task() RETURNS TRIGGER AS $$
+DECLARE
+    changed JSONB := CASE TG_OP WHEN 'DELETE' THEN to_jsonb(OLD) ELSE to_jsonb(NEW) END;
+BEGIN
+    UPDATE tasks SET change_seq = current_change_seq()
+    WHERE id = (changed->>TG_ARGV[0])::INTEGER;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_labels_touch_task
+AFTER INSERT OR DELETE ON task_labels
+FOR EACH ROW EXECUTE FUNCTION touch_task('task_id');
+
+CREATE TRIGGER task_dependencies_touch_blocked
+AFTER INSERT OR DELETE ON task_dependencies
+FOR EACH ROW EXECUTE FUNCTION touch_task('blocked_id');
+
+CREATE TRIGGER task_dependencies_touch_blocker
+AFTER INSERT OR DELETE ON task_dependencies
+FOR EACH ROW EXECUTE FUNCTION touch_task('blocker_id');
+
+-- What is left of tasks deleted for good, for clients to sync the delete
+CREATE TABLE task_tombstones (
+    task_id INTEGER PRIMARY KEY,
+    user_pid UUID NOT NULL REFERENCES users (pid) ON DELETE CASCADE,
+    change_seq BIGINT NOT NULL DEFAULT current_change_seq(),
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX task_tombstones_user_pid_change_seq_idx ON task_tombstones (user_pid, change_seq, task_id);
+
+CREATE FUNCTION bury_task() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO task_tombstones (task_id, user_pid) VALUES (OLD.id, OLD.user_pid)
+    ON CONFLICT (task_id) DO NOTHING;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tasks_bury
+AFTER DELETE ON tasks
+FOR EACH ROW EXECUTE FUNCTION bury_task();
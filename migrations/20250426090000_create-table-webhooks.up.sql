@@ -0,0 +1,38 @@
+-- Add up migration script here
+CREATE TABLE webhooks (
+    id SERIAL PRIMARY KEY,
+    pid UUID NOT NULL DEFAULT (uuid_generate_v4()),
+    user_pid UUID NOT NULL REFERENCES users (pid) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    -- Key deliveries are signed with, shown to the owner once
+    secret VARCHAR(128) NOT NULL,
+    -- Event types to deliver, every type when empty
+    events TEXT[] NOT NULL DEFAULT '{}',
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX webhooks_user_pid_idx ON webhooks (user_pid);
+
+CREATE TABLE webhook_deliveries (
+    id BIGSERIAL PRIMARY KEY,
+    pid UUID NOT NULL DEFAULT (uuid_generate_v4()),
+    webhook_id INTEGER NOT NULL REFERENCES webhooks (id) ON DELETE CASCADE,
+    event VARCHAR(32) NOT NULL,
+    payload JSONB NOT NULL,
+    -- 'dead' once every attempt has failed
+    status VARCHAR(16) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'delivered', 'dead')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    -- Also pushed back while an attempt is in flight, so that a delivery
+    -- whose worker died is picked up again
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    response_status SMALLINT,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMPTZ
+);
+
+CREATE INDEX webhook_deliveries_webhook_id_idx ON webhook_deliveries (webhook_id, id);
+CREATE INDEX webhook_deliveries_due_idx ON webhook_deliveries (next_attempt_at)
+    WHERE status = 'pending';
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS outbox_events_inserted ON outbox_events;
+DROP FUNCTION IF EXISTS outbox_events_wake_relay();
+DROP TABLE IF EXISTS outbox_events;
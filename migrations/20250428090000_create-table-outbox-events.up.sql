@@ -0,0 +1,28 @@
+-- Add up migration script here
+CREATE TABLE outbox_events (
+    id BIGSERIAL PRIMARY KEY,
+    -- What the event is about, such as a task and its id. Events of one
+    -- aggregate are published in the order they were recorded.
+    aggregate_type VARCHAR(32) NOT NULL,
+    aggregate_id TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- NULL until the relay has handed the event on
+    published_at TIMESTAMPTZ
+);
+
+CREATE INDEX outbox_events_unpublished_idx ON outbox_events (id) WHERE published_at IS NULL;
+CREATE INDEX outbox_events_published_at_idx ON outbox_events (published_at);
+
+-- Wakes the relay once events are committed instead of waiting for its
+-- next poll
+CREATE OR REPLACE FUNCTION outbox_events_wake_relay() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('outbox_events', '');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER outbox_events_inserted
+AFTER INSERT ON outbox_events
+FOR EACH STATEMENT EXECUTE FUNCTION outbox_events_wake_relay();
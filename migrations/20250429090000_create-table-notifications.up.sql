@@ -0,0 +1,36 @@
+-- Add up migration script here
+CREATE TABLE notifications (
+    id BIGSERIAL PRIMARY KEY,
+    user_pid UUID NOT NULL REFERENCES users (pid) ON DELETE CASCADE,
+    kind VARCHAR(16) NOT NULL CHECK (kind IN ('mentioned', 'assigned', 'shared', 'reminder')),
+    message TEXT NOT NULL,
+    -- What the notification is about, kept when it is gone
+    task_id INTEGER REFERENCES tasks (id) ON DELETE SET NULL,
+    project_id INTEGER REFERENCES projects (id) ON DELETE SET NULL,
+    comment_id INTEGER REFERENCES comments (id) ON DELETE SET NULL,
+    actor_pid UUID REFERENCES users (pid) ON DELETE SET NULL,
+    read_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX notifications_user_pid_idx ON notifications (user_pid, id);
+CREATE INDEX notifications_unread_idx ON notifications (user_pid) WHERE read_at IS NULL;
+
+-- Channels each kind of notification is sent on, the defaults apply to
+-- kinds without a row
+CREATE TABLE notification_preferences (
+    user_pid UUID NOT NULL REFERENCES users (pid) ON DELETE CASCADE,
+    kind VARCHAR(16) NOT NULL CHECK (kind IN ('mentioned', 'assigned', 'shared', 'reminder')),
+    in_app BOOLEAN NOT NULL DEFAULT TRUE,
+    email BOOLEAN NOT NULL DEFAULT FALSE,
+    webhook BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_pid, kind)
+);
+
+-- Due date each task was last reminded of, so a reminder is sent once
+-- unless the task is rescheduled
+CREATE TABLE task_reminders (
+    task_id INTEGER PRIMARY KEY REFERENCES tasks (id) ON DELETE CASCADE,
+    due_at TIMESTAMPTZ NOT NULL
+);
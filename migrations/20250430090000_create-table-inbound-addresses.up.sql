@@ -0,0 +1,8 @@
+-- Add up migration script here
+-- Secret part of the address each user mails tasks to, rotated to revoke
+-- an address that leaked
+CREATE TABLE inbound_addresses (
+    user_pid UUID PRIMARY KEY REFERENCES users (pid) ON DELETE CASCADE,
+    token VARCHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
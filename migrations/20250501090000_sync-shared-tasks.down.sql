@@ -0,0 +1,13 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS tasks_revoke_unassigned ON tasks;
+DROP FUNCTION IF EXISTS tasks_revoke_unassigned();
+DROP TRIGGER IF EXISTS project_shares_sync_access ON project_shares;
+DROP FUNCTION IF EXISTS project_shares_sync_access();
+DROP TRIGGER IF EXISTS task_shares_sync_access ON task_shares;
+DROP FUNCTION IF EXISTS task_shares_sync_access();
+
+DROP FUNCTION IF EXISTS revoke_task_access(INTEGER, UUID);
+DROP FUNCTION IF EXISTS touch_task_subtree(INTEGER);
+DROP TABLE IF EXISTS task_revocations;
+DROP FUNCTION IF EXISTS task_access(INTEGER, UUID);
+DROP INDEX IF EXISTS tasks_change_seq_idx;
@@ -0,0 +1,112 @@
+-- Add up migration script here
+
+-- Changes are synced to everyone with access to a task, not only its owner
+CREATE INDEX tasks_change_seq_idx ON tasks (change_seq, id);
+
+-- Permission `member` holds on a task as if it were not trashed, to tell who
+-- should learn that it was
+CREATE FUNCTION task_access(task INTEGER, member UUID) RETURNS TEXT
+LANGUAGE SQL STABLE AS $$
+    WITH RECURSIVE ancestors AS (
+        SELECT id, parent_id, user_pid, project_id FROM tasks WHERE id = task
+        UNION ALL
+        SELECT t.id, t.parent_id, t.user_pid, t.project_id
+        FROM tasks t JOIN ancestors a ON t.id = a.parent_id
+    )
+    SELECT granted.permission FROM (
+        SELECT 'owner' AS permission FROM ancestors WHERE user_pid = member
+        UNION ALL
+        SELECT s.permission FROM task_shares s
+        JOIN ancestors a ON a.id = s.task_id
+        WHERE s.user_pid = member
+        UNION ALL
+        SELECT project_permission(a.project_id, member) FROM ancestors a
+        WHERE a.project_id IS NOT NULL
+    ) granted
+    WHERE granted.permission IS NOT NULL
+    ORDER BY permission_rank(granted.permission) DESC
+    LIMIT 1
+$$;
+
+-- Tasks a user can no longer see, for their clients to sync as deletes
+CREATE TABLE task_revocations (
+    task_id INTEGER NOT NULL,
+    user_pid UUID NOT NULL REFERENCES users (pid) ON DELETE CASCADE,
+    change_seq BIGINT NOT NULL DEFAULT current_change_seq(),
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (task_id, user_pid)
+);
+
+CREATE INDEX task_revocations_user_pid_change_seq_idx ON task_revocations (user_pid, change_seq, task_id);
+
+-- Marks `root` and its subtasks as changed, so that a member who was just
+-- given access syncs them
+CREATE FUNCTION touch_task_subtree(root INTEGER) RETURNS VOID
+LANGUAGE SQL VOLATILE AS $$
+    WITH RECURSIVE subtree AS (
+        SELECT id FROM tasks WHERE id = root
+        UNION
+        SELECT t.id FROM tasks t JOIN subtree s ON t.parent_id = s.id
+    )
+    UPDATE tasks SET change_seq = current_change_seq()
+    WHERE id IN (SELECT id FROM subtree)
+$$;
+
+-- Records the tasks of the subtree of `root` that `member` lost access to.
+-- Members deleted along with their shares are skipped.
+CREATE FUNCTION revoke_task_access(root INTEGER, member UUID) RETURNS VOID
+LANGUAGE SQL VOLATILE AS $$
+    WITH RECURSIVE subtree AS (
+        SELECT id FROM tasks WHERE id = root
+        UNION
+        SELECT t.id FROM tasks t JOIN subtree s ON t.parent_id = s.id
+    )
+    INSERT INTO task_revocations (task_id, user_pid)
+    SELECT s.id, u.pid FROM subtree s JOIN users u ON u.pid = member
+    WHERE task_access(s.id, member) IS NULL
+    ON CONFLICT (task_id, user_pid) DO UPDATE
+    SET change_seq = EXCLUDED.change_seq, revoked_at = EXCLUDED.revoked_at
+$$;
+
+CREATE FUNCTION task_shares_sync_access() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        PERFORM touch_task_subtree(NEW.task_id);
+    ELSE
+        PERFORM revoke_task_access(OLD.task_id, OLD.user_pid);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_shares_sync_access
+AFTER INSERT OR DELETE ON task_shares
+FOR EACH ROW EXECUTE FUNCTION task_shares_sync_access();
+
+CREATE FUNCTION project_shares_sync_access() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        PERFORM touch_task_subtree(id) FROM tasks WHERE project_id = NEW.project_id;
+    ELSE
+        PERFORM revoke_task_access(id, OLD.user_pid) FROM tasks WHERE project_id = OLD.project_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER project_shares_sync_access
+AFTER INSERT OR DELETE ON project_shares
+FOR EACH ROW EXECUTE FUNCTION project_shares_sync_access();
+
+CREATE FUNCTION tasks_revoke_unassigned() RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.assignee_pid IS NOT NULL AND OLD.assignee_pid IS DISTINCT FROM NEW.assignee_pid THEN
+        PERFORM revoke_task_access(NEW.id, OLD.assignee_pid);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tasks_revoke_unassigned
+AFTER UPDATE OF assignee_pid ON tasks
+FOR EACH ROW EXECUTE FUNCTION tasks_revoke_unassigned();
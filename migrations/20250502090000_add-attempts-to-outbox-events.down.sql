@@ -0,0 +1,7 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS outbox_events_unpublished_idx;
+CREATE INDEX outbox_events_unpublished_idx ON outbox_events (id) WHERE published_at IS NULL;
+
+ALTER TABLE outbox_events DROP COLUMN IF EXISTS dead_at;
+ALTER TABLE outbox_events DROP COLUMN IF EXISTS last_error;
+ALTER TABLE outbox_events DROP COLUMN IF EXISTS attempts;
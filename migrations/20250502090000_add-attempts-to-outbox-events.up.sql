@@ -0,0 +1,9 @@
+-- Add up migration script here
+ALTER TABLE outbox_events ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE outbox_events ADD COLUMN last_error TEXT;
+-- Set once the relay gave up on the event, which is kept for inspection
+ALTER TABLE outbox_events ADD COLUMN dead_at TIMESTAMPTZ;
+
+DROP INDEX outbox_events_unpublished_idx;
+CREATE INDEX outbox_events_unpublished_idx ON outbox_events (id)
+WHERE published_at IS NULL AND dead_at IS NULL;
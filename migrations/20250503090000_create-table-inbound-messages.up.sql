@@ -0,0 +1,9 @@
+-- Add up migration script here
+-- Messages already turned into tasks, so that mail the sending server
+-- delivers again does not become a second task
+CREATE TABLE inbound_messages (
+    user_pid UUID NOT NULL REFERENCES users (pid) ON DELETE CASCADE,
+    message_id TEXT NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_pid, message_id)
+);
@@ -0,0 +1,7 @@
+-- Add up migration script here
+-- The user an anonymous request created, whose erasure takes the stored
+-- response with it
+ALTER TABLE idempotency_keys
+ADD COLUMN owner_pid UUID REFERENCES users (pid) ON DELETE CASCADE;
+
+CREATE INDEX idempotency_keys_owner_pid_idx ON idempotency_keys (owner_pid);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS tasks_revoke_moved ON tasks;
+DROP FUNCTION IF EXISTS tasks_revoke_moved();
+DROP FUNCTION IF EXISTS task_audience(INTEGER);
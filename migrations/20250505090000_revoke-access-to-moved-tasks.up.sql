@@ -0,0 +1,43 @@
+-- Add up migration script here
+
+-- Everyone with some access to a task, through it, its parents or their
+-- projects
+CREATE FUNCTION task_audience(task INTEGER) RETURNS SETOF UUID
+LANGUAGE SQL STABLE AS $$
+    WITH RECURSIVE ancestors AS (
+        SELECT id, parent_id, user_pid, project_id FROM tasks WHERE id = task
+        UNION ALL
+        SELECT t.id, t.parent_id, t.user_pid, t.project_id
+        FROM tasks t JOIN ancestors a ON t.id = a.parent_id
+    )
+    SELECT user_pid FROM ancestors
+    UNION
+    SELECT s.user_pid FROM task_shares s JOIN ancestors a ON a.id = s.task_id
+    UNION
+    SELECT p.user_pid FROM projects p JOIN ancestors a ON a.project_id = p.id
+    UNION
+    SELECT s.user_pid FROM project_shares s JOIN ancestors a ON a.project_id = s.project_id
+$$;
+
+-- A task moved out of a project or away from a parent leaves their members
+-- behind, unless they reach it some other way
+CREATE FUNCTION tasks_revoke_moved() RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.project_id IS DISTINCT FROM NEW.project_id
+        OR OLD.parent_id IS DISTINCT FROM NEW.parent_id
+    THEN
+        PERFORM revoke_task_access(NEW.id, audience.member) FROM (
+            SELECT user_pid AS member FROM projects WHERE id = OLD.project_id
+            UNION
+            SELECT user_pid FROM project_shares WHERE project_id = OLD.project_id
+            UNION
+            SELECT task_audience(OLD.parent_id)
+        ) audience;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER tasks_revoke_moved
+AFTER UPDATE OF project_id, parent_id ON tasks
+FOR EACH ROW EXECUTE FUNCTION tasks_revoke_moved();
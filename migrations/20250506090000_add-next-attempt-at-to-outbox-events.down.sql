@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS outbox_events_pending_aggregate_idx;
+ALTER TABLE outbox_events DROP COLUMN IF EXISTS next_attempt_at;
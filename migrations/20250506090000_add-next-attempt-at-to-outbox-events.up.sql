@@ -0,0 +1,8 @@
+-- Add up migration script here
+-- When a failed event is tried again, NULL to publish it at once
+ALTER TABLE outbox_events ADD COLUMN next_attempt_at TIMESTAMPTZ;
+
+-- Finds the pending events that hold back later ones of their aggregate
+CREATE INDEX outbox_events_pending_aggregate_idx
+ON outbox_events (aggregate_type, aggregate_id, id)
+WHERE published_at IS NULL AND dead_at IS NULL;
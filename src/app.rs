@@ -1,10 +1,16 @@
-use std::io::IsTerminal;
+use std::{io::IsTerminal, sync::Arc};
 
-use crate::{AppConfig, AppEnvironment, Error, context::AppState};
+use crate::{
+    AppConfig, AppEnvironment, Error,
+    context::AppState,
+    inbound::{self, Inbound},
+    storage::{self, BlobStore},
+};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use dotenv::dotenv;
-use tokio::net::TcpListener;
+use sqlx::PgPool;
+use tokio::{io::AsyncReadExt, net::TcpListener};
 
 #[derive(Parser)]
 #[command(
@@ -13,8 +19,33 @@ use tokio::net::TcpListener;
     author = "Simon Bittok <bittokks@gmail.com>"
 )]
 pub struct App {
-    #[arg(long, short, default_value_t = AppEnvironment::default())]
+    #[arg(long, short, default_value_t = AppEnvironment::default(), global = true)]
     env: AppEnvironment,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Serves the API, running background jobs in the same process unless
+    /// told otherwise. This is the default.
+    Serve {
+        /// Leave background jobs to separate `worker` processes
+        #[arg(long)]
+        no_worker: bool,
+    },
+    /// Runs background jobs without serving the API
+    Worker,
+    /// Receives email over SMTP, turning mail sent to the users' inbound
+    /// addresses into tasks
+    Smtp,
+    /// Turns an email read from standard input into a task, as if it was
+    /// sent to the given inbound address
+    Ingest {
+        /// Inbound address the email is for
+        #[arg(long)]
+        to: String,
+    },
 }
 
 impl App {
@@ -41,10 +72,73 @@ impl App {
 
         config.db().migrate().await?;
 
+        match cli.command.unwrap_or(Command::Serve { no_worker: false }) {
+            Command::Serve { no_worker } => Self::serve(&config, !no_worker).await,
+            Command::Worker => Self::work(&config).await,
+            Command::Smtp => Self::receive_mail(&config).await,
+            Command::Ingest { to } => Self::ingest(&config, &to).await,
+        }
+    }
+
+    async fn serve(config: &AppConfig, with_worker: bool) -> Result<(), Error> {
         let listener: TcpListener = TcpListener::bind(config.server.address()).await?;
-        let router = crate::router::router(&AppState::new(&config)?);
+        let ctx = AppState::new(config)?;
+        crate::events::listen(&ctx.db, ctx.events.clone()).await?;
+        if with_worker {
+            Self::start_worker(ctx.db.clone(), ctx.blobs.clone(), config).await?;
+        }
+        let router = crate::router::router(&ctx);
 
         println!("Running on: {}", config.server());
         axum::serve(listener, router).await.map_err(Into::into)
     }
+
+    async fn work(config: &AppConfig) -> Result<(), Error> {
+        let db = config.db().connection_pool()?;
+        let blobs = storage::from_config(config.storage());
+        Self::start_worker(db, blobs, config).await?;
+
+        println!("Worker running, press Ctrl+C to stop");
+        tokio::signal::ctrl_c().await.map_err(Into::into)
+    }
+
+    async fn receive_mail(config: &AppConfig) -> Result<(), Error> {
+        let listener = TcpListener::bind(config.inbound().address()).await?;
+        let db = config.db().connection_pool()?;
+        let blobs = storage::from_config(config.storage());
+
+        println!("Receiving mail on: smtp://{}", config.inbound().address());
+        inbound::smtp::serve(listener, Inbound::new(db, blobs, config)).await;
+
+        Ok(())
+    }
+
+    async fn ingest(config: &AppConfig, to: &str) -> Result<(), Error> {
+        let db = config.db().connection_pool()?;
+        let blobs = storage::from_config(config.storage());
+
+        let mut raw = Vec::new();
+        tokio::io::stdin().read_to_end(&mut raw).await?;
+        match Inbound::new(db, blobs, config).ingest(to, &raw).await? {
+            Some(task) => println!("Created task {}: {}", task.id, task.title),
+            None => println!("Message was already turned into a task"),
+        }
+        Ok(())
+    }
+
+    /// Starts the job worker, the outbox relay and the webhook deliveries in
+    /// the background.
+    async fn start_worker(
+        db: PgPool,
+        blobs: Arc<dyn BlobStore>,
+        config: &AppConfig,
+    ) -> Result<(), Error> {
+        crate::jobs::worker(db.clone(), blobs, config)?
+            .start()
+            .await?;
+        crate::events::relay(&db, config.outbox()).await?;
+        crate::jobs::spawn_webhook_deliveries(db, config.webhooks())?;
+
+        Ok(())
+    }
 }
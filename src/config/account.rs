@@ -0,0 +1,17 @@
+use chrono::TimeDelta;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountConfig {
+    /// Days an account data export can be downloaded for
+    pub export_retention_days: u32,
+    /// When expired exports are purged, as a cron expression with seconds
+    pub export_purge_schedule: String,
+}
+
+impl AccountConfig {
+    #[must_use]
+    pub fn export_retention(&self) -> TimeDelta {
+        TimeDelta::days(i64::from(self.export_retention_days))
+    }
+}
@@ -39,7 +39,7 @@ impl DatabaseConfig {
     /// This function will return an error if .
     /// * Database does not exist
     /// * Database connection error
-    ///  *File IO Errors
+    /// * File IO Errors
     pub async fn migrate(&self) -> Result<(), Error> {
         let migrator = self.migrator().await?;
         let pool = self.connection_pool()?;
@@ -0,0 +1,21 @@
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventConfig {
+    /// Origins of the web apps allowed to open the event WebSocket, such as
+    /// `https://tasks.example.com`
+    pub allowed_origins: Vec<String>,
+}
+
+impl EventConfig {
+    /// Whether a browser page from `origin` may open the event WebSocket.
+    /// Browsers send the cookie of the site along with the upgrade whichever
+    /// page opens it, and apply no CORS checks to WebSockets.
+    #[must_use]
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        let origin = origin.trim_end_matches('/');
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+    }
+}
@@ -0,0 +1,26 @@
+use chrono::TimeDelta;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdempotencyConfig {
+    /// Seconds a stored response is replayed for its `Idempotency-Key`
+    pub window: u32,
+    /// Seconds a key stays reserved for a request that was never answered,
+    /// after which a retry may take it over
+    pub lock_timeout: u32,
+    /// When keys past their window are purged, as a cron expression with
+    /// seconds
+    pub purge_schedule: String,
+}
+
+impl IdempotencyConfig {
+    #[must_use]
+    pub fn window(&self) -> TimeDelta {
+        TimeDelta::seconds(i64::from(self.window))
+    }
+
+    #[must_use]
+    pub fn lock_timeout(&self) -> TimeDelta {
+        TimeDelta::seconds(i64::from(self.lock_timeout))
+    }
+}
@@ -0,0 +1,28 @@
+use serde::Deserialize;
+
+/// The SMTP listener turning mail into tasks.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InboundConfig {
+    pub host: String,
+    pub port: u16,
+    /// Domain of the users' inbound addresses, mail to others is refused
+    pub domain: String,
+    /// Largest accepted message, in bytes
+    pub max_message_size: usize,
+    /// Most SMTP sessions served at once, each may hold a message of up to
+    /// `max_message_size` in memory
+    pub max_sessions: usize,
+}
+
+impl InboundConfig {
+    #[must_use]
+    pub fn address(&self) -> String {
+        format!("{}:{}", &self.host, self.port)
+    }
+
+    /// The address mail sent to becomes a task of the user holding `token`.
+    #[must_use]
+    pub fn mailbox(&self, token: &str) -> String {
+        format!("{token}@{}", &self.domain)
+    }
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use chrono::TimeDelta;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobConfig {
+    /// Seconds between two checks for due jobs
+    pub poll_interval: u64,
+    /// Most jobs a worker runs at once
+    pub batch_size: u32,
+    /// Seconds a claimed job stays hidden from other workers, renewed while
+    /// it runs. A job whose worker stopped renewing it is retried after that
+    pub lease: u64,
+    /// Seconds before the first retry, doubled after every failed attempt
+    pub retry_base: u64,
+    /// Longest pause between two attempts, in seconds
+    pub retry_max: u64,
+    /// Days finished jobs are kept for
+    pub retention_days: u32,
+    /// When finished jobs are purged, as a cron expression with seconds
+    pub purge_schedule: String,
+}
+
+impl JobConfig {
+    #[must_use]
+    pub const fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval)
+    }
+
+    #[must_use]
+    pub fn lease(&self) -> TimeDelta {
+        TimeDelta::seconds(i64::try_from(self.lease).unwrap_or(i64::MAX))
+    }
+
+    #[must_use]
+    pub fn retention(&self) -> TimeDelta {
+        TimeDelta::days(i64::from(self.retention_days))
+    }
+
+    /// Pause before the next attempt after `attempts` failed ones.
+    #[must_use]
+    pub fn backoff(&self, attempts: u32) -> TimeDelta {
+        let delay = self
+            .retry_base
+            .saturating_mul(1 << attempts.saturating_sub(1).min(32))
+            .min(self.retry_max);
+
+        TimeDelta::seconds(i64::try_from(delay).unwrap_or(i64::MAX))
+    }
+}
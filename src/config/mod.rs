@@ -1,11 +1,33 @@
+pub mod account;
 pub mod db;
+pub mod events;
+pub mod idempotency;
+pub mod inbound;
+pub mod jobs;
 pub mod jwt;
 pub mod logger;
+pub mod mail;
+pub mod notifications;
+pub mod outbox;
+pub mod storage;
+pub mod trash;
+pub mod webhooks;
 
 pub use self::{
+    account::AccountConfig,
     db::DatabaseConfig,
+    events::EventConfig,
+    idempotency::IdempotencyConfig,
+    inbound::InboundConfig,
+    jobs::JobConfig,
     jwt::{AuthConfig, RsaJwtConfig},
     logger::Telemetry,
+    mail::MailConfig,
+    notifications::NotificationConfig,
+    outbox::OutboxConfig,
+    storage::{S3Config, StorageBackend, StorageConfig},
+    trash::TrashConfig,
+    webhooks::WebhookConfig,
 };
 
 use serde::Deserialize;
@@ -71,6 +93,17 @@ pub struct AppConfig {
     pub(crate) logger: Telemetry,
     pub(crate) db: DatabaseConfig,
     pub(crate) auth: AuthConfig,
+    pub(crate) storage: StorageConfig,
+    pub(crate) trash: TrashConfig,
+    pub(crate) idempotency: IdempotencyConfig,
+    pub(crate) account: AccountConfig,
+    pub(crate) webhooks: WebhookConfig,
+    pub(crate) jobs: JobConfig,
+    pub(crate) outbox: OutboxConfig,
+    pub(crate) mail: MailConfig,
+    pub(crate) notifications: NotificationConfig,
+    pub(crate) inbound: InboundConfig,
+    pub(crate) events: EventConfig,
 }
 
 impl AppConfig {
@@ -114,4 +147,59 @@ impl AppConfig {
     pub const fn auth(&self) -> &AuthConfig {
         &self.auth
     }
+
+    #[must_use]
+    pub const fn storage(&self) -> &StorageConfig {
+        &self.storage
+    }
+
+    #[must_use]
+    pub const fn trash(&self) -> &TrashConfig {
+        &self.trash
+    }
+
+    #[must_use]
+    pub const fn idempotency(&self) -> &IdempotencyConfig {
+        &self.idempotency
+    }
+
+    #[must_use]
+    pub const fn account(&self) -> &AccountConfig {
+        &self.account
+    }
+
+    #[must_use]
+    pub const fn webhooks(&self) -> &WebhookConfig {
+        &self.webhooks
+    }
+
+    #[must_use]
+    pub const fn jobs(&self) -> &JobConfig {
+        &self.jobs
+    }
+
+    #[must_use]
+    pub const fn outbox(&self) -> &OutboxConfig {
+        &self.outbox
+    }
+
+    #[must_use]
+    pub const fn mail(&self) -> &MailConfig {
+        &self.mail
+    }
+
+    #[must_use]
+    pub const fn notifications(&self) -> &NotificationConfig {
+        &self.notifications
+    }
+
+    #[must_use]
+    pub const fn inbound(&self) -> &InboundConfig {
+        &self.inbound
+    }
+
+    #[must_use]
+    pub const fn events(&self) -> &EventConfig {
+        &self.events
+    }
 }
@@ -0,0 +1,17 @@
+use chrono::TimeDelta;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotificationConfig {
+    /// Seconds before a task is due that its reminder is sent
+    pub reminder_lead: u32,
+    /// When due tasks are looked for, as a cron expression with seconds
+    pub reminder_schedule: String,
+}
+
+impl NotificationConfig {
+    #[must_use]
+    pub fn reminder_lead(&self) -> TimeDelta {
+        TimeDelta::seconds(i64::from(self.reminder_lead))
+    }
+}
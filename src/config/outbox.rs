@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use chrono::TimeDelta;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OutboxConfig {
+    /// Seconds the relay waits for new events before looking anyway
+    pub poll_interval: u64,
+    /// Most events relayed in one transaction
+    pub batch_size: u32,
+    /// Attempts at publishing an event before it is dead-lettered
+    pub max_attempts: u32,
+    /// Seconds before a failed event is tried again, doubled after every
+    /// failed attempt
+    pub retry_base: u64,
+    /// Longest pause between two attempts, in seconds
+    pub retry_max: u64,
+    /// Days published events are kept for
+    pub retention_days: u32,
+    /// When published events are purged, as a cron expression with seconds
+    pub purge_schedule: String,
+}
+
+impl OutboxConfig {
+    #[must_use]
+    pub const fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval)
+    }
+
+    #[must_use]
+    pub fn retention(&self) -> TimeDelta {
+        TimeDelta::days(i64::from(self.retention_days))
+    }
+
+    /// Pause before the next attempt at an event after `attempts` failed
+    /// ones, or `None` once it should be dead-lettered.
+    #[must_use]
+    pub fn backoff(&self, attempts: u32) -> Option<TimeDelta> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+
+        let delay = self
+            .retry_base
+            .saturating_mul(1 << attempts.saturating_sub(1).min(32))
+            .min(self.retry_max);
+
+        Some(TimeDelta::seconds(i64::try_from(delay).unwrap_or(i64::MAX)))
+    }
+}
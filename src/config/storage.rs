@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct S3Config {
+    /// Base URL of the S3 compatible service, e.g. `http://localhost:9000`
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+/// Where attachment contents are kept.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StorageBackend {
+    Local { root: PathBuf },
+    S3(S3Config),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageConfig {
+    /// Largest accepted upload, in bytes
+    pub max_upload_size: usize,
+    pub allowed_mime_types: Vec<String>,
+    pub backend: StorageBackend,
+}
+
+impl StorageConfig {
+    #[must_use]
+    pub fn is_allowed(&self, mime_type: &str) -> bool {
+        self.allowed_mime_types
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(mime_type))
+    }
+}
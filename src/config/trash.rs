@@ -0,0 +1,17 @@
+use chrono::TimeDelta;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TrashConfig {
+    /// Days a deleted task stays in the trash before it is purged
+    pub retention_days: u32,
+    /// When the trash is purged, as a cron expression with seconds
+    pub purge_schedule: String,
+}
+
+impl TrashConfig {
+    #[must_use]
+    pub fn retention(&self) -> TimeDelta {
+        TimeDelta::days(i64::from(self.retention_days))
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use chrono::TimeDelta;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    /// Attempts at a delivery before it is dead-lettered
+    pub max_attempts: u32,
+    /// Seconds before the first retry, doubled after every failed attempt
+    pub retry_base: u64,
+    /// Longest pause between two attempts, in seconds
+    pub retry_max: u64,
+    /// Seconds an endpoint has to answer
+    pub timeout: u64,
+    /// Seconds between two checks for due deliveries
+    pub poll_interval: u64,
+    /// Most deliveries sent at once
+    pub batch_size: u32,
+    /// Hosts webhooks may be sent to even when they resolve to a private,
+    /// loopback or link-local address, for local development
+    pub allowed_hosts: Vec<String>,
+}
+
+impl WebhookConfig {
+    #[must_use]
+    pub const fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+
+    #[must_use]
+    pub const fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval)
+    }
+
+    /// How long a claimed delivery stays hidden from other workers, after
+    /// which it is retried as if the attempt had failed.
+    #[must_use]
+    pub fn lease(&self) -> TimeDelta {
+        TimeDelta::seconds(i64::try_from(self.timeout.saturating_mul(2)).unwrap_or(i64::MAX))
+    }
+
+    /// Pause before the next attempt after `attempts` failed ones, or `None`
+    /// once the delivery should be given up on.
+    #[must_use]
+    pub fn backoff(&self, attempts: u32) -> Option<TimeDelta> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+
+        let delay = self
+            .retry_base
+            .saturating_mul(1 << attempts.saturating_sub(1).min(32))
+            .min(self.retry_max);
+
+        Some(TimeDelta::seconds(i64::try_from(delay).unwrap_or(i64::MAX)))
+    }
+}
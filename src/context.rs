@@ -1,15 +1,21 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
 use jsonwebtoken::{DecodingKey, EncodingKey};
 use sqlx::PgPool;
 
-use crate::{AppConfig, Error};
+use crate::{
+    AppConfig, Error,
+    events::EventBus,
+    storage::{self, BlobStore},
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub config: AppConfig,
     pub db: PgPool,
     pub jwt: JwtState,
+    pub events: EventBus,
+    pub blobs: Arc<dyn BlobStore>,
 }
 
 impl AppState {
@@ -24,6 +30,8 @@ impl AppState {
             config: config.clone(),
             db,
             jwt,
+            events: EventBus::new(),
+            blobs: storage::from_config(&config.storage),
         })
     }
 }
@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    body::Body,
+    debug_handler,
+    extract::{DefaultBodyLimit, Multipart, Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use bytes::BytesMut;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    AppState, Result,
+    errors::response::ErrorResponse,
+    middlewares::auth::AuthClaims,
+    models::attachments::{
+        AttachmentResponse, Upload, UploadForm, detect_content_type, sanitize_filename,
+    },
+    repositories::{ModelError, attachments::Attachment},
+};
+
+const ATTACHMENT_TAG: &str = "Attachments";
+
+/// Room for the multipart framing around the file itself.
+const MULTIPART_OVERHEAD: usize = 64 * 1024;
+
+/// Reads the `file` field of an upload, enforcing the configured size and
+/// MIME type limits while it arrives.
+async fn read_upload(ctx: &AppState, mut multipart: Multipart) -> Result<Upload> {
+    let storage = ctx.config.storage();
+
+    while let Some(mut field) = multipart.next_field().await? {
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let filename = sanitize_filename(field.file_name().unwrap_or_default());
+        let declared = field.content_type().map(ToString::to_string);
+
+        let mut data = BytesMut::new();
+        while let Some(chunk) = field.chunk().await? {
+            if data.len() + chunk.len() > storage.max_upload_size {
+                return Err(ModelError::AttachmentTooLarge.into());
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        let content_type = detect_content_type(&data, declared.as_deref());
+        if !storage.is_allowed(&content_type) {
+            return Err(ModelError::UnsupportedMediaType(content_type).into());
+        }
+
+        return Ok(Upload {
+            filename,
+            content_type,
+            data: data.freeze(),
+        });
+    }
+
+    Err(crate::Error::Validation(
+        serde_json::json!({ "file": "Upload must contain a file field" }).to_string(),
+    ))
+}
+
+/// Attach a file to a task
+///
+/// Uploads a file as the `file` field of a multipart form. Size and type are
+/// limited by the storage configuration.
+#[debug_handler]
+#[utoipa::path(
+    tag = ATTACHMENT_TAG,
+    post,
+    path = "/{id}/attachments",
+    params(("id" = i32, Path, description = "Task ID")),
+    security(("token" = [])),
+    request_body(content = UploadForm, content_type = "multipart/form-data", description = "File to attach"),
+    responses(
+        (status = 201, body = AttachmentResponse, description = "Successful upload"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 403, body = ErrorResponse, description = "Authorisation failure"),
+        (status = 404, body = ErrorResponse, description = "Task not found"),
+        (status = 413, body = ErrorResponse, description = "File is too large"),
+        (status = 415, body = ErrorResponse, description = "File type is not allowed"),
+        (status = 422, body = ErrorResponse, description = "Upload has no file field"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn add(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    multipart: Multipart,
+) -> Result<Response> {
+    let upload = read_upload(&ctx, multipart).await?;
+
+    let attachment =
+        Attachment::create(&ctx.db, ctx.blobs.as_ref(), id, upload, auth.pid()).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(AttachmentResponse::from(attachment)),
+    )
+        .into_response())
+}
+
+/// Get the attachments of a task
+#[debug_handler]
+#[utoipa::path(
+    tag = ATTACHMENT_TAG,
+    get,
+    path = "/{id}/attachments",
+    params(("id" = i32, Path, description = "Task ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<AttachmentResponse>, description = "Successful attachments retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Task not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn all(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let attachments = Attachment::find_all(&ctx.db, id, auth.pid())
+        .await?
+        .into_iter()
+        .map(AttachmentResponse::from)
+        .collect::<Vec<AttachmentResponse>>();
+
+    Ok((StatusCode::OK, Json(attachments)).into_response())
+}
+
+/// Download an attachment
+///
+/// Streams the contents of an [`Attachment`] to anyone who can see its task
+#[debug_handler]
+#[utoipa::path(
+    tag = ATTACHMENT_TAG,
+    get,
+    path = "/{id}/attachments/{attachment_id}",
+    params(
+        ("id" = i32, Path, description = "Task ID"),
+        ("attachment_id" = i32, Path, description = "Attachment ID")
+    ),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<u8>, content_type = "application/octet-stream", description = "File contents"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Task or attachment not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn download(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path((id, attachment_id)): Path<(i32, i32)>,
+) -> Result<Response> {
+    let (attachment, stream) =
+        Attachment::download(&ctx.db, ctx.blobs.as_ref(), id, attachment_id, auth.pid()).await?;
+
+    let headers = [
+        (header::CONTENT_TYPE, attachment.content_type),
+        (header::CONTENT_LENGTH, attachment.size.to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", attachment.filename),
+        ),
+    ];
+
+    Ok((StatusCode::OK, headers, Body::from_stream(stream)).into_response())
+}
+
+/// Delete an attachment
+#[debug_handler]
+#[utoipa::path(
+    tag = ATTACHMENT_TAG,
+    delete,
+    path = "/{id}/attachments/{attachment_id}",
+    params(
+        ("id" = i32, Path, description = "Task ID"),
+        ("attachment_id" = i32, Path, description = "Attachment ID")
+    ),
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Successful attachment deletion"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 403, body = ErrorResponse, description = "Authorisation failure"),
+        (status = 404, body = ErrorResponse, description = "Task or attachment not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn remove(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path((id, attachment_id)): Path<(i32, i32)>,
+) -> Result<Response> {
+    Attachment::delete_by_id(&ctx.db, ctx.blobs.as_ref(), id, attachment_id, auth.pid()).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Attachment routes, merged into the task routes under `/{id}/attachments`.
+pub fn attachment_routes(ctx: &AppState) -> OpenApiRouter {
+    let body_limit = ctx.config.storage().max_upload_size + MULTIPART_OVERHEAD;
+
+    OpenApiRouter::new()
+        .routes(routes!(add))
+        .routes(routes!(all))
+        .routes(routes!(download))
+        .routes(routes!(remove))
+        .layer(DefaultBodyLimit::max(body_limit))
+        .with_state(Arc::new(ctx.clone()))
+}
@@ -9,11 +9,18 @@ use axum::{
 };
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use serde_json::json;
-use utoipa_axum::{router::OpenApiRouter, routes};
+use utoipa_axum::{
+    router::{OpenApiRouter, UtoipaMethodRouterExt},
+    routes,
+};
 
 use crate::{
     AppState, Result,
     errors::response::ErrorResponse,
+    middlewares::{
+        auth::ACCESS_TOKEN_COOKIE,
+        idempotency::{IdempotencyLayer, ResponseOwner},
+    },
     models::{
         Validator,
         auth::{AuthResponse, LoginResponse, LoginUser, RegisterUser},
@@ -34,11 +41,12 @@ const AUTH_TAG: &str = "Auth";
     tag = AUTH_TAG,
     post,
     path = "/register",
+    params(("Idempotency-Key" = Option<String>, Header, description = "Makes retries of the request return the first response")),
     request_body(content=RegisterUser, content_type="application/json", description="Registration data"),
     responses(
         (status=201, description="User registration success", body=AuthResponse),
         (status=422, description="Validation error on request body", body=ErrorResponse),
-        (status=409, description="Username or email is already registered", body=ErrorResponse),
+        (status=409, description="Username or email is already registered, or a request with the same Idempotency-Key is in progress", body=ErrorResponse),
         (status=500, description="Internal server error", body=ErrorResponse)
     )
 )]
@@ -53,13 +61,16 @@ async fn register(
 
     tracing::info!("User {} registered successful.", &user.username);
 
-    Ok((
+    let mut response = (
         StatusCode::CREATED,
         Json(AuthResponse::new(
             "Account created successful. Verify email",
         )),
     )
-        .into_response())
+        .into_response();
+    response.extensions_mut().insert(ResponseOwner(user.pid));
+
+    Ok(response)
 }
 
 /// Logs in a user
@@ -89,7 +100,7 @@ async fn login(
 
     let user = User::login_user(&ctx.db, dto, &ctx.jwt).await?;
 
-    let access_cookie = Cookie::build(("accessToken", &user.token))
+    let access_cookie = Cookie::build((ACCESS_TOKEN_COOKIE, &user.token))
         .path("/")
         .max_age(time::Duration::seconds(ctx.jwt.max_age as i64))
         .same_site(SameSite::Lax)
@@ -107,7 +118,7 @@ async fn login(
 
 pub fn auth_routes(ctx: &AppState) -> OpenApiRouter {
     OpenApiRouter::new()
-        .routes(routes!(register))
+        .routes(routes!(register).layer(IdempotencyLayer::new(ctx)))
         .routes(routes!(login))
         .with_state(Arc::new(ctx.clone()))
 }
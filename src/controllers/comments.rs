@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, debug_handler,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    AppState, Result,
+    errors::response::ErrorResponse,
+    middlewares::auth::AuthClaims,
+    models::{
+        Validator,
+        comments::{CommentResponse, NewComment, UpdateComment},
+    },
+    repositories::comments::Comment,
+};
+
+const COMMENT_TAG: &str = "Comments";
+
+/// Comment on a task
+///
+/// Adds a [`Comment`] to a task the current user can see. Every user
+/// mentioned as `@username` who can see the task is notified.
+#[debug_handler]
+#[utoipa::path(
+    tag = COMMENT_TAG,
+    post,
+    path = "/{id}/comments",
+    params(("id" = i32, Path, description = "Task ID")),
+    security(("token" = [])),
+    request_body(content = NewComment, content_type = "application/json", description = "Comment text"),
+    responses(
+        (status = 201, body = CommentResponse, description = "Successful comment creation"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Task not found"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn add(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Json(params): Json<NewComment>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    let (comment, _) = Comment::create(&ctx.db, id, dto, auth.pid()).await?;
+
+    Ok((StatusCode::CREATED, Json(CommentResponse::from(comment))).into_response())
+}
+
+/// Get the comments of a task
+///
+/// Comments are returned oldest first
+#[debug_handler]
+#[utoipa::path(
+    tag = COMMENT_TAG,
+    get,
+    path = "/{id}/comments",
+    params(("id" = i32, Path, description = "Task ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<CommentResponse>, description = "Successful comments retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Task not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn all(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let comments = Comment::find_all(&ctx.db, id, auth.pid())
+        .await?
+        .into_iter()
+        .map(CommentResponse::from)
+        .collect::<Vec<CommentResponse>>();
+
+    Ok((StatusCode::OK, Json(comments)).into_response())
+}
+
+/// Edit a comment
+///
+/// Only the author of a [`Comment`] can edit it. Edited comments are
+/// flagged, and users mentioned for the first time are notified.
+#[debug_handler]
+#[utoipa::path(
+    tag = COMMENT_TAG,
+    patch,
+    path = "/{id}/comments/{comment_id}",
+    params(
+        ("id" = i32, Path, description = "Task ID"),
+        ("comment_id" = i32, Path, description = "Comment ID")
+    ),
+    security(("token" = [])),
+    request_body(content = UpdateComment, content_type = "application/json", description = "New comment text"),
+    responses(
+        (status = 200, body = CommentResponse, description = "Successful comment update"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 403, body = ErrorResponse, description = "Not the author of the comment"),
+        (status = 404, body = ErrorResponse, description = "Task or comment not found"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn update(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path((id, comment_id)): Path<(i32, i32)>,
+    Json(params): Json<UpdateComment>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    let (comment, _) = Comment::update_by_id(&ctx.db, id, comment_id, dto, auth.pid()).await?;
+
+    Ok((StatusCode::OK, Json(CommentResponse::from(comment))).into_response())
+}
+
+/// Delete a comment
+///
+/// Authors can delete their own comments, task owners any comment
+#[debug_handler]
+#[utoipa::path(
+    tag = COMMENT_TAG,
+    delete,
+    path = "/{id}/comments/{comment_id}",
+    params(
+        ("id" = i32, Path, description = "Task ID"),
+        ("comment_id" = i32, Path, description = "Comment ID")
+    ),
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Successful comment deletion"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 403, body = ErrorResponse, description = "Not allowed to delete the comment"),
+        (status = 404, body = ErrorResponse, description = "Task or comment not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn remove(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path((id, comment_id)): Path<(i32, i32)>,
+) -> Result<Response> {
+    Comment::delete_by_id(&ctx.db, id, comment_id, auth.pid()).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Comment routes, merged into the task routes under `/{id}/comments`.
+pub fn comment_routes(ctx: &AppState) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(add))
+        .routes(routes!(all))
+        .routes(routes!(update))
+        .routes(routes!(remove))
+        .with_state(Arc::new(ctx.clone()))
+}
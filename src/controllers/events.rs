@@ -0,0 +1,144 @@
+use std::{pin::pin, sync::Arc};
+
+use axum::{
+    Extension, Json, debug_handler,
+    extract::{
+        State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{HeaderMap, StatusCode, header::ORIGIN},
+    response::{
+        IntoResponse, Response,
+        sse::{Event as SseEvent, KeepAlive, Sse},
+    },
+};
+use futures_util::{Stream, StreamExt, stream};
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::{
+    AppState, errors::response::ErrorResponse, events::EventBus, middlewares::auth::AuthClaims,
+    models::events::TaskEventResponse,
+};
+
+const EVENT_TAG: &str = "Events";
+
+/// The task events and notifications `user_pid` may see, from now on.
+fn task_events(bus: &EventBus, user_pid: Uuid) -> impl Stream<Item = TaskEventResponse> + use<> {
+    stream::unfold(bus.subscribe(), move |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    if let Some(event) = TaskEventResponse::for_user(&event, user_pid) {
+                        return Some((event, receiver));
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!("Event stream of {user_pid} missed {missed} events");
+                    return Some((TaskEventResponse::resync(), receiver));
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+/// Stream task events
+///
+/// Pushes Server-Sent Events as the current user's tasks, and tasks shared
+/// with them, are created, updated or deleted, and as notifications land in
+/// their inbox. The event name is the event type. Browsers may authenticate
+/// with the access token cookie set on login.
+#[debug_handler]
+#[utoipa::path(
+    tag = EVENT_TAG,
+    get,
+    path = "/",
+    security(("token" = [])),
+    responses(
+        (status = 200, body = TaskEventResponse, content_type = "text/event-stream", description = "Stream of task events"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure")
+    )
+)]
+async fn events(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+) -> Response {
+    let events = task_events(&ctx.events, auth.pid()).map(|event| {
+        SseEvent::default()
+            .event(event.kind.as_str())
+            .json_data(&event)
+    });
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Task events over a WebSocket
+///
+/// Sends the same events as the Server-Sent Events stream as JSON text
+/// messages. Messages from the client are ignored. Browsers may only open
+/// it from the configured origins.
+#[debug_handler]
+#[utoipa::path(
+    tag = EVENT_TAG,
+    get,
+    path = "/ws",
+    security(("token" = [])),
+    responses(
+        (status = 101, body = TaskEventResponse, description = "Switching to the WebSocket protocol"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 403, body = ErrorResponse, description = "Opened from a page of another origin")
+    )
+)]
+async fn socket(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    // Any site could otherwise open it with the user's cookie
+    if let Some(origin) = headers.get(ORIGIN)
+        && !origin
+            .to_str()
+            .is_ok_and(|origin| ctx.config.events().allows_origin(origin))
+    {
+        let body = Json(json!({ "message": "Origin is not allowed" }));
+        return (StatusCode::FORBIDDEN, body).into_response();
+    }
+
+    let events = task_events(&ctx.events, auth.pid());
+
+    upgrade.on_upgrade(move |socket| forward(socket, events))
+}
+
+/// Sends `events` over `socket` until either end goes away.
+async fn forward(mut socket: WebSocket, events: impl Stream<Item = TaskEventResponse>) {
+    let mut events = pin!(events);
+
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(event) = event else { break };
+                let Ok(text) = serde_json::to_string(&event) else { continue };
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+pub fn event_routes(ctx: &AppState) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(events))
+        .routes(routes!(socket))
+        .with_state(Arc::new(ctx.clone()))
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, debug_handler,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    AppState, Result,
+    errors::response::ErrorResponse,
+    middlewares::auth::AuthClaims,
+    models::{
+        Validator,
+        labels::{LabelResponse, NewLabel, UpdateLabel},
+    },
+    repositories::labels::Label,
+};
+
+const LABEL_TAG: &str = "Labels";
+
+/// Create new label
+///
+/// Attempts to create a new [`Label`] for the current user
+#[debug_handler]
+#[utoipa::path(
+    tag = LABEL_TAG,
+    post,
+    path = "/",
+    security(("token" = [])),
+    request_body(content = NewLabel, content_type = "application/json", description = "Data to create a new label"),
+    responses(
+        (status = 201, body = LabelResponse, description = "Successful label creation"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 409, body = ErrorResponse, description = "Label name already in use"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn add(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Json(params): Json<NewLabel>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    let label = Label::create(&ctx.db, dto, auth.pid()).await?;
+
+    Ok((StatusCode::CREATED, Json(LabelResponse::from(label))).into_response())
+}
+
+/// Get list of labels
+///
+/// Attempts to get the [`Label`]s of the current user
+#[debug_handler]
+#[utoipa::path(
+    tag = LABEL_TAG,
+    get,
+    path = "/",
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<LabelResponse>, description = "Successful labels retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn all(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+) -> Result<Response> {
+    let labels = Label::find_all(&ctx.db, auth.pid())
+        .await?
+        .into_iter()
+        .map(LabelResponse::from)
+        .collect::<Vec<LabelResponse>>();
+
+    Ok((StatusCode::OK, Json(labels)).into_response())
+}
+
+/// Update a label
+///
+/// Attempts to rename or recolour a [`Label`] by its ID
+#[debug_handler]
+#[utoipa::path(
+    tag = LABEL_TAG,
+    patch,
+    path = "/{id}",
+    params(("id" = i32, Path, description = "Label ID")),
+    security(("token" = [])),
+    request_body(content = UpdateLabel, content_type = "application/json", description = "Fields to change"),
+    responses(
+        (status = 200, body = LabelResponse, description = "Successful label update"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Label not found"),
+        (status = 409, body = ErrorResponse, description = "Label name already in use"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn update(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Json(params): Json<UpdateLabel>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    let label = Label::update_by_id(&ctx.db, dto, id, auth.pid()).await?;
+
+    Ok((StatusCode::OK, Json(LabelResponse::from(label))).into_response())
+}
+
+/// Delete a label
+///
+/// Attempts to delete a [`Label`] by its ID, detaching it from every task
+#[debug_handler]
+#[utoipa::path(
+    tag = LABEL_TAG,
+    delete,
+    path = "/{id}",
+    params(("id" = i32, Path, description = "Label ID")),
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Successful label deletion"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Label not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn remove(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    Label::delete_by_id(&ctx.db, id, auth.pid()).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub fn label_routes(ctx: &AppState) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(add))
+        .routes(routes!(all))
+        .routes(routes!(update))
+        .routes(routes!(remove))
+        .with_state(Arc::new(ctx.clone()))
+}
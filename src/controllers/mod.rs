@@ -1,2 +1,11 @@
+pub mod attachments;
 pub mod auth;
+pub mod comments;
+pub mod events;
+pub mod labels;
+pub mod notifications;
+pub mod projects;
+pub mod sync;
 pub mod tasks;
+pub mod users;
+pub mod webhooks;
@@ -0,0 +1,202 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, debug_handler,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    AppState, Result,
+    errors::response::ErrorResponse,
+    middlewares::auth::AuthClaims,
+    models::notifications::{
+        NotificationKind, NotificationPage, NotificationQuery, NotificationResponse,
+        PreferenceResponse, UpdatePreference,
+    },
+    repositories::notifications::{Notification, NotificationPreference},
+};
+
+const NOTIFICATION_TAG: &str = "Notifications";
+
+/// Get the current user's notifications
+///
+/// Lists the inbox newest first, together with the number of unread
+/// notifications. Pass `nextBefore` as `before` to get the next page.
+#[debug_handler]
+#[utoipa::path(
+    tag = NOTIFICATION_TAG,
+    get,
+    path = "/",
+    params(NotificationQuery),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = NotificationPage, description = "Successful retrieval of notifications"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn all(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Query(query): Query<NotificationQuery>,
+) -> Result<Response> {
+    let notifications = Notification::find_all(&ctx.db, auth.pid(), &query).await?;
+    let unread_count = Notification::unread_count(&ctx.db, auth.pid()).await?;
+
+    let page = NotificationPage::new(notifications, unread_count, query.limit());
+
+    Ok((StatusCode::OK, Json(page)).into_response())
+}
+
+/// Mark every notification read
+#[debug_handler]
+#[utoipa::path(
+    tag = NOTIFICATION_TAG,
+    post,
+    path = "/read",
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Successful update of notifications"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn read_all(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+) -> Result<Response> {
+    Notification::mark_all_read(&ctx.db, auth.pid()).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Mark a notification read
+#[debug_handler]
+#[utoipa::path(
+    tag = NOTIFICATION_TAG,
+    post,
+    path = "/{id}/read",
+    params(("id" = i64, Path, description = "Notification ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = NotificationResponse, description = "Successful update of the notification"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Notification not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn read(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i64>,
+) -> Result<Response> {
+    let notification = Notification::mark(&ctx.db, id, auth.pid(), true).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(NotificationResponse::from(notification)),
+    )
+        .into_response())
+}
+
+/// Mark a notification unread
+#[debug_handler]
+#[utoipa::path(
+    tag = NOTIFICATION_TAG,
+    post,
+    path = "/{id}/unread",
+    params(("id" = i64, Path, description = "Notification ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = NotificationResponse, description = "Successful update of the notification"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Notification not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn unread(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i64>,
+) -> Result<Response> {
+    let notification = Notification::mark(&ctx.db, id, auth.pid(), false).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(NotificationResponse::from(notification)),
+    )
+        .into_response())
+}
+
+/// Get the current user's notification preferences
+///
+/// Lists the channels each kind of notification is sent on. By default
+/// notifications only land in the inbox.
+#[debug_handler]
+#[utoipa::path(
+    tag = NOTIFICATION_TAG,
+    get,
+    path = "/preferences",
+    security(("token" = [])),
+    responses(
+        (status = 200, body = [PreferenceResponse], description = "Successful retrieval of preferences"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn preferences(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+) -> Result<Response> {
+    let preferences = NotificationPreference::find_all(&ctx.db, auth.pid())
+        .await?
+        .into_iter()
+        .map(PreferenceResponse::from)
+        .collect::<Vec<PreferenceResponse>>();
+
+    Ok((StatusCode::OK, Json(preferences)).into_response())
+}
+
+/// Choose the channels of a kind of notification
+///
+/// Turns the in-app, email and webhook channels of one kind of notification
+/// on or off. Channels left out keep their current setting.
+#[debug_handler]
+#[utoipa::path(
+    tag = NOTIFICATION_TAG,
+    put,
+    path = "/preferences/{kind}",
+    params(("kind" = NotificationKind, Path, description = "Kind of notification")),
+    security(("token" = [])),
+    request_body(content = UpdatePreference, content_type = "application/json", description = "Channels to change"),
+    responses(
+        (status = 200, body = PreferenceResponse, description = "Successful update of the preference"),
+        (status = 400, body = ErrorResponse, description = "Unknown kind of notification"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn update_preference(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(kind): Path<NotificationKind>,
+    Json(params): Json<UpdatePreference>,
+) -> Result<Response> {
+    let preference = NotificationPreference::update(&ctx.db, auth.pid(), kind, &params).await?;
+
+    Ok((StatusCode::OK, Json(PreferenceResponse::from(preference))).into_response())
+}
+
+pub fn notification_routes(ctx: &AppState) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(all))
+        .routes(routes!(read_all))
+        .routes(routes!(read))
+        .routes(routes!(unread))
+        .routes(routes!(preferences))
+        .routes(routes!(update_preference))
+        .with_state(Arc::new(ctx.clone()))
+}
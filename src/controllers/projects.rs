@@ -0,0 +1,336 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, debug_handler,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    AppState, Result,
+    errors::response::ErrorResponse,
+    middlewares::auth::AuthClaims,
+    models::{
+        Validator,
+        projects::{NewProject, ProjectResponse, UpdateProject},
+        shares::{NewShare, ShareResponse},
+        tasks::TaskResponse,
+    },
+    repositories::{
+        projects::Project,
+        shares::{Share, ShareTarget},
+        tasks::Task,
+    },
+};
+
+const PROJECT_TAG: &str = "Projects";
+
+/// Create new project
+///
+/// Attempts to create a new [`Project`] for the current user
+#[debug_handler]
+#[utoipa::path(
+    tag = PROJECT_TAG,
+    post,
+    path = "/",
+    security(("token" = [])),
+    request_body(content = NewProject, content_type = "application/json", description = "Data to create a new project"),
+    responses(
+        (status = 201, body = ProjectResponse, description = "Successful project creation"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn add(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Json(params): Json<NewProject>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    let project = Project::create(&ctx.db, dto, auth.pid()).await?;
+
+    Ok((StatusCode::CREATED, Json(ProjectResponse::from(project))).into_response())
+}
+
+/// Get list of projects
+#[debug_handler]
+#[utoipa::path(
+    tag = PROJECT_TAG,
+    get,
+    path = "/",
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<ProjectResponse>, description = "Successful projects retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn all(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+) -> Result<Response> {
+    let projects = Project::find_all(&ctx.db, auth.pid())
+        .await?
+        .into_iter()
+        .map(ProjectResponse::from)
+        .collect::<Vec<ProjectResponse>>();
+
+    Ok((StatusCode::OK, Json(projects)).into_response())
+}
+
+/// Get project by its ID
+#[debug_handler]
+#[utoipa::path(
+    tag = PROJECT_TAG,
+    get,
+    path = "/{id}",
+    params(("id" = i32, Path, description = "Project ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = ProjectResponse, description = "Successful project retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Project not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn one(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let project = Project::find_by_id(&ctx.db, auth.pid(), id).await?;
+
+    Ok((StatusCode::OK, Json(ProjectResponse::from(project))).into_response())
+}
+
+/// Update a project
+#[debug_handler]
+#[utoipa::path(
+    tag = PROJECT_TAG,
+    patch,
+    path = "/{id}",
+    params(("id" = i32, Path, description = "Project ID")),
+    security(("token" = [])),
+    request_body(content = UpdateProject, content_type = "application/json", description = "Fields to change"),
+    responses(
+        (status = 200, body = ProjectResponse, description = "Successful project update"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Project not found"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn update(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Json(params): Json<UpdateProject>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    let project = Project::update_by_id(&ctx.db, dto, id, auth.pid()).await?;
+
+    Ok((StatusCode::OK, Json(ProjectResponse::from(project))).into_response())
+}
+
+/// Delete a project
+///
+/// Attempts to delete a [`Project`]. Its tasks are moved to the inbox.
+/// Requires owner permission on the project.
+#[debug_handler]
+#[utoipa::path(
+    tag = PROJECT_TAG,
+    delete,
+    path = "/{id}",
+    params(("id" = i32, Path, description = "Project ID")),
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Successful project deletion"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Project not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn remove(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    Project::delete_by_id(&ctx.db, id, auth.pid()).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Get the tasks of a project
+///
+/// Tasks are returned in their manual `position` order
+#[debug_handler]
+#[utoipa::path(
+    tag = PROJECT_TAG,
+    get,
+    path = "/{id}/tasks",
+    params(("id" = i32, Path, description = "Project ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<TaskResponse>, description = "Successful tasks retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Project not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn tasks(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let tasks = Project::tasks(&ctx.db, auth.pid(), id).await?;
+    let tasks = Task::responses(&ctx.db, tasks).await?;
+
+    Ok((StatusCode::OK, Json(tasks)).into_response())
+}
+
+/// Get the projects shared with me
+///
+/// Lists the [`Project`]s other users shared with the current user
+#[debug_handler]
+#[utoipa::path(
+    tag = PROJECT_TAG,
+    get,
+    path = "/shared",
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<ProjectResponse>, description = "Successful shared projects retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn shared(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+) -> Result<Response> {
+    let projects = Project::find_shared(&ctx.db, auth.pid())
+        .await?
+        .into_iter()
+        .map(ProjectResponse::from)
+        .collect::<Vec<ProjectResponse>>();
+
+    Ok((StatusCode::OK, Json(projects)).into_response())
+}
+
+/// Get the shares of a project
+///
+/// Lists everyone the [`Project`] is shared with and their permission
+#[debug_handler]
+#[utoipa::path(
+    tag = PROJECT_TAG,
+    get,
+    path = "/{id}/shares",
+    params(("id" = i32, Path, description = "Project ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<ShareResponse>, description = "Successful shares retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Project not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn shares(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let shares = Share::find_all(&ctx.db, ShareTarget::Project(id), auth.pid())
+        .await?
+        .into_iter()
+        .map(ShareResponse::from)
+        .collect::<Vec<ShareResponse>>();
+
+    Ok((StatusCode::OK, Json(shares)).into_response())
+}
+
+/// Share a project
+///
+/// Shares a [`Project`] with another user as a `viewer`, `editor` or `owner`,
+/// or changes the permission of an existing share. Requires owner permission.
+#[debug_handler]
+#[utoipa::path(
+    tag = PROJECT_TAG,
+    post,
+    path = "/{id}/shares",
+    params(("id" = i32, Path, description = "Project ID")),
+    security(("token" = [])),
+    request_body(content = NewShare, content_type = "application/json", description = "User to share with and their permission"),
+    responses(
+        (status = 200, body = ShareResponse, description = "Successful share"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 403, body = ErrorResponse, description = "Only owners can share"),
+        (status = 404, body = ErrorResponse, description = "Project or user not found"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn share(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Json(params): Json<NewShare>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    let share = Share::grant(&ctx.db, ShareTarget::Project(id), dto, auth.pid()).await?;
+
+    Ok((StatusCode::OK, Json(ShareResponse::from(share))).into_response())
+}
+
+/// Stop sharing a project
+///
+/// Owners can remove anyone's share, other users only their own
+#[debug_handler]
+#[utoipa::path(
+    tag = PROJECT_TAG,
+    delete,
+    path = "/{id}/shares/{username}",
+    params(
+        ("id" = i32, Path, description = "Project ID"),
+        ("username" = String, Path, description = "Username of the user to remove")
+    ),
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Share removed"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 403, body = ErrorResponse, description = "Only owners can remove other users"),
+        (status = 404, body = ErrorResponse, description = "Share not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn unshare(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path((id, username)): Path<(i32, String)>,
+) -> Result<Response> {
+    Share::revoke(&ctx.db, ShareTarget::Project(id), &username, auth.pid()).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub fn project_routes(ctx: &AppState) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(add))
+        .routes(routes!(all))
+        .routes(routes!(shared))
+        .routes(routes!(one))
+        .routes(routes!(update))
+        .routes(routes!(remove))
+        .routes(routes!(tasks))
+        .routes(routes!(shares))
+        .routes(routes!(share))
+        .routes(routes!(unshare))
+        .with_state(Arc::new(ctx.clone()))
+}
@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, debug_handler,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use utoipa_axum::{
+    router::{OpenApiRouter, UtoipaMethodRouterExt},
+    routes,
+};
+
+use crate::{
+    AppState, Result,
+    errors::response::ErrorResponse,
+    middlewares::{auth::AuthClaims, idempotency::IdempotencyLayer},
+    models::{
+        sync::{
+            SyncOutcome, SyncPush, SyncPushResponse, SyncQuery, SyncResponse, SyncResultResponse,
+        },
+        tasks::TaskResponse,
+    },
+    repositories::{
+        ModelError,
+        tasks::{BulkApplied, Task},
+    },
+};
+
+const SYNC_TAG: &str = "Sync";
+
+/// Pull changes
+///
+/// Returns the tasks the current user can see that were created, updated or
+/// deleted since the `since` token, oldest change first. Tasks the user lost
+/// access to are listed as deleted. Keep fetching from `next` while
+/// `hasMore` is set; store the last `next` for the following sync.
+///
+/// Changes only show up once every transaction that started before them has
+/// finished, anywhere on the database server. A long-running transaction,
+/// even one unrelated to tasks, holds back the changes made after it began.
+#[debug_handler]
+#[utoipa::path(
+    tag = SYNC_TAG,
+    get,
+    path = "/",
+    params(SyncQuery),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = SyncResponse, description = "Changes since the token"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 422, body = ErrorResponse, description = "Malformed sync token"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn pull(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Query(query): Query<SyncQuery>,
+) -> Result<Response> {
+    let changes = Task::changes_since(&ctx.db, auth.pid(), query.token()?, query.limit()).await?;
+
+    let response = SyncResponse {
+        changed: Task::responses(&ctx.db, changes.changed).await?,
+        deleted: changes.deleted,
+        next: changes.next.to_string(),
+        has_more: changes.has_more,
+    };
+
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+/// Push changes
+///
+/// Applies changes a client made while offline, in order and each on its
+/// own. Updates and deletes carry the version they were based on; when the
+/// task has changed on the server since, the change is reported as a
+/// conflict together with the task as it is now. Send an `Idempotency-Key`
+/// header to retry a push safely.
+#[debug_handler]
+#[utoipa::path(
+    tag = SYNC_TAG,
+    post,
+    path = "/",
+    security(("token" = [])),
+    request_body(content = SyncPush, content_type = "application/json", description = "Changes made offline"),
+    responses(
+        (status = 200, body = SyncPushResponse, description = "Outcome of every change"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn push(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Json(params): Json<SyncPush>,
+) -> Result<Response> {
+    params.validate_all()?;
+
+    let results = Task::sync(&ctx.db, ctx.blobs.as_ref(), &params.changes, auth.pid()).await?;
+
+    let mut tasks = Vec::new();
+    let mut items = Vec::with_capacity(results.len());
+    for (index, (change, result)) in params.changes.iter().zip(results).enumerate() {
+        let mut item = SyncResultResponse {
+            index,
+            client_id: change.client_id().map(str::to_string),
+            outcome: SyncOutcome::Applied,
+            id: change.id(),
+            task: None,
+            error: None,
+        };
+
+        match result {
+            Ok(BulkApplied::Created(task) | BulkApplied::Updated(task)) => {
+                item.id = Some(task.id);
+                tasks.push((index, task));
+            }
+            Ok(BulkApplied::Deleted(_)) => {}
+            Err(e @ (ModelError::PreconditionFailed | ModelError::EntityNotFound))
+                if change.id().is_some() =>
+            {
+                item.outcome = SyncOutcome::Conflict;
+                item.error = Some(e.status().1.to_string());
+                item.task = current(&ctx, change.id(), &auth).await?;
+            }
+            Err(e) => {
+                let (status, message) = e.status();
+                if status.is_server_error() {
+                    tracing::error!("Sync change {index} failed: {e:?}");
+                }
+                item.outcome = SyncOutcome::Rejected;
+                item.error = Some(message.to_string());
+            }
+        }
+
+        items.push(item);
+    }
+
+    let (indices, tasks): (Vec<usize>, Vec<Task>) = tasks.into_iter().unzip();
+    let responses = Task::responses(&ctx.db, tasks).await?;
+    for (index, response) in indices.into_iter().zip(responses) {
+        items[index].task = Some(response);
+    }
+
+    Ok((StatusCode::OK, Json(SyncPushResponse { results: items })).into_response())
+}
+
+/// The task as the server has it now, `None` once it is gone.
+async fn current(
+    ctx: &AppState,
+    id: Option<i32>,
+    auth: &AuthClaims,
+) -> Result<Option<TaskResponse>> {
+    let Some(id) = id else {
+        return Ok(None);
+    };
+
+    match Task::find_by_id(&ctx.db, auth.pid(), id).await {
+        Ok(task) => Ok(Some(Task::response(&ctx.db, task).await?)),
+        Err(ModelError::EntityNotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn sync_routes(ctx: &AppState) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(pull))
+        .routes(routes!(push).layer(IdempotencyLayer::new(ctx)))
+        .with_state(Arc::new(ctx.clone()))
+}
@@ -1,40 +1,83 @@
 use std::sync::Arc;
 
 use axum::{
-    Extension, Json, debug_handler,
-    extract::{Path, State},
-    http::StatusCode,
+    Extension, Json,
+    body::{Body, Bytes},
+    debug_handler,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderName, StatusCode, header},
     response::{IntoResponse, Response},
 };
-use utoipa_axum::{router::OpenApiRouter, routes};
+use futures_util::{StreamExt, stream};
+use utoipa_axum::{
+    router::{OpenApiRouter, UtoipaMethodRouterExt},
+    routes,
+};
 
 use crate::{
     AppState, Result,
+    controllers::{attachments, comments},
     errors::response::ErrorResponse,
-    middlewares::auth::AuthClaims,
+    middlewares::{auth::AuthClaims, idempotency::IdempotencyLayer},
     models::{
         Validator,
-        tasks::{NewTask, TaskResponse, UpdateTask},
+        bulk::{BulkItemResponse, BulkRequest, BulkResponse},
+        export::ExportQuery,
+        history::HistoryResponse,
+        import::{self, ImportQuery, ImportResponse},
+        recurrence::Recurrence,
+        shares::{NewShare, ShareResponse},
+        tasks::{
+            AddDependency, DeleteOptions, MoveTask, NewTask, ReplaceTask, SetAssignee, SetParent,
+            SetRecurrence, TaskFilter, TaskResponse, TaskTreeResponse, TaskView, UpdateOptions,
+            UpdateTask, VersionMatch, etag,
+        },
+    },
+    repositories::{
+        ModelError,
+        dependencies::TaskDependency,
+        history::TaskHistory,
+        labels::Label,
+        shares::{Share, ShareTarget},
+        tasks::{BulkApplied, Task},
     },
-    repositories::tasks::Task,
 };
 
 const TASK_TAG: &str = "Tasks";
 
+/// Task versions listed in the `name` precondition header, `None` when the
+/// request has no such header.
+fn version_match(headers: &HeaderMap, name: HeaderName) -> Option<VersionMatch> {
+    let values = headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<&str>>();
+    if values.is_empty() {
+        return None;
+    }
+
+    values.join(",").parse().ok()
+}
+
 /// Create new task
 ///
-/// Attempts to create a new [`Task`] in the database
+/// Attempts to create a new [`Task`] in the database.
+/// Retries sent with the same `Idempotency-Key` get the first response back.
 #[debug_handler]
 #[utoipa::path(
     tag = TASK_TAG,
     post,
     path = "/",
+    params(("Idempotency-Key" = Option<String>, Header, description = "Makes retries of the request return the first response")),
     security(("token" = [])),
     request_body(content = NewTask, content_type = "application/json", description = "Data to create a new task"),
     responses(
         (status = 201, body = TaskResponse, description = "Successful task creation"),
         (status = 401, body = ErrorResponse, description = "Authentication failure"),
         (status = 403, body = ErrorResponse, description = "Authorisation failure"),
+        (status = 409, body = ErrorResponse, description = "Request with the same Idempotency-Key in progress"),
+        (status = 422, body = ErrorResponse, description = "Idempotency-Key reused for a different request"),
         (status = 500, body = ErrorResponse, description = "Internal server errors")
     )
 )]
@@ -47,18 +90,98 @@ async fn add(
     let dto = validator.validate()?;
 
     let task = Task::create_task(&ctx.db, dto, auth.pid()).await?;
+    let task = Task::response(&ctx.db, task).await?;
 
-    Ok((StatusCode::CREATED, Json(TaskResponse::from(task))).into_response())
+    Ok((StatusCode::CREATED, Json(task)).into_response())
+}
+
+/// Run bulk operations
+///
+/// Creates, updates and deletes many [`Task`]s in one transaction, either
+/// listed one by one or picked with a `selector`, e.g. every completed task.
+/// In `atomic` mode (the default) nothing is saved unless every operation
+/// succeeds, in `bestEffort` mode failures are skipped. Every operation gets
+/// the status it would have answered on its own.
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    post,
+    path = "/bulk",
+    security(("token" = [])),
+    request_body(content = BulkRequest, content_type = "application/json", description = "Operations to run"),
+    responses(
+        (status = 200, body = BulkResponse, description = "Outcome of every operation"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 422, body = ErrorResponse, description = "Validation errors"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn bulk(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Json(params): Json<BulkRequest>,
+) -> Result<Response> {
+    params.validate_all()?;
+
+    let (committed, results) = Task::bulk(&ctx.db, ctx.blobs.as_ref(), &params, auth.pid()).await?;
+
+    let mut tasks = Vec::new();
+    let mut items = Vec::with_capacity(results.len());
+    for (index, (id, result)) in results.into_iter().enumerate() {
+        let (status, error) = match result {
+            Ok(BulkApplied::Created(task)) => {
+                tasks.push((index, task));
+                (StatusCode::CREATED, None)
+            }
+            Ok(BulkApplied::Updated(task)) => {
+                tasks.push((index, task));
+                (StatusCode::OK, None)
+            }
+            Ok(BulkApplied::Deleted(_)) => (StatusCode::NO_CONTENT, None),
+            Err(e) => {
+                let (status, message) = e.status();
+                if status.is_server_error() {
+                    tracing::error!("Bulk operation {index} failed: {e:?}");
+                }
+                (status, Some(message.to_string()))
+            }
+        };
+
+        items.push(BulkItemResponse {
+            index,
+            status: status.as_u16(),
+            id,
+            task: None,
+            error,
+        });
+    }
+
+    let (indices, tasks): (Vec<usize>, Vec<Task>) = tasks.into_iter().unzip();
+    let responses = Task::responses(&ctx.db, tasks).await?;
+    for (index, response) in indices.into_iter().zip(responses) {
+        items[index].id = Some(response.id);
+        items[index].task = Some(response);
+    }
+
+    let response = BulkResponse {
+        committed,
+        results: items,
+    };
+
+    Ok((StatusCode::OK, Json(response)).into_response())
 }
 
 /// Get list of tasks
 ///
-/// Attempts to get a list of [`Task`] from the database
+/// Attempts to get a list of [`Task`] from the database, optionally
+/// filtered by label names. `assigned=me` lists the tasks assigned to the
+/// current user instead.
 #[debug_handler]
 #[utoipa::path(
     tag = TASK_TAG,
     get,
     path = "/",
+    params(TaskFilter),
     security(("token" = [])),
     responses(
         (status = 200, body = Vec<TaskResponse>, description = "Successful tasks retrieval"),
@@ -70,28 +193,155 @@ async fn add(
 async fn all(
     State(ctx): State<Arc<AppState>>,
     Extension(auth): Extension<AuthClaims>,
+    Query(filter): Query<TaskFilter>,
 ) -> Result<Response> {
-    let tasks = Task::find_all(&ctx.db, auth.pid())
-        .await?
-        .into_iter()
-        .map(TaskResponse::from)
-        .collect::<Vec<TaskResponse>>();
+    let tasks = Task::find_all(&ctx.db, auth.pid(), &filter).await?;
+    let tasks = Task::responses(&ctx.db, tasks).await?;
 
     Ok((StatusCode::OK, Json(tasks)).into_response())
 }
 
+/// Export tasks
+///
+/// Downloads every live [`Task`] of the current user as JSON, CSV, a
+/// Markdown task list or an iCalendar file of `VTODO`s. The file is streamed
+/// while tasks are read from the database.
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    get,
+    path = "/export",
+    params(ExportQuery),
+    security(("token" = [])),
+    responses(
+        (status = 200, description = "Exported tasks", content(
+            (Vec<TaskResponse> = "application/json"),
+            (String = "text/csv"),
+            (String = "text/markdown"),
+            (String = "text/calendar")
+        )),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn export(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response> {
+    let mut serializer = query.format.serializer();
+    let opening = Bytes::from(serializer.header());
+    let closing = Bytes::from(serializer.footer());
+
+    // Headers are already sent when a page fails, so the error can only cut
+    // the download short
+    let rows = Task::export(ctx.db.clone(), auth.pid()).map(move |page| {
+        let page = page.map_err(|err| {
+            tracing::error!("Failed to export tasks: {err}");
+            std::io::Error::other(err.to_string())
+        })?;
+
+        let rows = page
+            .iter()
+            .map(|task| serializer.row(task))
+            .collect::<String>();
+        Ok::<_, std::io::Error>(Bytes::from(rows))
+    });
+    let body = stream::once(async { Ok(opening) })
+        .chain(rows)
+        .chain(stream::once(async { Ok(closing) }));
+
+    let headers = [
+        (
+            header::CONTENT_TYPE,
+            query.format.content_type().to_string(),
+        ),
+        (
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"tasks.{}\"",
+                query.format.extension()
+            ),
+        ),
+    ];
+
+    Ok((StatusCode::OK, headers, Body::from_stream(body)).into_response())
+}
+
+/// Import tasks
+///
+/// Creates [`Task`]s from a CSV file, a JSON export of this API, a todo.txt
+/// file or a Todoist or Trello JSON export, together with any labels they
+/// use. Every row is validated first and nothing is saved unless all of them
+/// are valid. With `dryRun=true` the rows are only validated.
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    post,
+    path = "/import",
+    params(ImportQuery),
+    security(("token" = [])),
+    request_body(content = String, content_type = "text/plain", description = "File to import"),
+    responses(
+        (status = 200, body = ImportResponse, description = "Dry run without errors"),
+        (status = 201, body = ImportResponse, description = "Successful import"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 422, body = ImportResponse, description = "Rows with validation errors, nothing imported"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn import(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Query(query): Query<ImportQuery>,
+    body: Bytes,
+) -> Result<Response> {
+    let tasks = import::parse(&query, &body)?;
+    let errors = import::check(&tasks);
+
+    let mut response = ImportResponse {
+        dry_run: query.dry_run,
+        committed: false,
+        total: tasks.len(),
+        errors,
+        tasks: Vec::new(),
+    };
+
+    if !response.errors.is_empty() {
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Json(response)).into_response());
+    }
+    if query.dry_run {
+        return Ok((StatusCode::OK, Json(response)).into_response());
+    }
+
+    let created = Task::import(&ctx.db, &tasks, auth.pid()).await?;
+    response.tasks = Task::responses(&ctx.db, created).await?;
+    response.committed = true;
+
+    Ok((StatusCode::CREATED, Json(response)).into_response())
+}
+
 /// Get task by its ID
 ///
-/// Attempts to get a [`Task`] by its ID from the database
+/// Attempts to get a [`Task`] by its ID from the database.
+/// With `subtree=true` the response is a [`TaskTreeResponse`] holding
+/// every nested subtask and the aggregate progress of the subtree.
+/// A single task carries its version as `ETag`, and `If-None-Match` with
+/// the current version answers 304.
 #[debug_handler]
 #[utoipa::path(
     tag = TASK_TAG,
     get,
     path = "/{id}",
-    params(("id" = i32, Path, description = "Task ID")),
+    params(
+        ("id" = i32, Path, description = "Task ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of the version the client holds"),
+        TaskView
+    ),
     security(("token" = [])),
     responses(
-        (status = 200, body = TaskResponse, description = "Successful task retrieval"),
+        (status = 200, body = TaskTreeResponse, description = "Successful task retrieval"),
+        (status = 304, description = "Task has not changed"),
         (status = 401, body = ErrorResponse, description = "Authentication failure"),
         (status = 403, body = ErrorResponse, description = "Authorisation failure"),
         (status = 500, body = ErrorResponse, description = "Internal server errors")
@@ -101,27 +351,60 @@ async fn one(
     State(ctx): State<Arc<AppState>>,
     Extension(auth): Extension<AuthClaims>,
     Path(id): Path<i32>,
+    Query(view): Query<TaskView>,
+    headers: HeaderMap,
 ) -> Result<Response> {
     let task = Task::find_by_id(&ctx.db, auth.pid(), id).await?;
 
-    Ok((StatusCode::OK, Json(TaskResponse::from(task))).into_response())
+    if !view.subtree {
+        let etag = etag(task.version);
+        if version_match(&headers, header::IF_NONE_MATCH)
+            .is_some_and(|if_none_match| if_none_match.matches_weak(task.version))
+        {
+            return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+        }
+
+        let task = Task::response(&ctx.db, task).await?;
+        return Ok((StatusCode::OK, [(header::ETAG, etag)], Json(task)).into_response());
+    }
+
+    let nodes = Task::subtree(&ctx.db, task.id)
+        .await?
+        .into_iter()
+        .map(|node| node.task)
+        .collect::<Vec<Task>>();
+    let mut tasks = Task::responses(&ctx.db, nodes).await?.into_iter();
+    let root = tasks.next().ok_or_else(|| ModelError::EntityNotFound)?;
+
+    let tree = TaskTreeResponse::build(root, tasks.collect());
+
+    Ok((StatusCode::OK, Json(tree)).into_response())
 }
 
 /// Delete a task
 ///
-/// Attempts to delete  a [`Task`] by its ID from the database
-/// Only creator(`User`) of the task can delete it.
+/// Moves a [`Task`] and its subtasks to the trash, from where they can be
+/// restored until the trash is purged. `permanent=true` deletes for good.
+/// Requires owner permission on the task.
+/// A task with subtasks is only deleted when `cascade=true`.
+/// With `If-Match` the task is only deleted while still at that version.
 #[debug_handler]
 #[utoipa::path(
     tag = TASK_TAG,
     delete,
     path = "/{id}",
-    params(("id" = i32, Path, description = "Task ID")),
+    params(
+        ("id" = i32, Path, description = "Task ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the task must still have"),
+        DeleteOptions
+    ),
     security(("token" = [])),
     responses(
         (status = 204, description = "Successful task deletion"),
         (status = 401, body = ErrorResponse, description = "Authentication failure"),
         (status = 403, body = ErrorResponse, description = "Authorisation failure"),
+        (status = 409, body = ErrorResponse, description = "Task has subtasks and cascade was not requested"),
+        (status = 412, body = ErrorResponse, description = "Task changed since the given version"),
         (status = 500, body = ErrorResponse, description = "Internal server errors")
     )
 )]
@@ -129,27 +412,42 @@ async fn remove(
     State(ctx): State<Arc<AppState>>,
     Extension(auth): Extension<AuthClaims>,
     Path(id): Path<i32>,
+    Query(mut options): Query<DeleteOptions>,
+    headers: HeaderMap,
 ) -> Result<Response> {
-    let query = Task::delete_by_id(&ctx.db, id, auth.pid()).await?;
+    options.if_match = version_match(&headers, header::IF_MATCH);
+    let query = Task::delete_by_id(&ctx.db, ctx.blobs.as_ref(), id, auth.pid(), &options).await?;
     tracing::info!("Deleted rows {}", query.rows_affected());
-    Ok((StatusCode::NO_CONTENT, Json({})).into_response())
+    Ok(StatusCode::NO_CONTENT.into_response())
 }
 
 /// Update a task
 ///
-/// Attempts to update  a [`Task`] by its ID inside the database
-/// Only creator (`User`) of the task can update it.
+/// Applies a JSON Merge Patch to a [`Task`]: fields left out stay as they
+/// are and `null` clears a nullable field, while `null` for the title or
+/// done state is rejected.
+/// Requires editor permission on the task.
+/// Completing a task with unfinished blockers requires `force=true`.
+/// With `If-Match` the task is only updated while still at that version.
 #[debug_handler]
 #[utoipa::path(
     tag = TASK_TAG,
     patch,
     path = "/{id}",
-    params(("id" = i32, Path, description = "Task ID")),
+    params(
+        ("id" = i32, Path, description = "Task ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the task must still have"),
+        UpdateOptions
+    ),
     security(("token" = [])),
+    request_body(content = UpdateTask, content_type = "application/merge-patch+json", description = "Fields to change"),
     responses(
-        (status = 201, body= TaskResponse , description = "Successful task update"),
+        (status = 200, body = TaskResponse, description = "Successful task update"),
         (status = 401, body = ErrorResponse, description = "Authentication failure"),
         (status = 403, body = ErrorResponse, description = "Authorisation failure"),
+        (status = 409, body = ErrorResponse, description = "Task has unfinished blockers"),
+        (status = 412, body = ErrorResponse, description = "Task changed since the given version"),
+        (status = 422, body = ErrorResponse, description = "Validation errors, such as `null` for the title"),
         (status = 500, body = ErrorResponse, description = "Internal server errors")
     )
 )]
@@ -157,19 +455,570 @@ async fn update(
     State(ctx): State<Arc<AppState>>,
     Extension(auth): Extension<AuthClaims>,
     Path(id): Path<i32>,
+    Query(mut options): Query<UpdateOptions>,
+    headers: HeaderMap,
     Json(params): Json<UpdateTask>,
 ) -> Result<Response> {
-    let task = Task::update_by_id(&ctx.db, &params, id, auth.pid()).await?;
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    options.if_match = version_match(&headers, header::IF_MATCH);
+    let task = Task::update_by_id(&ctx.db, dto, id, auth.pid(), &options).await?;
+    let etag = etag(task.version);
+    let task = Task::response(&ctx.db, task).await?;
+
+    Ok((StatusCode::OK, [(header::ETAG, etag)], Json(task)).into_response())
+}
+
+/// Replace a task
+///
+/// Overwrites the title, done state and due date of a [`Task`], clearing the
+/// due date when it is left out. The project, parent, recurrence and
+/// assignee are not replaced, they are changed through their own endpoints.
+/// Requires editor permission on the task.
+/// Completing a task with unfinished blockers requires `force=true`.
+/// With `If-Match` the task is only replaced while still at that version.
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    put,
+    path = "/{id}",
+    params(
+        ("id" = i32, Path, description = "Task ID"),
+        ("If-Match" = Option<String>, Header, description = "ETag the task must still have"),
+        UpdateOptions
+    ),
+    security(("token" = [])),
+    request_body(content = ReplaceTask, content_type = "application/json", description = "New state of the task"),
+    responses(
+        (status = 200, body = TaskResponse, description = "Successful task replacement"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 403, body = ErrorResponse, description = "Authorisation failure"),
+        (status = 409, body = ErrorResponse, description = "Task has unfinished blockers"),
+        (status = 412, body = ErrorResponse, description = "Task changed since the given version"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn replace(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Query(mut options): Query<UpdateOptions>,
+    headers: HeaderMap,
+    Json(params): Json<ReplaceTask>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    options.if_match = version_match(&headers, header::IF_MATCH);
+    let params = UpdateTask::from(dto.clone());
+    let task = Task::update_by_id(&ctx.db, &params, id, auth.pid(), &options).await?;
+    let etag = etag(task.version);
+    let task = Task::response(&ctx.db, task).await?;
+
+    Ok((StatusCode::OK, [(header::ETAG, etag)], Json(task)).into_response())
+}
+
+/// Move or reorder a task
+///
+/// Moves a [`Task`] to another project and/or position in one atomic step,
+/// renumbering the affected lists so positions stay without gaps.
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    post,
+    path = "/{id}/move",
+    params(("id" = i32, Path, description = "Task ID")),
+    security(("token" = [])),
+    request_body(content = MoveTask, content_type = "application/json", description = "Destination project and position"),
+    responses(
+        (status = 200, body = TaskResponse, description = "Successful task move"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Task or project not found"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn move_task(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Json(params): Json<MoveTask>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    let task = Task::move_task(&ctx.db, dto, id, auth.pid()).await?;
+    let task = Task::response(&ctx.db, task).await?;
+
+    Ok((StatusCode::OK, Json(task)).into_response())
+}
+
+/// Set the parent of a task
+///
+/// Nests a [`Task`] under another task as a subtask, or makes it a top level
+/// task again when `parentId` is `null`.
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    put,
+    path = "/{id}/parent",
+    params(("id" = i32, Path, description = "Task ID")),
+    security(("token" = [])),
+    request_body(content = SetParent, content_type = "application/json", description = "New parent task"),
+    responses(
+        (status = 200, body = TaskResponse, description = "Successful parent change"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Task or parent not found"),
+        (status = 422, body = ErrorResponse, description = "Parent would create a cycle or nest too deep"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn set_parent(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Json(params): Json<SetParent>,
+) -> Result<Response> {
+    let task = Task::set_parent(&ctx.db, id, params.parent_id, auth.pid()).await?;
+    let task = Task::response(&ctx.db, task).await?;
+
+    Ok((StatusCode::OK, Json(task)).into_response())
+}
+
+/// Set the recurrence of a task
+///
+/// Sets or replaces the recurrence rule of a [`Task`]. Completing a recurring
+/// task creates the next task of the series. A `null` rule ends the series.
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    put,
+    path = "/{id}/recurrence",
+    params(("id" = i32, Path, description = "Task ID")),
+    security(("token" = [])),
+    request_body(content = SetRecurrence, content_type = "application/json", description = "New recurrence rule"),
+    responses(
+        (status = 200, body = TaskResponse, description = "Successful recurrence change"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Task not found"),
+        (status = 422, body = ErrorResponse, description = "Recurrence rule is not valid"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn set_recurrence(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Json(params): Json<SetRecurrence>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    let rule = dto
+        .rule
+        .as_deref()
+        .map(str::parse::<Recurrence>)
+        .transpose()
+        .map_err(ModelError::from)?;
+
+    let task = Task::set_recurrence(&ctx.db, id, rule.as_ref(), auth.pid()).await?;
+    let task = Task::response(&ctx.db, task).await?;
+
+    Ok((StatusCode::OK, Json(task)).into_response())
+}
+
+/// Assign a task
+///
+/// Makes a user responsible for a [`Task`], or unassigns it when `username`
+/// is `null`. The assignee must already have access to the task and is
+/// notified of the assignment.
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    put,
+    path = "/{id}/assignee",
+    params(("id" = i32, Path, description = "Task ID")),
+    security(("token" = [])),
+    request_body(content = SetAssignee, content_type = "application/json", description = "New assignee"),
+    responses(
+        (status = 200, body = TaskResponse, description = "Successful assignment"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 403, body = ErrorResponse, description = "Authorisation failure"),
+        (status = 404, body = ErrorResponse, description = "Task or user not found"),
+        (status = 422, body = ErrorResponse, description = "Assignee cannot access the task"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn set_assignee(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Json(params): Json<SetAssignee>,
+) -> Result<Response> {
+    let (task, _) = Task::assign(&ctx.db, id, params.username.as_deref(), auth.pid()).await?;
+
+    let task = Task::response(&ctx.db, task).await?;
+
+    Ok((StatusCode::OK, Json(task)).into_response())
+}
+
+/// Add a dependency to a task
+///
+/// Records that the task `blockedBy` blocks this [`Task`]. Edges that would
+/// create a cycle are rejected.
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    post,
+    path = "/{id}/dependencies",
+    params(("id" = i32, Path, description = "ID of the blocked task")),
+    security(("token" = [])),
+    request_body(content = AddDependency, content_type = "application/json", description = "Blocking task"),
+    responses(
+        (status = 200, body = TaskResponse, description = "Dependency added"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Task not found"),
+        (status = 422, body = ErrorResponse, description = "Dependency would create a cycle"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn add_dependency(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Json(params): Json<AddDependency>,
+) -> Result<Response> {
+    TaskDependency::add(&ctx.db, id, params.blocked_by, auth.pid()).await?;
+
+    let task = Task::find_by_id(&ctx.db, auth.pid(), id).await?;
+    let task = Task::response(&ctx.db, task).await?;
+
+    Ok((StatusCode::OK, Json(task)).into_response())
+}
+
+/// Remove a dependency from a task
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    delete,
+    path = "/{id}/dependencies/{blocker_id}",
+    params(
+        ("id" = i32, Path, description = "ID of the blocked task"),
+        ("blocker_id" = i32, Path, description = "ID of the blocking task")
+    ),
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Dependency removed"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Dependency not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn remove_dependency(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path((id, blocker_id)): Path<(i32, i32)>,
+) -> Result<Response> {
+    TaskDependency::remove(&ctx.db, id, blocker_id, auth.pid()).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Attach a label to a task
+///
+/// Attaches one of the user's [`Label`]s to a [`Task`] they own
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    put,
+    path = "/{id}/labels/{label_id}",
+    params(
+        ("id" = i32, Path, description = "Task ID"),
+        ("label_id" = i32, Path, description = "Label ID")
+    ),
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Label attached"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Task or label not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn attach_label(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path((id, label_id)): Path<(i32, i32)>,
+) -> Result<Response> {
+    Label::attach(&ctx.db, id, label_id, auth.pid()).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Detach a label from a task
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    delete,
+    path = "/{id}/labels/{label_id}",
+    params(
+        ("id" = i32, Path, description = "Task ID"),
+        ("label_id" = i32, Path, description = "Label ID")
+    ),
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Label detached"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Label is not attached to the task"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn detach_label(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path((id, label_id)): Path<(i32, i32)>,
+) -> Result<Response> {
+    Label::detach(&ctx.db, id, label_id, auth.pid()).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Get the tasks shared with me
+///
+/// Lists the [`Task`]s other users shared with the current user
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    get,
+    path = "/shared",
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<TaskResponse>, description = "Successful shared tasks retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn shared(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+) -> Result<Response> {
+    let tasks = Task::find_shared(&ctx.db, auth.pid()).await?;
+    let tasks = Task::responses(&ctx.db, tasks).await?;
+
+    Ok((StatusCode::OK, Json(tasks)).into_response())
+}
+
+/// Get the trash
+///
+/// Lists the current user's deleted [`Task`]s, most recently deleted first
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    get,
+    path = "/trash",
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<TaskResponse>, description = "Successful trash retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn trash(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+) -> Result<Response> {
+    let tasks = Task::find_trashed(&ctx.db, auth.pid()).await?;
+    let tasks = Task::responses(&ctx.db, tasks).await?;
+
+    Ok((StatusCode::OK, Json(tasks)).into_response())
+}
+
+/// Restore a task
+///
+/// Brings a deleted [`Task`] back from the trash together with the subtasks
+/// deleted with it. Restored tasks are placed at the end of their list and
+/// lose their parent if it is no longer there.
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    post,
+    path = "/{id}/restore",
+    params(("id" = i32, Path, description = "Task ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = TaskResponse, description = "Successful task restore"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Task is not in the trash"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn restore(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let task = Task::restore(&ctx.db, id, auth.pid()).await?;
+    let task = Task::response(&ctx.db, task).await?;
+
+    Ok((StatusCode::OK, Json(task)).into_response())
+}
+
+/// Get the history of a task
+///
+/// Lists every change made to a [`Task`], oldest first, with who made it and
+/// the fields it changed
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    get,
+    path = "/{id}/history",
+    params(("id" = i32, Path, description = "Task ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<HistoryResponse>, description = "Successful history retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Task not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn history(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let history = TaskHistory::find_all(&ctx.db, id, auth.pid())
+        .await?
+        .into_iter()
+        .map(HistoryResponse::from)
+        .collect::<Vec<HistoryResponse>>();
+
+    Ok((StatusCode::OK, Json(history)).into_response())
+}
+
+/// Get the shares of a task
+///
+/// Lists everyone the [`Task`] is shared with and their permission
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    get,
+    path = "/{id}/shares",
+    params(("id" = i32, Path, description = "Task ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<ShareResponse>, description = "Successful shares retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Task not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn shares(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let shares = Share::find_all(&ctx.db, ShareTarget::Task(id), auth.pid())
+        .await?
+        .into_iter()
+        .map(ShareResponse::from)
+        .collect::<Vec<ShareResponse>>();
+
+    Ok((StatusCode::OK, Json(shares)).into_response())
+}
+
+/// Share a task
+///
+/// Shares a [`Task`] with another user as a `viewer`, `editor` or `owner`,
+/// or changes the permission of an existing share. Requires owner permission.
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    post,
+    path = "/{id}/shares",
+    params(("id" = i32, Path, description = "Task ID")),
+    security(("token" = [])),
+    request_body(content = NewShare, content_type = "application/json", description = "User to share with and their permission"),
+    responses(
+        (status = 200, body = ShareResponse, description = "Successful share"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 403, body = ErrorResponse, description = "Only owners can share"),
+        (status = 404, body = ErrorResponse, description = "Task or user not found"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn share(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Json(params): Json<NewShare>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    let share = Share::grant(&ctx.db, ShareTarget::Task(id), dto, auth.pid()).await?;
+
+    Ok((StatusCode::OK, Json(ShareResponse::from(share))).into_response())
+}
+
+/// Stop sharing a task
+///
+/// Owners can remove anyone's share, other users only their own
+#[debug_handler]
+#[utoipa::path(
+    tag = TASK_TAG,
+    delete,
+    path = "/{id}/shares/{username}",
+    params(
+        ("id" = i32, Path, description = "Task ID"),
+        ("username" = String, Path, description = "Username of the user to remove")
+    ),
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Share removed"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 403, body = ErrorResponse, description = "Only owners can remove other users"),
+        (status = 404, body = ErrorResponse, description = "Share not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn unshare(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path((id, username)): Path<(i32, String)>,
+) -> Result<Response> {
+    Share::revoke(&ctx.db, ShareTarget::Task(id), &username, auth.pid()).await?;
 
-    Ok((StatusCode::CREATED, Json(TaskResponse::from(task))).into_response())
+    Ok(StatusCode::NO_CONTENT.into_response())
 }
 
 pub fn task_routes(ctx: &AppState) -> OpenApiRouter {
     OpenApiRouter::new()
-        .routes(routes!(add))
+        .routes(routes!(add).layer(IdempotencyLayer::new(ctx)))
+        .routes(routes!(bulk))
         .routes(routes!(all))
+        .routes(routes!(export))
+        .routes(routes!(import))
+        .routes(routes!(shared))
+        .routes(routes!(trash))
         .routes(routes!(one))
+        .routes(routes!(restore))
+        .routes(routes!(history))
         .routes(routes!(remove))
         .routes(routes!(update))
+        .routes(routes!(replace))
+        .routes(routes!(move_task))
+        .routes(routes!(set_parent))
+        .routes(routes!(set_recurrence))
+        .routes(routes!(set_assignee))
+        .routes(routes!(add_dependency))
+        .routes(routes!(remove_dependency))
+        .routes(routes!(attach_label))
+        .routes(routes!(detach_label))
+        .routes(routes!(shares))
+        .routes(routes!(share))
+        .routes(routes!(unshare))
         .with_state(Arc::new(ctx.clone()))
+        .merge(comments::comment_routes(ctx))
+        .merge(attachments::attachment_routes(ctx))
 }
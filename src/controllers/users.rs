@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    body::Body,
+    debug_handler,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+use uuid::Uuid;
+
+use crate::{
+    AppState, Result,
+    errors::response::ErrorResponse,
+    jobs::{self, EraseUser},
+    middlewares::auth::AuthClaims,
+    models::{
+        Validator,
+        account::{AccountExportResponse, EraseAccount},
+        inbound::InboundAddressResponse,
+    },
+    repositories::{account_exports::AccountExport, inbound::InboundAddress, users::User},
+};
+
+const USER_TAG: &str = "Users";
+
+/// Export account data
+///
+/// Queues an archive of everything stored about the current user: their
+/// profile, tasks, projects, labels, comments, uploaded files and history.
+/// Poll the returned export until it is `ready`, then download it.
+#[debug_handler]
+#[utoipa::path(
+    tag = USER_TAG,
+    post,
+    path = "/me/export",
+    security(("token" = [])),
+    responses(
+        (status = 202, body = AccountExportResponse, description = "Export queued"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn export(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+) -> Result<Response> {
+    let export = AccountExport::queue(&ctx.db, auth.pid()).await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(AccountExportResponse::from(export)),
+    )
+        .into_response())
+}
+
+/// Get an account data export
+///
+/// Returns the status of one of the current user's exports
+#[debug_handler]
+#[utoipa::path(
+    tag = USER_TAG,
+    get,
+    path = "/me/exports/{id}",
+    params(("id" = String, Path, description = "Export ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = AccountExportResponse, description = "Successful export retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Export not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn export_status(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<Uuid>,
+) -> Result<Response> {
+    let export = AccountExport::find_by_pid(&ctx.db, id, auth.pid()).await?;
+
+    Ok((StatusCode::OK, Json(AccountExportResponse::from(export))).into_response())
+}
+
+/// Download an account data export
+///
+/// Streams the zip archive of a ready export until it expires
+#[debug_handler]
+#[utoipa::path(
+    tag = USER_TAG,
+    get,
+    path = "/me/exports/{id}/download",
+    params(("id" = String, Path, description = "Export ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<u8>, content_type = "application/zip", description = "Zip archive"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Export not found, not ready or expired"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn export_download(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<Uuid>,
+) -> Result<Response> {
+    let (export, stream) =
+        AccountExport::download(&ctx.db, ctx.blobs.as_ref(), id, auth.pid()).await?;
+
+    let headers = [
+        (header::CONTENT_TYPE, "application/zip".to_string()),
+        (
+            header::CONTENT_LENGTH,
+            export.size.unwrap_or_default().to_string(),
+        ),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"account-{}.zip\"", export.pid),
+        ),
+    ];
+
+    Ok((StatusCode::OK, headers, Body::from_stream(stream)).into_response())
+}
+
+/// Erase the account
+///
+/// Checks the current user's password, then erases the account in the
+/// background together with everything keyed by it. Changes the user made
+/// to other users' tasks stay in their history, anonymised.
+#[debug_handler]
+#[utoipa::path(
+    tag = USER_TAG,
+    delete,
+    path = "/me",
+    security(("token" = [])),
+    request_body(content = EraseAccount, content_type = "application/json", description = "Password of the current user"),
+    responses(
+        (status = 202, description = "Erasure queued"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure or wrong password"),
+        (status = 404, body = ErrorResponse, description = "User not found"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn erase(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Json(params): Json<EraseAccount>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+
+    let user = User::find_by_pid(&ctx.db, auth.pid()).await?;
+    user.verify_password(&dto.password)?;
+
+    jobs::enqueue(&ctx.db, &EraseUser { user_pid: user.pid }).await?;
+
+    Ok(StatusCode::ACCEPTED.into_response())
+}
+
+/// Get the inbound address
+///
+/// Returns the secret address the current user can forward emails to. Each
+/// email becomes a task: the subject is its title, `Due:` and `Repeat:`
+/// lines at the top of the body set the due date and recurrence rule, the
+/// rest of the body is added as a comment and attached files are kept.
+#[debug_handler]
+#[utoipa::path(
+    tag = USER_TAG,
+    get,
+    path = "/me/inbound-address",
+    security(("token" = [])),
+    responses(
+        (status = 200, body = InboundAddressResponse, description = "Successful retrieval of the address"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn inbound_address(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+) -> Result<Response> {
+    let address = InboundAddress::find_or_create(&ctx.db, auth.pid()).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(InboundAddressResponse::new(&address, ctx.config.inbound())),
+    )
+        .into_response())
+}
+
+/// Replace the inbound address
+///
+/// Gives the current user a new inbound address. Mail sent to the old one
+/// is refused from then on.
+#[debug_handler]
+#[utoipa::path(
+    tag = USER_TAG,
+    post,
+    path = "/me/inbound-address/rotate",
+    security(("token" = [])),
+    responses(
+        (status = 200, body = InboundAddressResponse, description = "Successful replacement of the address"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn rotate_inbound_address(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+) -> Result<Response> {
+    let address = InboundAddress::rotate(&ctx.db, auth.pid()).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(InboundAddressResponse::new(&address, ctx.config.inbound())),
+    )
+        .into_response())
+}
+
+pub fn user_routes(ctx: &AppState) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(export))
+        .routes(routes!(export_status))
+        .routes(routes!(export_download))
+        .routes(routes!(erase))
+        .routes(routes!(inbound_address))
+        .routes(routes!(rotate_inbound_address))
+        .with_state(Arc::new(ctx.clone()))
+}
@@ -0,0 +1,262 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, debug_handler,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    AppState, Result,
+    errors::response::ErrorResponse,
+    middlewares::auth::AuthClaims,
+    models::{
+        Validator,
+        webhooks::{
+            DeliveryQuery, DeliveryResponse, EndpointResolver, NewWebhook, UpdateWebhook,
+            WebhookResponse,
+        },
+    },
+    repositories::webhooks::{Webhook, WebhookDelivery},
+};
+
+const WEBHOOK_TAG: &str = "Webhooks";
+
+/// Register a webhook
+///
+/// Registers an endpoint the current user's task events are sent to. Every
+/// delivery is a JSON `POST` signed with the returned secret, which is not
+/// shown again: the `X-Webhook-Signature` header holds `sha256=` and the hex
+/// HMAC-SHA256 of `{X-Webhook-Timestamp}.{body}`. Endpoints on private,
+/// loopback or link-local addresses are refused.
+#[debug_handler]
+#[utoipa::path(
+    tag = WEBHOOK_TAG,
+    post,
+    path = "/",
+    security(("token" = [])),
+    request_body(content = NewWebhook, content_type = "application/json", description = "Endpoint and events to deliver"),
+    responses(
+        (status = 201, body = WebhookResponse, description = "Successful webhook registration"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn add(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Json(params): Json<NewWebhook>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+    EndpointResolver::new(ctx.config.webhooks())
+        .check(&dto.url)
+        .await?;
+
+    let webhook = Webhook::create(&ctx.db, dto, auth.pid()).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(WebhookResponse::with_secret(webhook)),
+    )
+        .into_response())
+}
+
+/// Get list of webhooks
+///
+/// Attempts to get the [`Webhook`]s of the current user
+#[debug_handler]
+#[utoipa::path(
+    tag = WEBHOOK_TAG,
+    get,
+    path = "/",
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<WebhookResponse>, description = "Successful webhooks retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn all(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+) -> Result<Response> {
+    let webhooks = Webhook::find_all(&ctx.db, auth.pid())
+        .await?
+        .into_iter()
+        .map(WebhookResponse::from)
+        .collect::<Vec<WebhookResponse>>();
+
+    Ok((StatusCode::OK, Json(webhooks)).into_response())
+}
+
+/// Get a webhook
+///
+/// Attempts to get a [`Webhook`] by its ID
+#[debug_handler]
+#[utoipa::path(
+    tag = WEBHOOK_TAG,
+    get,
+    path = "/{id}",
+    params(("id" = i32, Path, description = "Webhook ID")),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = WebhookResponse, description = "Successful webhook retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Webhook not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn one(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    let webhook = Webhook::find_by_id(&ctx.db, auth.pid(), id).await?;
+
+    Ok((StatusCode::OK, Json(WebhookResponse::from(webhook))).into_response())
+}
+
+/// Update a webhook
+///
+/// Attempts to change the URL or events of a [`Webhook`], or to pause it
+#[debug_handler]
+#[utoipa::path(
+    tag = WEBHOOK_TAG,
+    patch,
+    path = "/{id}",
+    params(("id" = i32, Path, description = "Webhook ID")),
+    security(("token" = [])),
+    request_body(content = UpdateWebhook, content_type = "application/json", description = "Fields to change"),
+    responses(
+        (status = 200, body = WebhookResponse, description = "Successful webhook update"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Webhook not found"),
+        (status = 422, body = ErrorResponse, description = "Validation error on request body"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn update(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Json(params): Json<UpdateWebhook>,
+) -> Result<Response> {
+    let validator = Validator::new(params);
+    let dto = validator.validate()?;
+    if let Some(url) = &dto.url {
+        EndpointResolver::new(ctx.config.webhooks())
+            .check(url)
+            .await?;
+    }
+
+    let webhook = Webhook::update_by_id(&ctx.db, dto, id, auth.pid()).await?;
+
+    Ok((StatusCode::OK, Json(WebhookResponse::from(webhook))).into_response())
+}
+
+/// Delete a webhook
+///
+/// Attempts to delete a [`Webhook`] by its ID, together with its deliveries
+#[debug_handler]
+#[utoipa::path(
+    tag = WEBHOOK_TAG,
+    delete,
+    path = "/{id}",
+    params(("id" = i32, Path, description = "Webhook ID")),
+    security(("token" = [])),
+    responses(
+        (status = 204, description = "Successful webhook deletion"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Webhook not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn remove(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+) -> Result<Response> {
+    Webhook::delete_by_id(&ctx.db, id, auth.pid()).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Get the delivery log of a webhook
+///
+/// Lists the deliveries of a [`Webhook`], newest first, with the outcome of
+/// their last attempt. Page through older ones with `before`.
+#[debug_handler]
+#[utoipa::path(
+    tag = WEBHOOK_TAG,
+    get,
+    path = "/{id}/deliveries",
+    params(("id" = i32, Path, description = "Webhook ID"), DeliveryQuery),
+    security(("token" = [])),
+    responses(
+        (status = 200, body = Vec<DeliveryResponse>, description = "Successful deliveries retrieval"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Webhook not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn deliveries(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path(id): Path<i32>,
+    Query(query): Query<DeliveryQuery>,
+) -> Result<Response> {
+    let deliveries = WebhookDelivery::find_for_webhook(&ctx.db, id, auth.pid(), &query)
+        .await?
+        .into_iter()
+        .map(DeliveryResponse::from)
+        .collect::<Vec<DeliveryResponse>>();
+
+    Ok((StatusCode::OK, Json(deliveries)).into_response())
+}
+
+/// Redeliver an event
+///
+/// Queues a delivery again with a fresh set of attempts, typically one that
+/// was dead-lettered after every attempt failed
+#[debug_handler]
+#[utoipa::path(
+    tag = WEBHOOK_TAG,
+    post,
+    path = "/{id}/deliveries/{delivery_id}/redeliver",
+    params(
+        ("id" = i32, Path, description = "Webhook ID"),
+        ("delivery_id" = i64, Path, description = "Delivery ID")
+    ),
+    security(("token" = [])),
+    responses(
+        (status = 202, body = DeliveryResponse, description = "Delivery queued"),
+        (status = 401, body = ErrorResponse, description = "Authentication failure"),
+        (status = 404, body = ErrorResponse, description = "Webhook or delivery not found"),
+        (status = 500, body = ErrorResponse, description = "Internal server errors")
+    )
+)]
+async fn redeliver(
+    State(ctx): State<Arc<AppState>>,
+    Extension(auth): Extension<AuthClaims>,
+    Path((id, delivery_id)): Path<(i32, i64)>,
+) -> Result<Response> {
+    let delivery = WebhookDelivery::redeliver(&ctx.db, id, delivery_id, auth.pid()).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(DeliveryResponse::from(delivery))).into_response())
+}
+
+pub fn webhook_routes(ctx: &AppState) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(add))
+        .routes(routes!(all))
+        .routes(routes!(one))
+        .routes(routes!(update))
+        .routes(routes!(remove))
+        .routes(routes!(deliveries))
+        .routes(routes!(redeliver))
+        .with_state(Arc::new(ctx.clone()))
+}
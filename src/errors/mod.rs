@@ -17,14 +17,20 @@ pub enum Error {
     #[error(transparent)]
     Config(#[from] config::ConfigError),
     #[error(transparent)]
+    Cron(#[from] cron::error::Error),
+    #[error(transparent)]
     Env(#[from] FromEnvError),
     #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error(transparent)]
     IO(#[from] std::io::Error),
     #[error(transparent)]
     JsonWebToken(#[from] jsonwebtoken::errors::Error),
     #[error(transparent)]
     Model(#[from] ModelError),
     #[error(transparent)]
+    Multipart(#[from] axum::extract::multipart::MultipartError),
+    #[error(transparent)]
     Parse(#[from] tracing_subscriber::filter::ParseError),
     #[error(transparent)]
     Sqlx(#[from] sqlx::Error),
@@ -16,7 +16,9 @@ impl Error {
             | Self::AxumHttp(_)
             | Self::ColorEyre(_)
             | Self::Config(_)
+            | Self::Cron(_)
             | Self::Env(_)
+            | Self::Http(_)
             | Self::IO(_)
             | Self::JsonWebToken(_)
             | Self::Parse(_)
@@ -27,6 +29,7 @@ impl Error {
                 "Something went wrong on our end.",
             ),
             Self::Validation(e) => (StatusCode::UNPROCESSABLE_ENTITY, e.as_str()),
+            Self::Multipart(e) => (e.status(), "Upload could not be read"),
             Self::Model(e) => return e.response(),
         };
 
@@ -0,0 +1,244 @@
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, postgres::PgListener};
+use tokio::{sync::broadcast, task::JoinHandle};
+use uuid::Uuid;
+
+use crate::{config::OutboxConfig, repositories::outbox::OutboxEvent};
+
+/// How many events a slow subscriber may fall behind before it starts
+/// missing them.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Postgres channel the outbox relay publishes events on, so that every
+/// instance of the service hears about changes made through any of them.
+pub const EVENTS_CHANNEL: &str = "events";
+
+/// Postgres channel the outbox relay is woken on when events are recorded.
+pub const OUTBOX_CHANNEL: &str = "outbox_events";
+
+/// Pause before listening again after the connection to Postgres failed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Something that happened to a task or a user which other parts of the
+/// service, such as notifications, react to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Event {
+    /// `assignee_pid` was made responsible for a task by `assigned_by`.
+    TaskAssigned {
+        task_id: i32,
+        assignee_pid: Uuid,
+        assigned_by: Uuid,
+    },
+    /// `user_pid` was mentioned in a comment on a task.
+    Mentioned {
+        task_id: i32,
+        comment_id: i32,
+        user_pid: Uuid,
+        mentioned_by: Uuid,
+    },
+    /// A task was created, or restored from the trash. `audience` are the
+    /// users who can see it.
+    TaskCreated { task_id: i32, audience: Vec<Uuid> },
+    /// A task was changed.
+    TaskUpdated { task_id: i32, audience: Vec<Uuid> },
+    /// A task was moved to the trash or deleted for good.
+    TaskDeleted { task_id: i32, audience: Vec<Uuid> },
+    /// A task was shared with `user_pid` by `shared_by`.
+    TaskShared {
+        task_id: i32,
+        user_pid: Uuid,
+        shared_by: Uuid,
+    },
+    /// A project was shared with `user_pid` by `shared_by`.
+    ProjectShared {
+        project_id: i32,
+        user_pid: Uuid,
+        shared_by: Uuid,
+    },
+    /// A task `user_pid` is responsible for falls due soon.
+    TaskDue {
+        task_id: i32,
+        user_pid: Uuid,
+        due_at: DateTime<FixedOffset>,
+    },
+    /// An in-app notification landed in the inbox of `user_pid`.
+    Notified {
+        notification_id: i64,
+        user_pid: Uuid,
+        task_id: Option<i32>,
+    },
+    /// A user signed up.
+    UserCreated { user_pid: Uuid },
+    /// A user's account was erased.
+    UserDeleted { user_pid: Uuid },
+}
+
+impl Event {
+    /// Whether `user_pid` may be told about the event.
+    #[must_use]
+    pub fn concerns(&self, user_pid: Uuid) -> bool {
+        match self {
+            Self::TaskAssigned { assignee_pid, .. } => *assignee_pid == user_pid,
+            Self::Mentioned {
+                user_pid: mentioned,
+                ..
+            } => *mentioned == user_pid,
+            Self::TaskCreated { audience, .. }
+            | Self::TaskUpdated { audience, .. }
+            | Self::TaskDeleted { audience, .. } => audience.contains(&user_pid),
+            Self::TaskShared { user_pid: pid, .. }
+            | Self::ProjectShared { user_pid: pid, .. }
+            | Self::TaskDue { user_pid: pid, .. }
+            | Self::Notified { user_pid: pid, .. }
+            | Self::UserCreated { user_pid: pid }
+            | Self::UserDeleted { user_pid: pid } => *pid == user_pid,
+        }
+    }
+
+    /// Type and id of what the event is about. Events about the same thing
+    /// are published in the order they happened.
+    #[must_use]
+    pub fn aggregate(&self) -> (&'static str, String) {
+        match self {
+            Self::TaskAssigned { task_id, .. }
+            | Self::Mentioned { task_id, .. }
+            | Self::TaskCreated { task_id, .. }
+            | Self::TaskUpdated { task_id, .. }
+            | Self::TaskDeleted { task_id, .. }
+            | Self::TaskShared { task_id, .. }
+            | Self::TaskDue { task_id, .. } => ("task", task_id.to_string()),
+            Self::ProjectShared { project_id, .. } => ("project", project_id.to_string()),
+            Self::Notified { user_pid, .. }
+            | Self::UserCreated { user_pid }
+            | Self::UserDeleted { user_pid } => ("user", user_pid.to_string()),
+        }
+    }
+}
+
+/// An event as the outbox relay announces it on [`EVENTS_CHANNEL`]: its id
+/// in the outbox, to load it by, and its kind, such as `taskCreated`. The id
+/// also tells repeated notifications apart, which Postgres would otherwise
+/// merge within a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Envelope {
+    pub id: i64,
+    pub kind: String,
+}
+
+/// In-process publish/subscribe channel for [`Event`]s.
+///
+/// Publishing never blocks and never fails: events published while nobody
+/// is subscribed are dropped.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    #[must_use]
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: Event) {
+        tracing::debug!(?event, "Publishing event");
+        // Only fails when there are no subscribers
+        let _ = self.sender.send(event);
+    }
+
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+/// Relays the events published on [`EVENTS_CHANNEL`] by any instance of the
+/// service, this one included, to `bus`, loading each from the outbox.
+///
+/// Events sent while the connection is being re-established, or purged
+/// before they are loaded, are lost.
+///
+/// # Errors
+/// * Failing to connect or to listen on the channel
+pub async fn listen(db: &PgPool, bus: EventBus) -> Result<JoinHandle<()>, sqlx::Error> {
+    let mut listener = PgListener::connect_with(db).await?;
+    listener.listen(EVENTS_CHANNEL).await?;
+    let db = db.clone();
+
+    Ok(tokio::spawn(async move {
+        loop {
+            match listener.recv().await {
+                Ok(notification) => {
+                    let envelope = match serde_json::from_str::<Envelope>(notification.payload()) {
+                        Ok(envelope) => envelope,
+                        Err(e) => {
+                            tracing::warn!("Ignoring malformed event: {e}");
+                            continue;
+                        }
+                    };
+                    match OutboxEvent::find_event(&db, envelope.id).await {
+                        Ok(event) => bus.publish(event),
+                        Err(e) => tracing::warn!("Failed to load event {}: {e}", envelope.id),
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to receive events: {e}");
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    }))
+}
+
+/// Publishes the events recorded in the outbox, in order, as they are
+/// committed. Every instance may run a relay: they take turns, one batch at
+/// a time.
+///
+/// # Errors
+/// * Failing to connect or to listen for new events
+pub async fn relay(db: &PgPool, config: &OutboxConfig) -> Result<JoinHandle<()>, sqlx::Error> {
+    let mut listener = PgListener::connect_with(db).await?;
+    listener.listen(OUTBOX_CHANNEL).await?;
+    let db = db.clone();
+    let config = config.clone();
+    let limit = i64::from(config.batch_size);
+    let batch_size = usize::try_from(config.batch_size).unwrap_or(usize::MAX);
+    let poll_interval = config.poll_interval();
+
+    Ok(tokio::spawn(async move {
+        loop {
+            loop {
+                match OutboxEvent::relay(&db, limit, &config).await {
+                    Ok(relayed) if relayed >= batch_size => {}
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::error!("Failed to relay outbox events: {e}");
+                        break;
+                    }
+                }
+            }
+
+            // Also looks again after a while, for events another relay was
+            // busy with or that were recorded while reconnecting
+            if let Ok(Err(e)) = tokio::time::timeout(poll_interval, listener.recv()).await {
+                tracing::error!("Failed to wait for outbox events: {e}");
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }))
+}
@@ -0,0 +1,141 @@
+pub mod smtp;
+
+use std::sync::Arc;
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    AppConfig, Error,
+    config::{InboundConfig, StorageConfig},
+    models::{
+        comments::NewComment,
+        inbound::{self, InboundMail, recipient_token},
+    },
+    repositories::{
+        ModelError,
+        attachments::Attachment,
+        comments::Comment,
+        inbound::{InboundAddress, InboundMessage},
+        tasks::Task,
+    },
+    storage::{BlobStore, content_key},
+};
+
+/// Turns emails sent to the users' inbound addresses into tasks, for the
+/// SMTP listener and for messages piped in by hand alike.
+#[derive(Clone)]
+pub struct Inbound {
+    db: PgPool,
+    blobs: Arc<dyn BlobStore>,
+    storage: StorageConfig,
+    config: InboundConfig,
+}
+
+impl Inbound {
+    #[must_use]
+    pub fn new(db: PgPool, blobs: Arc<dyn BlobStore>, config: &AppConfig) -> Self {
+        Self {
+            db,
+            blobs,
+            storage: config.storage().clone(),
+            config: config.inbound().clone(),
+        }
+    }
+
+    #[must_use]
+    pub const fn config(&self) -> &InboundConfig {
+        &self.config
+    }
+
+    /// The user mail to `address` is for, `None` when it is not a current
+    /// inbound address.
+    pub async fn recipient(&self, address: &str) -> Result<Option<Uuid>, ModelError> {
+        let Some(token) = recipient_token(address, &self.config.domain) else {
+            return Ok(None);
+        };
+
+        match InboundAddress::find_by_token(&self.db, &token).await {
+            Ok(address) => Ok(Some(address.user_pid)),
+            Err(ModelError::EntityNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates the task `mail` describes for `user_pid`, adding its note as
+    /// a comment and attaching its files, all or nothing. Files the storage
+    /// limits do not allow are left out.
+    ///
+    /// Returns `None` for a message with a `Message-ID` that already became
+    /// a task of the user, so that the sender can retry a delivery that
+    /// failed for some of the recipients.
+    pub async fn deliver(&self, user_pid: Uuid, mail: &InboundMail) -> Result<Option<Task>, Error> {
+        let uploads = mail
+            .attachments
+            .iter()
+            .filter(|upload| {
+                let allowed = upload.data.len() <= self.storage.max_upload_size
+                    && self.storage.is_allowed(&upload.content_type);
+                if !allowed {
+                    tracing::warn!(
+                        "Left {} ({}) off a task emailed to {user_pid}",
+                        upload.filename,
+                        upload.content_type
+                    );
+                }
+                allowed
+            })
+            .collect::<Vec<_>>();
+
+        let mut txn = self.db.begin().await?;
+        if let Some(message_id) = &mail.message_id
+            && !InboundMessage::record(&mut txn, user_pid, message_id).await?
+        {
+            return Ok(None);
+        }
+
+        // Files are stored before any row refers to them, each under a blob
+        // lock taken in key order
+        let mut keys = uploads
+            .iter()
+            .map(|upload| content_key(&upload.data))
+            .collect::<Vec<String>>();
+        keys.sort_unstable();
+        keys.dedup();
+        for key in &keys {
+            Attachment::lock_blob(&mut *txn, key).await?;
+        }
+        let mut stored = Vec::with_capacity(uploads.len());
+        for upload in uploads {
+            let storage_key =
+                Attachment::store(&mut txn, self.blobs.as_ref(), &upload.data).await?;
+            stored.push((upload, storage_key));
+        }
+
+        let task = Task::create_in(&mut txn, &mail.task, user_pid).await?;
+        if let Some(body) = &mail.note {
+            let comment = NewComment { body: body.clone() };
+            Comment::create_in(&mut txn, task.id, &comment, user_pid).await?;
+        }
+        for (upload, storage_key) in stored {
+            Attachment::insert(&mut txn, task.id, upload.clone(), storage_key, user_pid).await?;
+        }
+
+        txn.commit().await?;
+
+        Ok(Some(task))
+    }
+
+    /// Turns the email `raw` into a task of the user `address` belongs to,
+    /// the same way as mail received over SMTP. `None` when the message
+    /// already was.
+    pub async fn ingest(&self, address: &str, raw: &[u8]) -> Result<Option<Task>, Error> {
+        let user_pid = self
+            .recipient(address)
+            .await?
+            .ok_or(ModelError::EntityNotFound)?;
+        let mail = inbound::parse(raw)?;
+
+        self.deliver(user_pid, &mail).await
+    }
+}
@@ -0,0 +1,256 @@
+use std::{io, sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::Semaphore,
+};
+use uuid::Uuid;
+
+use crate::{Error, models::inbound};
+
+use super::Inbound;
+
+/// Longest command line, RFC 5321 asks for at least 512 bytes.
+const MAX_LINE_LEN: usize = 1000;
+
+/// Most message bytes read in one go, longer lines are read in pieces.
+const DATA_CHUNK: usize = 64 * 1024;
+
+/// Most recipients of one message.
+const MAX_RECIPIENTS: usize = 100;
+
+/// How long a client may stay silent before it is disconnected.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Accepts SMTP connections on `listener` until the process stops. Only
+/// mail to a current inbound address is accepted, each message becomes a
+/// task of every recipient.
+///
+/// This speaks the part of RFC 5321 needed to receive mail relayed by
+/// another server, without TLS or authentication: knowing an address is
+/// what allows mailing tasks to it. Clients connecting while the configured
+/// number of sessions is served are asked to come back later.
+pub async fn serve(listener: TcpListener, inbound: Inbound) {
+    let sessions = Arc::new(Semaphore::new(inbound.config().max_sessions));
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                tracing::error!("Failed to accept SMTP connection: {e}");
+                continue;
+            }
+        };
+
+        let Ok(permit) = Arc::clone(&sessions).try_acquire_owned() else {
+            tracing::warn!("Turned away SMTP connection from {peer}, too many sessions");
+            // A new connection's send buffer is empty, this does not block
+            let _ = stream.try_write(b"421 4.3.2 Too many connections, try again later\r\n");
+            continue;
+        };
+
+        let inbound = inbound.clone();
+        tokio::spawn(async move {
+            if let Err(e) = session(stream, &inbound).await {
+                tracing::warn!("SMTP session with {peer} ended: {e}");
+            }
+            drop(permit);
+        });
+    }
+}
+
+async fn session(mut stream: TcpStream, inbound: &Inbound) -> io::Result<()> {
+    let domain = inbound.config().domain.clone();
+    let max_size = inbound.config().max_message_size;
+    let (reader, mut writer) = stream.split();
+    let mut reader = BufReader::new(reader);
+
+    let mut mail = false;
+    let mut recipients = Vec::<Uuid>::new();
+
+    reply(&mut writer, &format!("220 {domain} ESMTP ready")).await?;
+    while let Some(line) = read_line(&mut reader, MAX_LINE_LEN).await? {
+        if !line.ends_with(b"\n") {
+            return reply(&mut writer, "500 5.5.2 Line too long").await;
+        }
+
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end();
+        let (verb, args) = line.split_once(' ').unwrap_or((line, ""));
+
+        let response = match verb.to_ascii_uppercase().as_str() {
+            "HELO" => {
+                (mail, recipients) = (false, Vec::new());
+                format!("250 {domain}")
+            }
+            "EHLO" => {
+                (mail, recipients) = (false, Vec::new());
+                format!("250-{domain}\r\n250-8BITMIME\r\n250 SIZE {max_size}")
+            }
+            "MAIL" => match strip_keyword(args, "FROM:") {
+                None => "501 5.5.4 Expected MAIL FROM:<address>".into(),
+                Some(params) if declared_size(params).is_some_and(|size| size > max_size) => {
+                    "552 5.3.4 Message too big".into()
+                }
+                Some(_) => {
+                    (mail, recipients) = (true, Vec::new());
+                    "250 2.1.0 OK".into()
+                }
+            },
+            "RCPT" => match strip_keyword(args, "TO:") {
+                _ if !mail => "503 5.5.1 MAIL first".into(),
+                None => "501 5.5.4 Expected RCPT TO:<address>".into(),
+                Some(_) if recipients.len() >= MAX_RECIPIENTS => {
+                    "452 4.5.3 Too many recipients".into()
+                }
+                Some(params) => {
+                    let address = params.split_whitespace().next().unwrap_or_default();
+                    match inbound.recipient(address).await {
+                        Ok(Some(user_pid)) => {
+                            if !recipients.contains(&user_pid) {
+                                recipients.push(user_pid);
+                            }
+                            "250 2.1.5 OK".into()
+                        }
+                        Ok(None) => "550 5.1.1 No such mailbox".into(),
+                        Err(e) => {
+                            tracing::error!("Failed to look up inbound address {address}: {e}");
+                            "451 4.3.0 Try again later".into()
+                        }
+                    }
+                }
+            },
+            "DATA" if recipients.is_empty() => "503 5.5.1 RCPT first".into(),
+            "DATA" => {
+                reply(&mut writer, "354 End data with <CR><LF>.<CR><LF>").await?;
+                let response = match read_data(&mut reader, max_size).await? {
+                    Some(raw) => deliver(inbound, &recipients, &raw).await,
+                    None => "552 5.3.4 Message too big".into(),
+                };
+                (mail, recipients) = (false, Vec::new());
+                response
+            }
+            "RSET" => {
+                (mail, recipients) = (false, Vec::new());
+                "250 2.0.0 OK".into()
+            }
+            "NOOP" => "250 2.0.0 OK".into(),
+            "VRFY" => "252 2.5.0 Cannot verify addresses".into(),
+            "QUIT" => return reply(&mut writer, "221 2.0.0 Bye").await,
+            _ => "502 5.5.2 Command not recognised".into(),
+        };
+        reply(&mut writer, &response).await?;
+    }
+
+    Ok(())
+}
+
+/// Turns a received message into a task of each recipient, returning the
+/// reply for the client.
+///
+/// SMTP has a single reply for all recipients, so when one fails the client
+/// is asked to retry the whole message. Recipients who already got it are
+/// skipped on the retry by its `Message-ID`.
+async fn deliver(inbound: &Inbound, recipients: &[Uuid], raw: &[u8]) -> String {
+    let mail = match inbound::parse(raw) {
+        Ok(mail) => mail,
+        Err(Error::Validation(details)) => {
+            return format!("550 5.6.0 Message cannot become a task: {details}");
+        }
+        Err(e) => return format!("550 5.6.0 Message cannot become a task: {e}"),
+    };
+
+    let mut failed = false;
+    for &user_pid in recipients {
+        match inbound.deliver(user_pid, &mail).await {
+            Ok(Some(task)) => tracing::info!("Created task {} from email", task.id),
+            Ok(None) => tracing::info!("Skipped email already delivered to {user_pid}"),
+            Err(e) => {
+                tracing::error!("Failed to create task from email for {user_pid}: {e:?}");
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        "451 4.3.0 Try again later".into()
+    } else {
+        "250 2.0.0 OK".into()
+    }
+}
+
+/// What follows `keyword` in the arguments of a command, ignoring case.
+fn strip_keyword<'a>(args: &'a str, keyword: &str) -> Option<&'a str> {
+    args.get(..keyword.len())
+        .filter(|start| start.eq_ignore_ascii_case(keyword))
+        .map(|_| args[keyword.len()..].trim_start())
+}
+
+/// The `SIZE=` parameter of `MAIL FROM`, from RFC 1870.
+fn declared_size(params: &str) -> Option<usize> {
+    params
+        .split_whitespace()
+        .find_map(|param| strip_keyword(param, "SIZE="))
+        .and_then(|size| size.parse().ok())
+}
+
+async fn reply<W>(writer: &mut W, response: &str) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer.write_all(format!("{response}\r\n").as_bytes()).await
+}
+
+/// Reads up to `limit` bytes ending at the next line break, `None` once the
+/// client hung up.
+async fn read_line<R>(reader: &mut R, limit: usize) -> io::Result<Option<Vec<u8>>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = Vec::new();
+    let limit = u64::try_from(limit).unwrap_or(u64::MAX);
+    let read = tokio::time::timeout(
+        IDLE_TIMEOUT,
+        (&mut *reader).take(limit).read_until(b'\n', &mut line),
+    )
+    .await
+    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "client went quiet"))??;
+
+    Ok((read > 0).then_some(line))
+}
+
+/// Reads a message up to the line holding a single dot, undoing the dot
+/// stuffing of lines starting with one. `None` when it is larger than
+/// `max_size`, the rest of it is still read and dropped.
+async fn read_data<R>(reader: &mut R, max_size: usize) -> io::Result<Option<Vec<u8>>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut data = Vec::new();
+    let mut too_large = false;
+    let mut line_start = true;
+
+    loop {
+        let line = read_line(reader, DATA_CHUNK)
+            .await?
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+
+        if line_start && matches!(line.as_slice(), b".\r\n" | b".\n") {
+            break;
+        }
+
+        let chunk = if line_start && line.starts_with(b".") {
+            &line[1..]
+        } else {
+            &line[..]
+        };
+        too_large |= data.len() + chunk.len() > max_size;
+        if !too_large {
+            data.extend_from_slice(chunk);
+        }
+        line_start = line.ends_with(b"\n");
+    }
+
+    Ok((!too_large).then_some(data))
+}
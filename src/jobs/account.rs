@@ -0,0 +1,94 @@
+use chrono::Utc;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::repositories::{ModelError, account_exports::AccountExport, users::User};
+
+use super::{Job, JobContext};
+
+/// Builds the archive of a queued account export, which is kept for the
+/// configured retention whether it succeeds or fails for good.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportAccount {
+    pub export_pid: Uuid,
+    pub user_pid: Uuid,
+}
+
+impl Job for ExportAccount {
+    const KIND: &'static str = "account.export";
+    const MAX_ATTEMPTS: i32 = 3;
+
+    fn run<'a>(&'a self, ctx: &'a JobContext) -> BoxFuture<'a, Result<(), ModelError>> {
+        Box::pin(async move {
+            // Gone with its account
+            let export =
+                match AccountExport::find_by_pid(&ctx.db, self.export_pid, self.user_pid).await {
+                    Err(ModelError::EntityNotFound) => return Ok(()),
+                    export => export?,
+                };
+            let expires_at = (Utc::now() + ctx.config.account().export_retention()).fixed_offset();
+
+            match export.build(&ctx.db, ctx.blobs.as_ref(), expires_at).await {
+                Ok(export) => {
+                    tracing::info!("Built account export {}", export.pid);
+                    Ok(())
+                }
+                Err(e) => {
+                    if ctx.is_last_attempt() {
+                        AccountExport::fail(&ctx.db, export.id, expires_at).await?;
+                    }
+                    Err(e)
+                }
+            }
+        })
+    }
+}
+
+/// Erases an account and everything stored about its user.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EraseUser {
+    pub user_pid: Uuid,
+}
+
+impl Job for EraseUser {
+    const KIND: &'static str = "account.erase";
+
+    fn run<'a>(&'a self, ctx: &'a JobContext) -> BoxFuture<'a, Result<(), ModelError>> {
+        Box::pin(async move {
+            match User::erase(&ctx.db, ctx.blobs.as_ref(), self.user_pid).await {
+                Ok(()) => tracing::info!("Erased account {}", self.user_pid),
+                // Already erased by an earlier attempt
+                Err(ModelError::EntityNotFound) => {}
+                Err(e) => return Err(e),
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Deletes account exports past their expiry, together with their archives.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PurgeExports {}
+
+impl Job for PurgeExports {
+    const KIND: &'static str = "account.purge_exports";
+    // The next scheduled run picks up where a failed one stopped
+    const MAX_ATTEMPTS: i32 = 1;
+
+    fn run<'a>(&'a self, ctx: &'a JobContext) -> BoxFuture<'a, Result<(), ModelError>> {
+        Box::pin(async move {
+            let before = Utc::now().fixed_offset();
+
+            let purged = AccountExport::purge(&ctx.db, ctx.blobs.as_ref(), before).await?;
+            if purged > 0 {
+                tracing::info!("Purged {purged} expired account exports");
+            }
+
+            Ok(())
+        })
+    }
+}
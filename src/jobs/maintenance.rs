@@ -0,0 +1,100 @@
+use chrono::Utc;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::repositories::{
+    ModelError, idempotency::IdempotencyKey, jobs::QueuedJob, outbox::OutboxEvent, tasks::Task,
+};
+
+use super::{Job, JobContext};
+
+/// Deletes the tasks that have been in the trash for longer than the
+/// configured retention, together with their attachments.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PurgeTrash {}
+
+impl Job for PurgeTrash {
+    const KIND: &'static str = "tasks.purge_trash";
+    // The next scheduled run picks up where a failed one stopped
+    const MAX_ATTEMPTS: i32 = 1;
+
+    fn run<'a>(&'a self, ctx: &'a JobContext) -> BoxFuture<'a, Result<(), ModelError>> {
+        Box::pin(async move {
+            let before = (Utc::now() - ctx.config.trash().retention()).fixed_offset();
+
+            let purged = Task::purge_trash(&ctx.db, ctx.blobs.as_ref(), before).await?;
+            if purged > 0 {
+                tracing::info!("Purged {purged} tasks from the trash");
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Deletes idempotency keys older than their replay window.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PurgeIdempotencyKeys {}
+
+impl Job for PurgeIdempotencyKeys {
+    const KIND: &'static str = "idempotency.purge_keys";
+    const MAX_ATTEMPTS: i32 = 1;
+
+    fn run<'a>(&'a self, ctx: &'a JobContext) -> BoxFuture<'a, Result<(), ModelError>> {
+        Box::pin(async move {
+            let before = (Utc::now() - ctx.config.idempotency().window()).fixed_offset();
+
+            let purged = IdempotencyKey::purge(&ctx.db, before).await?;
+            if purged > 0 {
+                tracing::info!("Purged {purged} expired idempotency keys");
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Deletes the jobs that finished longer ago than the configured retention.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PurgeJobs {}
+
+impl Job for PurgeJobs {
+    const KIND: &'static str = "jobs.purge";
+    const MAX_ATTEMPTS: i32 = 1;
+
+    fn run<'a>(&'a self, ctx: &'a JobContext) -> BoxFuture<'a, Result<(), ModelError>> {
+        Box::pin(async move {
+            let before = (Utc::now() - ctx.config.jobs().retention()).fixed_offset();
+
+            let purged = QueuedJob::purge(&ctx.db, before).await?;
+            if purged > 0 {
+                tracing::info!("Purged {purged} finished jobs");
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Deletes the outbox events published longer ago than the configured
+/// retention.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PurgeOutbox {}
+
+impl Job for PurgeOutbox {
+    const KIND: &'static str = "outbox.purge";
+    const MAX_ATTEMPTS: i32 = 1;
+
+    fn run<'a>(&'a self, ctx: &'a JobContext) -> BoxFuture<'a, Result<(), ModelError>> {
+        Box::pin(async move {
+            let before = (Utc::now() - ctx.config.outbox().retention()).fixed_offset();
+
+            let purged = OutboxEvent::purge(&ctx.db, before).await?;
+            if purged > 0 {
+                tracing::info!("Purged {purged} published outbox events");
+            }
+
+            Ok(())
+        })
+    }
+}
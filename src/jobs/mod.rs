@@ -0,0 +1,161 @@
+mod account;
+mod maintenance;
+mod notifications;
+mod worker;
+
+pub use self::{
+    account::{EraseUser, ExportAccount, PurgeExports},
+    maintenance::{PurgeIdempotencyKeys, PurgeJobs, PurgeOutbox, PurgeTrash},
+    notifications::{EmailNotification, SendReminders},
+    worker::{JobContext, Worker},
+};
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use futures_util::future::BoxFuture;
+use reqwest::{Client, redirect::Policy};
+use serde::{Serialize, de::DeserializeOwned};
+use sqlx::{Executor, PgPool, Postgres};
+use tokio::task::JoinHandle;
+
+use crate::{
+    AppConfig, Error,
+    config::WebhookConfig,
+    models::webhooks::EndpointResolver,
+    repositories::{ModelError, jobs::QueuedJob, webhooks::WebhookDelivery},
+    storage::BlobStore,
+};
+
+/// Shortest pause between two polls, as `tokio::time::interval` rejects a
+/// zero period.
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Background work that is stored in the `jobs` table until a worker has
+/// run it. The job itself is the payload, so it should only hold ids and
+/// settings, not whole rows.
+pub trait Job: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// Name jobs of this type are stored and dispatched under.
+    const KIND: &'static str;
+    /// Attempts before a failing job is given up on.
+    const MAX_ATTEMPTS: i32 = 5;
+
+    /// Does the work. Failed attempts are retried with backoff, so a job
+    /// must be safe to run again after it failed half way.
+    fn run<'a>(&'a self, ctx: &'a JobContext) -> BoxFuture<'a, Result<(), ModelError>>;
+}
+
+fn payload<J: Job>(job: &J) -> Result<serde_json::Value, ModelError> {
+    serde_json::to_value(job).map_err(|e| ModelError::Database(e.to_string()))
+}
+
+/// Queues `job` to run right away. Queued as part of a transaction, it only
+/// runs once the transaction commits.
+///
+/// # Errors
+/// * Database errors
+pub async fn enqueue<'e, J, C>(db: C, job: &J) -> Result<QueuedJob, ModelError>
+where
+    J: Job,
+    C: Executor<'e, Database = Postgres>,
+{
+    enqueue_at(db, job, Utc::now().fixed_offset()).await
+}
+
+/// Queues `job` to run from `run_at`.
+///
+/// # Errors
+/// * Database errors
+pub async fn enqueue_at<'e, J, C>(
+    db: C,
+    job: &J,
+    run_at: DateTime<FixedOffset>,
+) -> Result<QueuedJob, ModelError>
+where
+    J: Job,
+    C: Executor<'e, Database = Postgres>,
+{
+    QueuedJob::enqueue(db, J::KIND, &payload(job)?, J::MAX_ATTEMPTS, run_at).await
+}
+
+/// The worker running every job of the service, with the maintenance jobs
+/// scheduled as configured.
+///
+/// # Errors
+/// * Invalid cron expressions in the configuration
+pub fn worker(db: PgPool, blobs: Arc<dyn BlobStore>, config: &AppConfig) -> Result<Worker, Error> {
+    Worker::new(db, blobs, config)
+        .register::<ExportAccount>()
+        .register::<EraseUser>()
+        .register::<EmailNotification>()
+        .schedule(
+            "purge-trash",
+            &config.trash().purge_schedule,
+            &PurgeTrash {},
+        )?
+        .schedule(
+            "purge-idempotency-keys",
+            &config.idempotency().purge_schedule,
+            &PurgeIdempotencyKeys {},
+        )?
+        .schedule(
+            "purge-account-exports",
+            &config.account().export_purge_schedule,
+            &PurgeExports {},
+        )?
+        .schedule("purge-jobs", &config.jobs().purge_schedule, &PurgeJobs {})?
+        .schedule(
+            "purge-outbox",
+            &config.outbox().purge_schedule,
+            &PurgeOutbox {},
+        )?
+        .schedule(
+            "send-reminders",
+            &config.notifications().reminder_schedule,
+            &SendReminders {},
+        )
+}
+
+/// Sends queued webhook deliveries as they fall due, without waiting for
+/// the next poll while full batches keep coming.
+///
+/// # Errors
+/// * Failing to set up the HTTP client
+pub fn spawn_webhook_deliveries(
+    db: PgPool,
+    config: &WebhookConfig,
+) -> Result<JoinHandle<()>, reqwest::Error> {
+    // Redirects are not followed, endpoints have to answer themselves, and
+    // hosts only resolve to addresses webhooks may be sent to. No proxy is
+    // used, as it would resolve the hosts itself
+    let client = Client::builder()
+        .timeout(config.timeout())
+        .redirect(Policy::none())
+        .no_proxy()
+        .dns_resolver(Arc::new(EndpointResolver::new(config)))
+        .user_agent(concat!(
+            "tasks-authenticated-webhooks/",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .build()?;
+    let config = config.clone();
+    let batch_size = usize::try_from(config.batch_size).unwrap_or(usize::MAX);
+    let mut interval = tokio::time::interval(config.poll_interval().max(MIN_INTERVAL));
+
+    Ok(tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+
+            loop {
+                match WebhookDelivery::deliver_due(&db, &client, &config).await {
+                    Ok(sent) if sent >= batch_size => {}
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::error!("Failed to send webhook deliveries: {e}");
+                        break;
+                    }
+                }
+            }
+        }
+    }))
+}
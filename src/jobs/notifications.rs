@@ -0,0 +1,66 @@
+use futures_util::future::BoxFuture;
+use lettre::{AsyncTransport, Message, message::header::ContentType};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::repositories::{ModelError, notifications::Notification, users::User};
+
+use super::{Job, JobContext};
+
+/// Mails a notification to the address of its recipient's account.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailNotification {
+    pub user_pid: Uuid,
+    pub message: String,
+}
+
+impl Job for EmailNotification {
+    const KIND: &'static str = "notifications.email";
+    const MAX_ATTEMPTS: i32 = 3;
+
+    fn run<'a>(&'a self, ctx: &'a JobContext) -> BoxFuture<'a, Result<(), ModelError>> {
+        Box::pin(async move {
+            // Gone with its account
+            let user = match User::find_by_pid(&ctx.db, self.user_pid).await {
+                Err(ModelError::EntityNotFound) => return Ok(()),
+                user => user?,
+            };
+            let mail = ctx.config.mail();
+
+            let email = Message::builder()
+                .from(mail.from.parse()?)
+                .to(user.email.parse()?)
+                .subject(&self.message)
+                .header(ContentType::TEXT_PLAIN)
+                .body(self.message.clone())?;
+            mail.transport()?.send(email).await?;
+
+            Ok(())
+        })
+    }
+}
+
+/// Reminds users of the tasks they are responsible for that fall due within
+/// the configured lead.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SendReminders {}
+
+impl Job for SendReminders {
+    const KIND: &'static str = "notifications.reminders";
+    // The next scheduled run picks up where a failed one stopped
+    const MAX_ATTEMPTS: i32 = 1;
+
+    fn run<'a>(&'a self, ctx: &'a JobContext) -> BoxFuture<'a, Result<(), ModelError>> {
+        Box::pin(async move {
+            let lead = ctx.config.notifications().reminder_lead();
+
+            let queued = Notification::queue_reminders(&ctx.db, lead).await?;
+            if queued > 0 {
+                tracing::info!("Queued reminders for {queued} tasks");
+            }
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,297 @@
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use futures_util::future::{BoxFuture, join_all};
+use serde_json::Value;
+use sqlx::PgPool;
+use tokio::task::{JoinError, JoinHandle};
+
+use crate::{
+    AppConfig, Error,
+    repositories::{
+        ModelError,
+        jobs::{JobSchedule, QueuedJob},
+    },
+    storage::BlobStore,
+};
+
+use super::{Job, MIN_INTERVAL, payload};
+
+type Handler =
+    Arc<dyn Fn(Value, JobContext) -> BoxFuture<'static, Result<(), ModelError>> + Send + Sync>;
+
+/// What a job is given to run with.
+#[derive(Clone)]
+pub struct JobContext {
+    pub db: PgPool,
+    pub blobs: Arc<dyn BlobStore>,
+    pub config: AppConfig,
+    /// Number of the current attempt, from 1
+    pub attempt: i32,
+    pub max_attempts: i32,
+}
+
+impl JobContext {
+    /// Whether the job is given up on should this attempt fail.
+    #[must_use]
+    pub const fn is_last_attempt(&self) -> bool {
+        self.attempt >= self.max_attempts
+    }
+}
+
+/// A job queued on a cron schedule.
+struct Schedule {
+    name: String,
+    kind: &'static str,
+    cron: String,
+    schedule: cron::Schedule,
+    payload: Value,
+    max_attempts: i32,
+}
+
+impl Schedule {
+    fn next_run(&self) -> DateTime<FixedOffset> {
+        self.schedule
+            .after(&Utc::now())
+            .next()
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+            .fixed_offset()
+    }
+}
+
+/// Runs the jobs it has handlers for, claiming them with `SKIP LOCKED` so
+/// that any number of workers, in the service or in `worker` processes, can
+/// share the queue.
+pub struct Worker {
+    db: PgPool,
+    blobs: Arc<dyn BlobStore>,
+    config: AppConfig,
+    handlers: HashMap<&'static str, Handler>,
+    schedules: Vec<Schedule>,
+}
+
+impl Worker {
+    #[must_use]
+    pub fn new(db: PgPool, blobs: Arc<dyn BlobStore>, config: &AppConfig) -> Self {
+        Self {
+            db,
+            blobs,
+            config: config.clone(),
+            handlers: HashMap::new(),
+            schedules: Vec::new(),
+        }
+    }
+
+    /// Runs jobs of type `J`.
+    #[must_use]
+    pub fn register<J: Job>(mut self) -> Self {
+        let handler: Handler = Arc::new(|payload, ctx| {
+            Box::pin(async move {
+                let job = serde_json::from_value::<J>(payload)
+                    .map_err(|e| ModelError::Database(e.to_string()))?;
+                job.run(&ctx).await
+            })
+        });
+        self.handlers.insert(J::KIND, handler);
+        self
+    }
+
+    /// Runs jobs of type `J` and queues `job` under `name` whenever the cron
+    /// expression, which includes seconds, falls due.
+    ///
+    /// # Errors
+    /// * Invalid cron expressions
+    pub fn schedule<J: Job>(mut self, name: &str, cron: &str, job: &J) -> Result<Self, Error> {
+        let schedule = cron::Schedule::from_str(cron)?;
+        self.schedules.push(Schedule {
+            name: name.to_string(),
+            kind: J::KIND,
+            cron: cron.to_string(),
+            schedule,
+            payload: payload(job)?,
+            max_attempts: J::MAX_ATTEMPTS,
+        });
+
+        Ok(self.register::<J>())
+    }
+
+    /// Stores the schedules, keeping the next run of those that did not
+    /// change.
+    ///
+    /// # Errors
+    /// * Database errors
+    pub async fn sync_schedules(&self) -> Result<(), ModelError> {
+        for schedule in &self.schedules {
+            JobSchedule::upsert(
+                &self.db,
+                &schedule.name,
+                schedule.kind,
+                &schedule.cron,
+                &schedule.payload,
+                schedule.next_run(),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Queues the scheduled jobs that are due, returning how many were
+    /// queued. A schedule is only queued by one worker per run.
+    ///
+    /// # Errors
+    /// * Database errors
+    pub async fn queue_scheduled(&self) -> Result<usize, ModelError> {
+        let names = self
+            .schedules
+            .iter()
+            .map(|schedule| schedule.name.as_str())
+            .collect::<Vec<&str>>();
+        let mut txn = self.db.begin().await?;
+
+        let due = JobSchedule::lock_due(&mut txn, &names).await?;
+        for row in &due {
+            let Some(schedule) = self.schedules.iter().find(|s| s.name == row.name) else {
+                continue;
+            };
+            QueuedJob::enqueue(
+                &mut *txn,
+                schedule.kind,
+                &schedule.payload,
+                schedule.max_attempts,
+                Utc::now().fixed_offset(),
+            )
+            .await?;
+            JobSchedule::reschedule(&mut txn, &row.name, schedule.next_run()).await?;
+        }
+
+        txn.commit().await?;
+
+        Ok(due.len())
+    }
+
+    /// Claims a batch of due jobs, runs them side by side and records the
+    /// outcomes, returning how many ran.
+    ///
+    /// # Errors
+    /// * Database errors
+    pub async fn run_once(&self) -> Result<usize, ModelError> {
+        let jobs = self.config.jobs();
+        let kinds = self.handlers.keys().copied().collect::<Vec<&str>>();
+
+        let abandoned = QueuedJob::fail_abandoned(&self.db).await?;
+        if abandoned > 0 {
+            tracing::warn!("Failed {abandoned} abandoned jobs with no attempts left");
+        }
+
+        let claimed =
+            QueuedJob::claim(&self.db, &kinds, i64::from(jobs.batch_size), jobs.lease()).await?;
+
+        // Spawned so that a panicking job fails on its own
+        let runs = claimed.iter().map(|job| {
+            let handler = Arc::clone(&self.handlers[job.kind.as_str()]);
+            let ctx = JobContext {
+                db: self.db.clone(),
+                blobs: Arc::clone(&self.blobs),
+                config: self.config.clone(),
+                attempt: job.attempts,
+                max_attempts: job.max_attempts,
+            };
+            self.hold(job, tokio::spawn(handler(job.payload.clone(), ctx)))
+        });
+        let results = join_all(runs).await;
+
+        for (job, result) in claimed.iter().zip(results) {
+            let error = match result {
+                Ok(Ok(())) => {
+                    if !QueuedJob::complete(&self.db, job.id, job.attempts).await? {
+                        tracing::warn!("Job {} finished after losing its lease", job.id);
+                    }
+                    continue;
+                }
+                Ok(Err(e)) => e.to_string(),
+                Err(e) => format!("Job panicked: {e}"),
+            };
+
+            let retry_at = (job.attempts < job.max_attempts).then(|| {
+                let attempts = u32::try_from(job.attempts).unwrap_or_default();
+                (Utc::now() + jobs.backoff(attempts)).fixed_offset()
+            });
+            tracing::warn!(
+                "Job {} ({}) failed on attempt {}: {error}",
+                job.id,
+                job.kind,
+                job.attempts
+            );
+            if !QueuedJob::fail(&self.db, job.id, job.attempts, &error, retry_at).await? {
+                tracing::warn!("Job {} failed after losing its lease", job.id);
+            }
+        }
+
+        Ok(claimed.len())
+    }
+
+    /// Waits for the run of `job`, renewing its lease so that no other
+    /// worker claims it meanwhile. A run that lost its lease anyway is
+    /// stopped, as the job already runs elsewhere.
+    async fn hold(
+        &self,
+        job: &QueuedJob,
+        mut run: JoinHandle<Result<(), ModelError>>,
+    ) -> Result<Result<(), ModelError>, JoinError> {
+        let lease = self.config.jobs().lease();
+        let period = (lease / 3).to_std().unwrap_or_default().max(MIN_INTERVAL);
+        let mut renewal = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+
+        loop {
+            tokio::select! {
+                result = &mut run => return result,
+                _ = renewal.tick() => {
+                    match QueuedJob::renew(&self.db, job.id, job.attempts, lease).await {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            tracing::warn!("Job {} lost its lease, stopping it", job.id);
+                            run.abort();
+                            return run.await;
+                        }
+                        Err(e) => tracing::error!("Failed to renew the lease of job {}: {e}", job.id),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Stores the schedules, then queues and runs jobs in the background
+    /// until the process exits.
+    ///
+    /// # Errors
+    /// * Database errors while storing the schedules
+    pub async fn start(self) -> Result<JoinHandle<()>, ModelError> {
+        self.sync_schedules().await?;
+
+        let batch_size = usize::try_from(self.config.jobs().batch_size).unwrap_or(usize::MAX);
+        let mut interval =
+            tokio::time::interval(self.config.jobs().poll_interval().max(MIN_INTERVAL));
+
+        Ok(tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+
+                if let Err(e) = self.queue_scheduled().await {
+                    tracing::error!("Failed to queue scheduled jobs: {e}");
+                }
+
+                loop {
+                    match self.run_once().await {
+                        Ok(ran) if ran >= batch_size => {}
+                        Ok(_) => break,
+                        Err(e) => {
+                            tracing::error!("Failed to run jobs: {e}");
+                            break;
+                        }
+                    }
+                }
+            }
+        }))
+    }
+}
@@ -3,10 +3,14 @@ pub mod config;
 pub mod context;
 pub mod controllers;
 pub mod errors;
+pub mod events;
+pub mod inbound;
+pub mod jobs;
 pub mod middlewares;
 pub mod models;
 pub mod repositories;
 pub mod router;
+pub mod storage;
 
 pub use self::{
     app::App,
@@ -6,6 +6,7 @@ use std::{
 use axum::{RequestPartsExt, body::Body, extract::Request, response::Response};
 use axum_extra::{
     TypedHeader,
+    extract::cookie::CookieJar,
     headers::{Authorization, authorization::Bearer},
     typed_header::TypedHeaderRejectionReason,
 };
@@ -18,6 +19,9 @@ use crate::{AppState, models::auth::TokenClaims};
 
 use super::AuthError;
 
+/// Cookie the access token is set in on login.
+pub const ACCESS_TOKEN_COOKIE: &str = "accessToken";
+
 #[derive(Debug, Clone)]
 pub struct AuthClaims {
     pub pid: Uuid,
@@ -38,14 +42,25 @@ impl AuthClaims {
 #[derive(Clone)]
 pub struct JwtAuthLayer {
     state: AppState,
+    cookie: bool,
 }
 
 impl JwtAuthLayer {
     pub fn new(state: &AppState) -> Self {
         Self {
             state: state.clone(),
+            cookie: false,
         }
     }
+
+    /// Also accepts the token from the [`ACCESS_TOKEN_COOKIE`] when the
+    /// request has no `Authorization` header, as browsers cannot set headers
+    /// on `EventSource` and WebSocket requests.
+    #[must_use]
+    pub const fn with_cookie(mut self) -> Self {
+        self.cookie = true;
+        self
+    }
 }
 
 impl<S> Layer<S> for JwtAuthLayer {
@@ -55,6 +70,7 @@ impl<S> Layer<S> for JwtAuthLayer {
         Self::Service {
             inner,
             state: self.state.clone(),
+            cookie: self.cookie,
         }
     }
 }
@@ -63,6 +79,7 @@ impl<S> Layer<S> for JwtAuthLayer {
 pub struct JwtAuthService<S> {
     inner: S,
     state: AppState,
+    cookie: bool,
 }
 
 impl<S, B> Service<Request<B>> for JwtAuthService<S>
@@ -81,6 +98,7 @@ where
 
     fn call(&mut self, req: Request<B>) -> Self::Future {
         let state = self.state.clone();
+        let cookie = self.cookie;
         let clone = self.inner.clone();
 
         let mut inner = std::mem::replace(&mut self.inner, clone);
@@ -88,25 +106,35 @@ where
         Box::pin(async move {
             let (mut parts, body) = req.into_parts();
 
-            let TypedHeader(Authorization(bearer)) =
-                match parts.extract::<TypedHeader<Authorization<Bearer>>>().await {
-                    Ok(token) => token,
-                    Err(e) => {
-                        tracing::error!("Typed Header Auth error: {:?}", e);
-                        match e.reason() {
-                            TypedHeaderRejectionReason::Missing => {
-                                return Ok(AuthError::MissingCredentials.response());
-                            }
-                            TypedHeaderRejectionReason::Error(_e) => {
-                                return Ok(AuthError::WrongCredentials.response());
-                            }
-                            _ => return Ok(AuthError::Other(e.to_string()).response()),
+            let token = match parts.extract::<TypedHeader<Authorization<Bearer>>>().await {
+                Ok(TypedHeader(Authorization(bearer))) => bearer.token().to_owned(),
+                Err(e) => {
+                    let from_cookie = cookie
+                        .then(|| CookieJar::from_headers(&parts.headers))
+                        .and_then(|jar| {
+                            jar.get(ACCESS_TOKEN_COOKIE)
+                                .map(|cookie| cookie.value().to_owned())
+                        });
+                    match (e.reason(), from_cookie) {
+                        (TypedHeaderRejectionReason::Missing, Some(token)) => token,
+                        (TypedHeaderRejectionReason::Missing, None) => {
+                            tracing::error!("Typed Header Auth error: {:?}", e);
+                            return Ok(AuthError::MissingCredentials.response());
+                        }
+                        (TypedHeaderRejectionReason::Error(_e), _) => {
+                            tracing::error!("Typed Header Auth error: {:?}", e);
+                            return Ok(AuthError::WrongCredentials.response());
+                        }
+                        _ => {
+                            tracing::error!("Typed Header Auth error: {:?}", e);
+                            return Ok(AuthError::Other(e.to_string()).response());
                         }
                     }
-                };
+                }
+            };
 
             let token_data = match jsonwebtoken::decode::<TokenClaims>(
-                bearer.token(),
+                &token,
                 &state.jwt.decoding_key,
                 &Validation::new(Algorithm::RS256),
             ) {
@@ -0,0 +1,260 @@
+use std::{
+    convert::Infallible,
+    task::{Context, Poll},
+};
+
+use axum::{
+    Json,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{HeaderName, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use futures_util::future::BoxFuture;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use tower::{Layer, Service};
+use uuid::Uuid;
+
+use crate::{
+    AppState, Error,
+    repositories::{ModelError, idempotency::IdempotencyKey},
+};
+
+use super::auth::AuthClaims;
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Set on responses replayed for a repeated `Idempotency-Key`.
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// Largest request body the layer reads to fingerprint a request.
+const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// Set by a handler on its response when the request created a user, so
+/// that the stored response goes when that user is erased.
+#[derive(Debug, Clone, Copy)]
+pub struct ResponseOwner(pub Uuid);
+
+/// Body fields left out of a request's fingerprint, so that no fast hash of
+/// a password is stored with its key.
+const SECRET_FIELDS: [&str; 3] = ["password", "confirm_password", "confirmPassword"];
+
+/// Makes retries of a request carrying an `Idempotency-Key` header safe.
+///
+/// The first request with a key runs normally and its response is stored
+/// for the configured window. Retries with the same key and request get the
+/// stored response back, while a key reused for a different request is
+/// rejected. Keys are scoped to the signed in user, so the layer must run
+/// inside [`super::auth::JwtAuthLayer`] on authenticated routes. Requests
+/// made without signing in share no scope, their keys only match retries
+/// of the very same request.
+#[derive(Clone)]
+pub struct IdempotencyLayer {
+    state: AppState,
+}
+
+impl IdempotencyLayer {
+    pub fn new(state: &AppState) -> Self {
+        Self {
+            state: state.clone(),
+        }
+    }
+}
+
+impl<S> Layer<S> for IdempotencyLayer {
+    type Service = IdempotencyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Self::Service {
+            inner,
+            state: self.state.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct IdempotencyService<S> {
+    inner: S,
+    state: AppState,
+}
+
+impl<S> Service<Request<Body>> for IdempotencyService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let state = self.state.clone();
+        let clone = self.inner.clone();
+
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let Some(key) = req.headers().get(&IDEMPOTENCY_KEY) else {
+                return inner.call(req).await;
+            };
+            let key = match key.to_str() {
+                Ok(key) if !key.is_empty() && key.len() <= 255 => key.to_string(),
+                _ => return Ok(ModelError::InvalidIdempotencyKey.response()),
+            };
+            let user_pid = req.extensions().get::<AuthClaims>().map(AuthClaims::pid);
+
+            let (parts, body) = req.into_parts();
+            let Ok(body) = to_bytes(body, MAX_BODY_SIZE).await else {
+                let body = Json(json!({ "message": "Request body is too large" }));
+                return Ok((StatusCode::PAYLOAD_TOO_LARGE, body).into_response());
+            };
+
+            let request_hash = request_hash(&parts.method, parts.uri.path(), &body);
+
+            // Anonymous keys are stored under the nil user, tied to the
+            // request so that one client cannot replay or block another's
+            let (user_pid, key) = match user_pid {
+                Some(user_pid) => (user_pid, key),
+                None => (
+                    Uuid::nil(),
+                    hex::encode(Sha256::digest(format!("{request_hash}:{key}"))),
+                ),
+            };
+
+            let now = Utc::now();
+            let since = (now - state.config.idempotency().window()).fixed_offset();
+            let stale_before = (now - state.config.idempotency().lock_timeout()).fixed_offset();
+
+            match IdempotencyKey::reserve(
+                &state.db,
+                user_pid,
+                &key,
+                &request_hash,
+                since,
+                stale_before,
+            )
+            .await
+            {
+                Ok(true) => {}
+                Ok(false) => {
+                    let record = IdempotencyKey::find(&state.db, user_pid, &key, since).await;
+                    return Ok(match record {
+                        Ok(Some(record)) => replay(record, &request_hash),
+                        // Expired between the two queries
+                        Ok(None) => ModelError::IdempotencyKeyInUse.response(),
+                        Err(e) => e.response(),
+                    });
+                }
+                Err(e) => return Ok(e.response()),
+            }
+
+            let response = inner
+                .call(Request::from_parts(parts, Body::from(body)))
+                .await?;
+
+            Ok(store(&state, user_pid, &key, response).await)
+        })
+    }
+}
+
+/// Fingerprint telling a retry apart from another request made with the
+/// same key. Password fields of a JSON body are left out, a retry that only
+/// changes them counts as the same request.
+#[must_use]
+pub fn request_hash(method: &Method, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str());
+    hasher.update(path);
+    match serde_json::from_slice(body) {
+        Ok(Value::Object(mut fields)) => {
+            for field in SECRET_FIELDS {
+                fields.remove(field);
+            }
+            hasher.update(Value::Object(fields).to_string());
+        }
+        _ => hasher.update(body),
+    }
+
+    hex::encode(hasher.finalize())
+}
+
+/// Answers a repeated request from the stored record of its key.
+fn replay(record: IdempotencyKey, request_hash: &str) -> Response {
+    if record.request_hash != request_hash {
+        return ModelError::IdempotencyKeyReused.response();
+    }
+    let Some(status) = record
+        .status
+        .and_then(|status| StatusCode::from_u16(status as u16).ok())
+    else {
+        return ModelError::IdempotencyKeyInUse.response();
+    };
+
+    let mut response = Response::new(Body::from(record.body.unwrap_or_default()));
+    *response.status_mut() = status;
+
+    let headers = response.headers_mut();
+    for (name, value) in record.headers.map(|headers| headers.0).unwrap_or_default() {
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+            headers.append(name, value);
+        }
+    }
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+
+    response
+}
+
+/// Stores `response` for its key and passes it on. Server errors free the
+/// key instead, so the request can be retried.
+async fn store(state: &AppState, user_pid: Uuid, key: &str, response: Response) -> Response {
+    if response.status().is_server_error() {
+        if let Err(e) = IdempotencyKey::release(&state.db, user_pid, key).await {
+            tracing::error!("Failed to release idempotency key: {e}");
+        }
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let owner_pid = parts.extensions.get::<ResponseOwner>().map(|owner| owner.0);
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("Failed to read response body: {e}");
+            return Error::Axum(e).response();
+        }
+    };
+
+    let headers = parts
+        .headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+
+    if let Err(e) = IdempotencyKey::complete(
+        &state.db,
+        user_pid,
+        key,
+        parts.status.as_u16(),
+        headers,
+        &body,
+        owner_pid,
+    )
+    .await
+    {
+        tracing::error!("Failed to store idempotent response: {e}");
+        if let Err(e) = IdempotencyKey::release(&state.db, user_pid, key).await {
+            tracing::error!("Failed to release idempotency key: {e}");
+        }
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
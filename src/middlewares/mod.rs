@@ -1,4 +1,5 @@
 pub mod auth;
+pub mod idempotency;
 pub mod trace;
 
 use axum::{
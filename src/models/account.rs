@@ -0,0 +1,137 @@
+use std::io::{self, Cursor, Write};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+use zip::{ZipWriter, result::ZipError, write::SimpleFileOptions};
+
+use crate::repositories::{account_exports::AccountExport, users::User};
+
+/// Profile of a user as written to their data export, without the
+/// password hash.
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProfile {
+    pub pid: String,
+    pub username: String,
+    pub email: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<&User> for AccountProfile {
+    fn from(value: &User) -> Self {
+        Self {
+            pid: value.pid.to_string(),
+            username: value.username.clone(),
+            email: value.email.clone(),
+            created_at: value.created_at.to_rfc3339(),
+            updated_at: value.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Zip archive of a user's data, built in memory.
+pub struct AccountArchive {
+    writer: ZipWriter<Cursor<Vec<u8>>>,
+}
+
+impl Default for AccountArchive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AccountArchive {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            writer: ZipWriter::new(Cursor::new(Vec::new())),
+        }
+    }
+
+    /// Adds `value` as a pretty printed JSON file.
+    ///
+    /// # Errors
+    /// * Serialisation errors
+    pub fn add_json<T: Serialize>(&mut self, name: &str, value: &T) -> Result<(), ZipError> {
+        let data = serde_json::to_vec_pretty(value).map_err(io::Error::from)?;
+        self.add_file(name, &data)
+    }
+
+    /// Adds a file holding `data`.
+    ///
+    /// # Errors
+    /// * Compression errors
+    pub fn add_file(&mut self, name: &str, data: &[u8]) -> Result<(), ZipError> {
+        self.writer.start_file(name, SimpleFileOptions::default())?;
+        self.writer.write_all(data)?;
+        Ok(())
+    }
+
+    /// Finishes the archive and returns its contents.
+    ///
+    /// # Errors
+    /// * Compression errors
+    pub fn finish(self) -> Result<Vec<u8>, ZipError> {
+        Ok(self.writer.finish()?.into_inner())
+    }
+}
+
+/// Name of an uploaded file within an archive, prefixed with the
+/// attachment's pid so that files of the same name do not collide.
+#[must_use]
+pub fn archive_filename(pid: &str, filename: &str) -> String {
+    let filename = filename
+        .chars()
+        .map(|c| {
+            if matches!(c, '/' | '\\') || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect::<String>();
+
+    format!("attachments/{pid}-{filename}")
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountExportResponse {
+    pub id: String,
+    /// `pending` while the archive is built, then `ready` or `failed`
+    pub status: String,
+    /// Size of the archive in bytes
+    pub size: Option<i64>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+    /// When the archive is deleted
+    pub expires_at: Option<String>,
+    /// Where to download the archive from once it is ready
+    pub download_url: Option<String>,
+}
+
+impl From<AccountExport> for AccountExportResponse {
+    fn from(value: AccountExport) -> Self {
+        let download_url = (value.status == "ready")
+            .then(|| format!("/api/users/me/exports/{}/download", value.pid));
+
+        Self {
+            id: value.pid.to_string(),
+            status: value.status,
+            size: value.size,
+            created_at: value.created_at.to_rfc3339(),
+            completed_at: value.completed_at.map(|at| at.to_rfc3339()),
+            expires_at: value.expires_at.map(|at| at.to_rfc3339()),
+            download_url,
+        }
+    }
+}
+
+/// Confirms the erasure of the current user's account.
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+pub struct EraseAccount {
+    #[validate(length(min = 1, message = "Password is required"))]
+    pub password: String,
+}
@@ -0,0 +1,90 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::repositories::attachments::Attachment;
+
+/// Longest stored file name, in bytes.
+const MAX_FILENAME_LEN: usize = 255;
+
+/// Multipart form accepted by `POST /api/tasks/{id}/attachments`.
+#[derive(Debug, ToSchema)]
+pub struct UploadForm {
+    #[schema(format = Binary, content_media_type = "application/octet-stream")]
+    pub file: String,
+}
+
+/// A file received in an upload, checked against the storage limits.
+#[derive(Debug, Clone)]
+pub struct Upload {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Bytes,
+}
+
+/// Reduces a client supplied file name to its last path component, without
+/// control characters or quotes, so it is safe to echo back in a
+/// `Content-Disposition` header.
+#[must_use]
+pub fn sanitize_filename(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let mut name = name
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, '"' | ';'))
+        .collect::<String>()
+        .trim()
+        .to_string();
+
+    while name.len() > MAX_FILENAME_LEN {
+        name.pop();
+    }
+
+    if name.is_empty() || name.chars().all(|c| c == '.') {
+        "attachment".to_string()
+    } else {
+        name
+    }
+}
+
+/// The MIME type of `data` judged by its contents, falling back to the type
+/// the client declared for formats without a signature such as plain text.
+#[must_use]
+pub fn detect_content_type(data: &[u8], declared: Option<&str>) -> String {
+    infer::get(data).map_or_else(
+        || {
+            declared
+                .and_then(|declared| declared.split(';').next())
+                .map_or("application/octet-stream", str::trim)
+                .to_ascii_lowercase()
+        },
+        |kind| kind.mime_type().to_string(),
+    )
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentResponse {
+    pub id: i32,
+    pub pid: String,
+    pub task_id: i32,
+    pub uploader_pid: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    pub created_at: String,
+}
+
+impl From<Attachment> for AttachmentResponse {
+    fn from(value: Attachment) -> Self {
+        Self {
+            id: value.id,
+            pid: value.pid.to_string(),
+            task_id: value.task_id,
+            uploader_pid: value.uploader_pid.to_string(),
+            filename: value.filename,
+            content_type: value.content_type,
+            size: value.size,
+            created_at: value.created_at.format("%d-%m-%Y %H:%M:%S").to_string(),
+        }
+    }
+}
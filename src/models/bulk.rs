@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::{
+    Error,
+    models::{
+        Validator,
+        tasks::{NewTask, TaskFilter, TaskResponse, UpdateTask},
+    },
+};
+
+/// How a bulk request handles failing operations.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum BulkMode {
+    /// Apply every operation or none of them
+    #[default]
+    Atomic,
+    /// Apply what succeeds and report what failed
+    BestEffort,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum BulkOperation {
+    Create {
+        task: NewTask,
+    },
+    Update {
+        id: i32,
+        patch: UpdateTask,
+        /// Complete the task even if some of its blockers are not done
+        #[serde(default)]
+        force: bool,
+    },
+    Delete {
+        id: i32,
+        #[serde(default)]
+        cascade: bool,
+        #[serde(default)]
+        permanent: bool,
+    },
+}
+
+impl BulkOperation {
+    /// Id of the task the operation targets, `None` when creating one.
+    #[must_use]
+    pub const fn id(&self) -> Option<i32> {
+        match self {
+            Self::Create { .. } => None,
+            Self::Update { id, .. } | Self::Delete { id, .. } => Some(*id),
+        }
+    }
+}
+
+/// What to do with every task a [`BulkSelector`] picks.
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum BulkAction {
+    Update {
+        patch: UpdateTask,
+        #[serde(default)]
+        force: bool,
+    },
+    Delete {
+        #[serde(default)]
+        cascade: bool,
+        #[serde(default)]
+        permanent: bool,
+    },
+}
+
+impl BulkAction {
+    /// The operation applying this action to the task `id`.
+    #[must_use]
+    pub fn operation(&self, id: i32) -> BulkOperation {
+        match self {
+            Self::Update { patch, force } => BulkOperation::Update {
+                id,
+                patch: patch.clone(),
+                force: *force,
+            },
+            Self::Delete { cascade, permanent } => BulkOperation::Delete {
+                id,
+                cascade: *cascade,
+                permanent: *permanent,
+            },
+        }
+    }
+}
+
+/// Applies one action to every task of the current user matching `filter`.
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+pub struct BulkSelector {
+    pub filter: TaskFilter,
+    pub action: BulkAction,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+pub struct BulkRequest {
+    #[serde(default)]
+    pub mode: BulkMode,
+    #[serde(default)]
+    #[validate(length(max = 500, message = "At most 500 operations per request"))]
+    pub operations: Vec<BulkOperation>,
+    /// Tasks to apply an action to, after the listed operations
+    pub selector: Option<BulkSelector>,
+}
+
+impl BulkRequest {
+    /// Validates the request and the task data of every operation, naming
+    /// the position of the first invalid operation.
+    ///
+    /// # Errors
+    /// * Validation errors
+    pub fn validate_all(&self) -> Result<(), Error> {
+        Validator::new(self).validate()?;
+
+        for (index, operation) in self.operations.iter().enumerate() {
+            let result = match operation {
+                BulkOperation::Create { task } => Validator::new(task).validate().map(|_| ()),
+                BulkOperation::Update { patch, .. } => Validator::new(patch).validate().map(|_| ()),
+                BulkOperation::Delete { .. } => Ok(()),
+            };
+            result.map_err(|e| at(&format!("operations[{index}]"), e))?;
+        }
+
+        if let Some(BulkSelector {
+            action: BulkAction::Update { patch, .. },
+            ..
+        }) = &self.selector
+        {
+            Validator::new(patch)
+                .validate()
+                .map_err(|e| at("selector", e))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Nests the field errors of a validation error under `field`.
+fn at(field: &str, error: Error) -> Error {
+    match error {
+        Error::Validation(errors) => {
+            let errors = serde_json::from_str::<Value>(&errors).unwrap_or(Value::String(errors));
+            Error::Validation(serde_json::json!({ field: errors }).to_string())
+        }
+        error => error,
+    }
+}
+
+/// Outcome of one operation of a bulk request.
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkItemResponse {
+    /// Position of the operation, selected tasks following the listed ones
+    pub index: usize,
+    /// HTTP status the operation would have answered on its own
+    pub status: u16,
+    /// Id of the task the operation touched
+    pub id: Option<i32>,
+    pub task: Option<TaskResponse>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkResponse {
+    /// Whether any change was saved
+    pub committed: bool,
+    pub results: Vec<BulkItemResponse>,
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::repositories::comments::Comment;
+
+/// Characters allowed in a mentioned username.
+fn is_username_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Usernames mentioned as `@username` in a comment, in order of first
+/// appearance and without duplicates.
+///
+/// An `@` only starts a mention at the beginning of the text or after a
+/// character that cannot be part of a word, so email addresses are skipped.
+/// Trailing dots and dashes are treated as punctuation.
+#[must_use]
+pub fn parse_mentions(body: &str) -> Vec<String> {
+    let mut mentions: Vec<String> = Vec::new();
+    let mut previous = None;
+
+    for (index, c) in body.char_indices() {
+        let starts_mention =
+            c == '@' && previous.is_none_or(|previous: char| !is_username_char(previous));
+        previous = Some(c);
+
+        if !starts_mention {
+            continue;
+        }
+
+        let rest = &body[index + 1..];
+        let end = rest.find(|c| !is_username_char(c)).unwrap_or(rest.len());
+        let username = rest[..end].trim_end_matches(['.', '-']);
+
+        if !username.is_empty() && !mentions.iter().any(|mention| mention == username) {
+            mentions.push(username.to_string());
+        }
+    }
+
+    mentions
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+pub struct NewComment {
+    /// Comment text, `@username` mentions notify that user
+    #[validate(length(
+        min = 1,
+        max = 5000,
+        message = "Comment must be between 1 to 5000 characters"
+    ))]
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+pub struct UpdateComment {
+    #[validate(length(
+        min = 1,
+        max = 5000,
+        message = "Comment must be between 1 to 5000 characters"
+    ))]
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentResponse {
+    pub id: i32,
+    pub pid: String,
+    pub task_id: i32,
+    pub author_pid: String,
+    /// Username of the author
+    pub author: String,
+    pub body: String,
+    pub edited: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<Comment> for CommentResponse {
+    fn from(value: Comment) -> Self {
+        Self {
+            id: value.id,
+            pid: value.pid.to_string(),
+            task_id: value.task_id,
+            author_pid: value.author_pid.to_string(),
+            author: value.author,
+            body: value.body,
+            edited: value.edited,
+            created_at: value.created_at.format("%d-%m-%Y %H:%M:%S").to_string(),
+            updated_at: value.updated_at.format("%d-%m-%Y %H:%M:%S").to_string(),
+        }
+    }
+}
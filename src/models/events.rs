@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::events::Event;
+
+/// A change to one of the user's tasks, or a new notification, pushed over
+/// the event stream.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskEventResponse {
+    /// One of `taskCreated`, `taskUpdated`, `taskDeleted` or
+    /// `notification`, or `resync` when events were missed and the tasks
+    /// should be fetched again
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub task_id: Option<i32>,
+    /// Id of the notification, for `notification` events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification_id: Option<i64>,
+}
+
+impl TaskEventResponse {
+    /// The event as `user_pid` is told about it, if it is a task change
+    /// they may see.
+    #[must_use]
+    pub fn for_user(event: &Event, user_pid: Uuid) -> Option<Self> {
+        if !event.concerns(user_pid) {
+            return None;
+        }
+
+        let (kind, task_id, notification_id) = match event {
+            Event::TaskCreated { task_id, .. } => ("taskCreated", Some(*task_id), None),
+            Event::TaskUpdated { task_id, .. } => ("taskUpdated", Some(*task_id), None),
+            Event::TaskDeleted { task_id, .. } => ("taskDeleted", Some(*task_id), None),
+            Event::Notified {
+                notification_id,
+                task_id,
+                ..
+            } => ("notification", *task_id, Some(*notification_id)),
+            Event::TaskAssigned { .. }
+            | Event::Mentioned { .. }
+            | Event::TaskShared { .. }
+            | Event::ProjectShared { .. }
+            | Event::TaskDue { .. }
+            | Event::UserCreated { .. }
+            | Event::UserDeleted { .. } => return None,
+        };
+
+        Some(Self {
+            kind: kind.into(),
+            task_id,
+            notification_id,
+        })
+    }
+
+    /// Tells the client it fell behind and missed events.
+    #[must_use]
+    pub fn resync() -> Self {
+        Self {
+            kind: "resync".into(),
+            task_id: None,
+            notification_id: None,
+        }
+    }
+}
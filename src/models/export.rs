@@ -0,0 +1,322 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::models::tasks::TaskResponse;
+
+/// Longest line allowed in an iCalendar file, in octets, before it has to be
+/// folded.
+const ICS_LINE_LIMIT: usize = 75;
+
+/// File formats tasks can be exported as.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+    Md,
+    Ics,
+}
+
+impl ExportFormat {
+    #[must_use]
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Md => "text/markdown; charset=utf-8",
+            Self::Ics => "text/calendar; charset=utf-8",
+        }
+    }
+
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+            Self::Md => "md",
+            Self::Ics => "ics",
+        }
+    }
+
+    /// Fresh serializer writing tasks in this format.
+    #[must_use]
+    pub fn serializer(self) -> Box<dyn TaskSerializer> {
+        match self {
+            Self::Json => Box::new(JsonSerializer::default()),
+            Self::Csv => Box::new(CsvSerializer),
+            Self::Md => Box::new(MarkdownSerializer),
+            Self::Ics => Box::new(IcsSerializer::new(Utc::now())),
+        }
+    }
+}
+
+/// Query parameters accepted by `GET /api/tasks/export`.
+#[derive(Debug, Deserialize, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// Format of the exported file, JSON when missing
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Writes tasks one at a time so an export can be streamed. The output of
+/// an export is the header, every task's row in order and then the footer.
+pub trait TaskSerializer: Send {
+    fn header(&self) -> String {
+        String::new()
+    }
+
+    fn row(&mut self, task: &TaskResponse) -> String;
+
+    fn footer(&self) -> String {
+        String::new()
+    }
+}
+
+/// A JSON array of [`TaskResponse`]s, the same as `GET /api/tasks` returns.
+#[derive(Debug, Default)]
+pub struct JsonSerializer {
+    written: bool,
+}
+
+impl TaskSerializer for JsonSerializer {
+    fn header(&self) -> String {
+        "[".into()
+    }
+
+    fn row(&mut self, task: &TaskResponse) -> String {
+        let separator = if self.written { "," } else { "" };
+        self.written = true;
+
+        // Serializing a struct of plain fields cannot fail
+        let task = serde_json::to_string(task).unwrap_or_default();
+        format!("{separator}{task}")
+    }
+
+    fn footer(&self) -> String {
+        "]".into()
+    }
+}
+
+/// RFC 4180 CSV with a header row. Labels and blockers are joined with `;`.
+#[derive(Debug, Default)]
+pub struct CsvSerializer;
+
+impl CsvSerializer {
+    const COLUMNS: [&'static str; 12] = [
+        "id",
+        "pid",
+        "title",
+        "done",
+        "projectId",
+        "parentId",
+        "dueAt",
+        "recurrence",
+        "assigneePid",
+        "labels",
+        "blockedBy",
+        "createdAt",
+    ];
+
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\r', '\n']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+impl TaskSerializer for CsvSerializer {
+    fn header(&self) -> String {
+        format!("{}\r\n", Self::COLUMNS.join(","))
+    }
+
+    fn row(&mut self, task: &TaskResponse) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        let labels = task
+            .labels
+            .iter()
+            .map(|label| label.name.as_str())
+            .collect::<Vec<&str>>()
+            .join(";");
+        let blocked_by = task
+            .blocked_by
+            .iter()
+            .map(i32::to_string)
+            .collect::<Vec<String>>()
+            .join(";");
+
+        let fields = [
+            task.id.to_string(),
+            task.pid.clone(),
+            task.title.clone(),
+            task.done.to_string(),
+            optional(task.project_id.map(|id| id.to_string())),
+            optional(task.parent_id.map(|id| id.to_string())),
+            optional(task.due_at.clone()),
+            optional(task.recurrence.clone()),
+            optional(task.assignee_pid.clone()),
+            labels,
+            blocked_by,
+            task.created_at.clone(),
+        ];
+
+        let row = fields
+            .iter()
+            .map(|field| Self::field(field))
+            .collect::<Vec<String>>()
+            .join(",");
+        format!("{row}\r\n")
+    }
+}
+
+/// A Markdown task list, e.g. `- [x] Buy milk (due 2025-05-01) #groceries`.
+#[derive(Debug, Default)]
+pub struct MarkdownSerializer;
+
+impl MarkdownSerializer {
+    fn escape(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '\\' | '`' | '*' | '_' | '[' | ']' | '#' | '<' | '>' | '|' => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                '\r' | '\n' => escaped.push(' '),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+}
+
+impl TaskSerializer for MarkdownSerializer {
+    fn header(&self) -> String {
+        "# Tasks\n\n".into()
+    }
+
+    fn row(&mut self, task: &TaskResponse) -> String {
+        let check = if task.done { 'x' } else { ' ' };
+        let mut line = format!("- [{check}] {}", Self::escape(&task.title));
+
+        if let Some(due_at) = task
+            .due_at
+            .as_deref()
+            .and_then(|due_at| DateTime::parse_from_rfc3339(due_at).ok())
+        {
+            let _ = write!(line, " (due {})", due_at.format("%Y-%m-%d"));
+        }
+        for label in &task.labels {
+            let _ = write!(line, " #{}", Self::escape(&label.name.replace(' ', "-")));
+        }
+
+        line.push('\n');
+        line
+    }
+}
+
+/// An iCalendar (RFC 5545) file holding a `VTODO` per task.
+#[derive(Debug)]
+pub struct IcsSerializer {
+    stamp: String,
+}
+
+impl IcsSerializer {
+    /// Serializer stamping every `VTODO` as created at `now`.
+    #[must_use]
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            stamp: Self::date_time(now),
+        }
+    }
+
+    fn date_time(value: DateTime<Utc>) -> String {
+        value.format("%Y%m%dT%H%M%SZ").to_string()
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('\\', "\\\\")
+            .replace(';', "\\;")
+            .replace(',', "\\,")
+            .replace("\r\n", "\\n")
+            .replace(['\r', '\n'], "\\n")
+    }
+
+    /// Content line ending in CRLF, folded so that no line is longer than
+    /// [`ICS_LINE_LIMIT`] octets. Folds never split a UTF-8 character.
+    fn line(out: &mut String, content: &str) {
+        let mut length = 0;
+        for c in content.chars() {
+            if length + c.len_utf8() > ICS_LINE_LIMIT {
+                out.push_str("\r\n ");
+                // The leading space counts towards the folded line
+                length = 1;
+            }
+            out.push(c);
+            length += c.len_utf8();
+        }
+        out.push_str("\r\n");
+    }
+}
+
+impl TaskSerializer for IcsSerializer {
+    fn header(&self) -> String {
+        let mut out = String::new();
+        Self::line(&mut out, "BEGIN:VCALENDAR");
+        Self::line(&mut out, "VERSION:2.0");
+        Self::line(&mut out, "PRODID:-//tasks-authenticated//Tasks Export//EN");
+        Self::line(&mut out, "CALSCALE:GREGORIAN");
+        out
+    }
+
+    fn row(&mut self, task: &TaskResponse) -> String {
+        let mut out = String::new();
+        Self::line(&mut out, "BEGIN:VTODO");
+        Self::line(&mut out, &format!("UID:{}", task.pid));
+        Self::line(&mut out, &format!("DTSTAMP:{}", self.stamp));
+        Self::line(&mut out, &format!("SUMMARY:{}", Self::escape(&task.title)));
+
+        if let Some(due_at) = task
+            .due_at
+            .as_deref()
+            .and_then(|due_at| DateTime::parse_from_rfc3339(due_at).ok())
+        {
+            let due_at = Self::date_time(due_at.with_timezone(&Utc));
+            Self::line(&mut out, &format!("DUE:{due_at}"));
+        }
+        if let Some(recurrence) = &task.recurrence {
+            Self::line(&mut out, &format!("RRULE:{recurrence}"));
+        }
+        if !task.labels.is_empty() {
+            let categories = task
+                .labels
+                .iter()
+                .map(|label| Self::escape(&label.name))
+                .collect::<Vec<String>>()
+                .join(",");
+            Self::line(&mut out, &format!("CATEGORIES:{categories}"));
+        }
+
+        if task.done {
+            Self::line(&mut out, "STATUS:COMPLETED");
+            Self::line(&mut out, "PERCENT-COMPLETE:100");
+        } else {
+            Self::line(&mut out, "STATUS:NEEDS-ACTION");
+        }
+
+        Self::line(&mut out, "END:VTODO");
+        out
+    }
+
+    fn footer(&self) -> String {
+        let mut out = String::new();
+        Self::line(&mut out, "END:VCALENDAR");
+        out
+    }
+}
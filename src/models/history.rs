@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use utoipa::ToSchema;
+
+use crate::repositories::history::TaskHistory;
+
+/// Fields that change with every write and would only add noise.
+const IGNORED_FIELDS: [&str; 2] = ["updated_at", "version"];
+
+/// Field by field difference between two snapshots of a record, as
+/// `{"field": {"from": old, "to": new}}` holding only the fields that
+/// changed.
+///
+/// A missing snapshot counts as every field being `null`, so the diff of a
+/// created record lists its initial values and that of a deleted record its
+/// last ones.
+#[must_use]
+pub fn diff(before: Option<&Value>, after: Option<&Value>) -> Map<String, Value> {
+    let empty = Map::new();
+    let before = before.and_then(Value::as_object).unwrap_or(&empty);
+    let after = after.and_then(Value::as_object).unwrap_or(&empty);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        if IGNORED_FIELDS.contains(&key.as_str()) || changes.contains_key(key) {
+            continue;
+        }
+
+        let from = before.get(key).unwrap_or(&Value::Null);
+        let to = after.get(key).unwrap_or(&Value::Null);
+        if from != to {
+            changes.insert(key.clone(), json!({ "from": from, "to": to }));
+        }
+    }
+
+    changes
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryResponse {
+    pub id: i32,
+    pub task_id: i32,
+    /// User who made the change, `null` for changes made by the service
+    pub actor_pid: Option<String>,
+    pub actor: Option<String>,
+    /// One of `created`, `updated`, `deleted`, `restored` or `purged`
+    pub action: String,
+    /// Changed fields as `{"field": {"from": old, "to": new}}`
+    #[schema(value_type = Object)]
+    pub changes: Value,
+    pub created_at: String,
+}
+
+impl From<TaskHistory> for HistoryResponse {
+    fn from(value: TaskHistory) -> Self {
+        Self {
+            id: value.id,
+            task_id: value.task_id,
+            actor_pid: value.actor_pid.map(|actor_pid| actor_pid.to_string()),
+            actor: value.actor,
+            action: value.action,
+            changes: value.changes.0,
+            created_at: value.created_at.format("%d-%m-%Y %H:%M:%S").to_string(),
+        }
+    }
+}
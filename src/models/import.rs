@@ -0,0 +1,655 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    Error,
+    models::{
+        Validator,
+        tasks::{NewTask, TaskResponse},
+    },
+    repositories::tasks::MAX_TASK_DEPTH,
+};
+
+/// Most tasks a single import may hold.
+pub const MAX_IMPORT_ROWS: usize = 5000;
+
+/// Longest label name, as for labels created directly.
+const MAX_LABEL_LENGTH: usize = 50;
+
+/// File formats tasks can be imported from.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// CSV with a header row, columns picked with the `*Column` parameters
+    Csv,
+    /// A JSON export of this API
+    Json,
+    /// One task per line in the todo.txt format
+    TodoTxt,
+    /// Todoist JSON backup or API response
+    Todoist,
+    /// Trello board JSON export, checklist items become subtasks
+    Trello,
+}
+
+fn title_column() -> String {
+    "title".into()
+}
+
+fn done_column() -> String {
+    "done".into()
+}
+
+fn due_column() -> String {
+    "dueAt".into()
+}
+
+fn labels_column() -> String {
+    "labels".into()
+}
+
+/// Query parameters accepted by `POST /api/tasks/import`.
+#[derive(Debug, Deserialize, Clone, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    pub format: ImportFormat,
+    /// Only check the file and report what is wrong with it
+    #[serde(default)]
+    pub dry_run: bool,
+    /// CSV column holding the title
+    #[serde(default = "title_column")]
+    #[param(default = "title")]
+    pub title_column: String,
+    /// CSV column holding whether the task is done
+    #[serde(default = "done_column")]
+    #[param(default = "done")]
+    pub done_column: String,
+    /// CSV column holding the due date
+    #[serde(default = "due_column")]
+    #[param(default = "dueAt")]
+    pub due_column: String,
+    /// CSV column holding label names separated by `;`
+    #[serde(default = "labels_column")]
+    #[param(default = "labels")]
+    pub labels_column: String,
+}
+
+/// A task read from an import file, not saved yet.
+#[derive(Debug, Clone)]
+pub struct ImportedTask {
+    /// Row of the file the task came from, counting from 1
+    pub row: usize,
+    /// Id of the task in the file, for its subtasks to refer to
+    pub key: Option<String>,
+    /// Id in the file of the task this one is nested under
+    pub parent: Option<String>,
+    pub task: NewTask,
+    pub labels: Vec<String>,
+    /// Fields that could not be read, with what is wrong with them
+    pub errors: BTreeMap<String, String>,
+}
+
+impl ImportedTask {
+    fn new(row: usize, title: impl Into<String>) -> Self {
+        Self {
+            row,
+            key: None,
+            parent: None,
+            task: NewTask {
+                title: title.into().trim().to_string(),
+                done: false,
+                project_id: None,
+                parent_id: None,
+                due_at: None,
+                recurrence: None,
+            },
+            labels: Vec::new(),
+            errors: BTreeMap::new(),
+        }
+    }
+
+    fn invalid(row: usize, message: impl Into<String>) -> Self {
+        let mut task = Self::new(row, "");
+        task.errors.insert("row".into(), message.into());
+        task
+    }
+
+    fn set_due(&mut self, value: &str) {
+        match parse_due(value) {
+            Ok(due_at) => self.task.due_at = due_at,
+            Err(message) => {
+                self.errors.insert("dueAt".into(), message.into());
+            }
+        }
+    }
+
+    fn add_label(&mut self, name: &str) {
+        let name = name.trim();
+        if !name.is_empty() && !self.labels.iter().any(|label| label == name) {
+            self.labels.push(name.to_string());
+        }
+    }
+}
+
+/// Problems with one row of an import.
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRowError {
+    pub row: usize,
+    /// Messages keyed by field
+    pub errors: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResponse {
+    pub dry_run: bool,
+    /// Whether the tasks were saved
+    pub committed: bool,
+    /// Tasks found in the file
+    pub total: usize,
+    pub errors: Vec<ImportRowError>,
+    /// Tasks created, empty unless committed
+    pub tasks: Vec<TaskResponse>,
+}
+
+/// Error for a file that cannot be read at all.
+fn unreadable(message: impl Into<String>) -> Error {
+    Error::Validation(serde_json::json!({ "body": message.into() }).to_string())
+}
+
+/// Reads the tasks in an import file, in file order. Rows that cannot be
+/// read are kept with their errors so they can be reported with the rest.
+pub fn parse(query: &ImportQuery, body: &[u8]) -> Result<Vec<ImportedTask>, Error> {
+    let text = std::str::from_utf8(body).map_err(|_| unreadable("File must be UTF-8 text"))?;
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    let tasks = match query.format {
+        ImportFormat::Csv => parse_csv(query, text)?,
+        ImportFormat::Json => parse_json(text)?,
+        ImportFormat::TodoTxt => parse_todo_txt(text),
+        ImportFormat::Todoist => parse_todoist(text)?,
+        ImportFormat::Trello => parse_trello(text)?,
+    };
+
+    if tasks.len() > MAX_IMPORT_ROWS {
+        return Err(unreadable(format!(
+            "An import can hold at most {MAX_IMPORT_ROWS} tasks"
+        )));
+    }
+
+    Ok(tasks)
+}
+
+/// Every problem with the imported tasks, validating them the same way as
+/// tasks created one at a time. Empty when the import can be saved.
+#[must_use]
+pub fn check(tasks: &[ImportedTask]) -> Vec<ImportRowError> {
+    let parents = tasks
+        .iter()
+        .filter_map(|task| Some((task.key.as_deref()?, task.parent.as_deref())))
+        .collect::<HashMap<&str, Option<&str>>>();
+
+    let mut report = Vec::new();
+    for task in tasks {
+        let mut errors = task.errors.clone();
+        if errors.contains_key("row") {
+            report.push(ImportRowError {
+                row: task.row,
+                errors,
+            });
+            continue;
+        }
+
+        if let Err(Error::Validation(messages)) = Validator::new(&task.task).validate() {
+            let messages =
+                serde_json::from_str::<BTreeMap<String, String>>(&messages).unwrap_or_default();
+            for (field, message) in messages {
+                errors.entry(field).or_insert(message);
+            }
+        }
+
+        if task
+            .labels
+            .iter()
+            .any(|label| label.chars().count() > MAX_LABEL_LENGTH)
+        {
+            errors.insert(
+                "labels".into(),
+                "Name must be between 1 to 50 characters".into(),
+            );
+        }
+
+        if let Some(message) = task
+            .parent
+            .as_deref()
+            .and_then(|parent| nesting_error(&parents, parent))
+        {
+            errors.insert("parentId".into(), message.into());
+        }
+
+        if !errors.is_empty() {
+            report.push(ImportRowError {
+                row: task.row,
+                errors,
+            });
+        }
+    }
+
+    report
+}
+
+/// Why a task cannot be nested under `parent`, if it cannot.
+fn nesting_error(parents: &HashMap<&str, Option<&str>>, parent: &str) -> Option<&'static str> {
+    let mut depth = 1;
+    let mut current = Some(parent);
+    while let Some(key) = current {
+        let Some(&next) = parents.get(key) else {
+            return Some("Parent task is not part of the import");
+        };
+
+        depth += 1;
+        if depth > MAX_TASK_DEPTH {
+            // Also stops at cycles, which never end
+            return Some("Subtasks cannot be nested that deep");
+        }
+        current = next;
+    }
+
+    None
+}
+
+/// Parses a due date given as RFC 3339, a date and time without an offset
+/// (taken as UTC) or a bare date (midnight UTC). Blank means no due date.
+pub(crate) fn parse_due(value: &str) -> Result<Option<DateTime<FixedOffset>>, &'static str> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    if let Ok(due_at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(Some(due_at));
+    }
+
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|date| date.and_time(NaiveTime::MIN))
+        })
+        .map_err(|_| "Due date must be a date such as 2025-05-01 or 2025-05-01T09:00:00Z")?;
+
+    Ok(Some(naive.and_utc().fixed_offset()))
+}
+
+fn parse_done(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "" | "false" | "no" | "n" | "0" => Some(false),
+        "true" | "yes" | "y" | "1" | "x" | "done" | "completed" => Some(true),
+        _ => None,
+    }
+}
+
+/// Splits CSV text into records with the line each starts on. Quoted fields
+/// may hold commas, doubled quotes and line breaks. Blank lines are skipped.
+fn csv_records(text: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = 1;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            '\n' if quoted => {
+                line += 1;
+                field.push(c);
+            }
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|field| !field.is_empty()) {
+                    records.push((start, std::mem::take(&mut record)));
+                }
+                record.clear();
+                line += 1;
+                start = line;
+            }
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(format!(
+            "Quoted field starting on line {start} is never closed"
+        ));
+    }
+    record.push(field);
+    if record.iter().any(|field| !field.is_empty()) {
+        records.push((start, record));
+    }
+
+    Ok(records)
+}
+
+fn parse_csv(query: &ImportQuery, text: &str) -> Result<Vec<ImportedTask>, Error> {
+    let mut records = csv_records(text).map_err(unreadable)?.into_iter();
+    let Some((_, header)) = records.next() else {
+        return Ok(Vec::new());
+    };
+
+    let column = |name: &str| header.iter().position(|column| column.trim() == name);
+    let title = column(&query.title_column)
+        .ok_or_else(|| unreadable(format!("CSV has no `{}` column", query.title_column)))?;
+    let done = column(&query.done_column);
+    let due = column(&query.due_column);
+    let labels = column(&query.labels_column);
+
+    let tasks = records
+        .map(|(row, fields)| {
+            let field = |index: Option<usize>| {
+                index
+                    .and_then(|index| fields.get(index))
+                    .map_or("", String::as_str)
+            };
+
+            let mut task = ImportedTask::new(row, field(Some(title)));
+            match parse_done(field(done)) {
+                Some(done) => task.task.done = done,
+                None => {
+                    task.errors
+                        .insert("done".into(), "Done must be true or false".into());
+                }
+            }
+            task.set_due(field(due));
+            for label in field(labels).split(';') {
+                task.add_label(label);
+            }
+
+            task
+        })
+        .collect();
+
+    Ok(tasks)
+}
+
+fn parse_array(text: &str) -> Result<Vec<Value>, Error> {
+    serde_json::from_str(text).map_err(|e| unreadable(format!("File is not valid JSON: {e}")))
+}
+
+/// Id as written in a JSON file, which may be a number or a string.
+fn json_key(value: &Value) -> Option<String> {
+    match value {
+        Value::String(key) => Some(key.clone()),
+        Value::Number(key) => Some(key.to_string()),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedTask {
+    #[serde(default)]
+    id: Value,
+    #[serde(default)]
+    parent_id: Value,
+    title: String,
+    #[serde(default)]
+    done: bool,
+    due_at: Option<String>,
+    recurrence: Option<String>,
+    #[serde(default)]
+    labels: Vec<ExportedLabel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportedLabel {
+    name: String,
+}
+
+fn parse_json(text: &str) -> Result<Vec<ImportedTask>, Error> {
+    let tasks = parse_array(text)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let row = index + 1;
+            let exported = match serde_json::from_value::<ExportedTask>(value) {
+                Ok(exported) => exported,
+                Err(e) => return ImportedTask::invalid(row, e.to_string()),
+            };
+
+            let mut task = ImportedTask::new(row, exported.title);
+            task.key = json_key(&exported.id);
+            task.parent = json_key(&exported.parent_id);
+            task.task.done = exported.done;
+            task.task.recurrence = exported.recurrence;
+            task.set_due(exported.due_at.as_deref().unwrap_or_default());
+            for label in &exported.labels {
+                task.add_label(&label.name);
+            }
+
+            task
+        })
+        .collect();
+
+    Ok(tasks)
+}
+
+fn is_date(word: &str) -> bool {
+    NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok()
+}
+
+/// Reads one todo.txt line, e.g.
+/// `x 2025-04-20 2025-04-18 Call the plumber +house @phone due:2025-04-21`.
+/// Projects and contexts become labels.
+fn parse_todo_line(row: usize, line: &str) -> ImportedTask {
+    let mut words = line.split_whitespace().peekable();
+
+    let done = words.next_if_eq(&"x").is_some();
+    if !done {
+        // Priority, e.g. `(A)`
+        words.next_if(|word| word.len() == 3 && word.starts_with('(') && word.ends_with(')'));
+    }
+    // Completion and creation dates
+    words.next_if(|word| is_date(word));
+    words.next_if(|word| is_date(word));
+
+    let mut task = ImportedTask::new(row, "");
+    task.task.done = done;
+
+    let mut title = Vec::new();
+    for word in words {
+        match word.split_once(':') {
+            Some(("due", due)) => task.set_due(due),
+            Some(("pri" | "rec" | "t" | "h", _)) => {}
+            _ => match word.strip_prefix(['+', '@']) {
+                Some(label) if !label.is_empty() => task.add_label(label),
+                _ => title.push(word),
+            },
+        }
+    }
+    task.task.title = title.join(" ");
+
+    task
+}
+
+fn parse_todo_txt(text: &str) -> Vec<ImportedTask> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| parse_todo_line(index + 1, line))
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct TodoistTask {
+    #[serde(default)]
+    id: Value,
+    #[serde(default)]
+    parent_id: Value,
+    content: String,
+    #[serde(default, alias = "is_completed")]
+    checked: bool,
+    due: Option<TodoistDue>,
+    #[serde(default)]
+    labels: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TodoistDue {
+    date: Option<String>,
+    datetime: Option<String>,
+}
+
+fn parse_todoist(text: &str) -> Result<Vec<ImportedTask>, Error> {
+    let items = match serde_json::from_str::<Value>(text) {
+        Ok(Value::Array(items)) => items,
+        // Sync API backups list tasks under `items`, paginated API
+        // responses under `results`
+        Ok(Value::Object(mut backup)) => ["items", "results", "tasks"]
+            .into_iter()
+            .find_map(|key| match backup.remove(key) {
+                Some(Value::Array(items)) => Some(items),
+                _ => None,
+            })
+            .ok_or_else(|| unreadable("Todoist export holds no tasks"))?,
+        Ok(_) => return Err(unreadable("Todoist export holds no tasks")),
+        Err(e) => return Err(unreadable(format!("File is not valid JSON: {e}"))),
+    };
+
+    let tasks = items
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let row = index + 1;
+            let item = match serde_json::from_value::<TodoistTask>(value) {
+                Ok(item) => item,
+                Err(e) => return ImportedTask::invalid(row, e.to_string()),
+            };
+
+            let mut task = ImportedTask::new(row, item.content);
+            task.key = json_key(&item.id);
+            task.parent = json_key(&item.parent_id);
+            task.task.done = item.checked;
+            if let Some(due) = item.due {
+                task.set_due(due.datetime.or(due.date).as_deref().unwrap_or_default());
+            }
+            for label in &item.labels {
+                task.add_label(label);
+            }
+
+            task
+        })
+        .collect();
+
+    Ok(tasks)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloBoard {
+    #[serde(default)]
+    cards: Vec<TrelloCard>,
+    #[serde(default)]
+    labels: Vec<TrelloLabel>,
+    #[serde(default)]
+    checklists: Vec<TrelloChecklist>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloCard {
+    id: String,
+    name: String,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    due_complete: bool,
+    due: Option<String>,
+    #[serde(default)]
+    id_labels: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrelloLabel {
+    id: String,
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloChecklist {
+    id_card: String,
+    #[serde(default)]
+    check_items: Vec<TrelloCheckItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrelloCheckItem {
+    name: String,
+    #[serde(default)]
+    state: String,
+    due: Option<String>,
+}
+
+/// Reads the open cards of a Trello board, followed by their checklist
+/// items as subtasks. Rows count cards first, then checklist items.
+fn parse_trello(text: &str) -> Result<Vec<ImportedTask>, Error> {
+    let board = serde_json::from_str::<TrelloBoard>(text)
+        .map_err(|e| unreadable(format!("File is not a Trello board export: {e}")))?;
+
+    let labels = board
+        .labels
+        .iter()
+        .map(|label| (label.id.as_str(), label.name.as_deref().unwrap_or_default()))
+        .collect::<HashMap<&str, &str>>();
+
+    let mut tasks = Vec::new();
+    for card in board.cards.iter().filter(|card| !card.closed) {
+        let mut task = ImportedTask::new(tasks.len() + 1, card.name.as_str());
+        task.key = Some(card.id.clone());
+        task.task.done = card.due_complete;
+        task.set_due(card.due.as_deref().unwrap_or_default());
+        for id in &card.id_labels {
+            task.add_label(labels.get(id.as_str()).copied().unwrap_or_default());
+        }
+
+        tasks.push(task);
+    }
+
+    for checklist in &board.checklists {
+        if !tasks
+            .iter()
+            .any(|task| task.key.as_deref() == Some(checklist.id_card.as_str()))
+        {
+            continue;
+        }
+
+        for item in &checklist.check_items {
+            let mut task = ImportedTask::new(tasks.len() + 1, item.name.as_str());
+            task.parent = Some(checklist.id_card.clone());
+            task.task.done = item.state == "complete";
+            task.set_due(item.due.as_deref().unwrap_or_default());
+
+            tasks.push(task);
+        }
+    }
+
+    Ok(tasks)
+}
@@ -0,0 +1,196 @@
+use chrono::{DateTime, FixedOffset};
+use mail_parser::{MessageParser, MimeHeaders};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    Error,
+    config::InboundConfig,
+    models::{
+        Validator,
+        attachments::{Upload, detect_content_type, sanitize_filename},
+        import::parse_due,
+        tasks::NewTask,
+    },
+    repositories::inbound::InboundAddress,
+};
+
+/// Random bytes in the secret part of an inbound address.
+pub const TOKEN_BYTES: usize = 16;
+
+/// Longest task title, longer subjects are cut.
+const MAX_TITLE_LEN: usize = 255;
+
+/// Longest note, as for comments written directly.
+const MAX_NOTE_LEN: usize = 5000;
+
+/// Prefixes mail clients put before the subject of replies and forwards.
+const SUBJECT_PREFIXES: [&str; 5] = ["re:", "fw:", "fwd:", "aw:", "wg:"];
+
+/// A task read from an email, not saved yet.
+#[derive(Debug, Clone)]
+pub struct InboundMail {
+    /// `Message-ID` header, which tells a message delivered again apart
+    pub message_id: Option<String>,
+    pub task: NewTask,
+    /// Text of the body below the directives, saved as a comment
+    pub note: Option<String>,
+    /// Attached files, not yet checked against the storage limits
+    pub attachments: Vec<Upload>,
+}
+
+/// Error for a message that cannot be turned into a task.
+fn unreadable(field: &str, message: impl Into<String>) -> Error {
+    Error::Validation(serde_json::json!({ field: message.into() }).to_string())
+}
+
+/// Reads an email the way the SMTP listener receives it. The subject
+/// becomes the title, `Due:` and `Repeat:` lines at the top of the body set
+/// the due date and recurrence rule, the rest of the body up to the
+/// signature becomes a note and attached files are kept.
+pub fn parse(raw: &[u8]) -> Result<InboundMail, Error> {
+    let message = MessageParser::default()
+        .parse(raw)
+        .ok_or_else(|| unreadable("body", "Message is not an email"))?;
+
+    let mut task = NewTask {
+        title: clean_subject(message.subject().unwrap_or_default()),
+        done: false,
+        project_id: None,
+        parent_id: None,
+        due_at: None,
+        recurrence: None,
+    };
+
+    let body = message.body_text(0).unwrap_or_default();
+    let directives = read_directives(&body)?;
+    task.due_at = directives.due_at;
+    task.recurrence = directives.recurrence;
+
+    let validator = Validator::new(task);
+    validator.validate()?;
+
+    let attachments = message
+        .attachments()
+        .map(|part| {
+            let declared = part.content_type().map(|content_type| {
+                content_type.subtype().map_or_else(
+                    || content_type.ctype().to_string(),
+                    |subtype| format!("{}/{subtype}", content_type.ctype()),
+                )
+            });
+            let data = part.contents();
+
+            Upload {
+                filename: sanitize_filename(part.attachment_name().unwrap_or_default()),
+                content_type: detect_content_type(data, declared.as_deref()),
+                data: data.to_vec().into(),
+            }
+        })
+        .collect();
+
+    Ok(InboundMail {
+        message_id: message.message_id().map(str::to_string),
+        task: validator.0,
+        note: directives.note,
+        attachments,
+    })
+}
+
+/// The subject without reply and forward prefixes or folding whitespace,
+/// cut to the longest title.
+fn clean_subject(subject: &str) -> String {
+    let mut subject = subject.trim();
+    while let Some(prefix) = SUBJECT_PREFIXES.iter().find(|prefix| {
+        subject
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    }) {
+        subject = subject[prefix.len()..].trim_start();
+    }
+
+    subject
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .chars()
+        .take(MAX_TITLE_LEN)
+        .collect()
+}
+
+#[derive(Debug, Default)]
+struct Directives {
+    due_at: Option<DateTime<FixedOffset>>,
+    recurrence: Option<String>,
+    note: Option<String>,
+}
+
+/// Splits the body into its leading `Key: value` directives and the note
+/// after them. The note ends at a `-- ` signature separator.
+fn read_directives(body: &str) -> Result<Directives, Error> {
+    let mut directives = Directives::default();
+    let mut lines = body
+        .lines()
+        .skip_while(|line| line.trim().is_empty())
+        .peekable();
+
+    while let Some((key, value)) = lines.peek().copied().and_then(|line| line.split_once(':')) {
+        match key.trim().to_ascii_lowercase().as_str() {
+            "due" => {
+                directives.due_at =
+                    parse_due(value).map_err(|message| unreadable("due", message))?;
+            }
+            "repeat" => {
+                let rule = value.trim();
+                directives.recurrence = (!rule.is_empty()).then(|| rule.to_string());
+            }
+            _ => break,
+        }
+        lines.next();
+    }
+
+    let note = lines
+        .take_while(|line| line.trim_end_matches('\r') != "-- ")
+        .collect::<Vec<&str>>()
+        .join("\n");
+    let note = note.trim();
+    directives.note = (!note.is_empty()).then(|| note.chars().take(MAX_NOTE_LEN).collect());
+
+    Ok(directives)
+}
+
+/// The token in an inbound address at `domain`, `None` for any other
+/// address. Angle brackets around the address are ignored.
+#[must_use]
+pub fn recipient_token(address: &str, domain: &str) -> Option<String> {
+    let address = address.trim();
+    let address = address
+        .strip_prefix('<')
+        .and_then(|address| address.strip_suffix('>'))
+        .unwrap_or(address);
+    let (token, host) = address.rsplit_once('@')?;
+
+    let valid = host.eq_ignore_ascii_case(domain)
+        && token.len() == TOKEN_BYTES * 2
+        && token.chars().all(|c| c.is_ascii_hexdigit());
+
+    valid.then(|| token.to_ascii_lowercase())
+}
+
+/// Address the current user can forward emails to, each becomes a task.
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboundAddressResponse {
+    pub address: String,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+impl InboundAddressResponse {
+    #[must_use]
+    pub fn new(value: &InboundAddress, config: &InboundConfig) -> Self {
+        Self {
+            address: config.mailbox(&value.token),
+            created_at: value.created_at,
+        }
+    }
+}
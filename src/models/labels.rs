@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+use crate::repositories::labels::Label;
+
+/// Colours are stored as `#rrggbb` hex strings.
+fn validate_colour(colour: &str) -> Result<(), ValidationError> {
+    let valid = colour.len() == 7
+        && colour.starts_with('#')
+        && colour[1..].chars().all(|c| c.is_ascii_hexdigit());
+
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError::new("colour")
+            .with_message("Colour must be a hex value such as #1e90ff".into()))
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+pub struct NewLabel {
+    #[validate(length(min = 1, max = 50, message = "Name must be between 1 to 50 characters"))]
+    pub name: String,
+    #[validate(custom(function = "validate_colour"))]
+    pub colour: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+pub struct UpdateLabel {
+    #[validate(length(min = 1, max = 50, message = "Name must be between 1 to 50 characters"))]
+    pub name: Option<String>,
+    #[validate(custom(function = "validate_colour"))]
+    pub colour: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelResponse {
+    pub id: i32,
+    pub pid: String,
+    pub name: String,
+    pub colour: String,
+}
+
+impl From<Label> for LabelResponse {
+    fn from(value: Label) -> Self {
+        Self {
+            id: value.id,
+            pid: value.pid.to_string(),
+            name: value.name,
+            colour: value.colour,
+        }
+    }
+}
@@ -1,5 +1,21 @@
+pub mod account;
+pub mod attachments;
 pub mod auth;
+pub mod bulk;
+pub mod comments;
+pub mod events;
+pub mod export;
+pub mod history;
+pub mod import;
+pub mod inbound;
+pub mod labels;
+pub mod notifications;
+pub mod projects;
+pub mod recurrence;
+pub mod shares;
+pub mod sync;
 pub mod tasks;
 pub mod validator;
+pub mod webhooks;
 
 pub use self::validator::Validator;
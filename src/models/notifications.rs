@@ -0,0 +1,290 @@
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    events::Event,
+    models::webhooks::WebhookEvent,
+    repositories::notifications::{Notification, NotificationPreference},
+};
+
+/// Most notifications listed at once.
+pub const MAX_NOTIFICATIONS: i64 = 100;
+
+/// What a notification tells its recipient about.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationKind {
+    /// Someone mentioned the user in a comment
+    Mentioned,
+    /// Someone made the user responsible for a task
+    Assigned,
+    /// Someone shared a task or project with the user
+    Shared,
+    /// A task the user is responsible for falls due soon
+    Reminder,
+}
+
+impl NotificationKind {
+    pub const ALL: [Self; 4] = [
+        Self::Mentioned,
+        Self::Assigned,
+        Self::Shared,
+        Self::Reminder,
+    ];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Mentioned => "mentioned",
+            Self::Assigned => "assigned",
+            Self::Shared => "shared",
+            Self::Reminder => "reminder",
+        }
+    }
+}
+
+impl std::str::FromStr for NotificationKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mentioned" => Ok(Self::Mentioned),
+            "assigned" => Ok(Self::Assigned),
+            "shared" => Ok(Self::Shared),
+            "reminder" => Ok(Self::Reminder),
+            _ => Err(format!("Unknown notification kind {s}")),
+        }
+    }
+}
+
+/// A notification an [`Event`] calls for, before its channels are known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewNotification {
+    pub user_pid: Uuid,
+    pub kind: NotificationKind,
+    pub task_id: Option<i32>,
+    pub project_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub actor_pid: Option<Uuid>,
+    pub due_at: Option<DateTime<FixedOffset>>,
+}
+
+impl NewNotification {
+    /// The notification `event` calls for, `None` for events nobody is
+    /// notified of.
+    #[must_use]
+    pub fn for_event(event: &Event) -> Option<Self> {
+        let new = |user_pid, kind, actor_pid| Self {
+            user_pid,
+            kind,
+            task_id: None,
+            project_id: None,
+            comment_id: None,
+            actor_pid,
+            due_at: None,
+        };
+
+        let notification = match *event {
+            Event::Mentioned {
+                task_id,
+                comment_id,
+                user_pid,
+                mentioned_by,
+            } => Self {
+                task_id: Some(task_id),
+                comment_id: Some(comment_id),
+                ..new(user_pid, NotificationKind::Mentioned, Some(mentioned_by))
+            },
+            Event::TaskAssigned {
+                task_id,
+                assignee_pid,
+                assigned_by,
+            } => Self {
+                task_id: Some(task_id),
+                ..new(assignee_pid, NotificationKind::Assigned, Some(assigned_by))
+            },
+            Event::TaskShared {
+                task_id,
+                user_pid,
+                shared_by,
+            } => Self {
+                task_id: Some(task_id),
+                ..new(user_pid, NotificationKind::Shared, Some(shared_by))
+            },
+            Event::ProjectShared {
+                project_id,
+                user_pid,
+                shared_by,
+            } => Self {
+                project_id: Some(project_id),
+                ..new(user_pid, NotificationKind::Shared, Some(shared_by))
+            },
+            Event::TaskDue {
+                task_id,
+                user_pid,
+                due_at,
+            } => Self {
+                task_id: Some(task_id),
+                due_at: Some(due_at),
+                ..new(user_pid, NotificationKind::Reminder, None)
+            },
+            Event::TaskCreated { .. }
+            | Event::TaskUpdated { .. }
+            | Event::TaskDeleted { .. }
+            | Event::Notified { .. }
+            | Event::UserCreated { .. }
+            | Event::UserDeleted { .. } => return None,
+        };
+
+        Some(notification)
+    }
+
+    /// Text of the notification, naming the task or project as `subject`
+    /// and who caused it as `actor`.
+    #[must_use]
+    pub fn message(&self, subject: &str, actor: Option<&str>) -> String {
+        let actor = actor.unwrap_or("Someone");
+
+        match self.kind {
+            NotificationKind::Mentioned => format!("{actor} mentioned you on \"{subject}\""),
+            NotificationKind::Assigned => format!("{actor} assigned \"{subject}\" to you"),
+            NotificationKind::Shared => format!("{actor} shared \"{subject}\" with you"),
+            NotificationKind::Reminder => match self.due_at {
+                Some(due_at) => format!(
+                    "\"{subject}\" is due {}",
+                    due_at.format("%Y-%m-%d %H:%M %:z")
+                ),
+                None => format!("\"{subject}\" is due soon"),
+            },
+        }
+    }
+}
+
+/// Query parameters accepted by `GET /api/notifications`.
+#[derive(Debug, Deserialize, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NotificationQuery {
+    /// Only list unread notifications
+    #[serde(default)]
+    pub unread: bool,
+    /// Only list notifications older than the one with this id
+    pub before: Option<i64>,
+    /// Most notifications to return, at most 100
+    pub limit: Option<i64>,
+}
+
+impl NotificationQuery {
+    #[must_use]
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(MAX_NOTIFICATIONS)
+            .clamp(1, MAX_NOTIFICATIONS)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationResponse {
+    pub id: i64,
+    pub kind: NotificationKind,
+    pub message: String,
+    pub task_id: Option<i32>,
+    pub project_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub read: bool,
+    pub read_at: Option<DateTime<FixedOffset>>,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+impl From<Notification> for NotificationResponse {
+    fn from(value: Notification) -> Self {
+        Self {
+            id: value.id,
+            kind: value.kind.parse().unwrap_or(NotificationKind::Mentioned),
+            message: value.message,
+            task_id: value.task_id,
+            project_id: value.project_id,
+            comment_id: value.comment_id,
+            read: value.read_at.is_some(),
+            read_at: value.read_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// A page of the inbox, newest first.
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationPage {
+    pub notifications: Vec<NotificationResponse>,
+    /// Unread notifications in the whole inbox
+    pub unread_count: i64,
+    /// Pass as `before` to get the next page, `null` on the last page
+    pub next_before: Option<i64>,
+}
+
+impl NotificationPage {
+    #[must_use]
+    pub fn new(notifications: Vec<Notification>, unread_count: i64, limit: i64) -> Self {
+        let full = i64::try_from(notifications.len()).unwrap_or(i64::MAX) >= limit;
+        let next_before = notifications.last().filter(|_| full).map(|last| last.id);
+
+        Self {
+            notifications: notifications.into_iter().map(Into::into).collect(),
+            unread_count,
+            next_before,
+        }
+    }
+}
+
+/// Channels one kind of notification is sent on.
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreferenceResponse {
+    pub kind: NotificationKind,
+    /// Shown in the inbox and pushed over the event stream
+    pub in_app: bool,
+    /// Mailed to the account's email address
+    pub email: bool,
+    /// Sent to the user's webhooks that subscribe to `notification`
+    pub webhook: bool,
+}
+
+impl From<NotificationPreference> for PreferenceResponse {
+    fn from(value: NotificationPreference) -> Self {
+        Self {
+            kind: value.kind.parse().unwrap_or(NotificationKind::Mentioned),
+            in_app: value.in_app,
+            email: value.email,
+            webhook: value.webhook,
+        }
+    }
+}
+
+/// Channels to turn on or off, missing channels are left as they are.
+#[derive(Debug, Deserialize, Clone, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePreference {
+    pub in_app: Option<bool>,
+    pub email: Option<bool>,
+    pub webhook: Option<bool>,
+}
+
+/// Body of a `notification` webhook delivery.
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationPayload {
+    /// Always `notification`
+    #[serde(rename = "type")]
+    pub event: WebhookEvent,
+    pub kind: NotificationKind,
+    pub message: String,
+    pub task_id: Option<i32>,
+    pub project_id: Option<i32>,
+    /// Id of the notification in the inbox, `null` when the user does not
+    /// get this kind in-app
+    pub notification_id: Option<i64>,
+    pub occurred_at: DateTime<FixedOffset>,
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::repositories::projects::Project;
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+pub struct NewProject {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 to 100 characters"
+    ))]
+    pub name: String,
+    #[validate(length(max = 2000, message = "Description must be at most 2000 characters"))]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+pub struct UpdateProject {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 to 100 characters"
+    ))]
+    pub name: Option<String>,
+    #[validate(length(max = 2000, message = "Description must be at most 2000 characters"))]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectResponse {
+    pub id: i32,
+    pub pid: String,
+    pub user_pid: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: String,
+}
+
+impl From<Project> for ProjectResponse {
+    fn from(value: Project) -> Self {
+        Self {
+            id: value.id,
+            pid: value.pid.to_string(),
+            user_pid: value.user_pid.to_string(),
+            name: value.name,
+            description: value.description,
+            created_at: value.created_at.format("%d-%m-%Y %H:%M:%S").to_string(),
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, Months, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+use validator::ValidationError;
+
+/// Upper bound on the occurrences skipped while catching up with a series
+/// whose due date lies far in the past.
+const MAX_CATCH_UP: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// Recurrence rule using a subset of the iCalendar RRULE syntax, e.g.
+/// `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH;UNTIL=20261231`.
+///
+/// Supported parts are `FREQ` (`DAILY`, `WEEKLY`, `MONTHLY`), `INTERVAL`,
+/// `BYDAY` (weekly only), `BYMONTHDAY` (monthly only) and `UNTIL`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<Weekday>,
+    pub by_month_day: Option<u32>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid recurrence rule: {0}")]
+pub struct RecurrenceError(String);
+
+fn parse_weekday(day: &str) -> Result<Weekday, RecurrenceError> {
+    match day {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => Err(RecurrenceError(format!("unknown weekday {day}"))),
+    }
+}
+
+const fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn parse_until(value: &str) -> Result<DateTime<Utc>, RecurrenceError> {
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ") {
+        return Ok(datetime.and_utc());
+    }
+
+    // A bare date includes the whole day
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+        .map(|datetime| datetime.and_utc())
+        .ok_or_else(|| {
+            RecurrenceError(format!(
+                "UNTIL must be YYYYMMDD or YYYYMMDDTHHMMSSZ, got {value}"
+            ))
+        })
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap_or_default();
+    let next = first.checked_add_months(Months::new(1)).unwrap_or(first);
+
+    u32::try_from((next - first).num_days()).unwrap_or(28)
+}
+
+impl FromStr for Recurrence {
+    type Err = RecurrenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule = s.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut by_day = Vec::new();
+        let mut by_month_day = None;
+        let mut until = None;
+
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| RecurrenceError(format!("expected KEY=VALUE, got {part}")))?;
+
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        other => {
+                            return Err(RecurrenceError(format!("unsupported FREQ {other}")));
+                        }
+                    });
+                }
+                "INTERVAL" => {
+                    interval = value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|interval| (1..=365).contains(interval))
+                        .ok_or_else(|| {
+                            RecurrenceError("INTERVAL must be between 1 and 365".into())
+                        })?;
+                }
+                "BYDAY" => {
+                    by_day = value
+                        .split(',')
+                        .map(|day| parse_weekday(&day.trim().to_ascii_uppercase()))
+                        .collect::<Result<Vec<Weekday>, _>>()?;
+                    by_day.sort_by_key(Weekday::num_days_from_monday);
+                    by_day.dedup();
+                }
+                "BYMONTHDAY" => {
+                    by_month_day = Some(
+                        value
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|day| (1..=31).contains(day))
+                            .ok_or_else(|| {
+                                RecurrenceError("BYMONTHDAY must be between 1 and 31".into())
+                            })?,
+                    );
+                }
+                "UNTIL" => until = Some(parse_until(value)?),
+                other => return Err(RecurrenceError(format!("unsupported part {other}"))),
+            }
+        }
+
+        let frequency = frequency.ok_or_else(|| RecurrenceError("FREQ is required".into()))?;
+
+        if !by_day.is_empty() && frequency != Frequency::Weekly {
+            return Err(RecurrenceError(
+                "BYDAY is only supported with FREQ=WEEKLY".into(),
+            ));
+        }
+        if by_month_day.is_some() && frequency != Frequency::Monthly {
+            return Err(RecurrenceError(
+                "BYMONTHDAY is only supported with FREQ=MONTHLY".into(),
+            ));
+        }
+
+        Ok(Self {
+            frequency,
+            interval,
+            by_day,
+            by_month_day,
+            until,
+        })
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+        };
+        write!(f, "FREQ={frequency}")?;
+
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days = self
+                .by_day
+                .iter()
+                .map(|day| weekday_code(*day))
+                .collect::<Vec<&str>>()
+                .join(",");
+            write!(f, ";BYDAY={days}")?;
+        }
+        if let Some(day) = self.by_month_day {
+            write!(f, ";BYMONTHDAY={day}")?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%dT%H%M%SZ"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Recurrence {
+    /// The first occurrence strictly after `after`, keeping its time of day
+    /// and offset. `None` once the series has passed `UNTIL`.
+    #[must_use]
+    pub fn next_after(&self, after: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        let date = after.date_naive();
+
+        let next = match self.frequency {
+            Frequency::Daily => date.checked_add_days(Days::new(u64::from(self.interval)))?,
+            Frequency::Weekly => self.next_weekly(date)?,
+            Frequency::Monthly => self.next_monthly(date)?,
+        };
+
+        let next = after
+            .offset()
+            .from_local_datetime(&next.and_time(after.time()))
+            .single()?;
+
+        match self.until {
+            Some(until) if next.with_timezone(&Utc) > until => None,
+            _ => Some(next),
+        }
+    }
+
+    /// The first occurrence after `after` that also lies after `not_before`,
+    /// skipping the occurrences missed while a task sat overdue.
+    #[must_use]
+    pub fn next_occurrence(
+        &self,
+        after: DateTime<FixedOffset>,
+        not_before: DateTime<FixedOffset>,
+    ) -> Option<DateTime<FixedOffset>> {
+        let mut next = self.next_after(after)?;
+        for _ in 0..MAX_CATCH_UP {
+            if next > not_before {
+                return Some(next);
+            }
+            next = self.next_after(next)?;
+        }
+
+        Some(next)
+    }
+
+    fn next_weekly(&self, date: NaiveDate) -> Option<NaiveDate> {
+        if self.by_day.is_empty() {
+            return date.checked_add_days(Days::new(7 * u64::from(self.interval)));
+        }
+
+        let offset = date.weekday().num_days_from_monday();
+        let week_start = date.checked_sub_days(Days::new(u64::from(offset)))?;
+
+        // A later day in the current week
+        if let Some(day) = self
+            .by_day
+            .iter()
+            .map(|day| day.num_days_from_monday())
+            .find(|day| *day > offset)
+        {
+            return week_start.checked_add_days(Days::new(u64::from(day)));
+        }
+
+        // Otherwise the first listed day, `interval` weeks on
+        let first = self.by_day.first()?.num_days_from_monday();
+        week_start.checked_add_days(Days::new(7 * u64::from(self.interval) + u64::from(first)))
+    }
+
+    fn next_monthly(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let day = self.by_month_day.unwrap_or_else(|| date.day());
+        let month_start = date.with_day(1)?;
+
+        // The rule's day may still be ahead in the current month
+        let this_month = day.min(days_in_month(date.year(), date.month()));
+        if this_month > date.day() {
+            return date.with_day(this_month);
+        }
+
+        let target = month_start.checked_add_months(Months::new(self.interval))?;
+        target.with_day(day.min(days_in_month(target.year(), target.month())))
+    }
+}
+
+/// Validator hook for fields holding a recurrence rule.
+pub fn validate_recurrence(rule: &str) -> Result<(), ValidationError> {
+    rule.parse::<Recurrence>()
+        .map(|_| ())
+        .map_err(|e| ValidationError::new("recurrence").with_message(e.to_string().into()))
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::repositories::{permissions::Permission, shares::Share};
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+pub struct NewShare {
+    /// Username of the user to share with
+    #[validate(length(min = 1, message = "Username is required"))]
+    pub username: String,
+    pub permission: Permission,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareResponse {
+    pub user_pid: String,
+    pub username: String,
+    pub permission: String,
+    pub created_at: String,
+}
+
+impl From<Share> for ShareResponse {
+    fn from(value: Share) -> Self {
+        Self {
+            user_pid: value.user_pid.to_string(),
+            username: value.username,
+            permission: value.permission,
+            created_at: value.created_at.format("%d-%m-%Y %H:%M:%S").to_string(),
+        }
+    }
+}
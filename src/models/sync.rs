@@ -0,0 +1,202 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    Error,
+    models::{
+        Validator,
+        tasks::{NewTask, TaskResponse, UpdateTask},
+    },
+};
+
+/// Most changes one sync page or push may hold.
+pub const MAX_SYNC_CHANGES: usize = 500;
+
+/// Position in the stream of task changes a client has synced up to: the
+/// change sequence and id of the last change it received.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SyncToken {
+    pub seq: i64,
+    pub id: i32,
+}
+
+impl fmt::Display for SyncToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.seq, self.id)
+    }
+}
+
+impl FromStr for SyncToken {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split_once('-')
+            .and_then(|(seq, id)| {
+                Some(Self {
+                    seq: seq.parse().ok()?,
+                    id: id.parse().ok()?,
+                })
+            })
+            .ok_or_else(|| {
+                Error::Validation(serde_json::json!({ "since": "Invalid sync token" }).to_string())
+            })
+    }
+}
+
+/// Query parameters accepted by `GET /api/sync`.
+#[derive(Debug, Deserialize, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SyncQuery {
+    /// `next` token of the previous sync, omitted to sync everything
+    pub since: Option<String>,
+    /// Most changes to return, at most 500
+    pub limit: Option<usize>,
+}
+
+impl SyncQuery {
+    /// # Errors
+    /// * Validation errors on a malformed `since` token
+    pub fn token(&self) -> Result<SyncToken, Error> {
+        self.since
+            .as_deref()
+            .map_or(Ok(SyncToken::default()), str::parse)
+    }
+
+    #[must_use]
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(MAX_SYNC_CHANGES)
+            .clamp(1, MAX_SYNC_CHANGES)
+    }
+}
+
+/// Tasks of the current user changed since a sync token.
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncResponse {
+    /// Tasks created, changed or restored, in the order they changed
+    pub changed: Vec<TaskResponse>,
+    /// Ids of the tasks moved to the trash or deleted for good
+    pub deleted: Vec<i32>,
+    /// Token to sync from next time
+    pub next: String,
+    /// Whether more changes are waiting, to be fetched from `next` right away
+    pub has_more: bool,
+}
+
+/// A change a client made while offline.
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+#[serde(tag = "op", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum SyncChange {
+    Create {
+        /// Id the client gave the task, echoed back with its server id
+        client_id: String,
+        task: NewTask,
+    },
+    /// Applies while the task is still at `baseVersion`
+    Update {
+        id: i32,
+        base_version: i32,
+        patch: UpdateTask,
+    },
+    /// Moves the task and its subtasks to the trash while the task is still
+    /// at `baseVersion`
+    Delete { id: i32, base_version: i32 },
+}
+
+impl SyncChange {
+    /// Id of the task the change targets, `None` when creating one.
+    #[must_use]
+    pub const fn id(&self) -> Option<i32> {
+        match self {
+            Self::Create { .. } => None,
+            Self::Update { id, .. } | Self::Delete { id, .. } => Some(*id),
+        }
+    }
+
+    #[must_use]
+    pub fn client_id(&self) -> Option<&str> {
+        match self {
+            Self::Create { client_id, .. } => Some(client_id),
+            Self::Update { .. } | Self::Delete { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+pub struct SyncPush {
+    /// At most [`MAX_SYNC_CHANGES`] changes, applied in order
+    pub changes: Vec<SyncChange>,
+}
+
+impl SyncPush {
+    /// Validates the task data of every change, naming the position of the
+    /// first invalid one.
+    ///
+    /// # Errors
+    /// * Validation errors
+    pub fn validate_all(&self) -> Result<(), Error> {
+        if self.changes.len() > MAX_SYNC_CHANGES {
+            return Err(Error::Validation(
+                serde_json::json!({
+                    "changes": format!("At most {MAX_SYNC_CHANGES} changes per request")
+                })
+                .to_string(),
+            ));
+        }
+
+        for (index, change) in self.changes.iter().enumerate() {
+            let result = match change {
+                SyncChange::Create { task, .. } => Validator::new(task).validate().map(|_| ()),
+                SyncChange::Update { patch, .. } => Validator::new(patch).validate().map(|_| ()),
+                SyncChange::Delete { .. } => Ok(()),
+            };
+            result.map_err(|e| match e {
+                Error::Validation(errors) => {
+                    let errors =
+                        serde_json::from_str::<Value>(&errors).unwrap_or(Value::String(errors));
+                    Error::Validation(
+                        serde_json::json!({ format!("changes[{index}]"): errors }).to_string(),
+                    )
+                }
+                e => e,
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What became of a pushed change.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncOutcome {
+    Applied,
+    /// The task changed or was deleted on the server since the version the
+    /// change was based on
+    Conflict,
+    Rejected,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncResultResponse {
+    /// Position of the change in the push
+    pub index: usize,
+    pub client_id: Option<String>,
+    pub outcome: SyncOutcome,
+    pub id: Option<i32>,
+    /// The task as saved, or as it is on the server after a conflict.
+    /// `null` after a conflict means the task no longer exists.
+    pub task: Option<TaskResponse>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPushResponse {
+    pub results: Vec<SyncResultResponse>,
+}
@@ -1,10 +1,17 @@
-use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
-use validator::Validate;
+use std::{collections::HashMap, str::FromStr};
 
-use crate::repositories::tasks::Task;
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Deserializer, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError, ValidationErrors};
 
-#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+use crate::{
+    models::{labels::LabelResponse, recurrence::validate_recurrence},
+    repositories::tasks::Task,
+};
+
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema, Validate)]
+#[serde(rename_all = "camelCase")]
 pub struct NewTask {
     #[validate(length(
         min = 5,
@@ -13,17 +20,124 @@ pub struct NewTask {
     ))]
     pub title: String,
     pub done: bool,
+    /// Project to file the task under, the inbox when omitted
+    pub project_id: Option<i32>,
+    /// Task to nest this one under as a subtask
+    pub parent_id: Option<i32>,
+    pub due_at: Option<DateTime<FixedOffset>>,
+    /// RRULE style schedule, e.g. `FREQ=WEEKLY;BYDAY=MO,TH`
+    #[validate(custom(function = "validate_recurrence"))]
+    pub recurrence: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+/// Tells a field that is absent, which deserializes to `None`, from one
+/// set to `null`, which deserializes to `Some(None)`.
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// JSON Merge Patch of a task: absent fields stay unchanged and `null`
+/// clears a nullable field. `null` for any other field fails validation.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, ToSchema)]
+#[serde(rename_all = "camelCase", from = "TaskPatch")]
 pub struct UpdateTask {
+    pub title: Option<String>,
+    pub done: Option<bool>,
+    /// `null` removes the due date
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub due_at: Option<Option<DateTime<FixedOffset>>>,
+    /// Fields that cannot be cleared but were sent as `null`
+    #[serde(skip)]
+    pub nulls: Vec<&'static str>,
+}
+
+/// A merge patch as sent, telling `null` from absent on every field.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskPatch {
+    #[serde(default, deserialize_with = "nullable")]
+    title: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    done: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "nullable")]
+    due_at: Option<Option<DateTime<FixedOffset>>>,
+}
+
+impl From<TaskPatch> for UpdateTask {
+    fn from(value: TaskPatch) -> Self {
+        let nulls = [
+            ("title", matches!(value.title, Some(None))),
+            ("done", matches!(value.done, Some(None))),
+        ]
+        .into_iter()
+        .filter_map(|(field, null)| null.then_some(field))
+        .collect();
+
+        Self {
+            title: value.title.flatten(),
+            done: value.done.flatten(),
+            due_at: value.due_at,
+            nulls,
+        }
+    }
+}
+
+impl Validate for UpdateTask {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if self
+            .title
+            .as_ref()
+            .is_some_and(|title| !(5..=255).contains(&title.chars().count()))
+        {
+            let error = ValidationError::new("length")
+                .with_message("Title must be between 5 to 255 characters".into());
+            errors.add("title", error);
+        }
+        for &field in &self.nulls {
+            let error = ValidationError::new("null").with_message("Cannot be null".into());
+            errors.add(field, error);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// The fields of a task a merge patch can change, all of them set by
+/// `PUT /api/tasks/{id}`. An omitted due date is cleared. The project,
+/// parent, recurrence and assignee have endpoints of their own and are left
+/// as they are.
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceTask {
     #[validate(length(
         min = 5,
         max = 255,
         message = "Title must be between 5 to 255 characters"
     ))]
-    pub title: Option<String>,
+    pub title: String,
     pub done: bool,
+    pub due_at: Option<DateTime<FixedOffset>>,
+}
+
+impl From<ReplaceTask> for UpdateTask {
+    fn from(value: ReplaceTask) -> Self {
+        Self {
+            title: Some(value.title),
+            done: Some(value.done),
+            due_at: Some(value.due_at),
+            nulls: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
@@ -35,6 +149,23 @@ pub struct TaskResponse {
     pub title: String,
     pub done: bool,
     pub created_at: String,
+    pub project_id: Option<i32>,
+    pub position: i32,
+    pub parent_id: Option<i32>,
+    pub due_at: Option<String>,
+    pub recurrence: Option<String>,
+    pub series_id: Option<String>,
+    /// User responsible for the task
+    pub assignee_pid: Option<String>,
+    /// When the task was moved to the trash
+    pub deleted_at: Option<String>,
+    /// Current version, also sent as the `ETag` header
+    pub version: i32,
+    pub labels: Vec<LabelResponse>,
+    /// Ids of the tasks that must be done before this one
+    pub blocked_by: Vec<i32>,
+    /// Ids of the tasks this one blocks
+    pub blocking: Vec<i32>,
 }
 
 impl From<Task> for TaskResponse {
@@ -46,6 +177,279 @@ impl From<Task> for TaskResponse {
             title: value.title.to_string(),
             done: value.done,
             created_at: value.created_at.format("%d-%m-%Y %H:%M:%S").to_string(),
+            project_id: value.project_id,
+            position: value.position,
+            parent_id: value.parent_id,
+            due_at: value.due_at.map(|due_at| due_at.to_rfc3339()),
+            recurrence: value.recurrence,
+            series_id: value.series_id.map(|series_id| series_id.to_string()),
+            assignee_pid: value
+                .assignee_pid
+                .map(|assignee_pid| assignee_pid.to_string()),
+            deleted_at: value.deleted_at.map(|deleted_at| deleted_at.to_rfc3339()),
+            version: value.version,
+            labels: Vec::new(),
+            blocked_by: Vec::new(),
+            blocking: Vec::new(),
+        }
+    }
+}
+
+/// Completion of a task's descendants, e.g. `3/5 done`.
+#[derive(Debug, Deserialize, Clone, Default, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+    pub summary: String,
+}
+
+impl Progress {
+    #[must_use]
+    pub fn new(done: usize, total: usize) -> Self {
+        Self {
+            done,
+            total,
+            summary: format!("{done}/{total} done"),
+        }
+    }
+}
+
+/// A task with its nested subtasks and the progress of the whole subtree.
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskTreeResponse {
+    #[serde(flatten)]
+    pub task: TaskResponse,
+    pub progress: Progress,
+    #[schema(no_recursion)]
+    pub subtasks: Vec<TaskTreeResponse>,
+}
+
+impl TaskTreeResponse {
+    /// Assembles a tree from a root and its descendants in any order.
+    #[must_use]
+    pub fn build(root: TaskResponse, descendants: Vec<TaskResponse>) -> Self {
+        let mut children: HashMap<i32, Vec<TaskResponse>> = HashMap::new();
+        for task in descendants {
+            if let Some(parent_id) = task.parent_id {
+                children.entry(parent_id).or_default().push(task);
+            }
+        }
+
+        Self::assemble(root, &mut children)
+    }
+
+    fn assemble(task: TaskResponse, children: &mut HashMap<i32, Vec<TaskResponse>>) -> Self {
+        let subtasks = children
+            .remove(&task.id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|child| Self::assemble(child, children))
+            .collect::<Vec<Self>>();
+
+        let (done, total) = subtasks.iter().fold((0, 0), |(done, total), subtask| {
+            (
+                done + subtask.progress.done + usize::from(subtask.task.done),
+                total + subtask.progress.total + 1,
+            )
+        });
+
+        Self {
+            task,
+            progress: Progress::new(done, total),
+            subtasks,
         }
     }
 }
+
+/// Query parameters accepted by `GET /api/tasks/{id}`.
+#[derive(Debug, Deserialize, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TaskView {
+    /// Include nested subtasks and their aggregate progress
+    #[serde(default)]
+    pub subtree: bool,
+}
+
+/// Entity tag of a task version, as sent in the `ETag` header.
+#[must_use]
+pub fn etag(version: i32) -> String {
+    format!("\"{version}\"")
+}
+
+/// Task versions listed in an `If-Match` or `If-None-Match` header.
+///
+/// Tags that are not task versions never match. Weak tags (`W/"3"`) are
+/// kept apart, since `If-Match` compares strongly and ignores them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionMatch {
+    /// `*`, any current version
+    Any,
+    Tags(Vec<(bool, i32)>),
+}
+
+impl VersionMatch {
+    /// Whether `version` passes an `If-Match` precondition.
+    #[must_use]
+    pub fn matches(&self, version: i32) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Tags(tags) => tags.iter().any(|&(weak, tag)| !weak && tag == version),
+        }
+    }
+
+    /// Whether `version` is one the client already holds, for
+    /// `If-None-Match`.
+    #[must_use]
+    pub fn matches_weak(&self, version: i32) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Tags(tags) => tags.iter().any(|&(_, tag)| tag == version),
+        }
+    }
+}
+
+impl FromStr for VersionMatch {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "*" {
+            return Ok(Self::Any);
+        }
+
+        let tags = s
+            .split(',')
+            .filter_map(|tag| {
+                let tag = tag.trim();
+                let (weak, tag) = tag
+                    .strip_prefix("W/")
+                    .map_or((false, tag), |tag| (true, tag));
+                let version = tag.strip_prefix('"')?.strip_suffix('"')?.parse().ok()?;
+                Some((weak, version))
+            })
+            .collect();
+
+        Ok(Self::Tags(tags))
+    }
+}
+
+/// Query parameters accepted by `DELETE /api/tasks/{id}`.
+#[derive(Debug, Deserialize, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteOptions {
+    /// Also delete every subtask. Without it a task with subtasks is kept.
+    #[serde(default)]
+    pub cascade: bool,
+    /// Delete the task for good instead of moving it to the trash. Tasks
+    /// already in the trash can only be deleted this way.
+    #[serde(default)]
+    pub permanent: bool,
+    /// Versions the task must still be at, from the `If-Match` header
+    #[serde(skip)]
+    #[param(ignore)]
+    pub if_match: Option<VersionMatch>,
+}
+
+/// Query parameters accepted by `PATCH /api/tasks/{id}`.
+#[derive(Debug, Deserialize, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UpdateOptions {
+    /// Complete the task even if some of its blockers are not done
+    #[serde(default)]
+    pub force: bool,
+    /// Versions the task must still be at, from the `If-Match` header
+    #[serde(skip)]
+    #[param(ignore)]
+    pub if_match: Option<VersionMatch>,
+}
+
+/// Adds a dependency: the task with id `blockedBy` blocks this one.
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddDependency {
+    pub blocked_by: i32,
+}
+
+/// New parent of a task, `null` to make it a top level task.
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SetParent {
+    pub parent_id: Option<i32>,
+}
+
+/// New recurrence rule of a task, `null` ends the series.
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+pub struct SetRecurrence {
+    /// RRULE style schedule, e.g. `FREQ=MONTHLY;BYMONTHDAY=1`
+    #[validate(custom(function = "validate_recurrence"))]
+    pub rule: Option<String>,
+}
+
+/// Destination of a task move. Omitting `projectId` moves the task to the
+/// inbox, omitting `position` appends it to the end of the list.
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveTask {
+    pub project_id: Option<i32>,
+    #[validate(range(min = 0, message = "Position cannot be negative"))]
+    pub position: Option<i32>,
+}
+
+/// New assignee of a task by username, `null` to unassign it.
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+pub struct SetAssignee {
+    pub username: Option<String>,
+}
+
+/// Whose assignments a [`TaskFilter`] selects.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Assigned {
+    /// Tasks assigned to the current user, whoever owns them.
+    Me,
+}
+
+/// How multiple labels in a [`TaskFilter`] are combined.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelMatch {
+    /// Task must carry every listed label.
+    #[default]
+    All,
+    /// Task must carry at least one of the listed labels.
+    Any,
+}
+
+/// Query parameters accepted by `GET /api/tasks`.
+#[derive(Debug, Deserialize, Clone, Default, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+pub struct TaskFilter {
+    /// Comma separated label names, e.g. `?label=work,urgent`
+    pub label: Option<String>,
+    /// `all` (default) or `any`
+    #[serde(rename = "match", default)]
+    #[param(inline)]
+    pub label_match: LabelMatch,
+    /// `me` lists the tasks assigned to the current user instead of the
+    /// tasks they own
+    #[param(inline)]
+    pub assigned: Option<Assigned>,
+    /// Only completed (`true`) or open (`false`) tasks
+    pub done: Option<bool>,
+}
+
+impl TaskFilter {
+    /// Label names from the `label` parameter, trimmed and without blanks.
+    #[must_use]
+    pub fn labels(&self) -> Vec<String> {
+        self.label
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(ToString::to_string)
+            .collect()
+    }
+}
@@ -0,0 +1,439 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{
+    Url,
+    dns::{Addrs, Name, Resolve, Resolving},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
+
+use crate::{
+    Error,
+    config::WebhookConfig,
+    events::Event,
+    repositories::webhooks::{Webhook, WebhookDelivery},
+};
+
+/// Header holding the id of a delivery, the same for every attempt at it.
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+/// Header holding the event type of a delivery.
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+/// Header holding the Unix time an attempt was signed at.
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+/// Header holding the signature of an attempt, see [`sign`].
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// Most deliveries listed at once.
+pub const MAX_DELIVERIES: i64 = 100;
+
+/// Signs a delivery body as sent at `timestamp`: the hex HMAC-SHA256 of
+/// `{timestamp}.{body}` keyed with the webhook secret, prefixed by `sha256=`.
+/// Receivers should recompute it and reject timestamps that are too old.
+#[must_use]
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Events a webhook can subscribe to.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WebhookEvent {
+    TaskCreated,
+    TaskUpdated,
+    TaskDeleted,
+    /// Notifications of the owner, for the kinds they get by webhook
+    Notification,
+}
+
+impl WebhookEvent {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::TaskCreated => "taskCreated",
+            Self::TaskUpdated => "taskUpdated",
+            Self::TaskDeleted => "taskDeleted",
+            Self::Notification => "notification",
+        }
+    }
+}
+
+impl std::str::FromStr for WebhookEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "taskCreated" => Ok(Self::TaskCreated),
+            "taskUpdated" => Ok(Self::TaskUpdated),
+            "taskDeleted" => Ok(Self::TaskDeleted),
+            "notification" => Ok(Self::Notification),
+            _ => Err(format!("Unknown webhook event {s}")),
+        }
+    }
+}
+
+/// Body of a delivery. It only names the task, which receivers fetch to
+/// see its current state.
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    #[serde(rename = "type")]
+    pub kind: WebhookEvent,
+    pub task_id: i32,
+    pub occurred_at: DateTime<FixedOffset>,
+}
+
+impl WebhookPayload {
+    /// The payload delivered for `event`, `None` for events webhooks are not
+    /// told about.
+    #[must_use]
+    pub fn for_event(event: &Event) -> Option<Self> {
+        let (kind, task_id) = match event {
+            Event::TaskCreated { task_id, .. } => (WebhookEvent::TaskCreated, *task_id),
+            Event::TaskUpdated { task_id, .. } => (WebhookEvent::TaskUpdated, *task_id),
+            Event::TaskDeleted { task_id, .. } => (WebhookEvent::TaskDeleted, *task_id),
+            Event::TaskAssigned { .. }
+            | Event::Mentioned { .. }
+            | Event::TaskShared { .. }
+            | Event::ProjectShared { .. }
+            | Event::TaskDue { .. }
+            | Event::Notified { .. }
+            | Event::UserCreated { .. }
+            | Event::UserDeleted { .. } => return None,
+        };
+
+        Some(Self {
+            kind,
+            task_id,
+            occurred_at: Utc::now().fixed_offset(),
+        })
+    }
+}
+
+/// Endpoints must be absolute `http` or `https` URLs.
+fn validate_endpoint(url: &str) -> Result<(), ValidationError> {
+    match Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => Ok(()),
+        _ => Err(ValidationError::new("url")
+            .with_message("URL must be an absolute http or https URL".into())),
+    }
+}
+
+/// Why webhooks cannot be sent to an endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum EndpointError {
+    #[error("URL must be an absolute http or https URL")]
+    InvalidUrl,
+    #[error("Host {0} cannot be resolved")]
+    Unresolved(String),
+    #[error("Host {0} points at an address that is not public")]
+    Internal(String),
+}
+
+impl From<EndpointError> for Error {
+    fn from(value: EndpointError) -> Self {
+        Self::Validation(serde_json::json!({ "url": value.to_string() }).to_string())
+    }
+}
+
+/// Whether `ip` is globally reachable, and so outside the server's own
+/// network. Every special-purpose range is refused: loopback, private,
+/// shared, link-local, reserved, benchmarking, documentation and multicast
+/// addresses. IPv6 addresses embedding an IPv4 one, as mapped, compatible,
+/// NAT64 or 6to4 addresses, are judged by the IPv4 address.
+#[must_use]
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => is_public_v6(ip),
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    !(a == 0
+        || ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        // Shared address space of carrier-grade NAT, 100.64.0.0/10
+        || (a == 100 && b & 0xc0 == 64)
+        // IETF protocol assignments, 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking, 198.18.0.0/15
+        || (a == 198 && b & 0xfe == 18)
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // Reserved, 240.0.0.0/4, including the broadcast address
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let embedded = |high: u16, low: u16| Ipv4Addr::from(u32::from(high) << 16 | u32::from(low));
+
+    match ip.segments() {
+        // IPv4-compatible, ::/96 including :: and ::1, and IPv4-mapped
+        [0, 0, 0, 0, 0, 0 | 0xffff, high, low] => is_public_v4(embedded(high, low)),
+        // NAT64, 64:ff9b::/96
+        [0x64, 0xff9b, 0, 0, 0, 0, high, low] => is_public_v4(embedded(high, low)),
+        // 6to4, 2002::/16
+        [0x2002, high, low, ..] => is_public_v4(embedded(high, low)),
+        [first, second, ..] => {
+            !(ip.is_multicast()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()
+                // Site-local, fec0::/10
+                || first & 0xffc0 == 0xfec0
+                // Discard-only, 100::/64
+                || ip.segments()[..4] == [0x100, 0, 0, 0]
+                // Other NAT64 prefixes in 64:ff9b::/32, such as local-use
+                // 64:ff9b:1::/48
+                || (first == 0x64 && second == 0xff9b)
+                // IETF protocol assignments, 2001::/23, with Teredo
+                || (first == 0x2001 && second < 0x200)
+                // Documentation, 2001:db8::/32
+                || (first == 0x2001 && second == 0xdb8))
+        }
+    }
+}
+
+/// Resolves the hosts of webhook endpoints, keeping only public addresses
+/// unless the host is in the configured allowlist.
+///
+/// The delivery client uses it as its DNS resolver, so the addresses checked
+/// are the ones connected to and a host cannot resolve to a public address
+/// at registration and to an internal one when a delivery is sent.
+#[derive(Debug, Clone)]
+pub struct EndpointResolver {
+    allowed_hosts: Arc<[String]>,
+}
+
+impl EndpointResolver {
+    #[must_use]
+    pub fn new(config: &WebhookConfig) -> Self {
+        Self {
+            allowed_hosts: config.allowed_hosts.iter().cloned().collect(),
+        }
+    }
+
+    fn is_allowed(&self, host: &str) -> bool {
+        self.allowed_hosts
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    }
+
+    /// Addresses of `host` webhooks may be sent to, an error when there are
+    /// none.
+    async fn resolve_host(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, EndpointError> {
+        let addrs = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| EndpointError::Unresolved(host.to_string()))?
+            .collect::<Vec<SocketAddr>>();
+        if addrs.is_empty() {
+            return Err(EndpointError::Unresolved(host.to_string()));
+        }
+        if self.is_allowed(host) {
+            return Ok(addrs);
+        }
+
+        let public = addrs
+            .into_iter()
+            .filter(|addr| is_public_address(addr.ip()))
+            .collect::<Vec<SocketAddr>>();
+        if public.is_empty() {
+            return Err(EndpointError::Internal(host.to_string()));
+        }
+
+        Ok(public)
+    }
+
+    /// Checks that webhooks may be sent to `url`, for hosts given as IP
+    /// addresses as well as names.
+    pub async fn check(&self, url: &str) -> Result<(), EndpointError> {
+        let url = Url::parse(url).map_err(|_| EndpointError::InvalidUrl)?;
+        let host = url.host_str().ok_or(EndpointError::InvalidUrl)?;
+        let port = url
+            .port_or_known_default()
+            .ok_or(EndpointError::InvalidUrl)?;
+
+        self.resolve_host(host.trim_start_matches('[').trim_end_matches(']'), port)
+            .await
+            .map(|_| ())
+    }
+}
+
+impl Resolve for EndpointResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let addrs = resolver.resolve_host(name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+pub struct NewWebhook {
+    #[validate(
+        length(max = 2048, message = "URL must be at most 2048 characters"),
+        custom(function = "validate_endpoint")
+    )]
+    pub url: String,
+    /// Events to deliver, every event when empty or missing
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Validate)]
+pub struct UpdateWebhook {
+    #[validate(
+        length(max = 2048, message = "URL must be at most 2048 characters"),
+        custom(function = "validate_endpoint")
+    )]
+    pub url: Option<String>,
+    pub events: Option<Vec<WebhookEvent>>,
+    /// Inactive webhooks are not sent new events
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookResponse {
+    pub id: i32,
+    pub pid: String,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub active: bool,
+    /// Key deliveries are signed with, only returned when the webhook is
+    /// created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+impl WebhookResponse {
+    /// The response to creating `webhook`, the only one holding its secret.
+    #[must_use]
+    pub fn with_secret(webhook: Webhook) -> Self {
+        let secret = webhook.secret.clone();
+        Self {
+            secret: Some(secret),
+            ..Self::from(webhook)
+        }
+    }
+}
+
+impl From<Webhook> for WebhookResponse {
+    fn from(value: Webhook) -> Self {
+        Self {
+            id: value.id,
+            pid: value.pid.to_string(),
+            url: value.url,
+            events: value
+                .events
+                .iter()
+                .filter_map(|event| event.parse().ok())
+                .collect(),
+            active: value.active,
+            secret: None,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+/// State of a delivery.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeliveryStatus {
+    /// Waiting for its next attempt
+    Pending,
+    Delivered,
+    /// Every attempt failed, it can be retried by hand
+    Dead,
+}
+
+impl DeliveryStatus {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::Dead => "dead",
+        }
+    }
+}
+
+/// Query parameters accepted by `GET /api/webhooks/{id}/deliveries`.
+#[derive(Debug, Deserialize, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeliveryQuery {
+    /// Only list deliveries in this state
+    pub status: Option<DeliveryStatus>,
+    /// Only list deliveries older than the one with this id
+    pub before: Option<i64>,
+    /// Most deliveries to return, at most 100
+    pub limit: Option<i64>,
+}
+
+impl DeliveryQuery {
+    #[must_use]
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(MAX_DELIVERIES)
+            .clamp(1, MAX_DELIVERIES)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, ToSchema, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryResponse {
+    pub id: i64,
+    pub pid: String,
+    pub event: String,
+    pub status: String,
+    pub attempts: i32,
+    /// When the next attempt is due, while the delivery is pending
+    pub next_attempt_at: Option<DateTime<FixedOffset>>,
+    /// Status code of the last response from the endpoint
+    pub response_status: Option<i16>,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+    #[schema(value_type = Object)]
+    pub payload: Value,
+    pub created_at: DateTime<FixedOffset>,
+    pub delivered_at: Option<DateTime<FixedOffset>>,
+}
+
+impl From<WebhookDelivery> for DeliveryResponse {
+    fn from(value: WebhookDelivery) -> Self {
+        Self {
+            id: value.id,
+            pid: value.pid.to_string(),
+            next_attempt_at: (value.status == DeliveryStatus::Pending.as_str())
+                .then_some(value.next_attempt_at),
+            event: value.event,
+            status: value.status,
+            attempts: value.attempts,
+            response_status: value.response_status,
+            error: value.error,
+            payload: value.payload,
+            created_at: value.created_at,
+            delivered_at: value.delivered_at,
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use bytes::Bytes;
+use chrono::{DateTime, FixedOffset};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, PgPool, Postgres, prelude::FromRow};
+use uuid::Uuid;
+
+use crate::{
+    jobs::{self, ExportAccount},
+    models::{
+        account::{AccountArchive, AccountProfile, archive_filename},
+        attachments::AttachmentResponse,
+        comments::CommentResponse,
+        history::HistoryResponse,
+        labels::LabelResponse,
+        projects::ProjectResponse,
+    },
+    storage::{BlobStore, ByteStream, StorageError, content_key},
+};
+
+use super::{
+    ModelError, attachments::Attachment, comments::Comment, history::TaskHistory, labels::Label,
+    projects::Project, tasks::Task, users::User,
+};
+
+/// Archive of everything stored about a user, built in the background.
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct AccountExport {
+    pub id: i32,
+    pub pid: Uuid,
+    pub user_pid: Uuid,
+    pub status: String,
+    pub storage_key: Option<String>,
+    pub size: Option<i64>,
+    pub created_at: DateTime<FixedOffset>,
+    pub completed_at: Option<DateTime<FixedOffset>>,
+    pub expires_at: Option<DateTime<FixedOffset>>,
+}
+
+impl AccountExport {
+    /// Queues a new export of the user's data.
+    pub async fn create<'e, C>(db: C, user_pid: Uuid) -> Result<Self, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let item = sqlx::query_as::<_, Self>(
+            "INSERT INTO account_exports (user_pid) VALUES ($1) RETURNING *",
+        )
+        .bind(user_pid)
+        .fetch_one(db)
+        .await?;
+
+        Ok(item)
+    }
+
+    /// Creates an export together with the job that builds it.
+    pub async fn queue(db: &PgPool, user_pid: Uuid) -> Result<Self, ModelError> {
+        let mut txn = db.begin().await?;
+
+        let export = Self::create(&mut *txn, user_pid).await?;
+        let job = ExportAccount {
+            export_pid: export.pid,
+            user_pid,
+        };
+        jobs::enqueue(&mut *txn, &job).await?;
+
+        txn.commit().await?;
+
+        Ok(export)
+    }
+
+    /// One of the user's exports, by its pid.
+    pub async fn find_by_pid<'e, C>(db: C, pid: Uuid, user_pid: Uuid) -> Result<Self, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Self>("SELECT * FROM account_exports WHERE pid = $1 AND user_pid = $2")
+            .bind(pid)
+            .bind(user_pid)
+            .fetch_optional(db)
+            .await?
+            .ok_or(ModelError::EntityNotFound)
+    }
+
+    /// Builds the archive, stores it and marks the export ready until
+    /// `expires_at`. The archive holds the user's profile, tasks (trashed
+    /// ones included), projects, labels, comments, uploaded files and the
+    /// history of their tasks and of their changes to other tasks.
+    pub async fn build(
+        &self,
+        db: &PgPool,
+        blobs: &dyn BlobStore,
+        expires_at: DateTime<FixedOffset>,
+    ) -> Result<Self, ModelError> {
+        let user = User::find_by_pid(db, self.user_pid).await?;
+        let mut archive = AccountArchive::new();
+        archive.add_json("profile.json", &AccountProfile::from(&user))?;
+
+        let tasks =
+            sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE user_pid = $1 ORDER BY id")
+                .bind(user.pid)
+                .fetch_all(db)
+                .await?;
+        archive.add_json("tasks.json", &Task::responses(db, tasks).await?)?;
+
+        let projects =
+            sqlx::query_as::<_, Project>("SELECT * FROM projects WHERE user_pid = $1 ORDER BY id")
+                .bind(user.pid)
+                .fetch_all(db)
+                .await?
+                .into_iter()
+                .map(ProjectResponse::from)
+                .collect::<Vec<ProjectResponse>>();
+        archive.add_json("projects.json", &projects)?;
+
+        let labels = Label::find_all(db, user.pid)
+            .await?
+            .into_iter()
+            .map(LabelResponse::from)
+            .collect::<Vec<LabelResponse>>();
+        archive.add_json("labels.json", &labels)?;
+
+        let comments = Comment::find_by_author(db, user.pid)
+            .await?
+            .into_iter()
+            .map(CommentResponse::from)
+            .collect::<Vec<CommentResponse>>();
+        archive.add_json("comments.json", &comments)?;
+
+        let history = TaskHistory::find_for_user(db, user.pid)
+            .await?
+            .into_iter()
+            .map(HistoryResponse::from)
+            .collect::<Vec<HistoryResponse>>();
+        archive.add_json("history.json", &history)?;
+
+        let attachments = Attachment::find_by_uploader(db, user.pid).await?;
+        for attachment in &attachments {
+            let data = match blobs.get(&attachment.storage_key).await {
+                Ok(stream) => read_all(stream).await?,
+                Err(StorageError::NotFound(key)) => {
+                    tracing::warn!("Attachment {} has no stored file {key}", attachment.id);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let name = archive_filename(&attachment.pid.to_string(), &attachment.filename);
+            archive.add_file(&name, &data)?;
+        }
+        let attachments = attachments
+            .into_iter()
+            .map(AttachmentResponse::from)
+            .collect::<Vec<AttachmentResponse>>();
+        archive.add_json("attachments.json", &attachments)?;
+
+        let data = archive.finish()?;
+        let size = i64::try_from(data.len()).map_err(|e| ModelError::Database(e.to_string()))?;
+
+        let mut txn = db.begin().await?;
+        Attachment::lock_blob(&mut *txn, &content_key(&data)).await?;
+        let storage_key = blobs.put(Bytes::from(data)).await?;
+
+        let item = sqlx::query_as::<_, Self>(
+            "
+            UPDATE account_exports
+            SET status = 'ready', storage_key = $2, size = $3, completed_at = NOW(), expires_at = $4
+            WHERE id = $1 RETURNING *
+            ",
+        )
+        .bind(self.id)
+        .bind(storage_key)
+        .bind(size)
+        .bind(expires_at)
+        .fetch_one(&mut *txn)
+        .await?;
+        txn.commit().await?;
+
+        Ok(item)
+    }
+
+    /// Marks an export whose archive could not be built, to be purged at
+    /// `expires_at` like any other.
+    pub async fn fail<'e, C>(
+        db: C,
+        id: i32,
+        expires_at: DateTime<FixedOffset>,
+    ) -> Result<(), ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        sqlx::query(
+            "
+            UPDATE account_exports SET status = 'failed', completed_at = NOW(), expires_at = $2
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .bind(expires_at)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Streams the archive of one of the user's ready, unexpired exports.
+    pub async fn download(
+        db: &PgPool,
+        blobs: &dyn BlobStore,
+        pid: Uuid,
+        user_pid: Uuid,
+    ) -> Result<(Self, ByteStream), ModelError> {
+        let export = sqlx::query_as::<_, Self>(
+            "
+            SELECT * FROM account_exports
+            WHERE pid = $1 AND user_pid = $2 AND status = 'ready' AND expires_at > NOW()
+            ",
+        )
+        .bind(pid)
+        .bind(user_pid)
+        .fetch_optional(db)
+        .await?
+        .ok_or(ModelError::EntityNotFound)?;
+
+        let storage_key = export
+            .storage_key
+            .as_deref()
+            .ok_or(ModelError::EntityNotFound)?;
+        let stream = blobs.get(storage_key).await?;
+
+        Ok((export, stream))
+    }
+
+    /// Deletes exports that expired before `before`, together with their
+    /// archives. Returns how many were deleted.
+    pub async fn purge(
+        db: &PgPool,
+        blobs: &dyn BlobStore,
+        before: DateTime<FixedOffset>,
+    ) -> Result<u64, ModelError> {
+        let keys = sqlx::query_scalar::<_, Option<String>>(
+            "DELETE FROM account_exports WHERE expires_at < $1 RETURNING storage_key",
+        )
+        .bind(before)
+        .fetch_all(db)
+        .await?;
+
+        let purged = keys.len() as u64;
+        let keys = keys.into_iter().flatten().collect::<Vec<String>>();
+        Attachment::release(db, blobs, &keys).await?;
+
+        Ok(purged)
+    }
+}
+
+async fn read_all(stream: ByteStream) -> Result<Vec<u8>, StorageError> {
+    stream
+        .try_fold(Vec::new(), |mut data, chunk| async move {
+            data.extend_from_slice(&chunk);
+            Ok(data)
+        })
+        .await
+}
@@ -0,0 +1,247 @@
+use bytes::Bytes;
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, PgConnection, PgPool, Postgres, prelude::FromRow};
+use uuid::Uuid;
+
+use crate::{
+    models::attachments::Upload,
+    storage::{BlobStore, ByteStream, content_key},
+};
+
+use super::{ModelError, permissions::Permission};
+
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct Attachment {
+    pub id: i32,
+    pub pid: Uuid,
+    pub task_id: i32,
+    pub uploader_pid: Uuid,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    pub storage_key: String,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+impl Attachment {
+    /// Stores an uploaded file and attaches it to a task the user can edit.
+    pub async fn create(
+        db: &PgPool,
+        blobs: &dyn BlobStore,
+        task_id: i32,
+        upload: Upload,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        Permission::require_task(db, task_id, user_pid, Permission::Editor).await?;
+
+        let mut txn = db.begin().await?;
+        let storage_key = Self::store(&mut txn, blobs, &upload.data).await?;
+        let item = Self::insert(&mut txn, task_id, upload, storage_key, user_pid).await?;
+        txn.commit().await?;
+
+        Ok(item)
+    }
+
+    /// Stores `data` under the blob lock, held until the transaction ends,
+    /// so that [`Self::release`] of an identical file cannot delete it
+    /// before the attachment referring to it is committed. Returns its key.
+    pub(crate) async fn store(
+        conn: &mut PgConnection,
+        blobs: &dyn BlobStore,
+        data: &Bytes,
+    ) -> Result<String, ModelError> {
+        Self::lock_blob(&mut *conn, &content_key(data)).await?;
+
+        Ok(blobs.put(data.clone()).await?)
+    }
+
+    /// Attaches the file of `upload` stored under `storage_key`, see
+    /// [`Self::store`], without checking permissions.
+    pub(crate) async fn insert(
+        conn: &mut PgConnection,
+        task_id: i32,
+        upload: Upload,
+        storage_key: String,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        let size = i64::try_from(upload.data.len()).map_err(|_| ModelError::AttachmentTooLarge)?;
+
+        let item = sqlx::query_as::<_, Self>(
+            "
+            INSERT INTO attachments (task_id, uploader_pid, filename, content_type, size, storage_key)
+            VALUES ($1, $2, $3, $4, $5, $6) RETURNING *
+            ",
+        )
+        .bind(task_id)
+        .bind(user_pid)
+        .bind(upload.filename)
+        .bind(upload.content_type)
+        .bind(size)
+        .bind(storage_key)
+        .fetch_one(conn)
+        .await?;
+
+        Ok(item)
+    }
+
+    /// Serialises storing and releasing the blob `storage_key` until the
+    /// end of the transaction.
+    pub(crate) async fn lock_blob<'e, C>(db: C, storage_key: &str) -> Result<(), ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(storage_key)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_all(
+        db: &PgPool,
+        task_id: i32,
+        user_pid: Uuid,
+    ) -> Result<Vec<Self>, ModelError> {
+        Permission::require_task(db, task_id, user_pid, Permission::Viewer).await?;
+
+        let items =
+            sqlx::query_as::<_, Self>("SELECT * FROM attachments WHERE task_id = $1 ORDER BY id")
+                .bind(task_id)
+                .fetch_all(db)
+                .await?;
+
+        Ok(items)
+    }
+
+    pub async fn find_by_id(
+        db: &PgPool,
+        task_id: i32,
+        id: i32,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        Permission::require_task(db, task_id, user_pid, Permission::Viewer).await?;
+
+        let item =
+            sqlx::query_as::<_, Self>("SELECT * FROM attachments WHERE id = $1 AND task_id = $2")
+                .bind(id)
+                .bind(task_id)
+                .fetch_optional(db)
+                .await?;
+
+        item.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Loads an attachment the user can see together with a stream of its
+    /// contents.
+    pub async fn download(
+        db: &PgPool,
+        blobs: &dyn BlobStore,
+        task_id: i32,
+        id: i32,
+        user_pid: Uuid,
+    ) -> Result<(Self, ByteStream), ModelError> {
+        let attachment = Self::find_by_id(db, task_id, id, user_pid).await?;
+        let stream = blobs.get(&attachment.storage_key).await?;
+
+        Ok((attachment, stream))
+    }
+
+    /// Removes an attachment from a task the user can edit. The stored file
+    /// is only deleted once no other attachment shares its contents.
+    pub async fn delete_by_id(
+        db: &PgPool,
+        blobs: &dyn BlobStore,
+        task_id: i32,
+        id: i32,
+        user_pid: Uuid,
+    ) -> Result<(), ModelError> {
+        Permission::require_task(db, task_id, user_pid, Permission::Editor).await?;
+
+        let storage_key = sqlx::query_scalar::<_, String>(
+            "DELETE FROM attachments WHERE id = $1 AND task_id = $2 RETURNING storage_key",
+        )
+        .bind(id)
+        .bind(task_id)
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| ModelError::EntityNotFound)?;
+
+        Self::release(db, blobs, &[storage_key]).await
+    }
+
+    /// Files the user uploaded, on any task, oldest first.
+    pub async fn find_by_uploader<'e, C>(db: C, uploader_pid: Uuid) -> Result<Vec<Self>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let items = sqlx::query_as::<_, Self>(
+            "SELECT * FROM attachments WHERE uploader_pid = $1 ORDER BY created_at, id",
+        )
+        .bind(uploader_pid)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Storage keys of the attachments of `task_ids` and all their subtasks,
+    /// trashed ones included, collected before the tasks are deleted for good.
+    pub async fn storage_keys<'e, C>(db: C, task_ids: &[i32]) -> Result<Vec<String>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let keys = sqlx::query_scalar::<_, String>(
+            "
+            WITH RECURSIVE subtree AS (
+                SELECT id FROM tasks WHERE id = ANY($1)
+                UNION
+                SELECT t.id FROM tasks t JOIN subtree s ON t.parent_id = s.id
+            )
+            SELECT DISTINCT storage_key FROM attachments
+            WHERE task_id IN (SELECT id FROM subtree)
+            ",
+        )
+        .bind(task_ids)
+        .fetch_all(db)
+        .await?;
+
+        Ok(keys)
+    }
+
+    /// Deletes the stored files of `storage_keys` that neither an attachment
+    /// nor an account export refers to anymore. Each key is checked and
+    /// deleted in its own transaction holding the blob lock.
+    pub async fn release(
+        db: &PgPool,
+        blobs: &dyn BlobStore,
+        storage_keys: &[String],
+    ) -> Result<(), ModelError> {
+        if storage_keys.is_empty() {
+            return Ok(());
+        }
+
+        for key in storage_keys {
+            let mut txn = db.begin().await?;
+            Self::lock_blob(&mut *txn, key).await?;
+
+            let referenced = sqlx::query_scalar::<_, bool>(
+                "
+                SELECT EXISTS (SELECT 1 FROM attachments WHERE storage_key = $1)
+                OR EXISTS (SELECT 1 FROM account_exports WHERE storage_key = $1)
+                ",
+            )
+            .bind(key)
+            .fetch_one(&mut *txn)
+            .await?;
+
+            if !referenced {
+                blobs.delete(key).await?;
+            }
+            txn.commit().await?;
+        }
+
+        Ok(())
+    }
+}
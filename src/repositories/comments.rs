@@ -0,0 +1,257 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction, prelude::FromRow};
+use uuid::Uuid;
+
+use crate::{
+    events::Event,
+    models::comments::{NewComment, UpdateComment, parse_mentions},
+};
+
+use super::{ModelError, outbox::OutboxEvent, permissions::Permission, users::User};
+
+/// Selects a comment row `c` together with its author's username.
+const SELECT_WITH_AUTHOR: &str =
+    "SELECT c.*, u.username AS author FROM c JOIN users u ON u.pid = c.author_pid";
+
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct Comment {
+    pub id: i32,
+    pub pid: Uuid,
+    pub task_id: i32,
+    pub author_pid: Uuid,
+    pub author: String,
+    pub body: String,
+    pub edited: bool,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+impl Comment {
+    /// Comments on a task `user_pid` can see, oldest first.
+    pub async fn find_all(
+        db: &PgPool,
+        task_id: i32,
+        user_pid: Uuid,
+    ) -> Result<Vec<Self>, ModelError> {
+        Permission::require_task(db, task_id, user_pid, Permission::Viewer).await?;
+
+        let items = sqlx::query_as::<_, Self>(&format!(
+            "
+            WITH c AS (SELECT * FROM comments WHERE task_id = $1)
+            {SELECT_WITH_AUTHOR}
+            ORDER BY c.created_at, c.id
+            "
+        ))
+        .bind(task_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Every comment the user wrote, on any task, oldest first.
+    pub async fn find_by_author(db: &PgPool, author_pid: Uuid) -> Result<Vec<Self>, ModelError> {
+        let items = sqlx::query_as::<_, Self>(&format!(
+            "
+            WITH c AS (SELECT * FROM comments WHERE author_pid = $1)
+            {SELECT_WITH_AUTHOR}
+            ORDER BY c.created_at, c.id
+            "
+        ))
+        .bind(author_pid)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Comments on a task. Anyone who can see the task may comment.
+    ///
+    /// Returns the comment together with the users it mentions.
+    pub async fn create(
+        db: &PgPool,
+        task_id: i32,
+        params: &NewComment,
+        user_pid: Uuid,
+    ) -> Result<(Self, Vec<Uuid>), ModelError> {
+        let mut txn = db.begin().await?;
+        let created = Self::create_in(&mut txn, task_id, params, user_pid).await?;
+        txn.commit().await?;
+
+        Ok(created)
+    }
+
+    /// [`Self::create`] as part of a larger transaction.
+    pub(crate) async fn create_in(
+        txn: &mut Transaction<'_, Postgres>,
+        task_id: i32,
+        params: &NewComment,
+        user_pid: Uuid,
+    ) -> Result<(Self, Vec<Uuid>), ModelError> {
+        Permission::require_task(&mut **txn, task_id, user_pid, Permission::Viewer).await?;
+
+        let comment = sqlx::query_as::<_, Self>(&format!(
+            "
+            WITH c AS (
+                INSERT INTO comments (task_id, author_pid, body)
+                VALUES ($1, $2, $3) RETURNING *
+            )
+            {SELECT_WITH_AUTHOR}
+            "
+        ))
+        .bind(task_id)
+        .bind(user_pid)
+        .bind(params.body.trim())
+        .fetch_one(&mut **txn)
+        .await?;
+
+        let mentioned = Self::mention(txn, &comment).await?;
+
+        Ok((comment, mentioned))
+    }
+
+    /// Edits a comment, which only its author can do.
+    ///
+    /// Returns the comment together with the users mentioned for the first
+    /// time by the edit.
+    pub async fn update_by_id(
+        db: &PgPool,
+        task_id: i32,
+        id: i32,
+        params: &UpdateComment,
+        user_pid: Uuid,
+    ) -> Result<(Self, Vec<Uuid>), ModelError> {
+        let mut txn = db.begin().await?;
+
+        Permission::require_task(&mut *txn, task_id, user_pid, Permission::Viewer).await?;
+        let author_pid = Self::lock_author(&mut txn, task_id, id).await?;
+        if author_pid != user_pid {
+            return Err(ModelError::Forbidden);
+        }
+
+        let comment = sqlx::query_as::<_, Self>(&format!(
+            "
+            WITH c AS (
+                UPDATE comments SET body = $2, edited = TRUE, updated_at = $3
+                WHERE id = $1 RETURNING *
+            )
+            {SELECT_WITH_AUTHOR}
+            "
+        ))
+        .bind(id)
+        .bind(params.body.trim())
+        .bind(Utc::now().fixed_offset())
+        .fetch_one(&mut *txn)
+        .await?;
+
+        let mentioned = Self::mention(&mut txn, &comment).await?;
+
+        txn.commit().await?;
+
+        Ok((comment, mentioned))
+    }
+
+    /// Deletes a comment. Authors can delete their own comments, task
+    /// owners any comment on the task.
+    pub async fn delete_by_id(
+        db: &PgPool,
+        task_id: i32,
+        id: i32,
+        user_pid: Uuid,
+    ) -> Result<(), ModelError> {
+        let mut txn = db.begin().await?;
+
+        let permission =
+            Permission::require_task(&mut *txn, task_id, user_pid, Permission::Viewer).await?;
+        let author_pid = Self::lock_author(&mut txn, task_id, id).await?;
+        if author_pid != user_pid && permission < Permission::Owner {
+            return Err(ModelError::Forbidden);
+        }
+
+        sqlx::query("DELETE FROM comments WHERE id = $1")
+            .bind(id)
+            .execute(&mut *txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    async fn lock_author(
+        txn: &mut Transaction<'_, Postgres>,
+        task_id: i32,
+        id: i32,
+    ) -> Result<Uuid, ModelError> {
+        let author_pid = sqlx::query_scalar::<_, Uuid>(
+            "SELECT author_pid FROM comments WHERE id = $1 AND task_id = $2 FOR UPDATE",
+        )
+        .bind(id)
+        .bind(task_id)
+        .fetch_optional(&mut **txn)
+        .await?;
+
+        author_pid.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Syncs the recorded mentions of a comment with its body and returns the
+    /// users that were not mentioned before, recording an event for each.
+    ///
+    /// Unknown usernames, the author and users who cannot see the task are
+    /// skipped, so a mention never reveals a task to someone outside it.
+    async fn mention(
+        txn: &mut Transaction<'_, Postgres>,
+        comment: &Self,
+    ) -> Result<Vec<Uuid>, ModelError> {
+        let mut mentioned = Vec::new();
+        for username in parse_mentions(&comment.body) {
+            let user = match User::find_by_username(&mut **txn, &username).await {
+                Ok(user) => user,
+                Err(ModelError::EntityNotFound) => continue,
+                Err(e) => return Err(e),
+            };
+
+            if user.pid == comment.author_pid
+                || Permission::for_task(&mut **txn, comment.task_id, user.pid)
+                    .await?
+                    .is_none()
+            {
+                continue;
+            }
+
+            mentioned.push(user.pid);
+        }
+
+        sqlx::query("DELETE FROM comment_mentions WHERE comment_id = $1 AND user_pid <> ALL($2)")
+            .bind(comment.id)
+            .bind(&mentioned)
+            .execute(&mut **txn)
+            .await?;
+
+        let added = sqlx::query_scalar::<_, Uuid>(
+            "
+            INSERT INTO comment_mentions (comment_id, user_pid)
+            SELECT $1, UNNEST($2::UUID[])
+            ON CONFLICT DO NOTHING
+            RETURNING user_pid
+            ",
+        )
+        .bind(comment.id)
+        .bind(&mentioned)
+        .fetch_all(&mut **txn)
+        .await?;
+
+        for user_pid in &added {
+            let event = Event::Mentioned {
+                task_id: comment.task_id,
+                comment_id: comment.id,
+                user_pid: *user_pid,
+                mentioned_by: comment.author_pid,
+            };
+            OutboxEvent::record(txn, &event).await?;
+        }
+
+        Ok(added)
+    }
+}
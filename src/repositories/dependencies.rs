@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, PgPool, Postgres, prelude::FromRow};
+use uuid::Uuid;
+
+use super::{ModelError, permissions::Permission};
+
+/// A "blocker blocks blocked" edge between two tasks.
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct TaskDependency {
+    pub blocker_id: i32,
+    pub blocked_id: i32,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+/// Dependency ids of a set of tasks, keyed by task id.
+#[derive(Debug, Default)]
+pub struct Dependencies {
+    pub blocked_by: HashMap<i32, Vec<i32>>,
+    pub blocking: HashMap<i32, Vec<i32>>,
+}
+
+impl TaskDependency {
+    /// Records that `blocker_id` blocks `blocked_id`. The user must be able
+    /// to edit the blocked task and view the blocker, and the edge must not
+    /// close a cycle.
+    pub async fn add(
+        db: &PgPool,
+        blocked_id: i32,
+        blocker_id: i32,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        if blocked_id == blocker_id {
+            return Err(ModelError::DependencyCycle);
+        }
+
+        let mut txn = db.begin().await?;
+
+        // Lock both rows in id order so concurrent inserts of the opposite
+        // edge serialise and the cycle check below sees the other's result.
+        let locked = sqlx::query_scalar::<_, i32>(
+            "SELECT id FROM tasks WHERE id = ANY($1) ORDER BY id FOR UPDATE",
+        )
+        .bind(vec![blocked_id, blocker_id])
+        .fetch_all(&mut *txn)
+        .await?;
+
+        if locked.len() != 2 {
+            return Err(ModelError::EntityNotFound);
+        }
+        Permission::require_task(&mut *txn, blocked_id, user_pid, Permission::Editor).await?;
+        Permission::require_task(&mut *txn, blocker_id, user_pid, Permission::Viewer).await?;
+
+        // Adding the edge closes a cycle when the blocked task already,
+        // directly or transitively, blocks the blocker.
+        let cycle = sqlx::query_scalar::<_, bool>(
+            "
+            WITH RECURSIVE downstream AS (
+                SELECT blocked_id FROM task_dependencies WHERE blocker_id = $1
+                UNION
+                SELECT d.blocked_id
+                FROM task_dependencies d JOIN downstream s ON d.blocker_id = s.blocked_id
+            )
+            SELECT EXISTS (SELECT 1 FROM downstream WHERE blocked_id = $2)
+            ",
+        )
+        .bind(blocked_id)
+        .bind(blocker_id)
+        .fetch_one(&mut *txn)
+        .await?;
+
+        if cycle {
+            return Err(ModelError::DependencyCycle);
+        }
+
+        let dependency = sqlx::query_as::<_, Self>(
+            "
+            INSERT INTO task_dependencies (blocker_id, blocked_id)
+            VALUES ($1, $2)
+            ON CONFLICT (blocker_id, blocked_id) DO UPDATE SET blocker_id = EXCLUDED.blocker_id
+            RETURNING *
+            ",
+        )
+        .bind(blocker_id)
+        .bind(blocked_id)
+        .fetch_one(&mut *txn)
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(dependency)
+    }
+
+    pub async fn remove(
+        db: &PgPool,
+        blocked_id: i32,
+        blocker_id: i32,
+        user_pid: Uuid,
+    ) -> Result<(), ModelError> {
+        Permission::require_task(db, blocked_id, user_pid, Permission::Editor).await?;
+
+        let query =
+            sqlx::query("DELETE FROM task_dependencies WHERE blocked_id = $1 AND blocker_id = $2")
+                .bind(blocked_id)
+                .bind(blocker_id)
+                .execute(db)
+                .await?;
+
+        if query.rows_affected() == 0 {
+            return Err(ModelError::EntityNotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Number of tasks blocking `task_id` that are not done yet.
+    pub async fn unfinished_blockers<'e, C>(db: C, task_id: i32) -> Result<i64, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let count = sqlx::query_scalar::<_, i64>(
+            "
+            SELECT COUNT(*) FROM task_dependencies d
+            JOIN tasks t ON t.id = d.blocker_id
+            WHERE d.blocked_id = $1 AND NOT t.done AND t.deleted_at IS NULL
+            ",
+        )
+        .bind(task_id)
+        .fetch_one(db)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Loads both directions of every edge touching `task_ids`. Edges to
+    /// trashed tasks are left out until the task is restored.
+    pub async fn find_for_tasks<'e, C>(db: C, task_ids: &[i32]) -> Result<Dependencies, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let edges = sqlx::query_as::<_, Self>(
+            "
+            SELECT d.* FROM task_dependencies d
+            JOIN tasks blocker ON blocker.id = d.blocker_id AND blocker.deleted_at IS NULL
+            JOIN tasks blocked ON blocked.id = d.blocked_id AND blocked.deleted_at IS NULL
+            WHERE d.blocked_id = ANY($1) OR d.blocker_id = ANY($1)
+            ORDER BY d.blocker_id, d.blocked_id
+            ",
+        )
+        .bind(task_ids)
+        .fetch_all(db)
+        .await?;
+
+        let mut dependencies = Dependencies::default();
+        for edge in edges {
+            dependencies
+                .blocked_by
+                .entry(edge.blocked_id)
+                .or_default()
+                .push(edge.blocker_id);
+            dependencies
+                .blocking
+                .entry(edge.blocker_id)
+                .or_default()
+                .push(edge.blocked_id);
+        }
+
+        Ok(dependencies)
+    }
+}
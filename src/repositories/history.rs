@@ -0,0 +1,144 @@
+use chrono::{DateTime, FixedOffset};
+use serde_json::Value;
+use sqlx::{Executor, PgPool, Postgres, prelude::FromRow, types::Json};
+use uuid::Uuid;
+
+use crate::models::history::diff;
+
+use super::{ModelError, permissions::Permission, tasks::Task};
+
+/// Common table `owned(task_id)` of the tasks `$1` owns or owned before
+/// they were purged, going by who the history says created them.
+pub(crate) const OWNED_TASKS: &str = "
+    WITH owned AS (
+        SELECT id AS task_id FROM tasks WHERE user_pid = $1
+        UNION
+        SELECT task_id FROM task_history WHERE changes->'user_pid'->>'to' = $1::TEXT
+    )";
+
+/// What happened to a task in a [`TaskHistory`] entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryAction {
+    Created,
+    Updated,
+    /// Moved to the trash, or deleted for good by its owner
+    Deleted,
+    Restored,
+    /// Removed from the trash once the retention ran out
+    Purged,
+}
+
+impl HistoryAction {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::Deleted => "deleted",
+            Self::Restored => "restored",
+            Self::Purged => "purged",
+        }
+    }
+}
+
+/// An immutable record of one change to a task.
+#[derive(Debug, Clone, FromRow)]
+pub struct TaskHistory {
+    pub id: i32,
+    pub task_id: i32,
+    pub actor_pid: Option<Uuid>,
+    pub actor: Option<String>,
+    pub action: String,
+    pub changes: Json<Value>,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+impl TaskHistory {
+    /// Records a change from `before` to `after`, either of which is `None`
+    /// when the task did not exist on that side of the change.
+    ///
+    /// Meant to run in the transaction making the change, so the history
+    /// never disagrees with the task. Updates that changed nothing are not
+    /// recorded. Returns whether the change was recorded.
+    pub async fn record<'e, C>(
+        db: C,
+        action: HistoryAction,
+        actor_pid: Option<Uuid>,
+        before: Option<&Task>,
+        after: Option<&Task>,
+    ) -> Result<bool, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let Some(task_id) = after.or(before).map(|task| task.id) else {
+            return Ok(false);
+        };
+
+        let before = before.map(snapshot).transpose()?;
+        let after = after.map(snapshot).transpose()?;
+        let changes = diff(before.as_ref(), after.as_ref());
+        if changes.is_empty() && action == HistoryAction::Updated {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "INSERT INTO task_history (task_id, actor_pid, action, changes) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(task_id)
+        .bind(actor_pid)
+        .bind(action.as_str())
+        .bind(Json(Value::Object(changes)))
+        .execute(db)
+        .await?;
+
+        Ok(true)
+    }
+
+    /// Changes to a task `user_pid` can see, oldest first.
+    pub async fn find_all(
+        db: &PgPool,
+        task_id: i32,
+        user_pid: Uuid,
+    ) -> Result<Vec<Self>, ModelError> {
+        Permission::require_task(db, task_id, user_pid, Permission::Viewer).await?;
+
+        let items = sqlx::query_as::<_, Self>(
+            "
+            SELECT h.*, u.username AS actor
+            FROM task_history h
+            LEFT JOIN users u ON u.pid = h.actor_pid
+            WHERE h.task_id = $1
+            ORDER BY h.created_at, h.id
+            ",
+        )
+        .bind(task_id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Changes the user made to any task, and every change to tasks they
+    /// own or owned before they were purged, oldest first.
+    pub async fn find_for_user(db: &PgPool, user_pid: Uuid) -> Result<Vec<Self>, ModelError> {
+        let items = sqlx::query_as::<_, Self>(&format!(
+            "
+            {OWNED_TASKS}
+            SELECT h.*, u.username AS actor
+            FROM task_history h
+            LEFT JOIN users u ON u.pid = h.actor_pid
+            WHERE h.actor_pid = $1 OR h.task_id IN (SELECT task_id FROM owned)
+            ORDER BY h.created_at, h.id
+            "
+        ))
+        .bind(user_pid)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+}
+
+fn snapshot(task: &Task) -> Result<Value, ModelError> {
+    serde_json::to_value(task).map_err(|e| ModelError::Database(e.to_string()))
+}
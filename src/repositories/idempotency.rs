@@ -0,0 +1,126 @@
+use chrono::{DateTime, FixedOffset};
+use sqlx::{PgPool, prelude::FromRow, types::Json};
+use uuid::Uuid;
+
+use super::ModelError;
+
+/// A request made with an `Idempotency-Key`, and once answered, the
+/// response to replay for retries of it.
+#[derive(Debug, Clone, FromRow)]
+pub struct IdempotencyKey {
+    pub user_pid: Uuid,
+    pub key: String,
+    pub request_hash: String,
+    pub status: Option<i16>,
+    pub headers: Option<Json<Vec<(String, String)>>>,
+    pub body: Option<Vec<u8>>,
+    pub owner_pid: Option<Uuid>,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+impl IdempotencyKey {
+    /// Claims `key` for a new request, taking over records created before
+    /// `since` and reservations still unanswered since `stale_before`, left
+    /// behind by a request that crashed. Returns `false` when the key is
+    /// already taken.
+    pub async fn reserve(
+        db: &PgPool,
+        user_pid: Uuid,
+        key: &str,
+        request_hash: &str,
+        since: DateTime<FixedOffset>,
+        stale_before: DateTime<FixedOffset>,
+    ) -> Result<bool, ModelError> {
+        let reserved = sqlx::query_scalar::<_, Uuid>(
+            "
+            INSERT INTO idempotency_keys (user_pid, key, request_hash) VALUES ($1, $2, $3)
+            ON CONFLICT (user_pid, key) DO UPDATE
+            SET request_hash = EXCLUDED.request_hash, status = NULL, headers = NULL,
+                body = NULL, created_at = NOW()
+            WHERE idempotency_keys.created_at < $4
+            OR (idempotency_keys.status IS NULL AND idempotency_keys.created_at < $5)
+            RETURNING user_pid
+            ",
+        )
+        .bind(user_pid)
+        .bind(key)
+        .bind(request_hash)
+        .bind(since)
+        .bind(stale_before)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(reserved.is_some())
+    }
+
+    /// The record of `key`, unless it was created before `since`.
+    pub async fn find(
+        db: &PgPool,
+        user_pid: Uuid,
+        key: &str,
+        since: DateTime<FixedOffset>,
+    ) -> Result<Option<Self>, ModelError> {
+        let item = sqlx::query_as::<_, Self>(
+            "SELECT * FROM idempotency_keys WHERE user_pid = $1 AND key = $2 AND created_at >= $3",
+        )
+        .bind(user_pid)
+        .bind(key)
+        .bind(since)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(item)
+    }
+
+    /// Stores the response to the request that reserved `key`. A response
+    /// that created the user `owner_pid` is deleted along with that user.
+    pub async fn complete(
+        db: &PgPool,
+        user_pid: Uuid,
+        key: &str,
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: &[u8],
+        owner_pid: Option<Uuid>,
+    ) -> Result<(), ModelError> {
+        sqlx::query(
+            "
+            UPDATE idempotency_keys SET status = $3, headers = $4, body = $5, owner_pid = $6
+            WHERE user_pid = $1 AND key = $2
+            ",
+        )
+        .bind(user_pid)
+        .bind(key)
+        .bind(status as i16)
+        .bind(Json(headers))
+        .bind(body)
+        .bind(owner_pid)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Frees `key` after a request that should be retried as new, such as
+    /// one that failed on the server.
+    pub async fn release(db: &PgPool, user_pid: Uuid, key: &str) -> Result<(), ModelError> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE user_pid = $1 AND key = $2")
+            .bind(user_pid)
+            .bind(key)
+            .execute(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Deletes records created before `before`. Returns how many were
+    /// removed.
+    pub async fn purge(db: &PgPool, before: DateTime<FixedOffset>) -> Result<u64, ModelError> {
+        let query = sqlx::query("DELETE FROM idempotency_keys WHERE created_at < $1")
+            .bind(before)
+            .execute(db)
+            .await?;
+
+        Ok(query.rows_affected())
+    }
+}
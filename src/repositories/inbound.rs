@@ -0,0 +1,108 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool, prelude::FromRow};
+use uuid::Uuid;
+
+use crate::models::inbound::TOKEN_BYTES;
+
+use super::ModelError;
+
+/// Secret address a user forwards emails to, each becomes one of their
+/// tasks.
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct InboundAddress {
+    pub user_pid: Uuid,
+    pub token: String,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0_u8; TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+impl InboundAddress {
+    /// The address of `user_pid`, created the first time it is asked for.
+    pub async fn find_or_create(db: &PgPool, user_pid: Uuid) -> Result<Self, ModelError> {
+        let item = sqlx::query_as::<_, Self>(
+            "
+            WITH created AS (
+                INSERT INTO inbound_addresses (user_pid, token) VALUES ($1, $2)
+                ON CONFLICT (user_pid) DO NOTHING
+                RETURNING *
+            )
+            SELECT * FROM created
+            UNION ALL
+            SELECT * FROM inbound_addresses WHERE user_pid = $1
+            ",
+        )
+        .bind(user_pid)
+        .bind(generate_token())
+        .fetch_one(db)
+        .await?;
+
+        Ok(item)
+    }
+
+    /// Replaces the address of `user_pid`, mail to the old one is refused
+    /// from then on.
+    pub async fn rotate(db: &PgPool, user_pid: Uuid) -> Result<Self, ModelError> {
+        let item = sqlx::query_as::<_, Self>(
+            "
+            INSERT INTO inbound_addresses (user_pid, token) VALUES ($1, $2)
+            ON CONFLICT (user_pid) DO UPDATE
+            SET token = EXCLUDED.token, created_at = NOW()
+            RETURNING *
+            ",
+        )
+        .bind(user_pid)
+        .bind(generate_token())
+        .fetch_one(db)
+        .await?;
+
+        Ok(item)
+    }
+
+    pub async fn find_by_token(db: &PgPool, token: &str) -> Result<Self, ModelError> {
+        let item = sqlx::query_as::<_, Self>("SELECT * FROM inbound_addresses WHERE token = $1")
+            .bind(token)
+            .fetch_optional(db)
+            .await?;
+
+        item.ok_or(ModelError::EntityNotFound)
+    }
+}
+
+/// An email already turned into a task of the user, by its `Message-ID`.
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct InboundMessage {
+    pub user_pid: Uuid,
+    pub message_id: String,
+    pub received_at: DateTime<FixedOffset>,
+}
+
+impl InboundMessage {
+    /// Records that `message_id` became a task of `user_pid`, as part of the
+    /// transaction creating it. Returns `false` when it already did, because
+    /// the sender retried a delivery.
+    pub async fn record(
+        conn: &mut PgConnection,
+        user_pid: Uuid,
+        message_id: &str,
+    ) -> Result<bool, ModelError> {
+        let query = sqlx::query(
+            "
+            INSERT INTO inbound_messages (user_pid, message_id) VALUES ($1, $2)
+            ON CONFLICT (user_pid, message_id) DO NOTHING
+            ",
+        )
+        .bind(user_pid)
+        .bind(message_id)
+        .execute(conn)
+        .await?;
+
+        Ok(query.rows_affected() > 0)
+    }
+}
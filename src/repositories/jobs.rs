@@ -0,0 +1,287 @@
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Executor, PgConnection, PgPool, Postgres, prelude::FromRow};
+
+use super::ModelError;
+
+/// A unit of background work, stored until it has run.
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct QueuedJob {
+    pub id: i64,
+    pub kind: String,
+    pub payload: Value,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<FixedOffset>,
+    pub locked_until: Option<DateTime<FixedOffset>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+    pub finished_at: Option<DateTime<FixedOffset>>,
+}
+
+impl QueuedJob {
+    /// Queues a job to run from `run_at`. Queued as part of a transaction,
+    /// the job only runs once it commits.
+    pub async fn enqueue<'e, C>(
+        db: C,
+        kind: &str,
+        payload: &Value,
+        max_attempts: i32,
+        run_at: DateTime<FixedOffset>,
+    ) -> Result<Self, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let item = sqlx::query_as::<_, Self>(
+            "
+            INSERT INTO jobs (kind, payload, max_attempts, run_at)
+            VALUES ($1, $2, $3, $4) RETURNING *
+            ",
+        )
+        .bind(kind)
+        .bind(payload)
+        .bind(max_attempts)
+        .bind(run_at)
+        .fetch_one(db)
+        .await?;
+
+        Ok(item)
+    }
+
+    pub async fn find_by_id<'e, C>(db: C, id: i64) -> Result<Self, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        sqlx::query_as::<_, Self>("SELECT * FROM jobs WHERE id = $1")
+            .bind(id)
+            .fetch_optional(db)
+            .await?
+            .ok_or(ModelError::EntityNotFound)
+    }
+
+    /// Claims up to `limit` due jobs of the given kinds, counting the attempt
+    /// and hiding them from other workers for `lease`. Running jobs whose
+    /// lease has run out are claimed again, as their worker is gone.
+    pub async fn claim(
+        db: &PgPool,
+        kinds: &[&str],
+        limit: i64,
+        lease: TimeDelta,
+    ) -> Result<Vec<Self>, ModelError> {
+        let items = sqlx::query_as::<_, Self>(
+            "
+            WITH due AS (
+                SELECT id FROM jobs
+                WHERE kind = ANY($1)
+                    AND ((status = 'queued' AND run_at <= NOW())
+                        OR (status = 'running' AND locked_until <= NOW()
+                            AND attempts < max_attempts))
+                ORDER BY run_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE jobs j
+            SET status = 'running', attempts = j.attempts + 1, locked_until = NOW() + $3
+            FROM due
+            WHERE j.id = due.id
+            RETURNING j.*
+            ",
+        )
+        .bind(kinds)
+        .bind(limit)
+        .bind(lease)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Fails the abandoned jobs that have no attempts left.
+    pub async fn fail_abandoned(db: &PgPool) -> Result<u64, ModelError> {
+        let query = sqlx::query(
+            "
+            UPDATE jobs
+            SET status = 'failed', finished_at = NOW(), locked_until = NULL,
+                last_error = 'Worker stopped before the job finished'
+            WHERE status = 'running' AND locked_until <= NOW() AND attempts >= max_attempts
+            ",
+        )
+        .execute(db)
+        .await?;
+
+        Ok(query.rows_affected())
+    }
+
+    /// Extends the lease of the attempt `attempts` at a running job by
+    /// `lease` from now. Returns `false` when the attempt no longer holds
+    /// the job, as its lease ran out and another worker claimed it.
+    pub async fn renew(
+        db: &PgPool,
+        id: i64,
+        attempts: i32,
+        lease: TimeDelta,
+    ) -> Result<bool, ModelError> {
+        let query = sqlx::query(
+            "
+            UPDATE jobs SET locked_until = NOW() + $3
+            WHERE id = $1 AND status = 'running' AND attempts = $2
+            ",
+        )
+        .bind(id)
+        .bind(attempts)
+        .bind(lease)
+        .execute(db)
+        .await?;
+
+        Ok(query.rows_affected() > 0)
+    }
+
+    /// Records that the attempt `attempts` at a job succeeded. Returns
+    /// `false`, recording nothing, when the attempt no longer holds the job.
+    pub async fn complete(db: &PgPool, id: i64, attempts: i32) -> Result<bool, ModelError> {
+        let query = sqlx::query(
+            "
+            UPDATE jobs
+            SET status = 'completed', finished_at = NOW(), locked_until = NULL, last_error = NULL
+            WHERE id = $1 AND status = 'running' AND attempts = $2
+            ",
+        )
+        .bind(id)
+        .bind(attempts)
+        .execute(db)
+        .await?;
+
+        Ok(query.rows_affected() > 0)
+    }
+
+    /// Records that the attempt `attempts` at a job failed: the job is
+    /// queued again at `retry_at`, or failed for good when that is `None`.
+    /// Returns `false`, recording nothing, when the attempt no longer holds
+    /// the job.
+    pub async fn fail(
+        db: &PgPool,
+        id: i64,
+        attempts: i32,
+        error: &str,
+        retry_at: Option<DateTime<FixedOffset>>,
+    ) -> Result<bool, ModelError> {
+        let query = sqlx::query(
+            "
+            UPDATE jobs
+            SET status = CASE WHEN $4::TIMESTAMPTZ IS NULL THEN 'failed' ELSE 'queued' END,
+                run_at = COALESCE($4, run_at),
+                finished_at = CASE WHEN $4::TIMESTAMPTZ IS NULL THEN NOW() END,
+                locked_until = NULL,
+                last_error = $3
+            WHERE id = $1 AND status = 'running' AND attempts = $2
+            ",
+        )
+        .bind(id)
+        .bind(attempts)
+        .bind(error)
+        .bind(retry_at)
+        .execute(db)
+        .await?;
+
+        Ok(query.rows_affected() > 0)
+    }
+
+    /// Deletes the jobs that finished before `before`.
+    pub async fn purge(db: &PgPool, before: DateTime<FixedOffset>) -> Result<u64, ModelError> {
+        let query = sqlx::query(
+            "DELETE FROM jobs WHERE status IN ('completed', 'failed') AND finished_at < $1",
+        )
+        .bind(before)
+        .execute(db)
+        .await?;
+
+        Ok(query.rows_affected())
+    }
+}
+
+/// A job queued again and again on a cron schedule.
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct JobSchedule {
+    pub name: String,
+    pub kind: String,
+    pub cron: String,
+    pub payload: Value,
+    pub next_run_at: DateTime<FixedOffset>,
+}
+
+impl JobSchedule {
+    /// Adds a schedule, or brings it up to date. The next run is only moved
+    /// when the cron expression changed.
+    pub async fn upsert<'e, C>(
+        db: C,
+        name: &str,
+        kind: &str,
+        cron: &str,
+        payload: &Value,
+        next_run_at: DateTime<FixedOffset>,
+    ) -> Result<Self, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let item = sqlx::query_as::<_, Self>(
+            "
+            INSERT INTO job_schedules (name, kind, cron, payload, next_run_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (name) DO UPDATE
+            SET kind = EXCLUDED.kind,
+                payload = EXCLUDED.payload,
+                cron = EXCLUDED.cron,
+                next_run_at = CASE
+                    WHEN job_schedules.cron = EXCLUDED.cron THEN job_schedules.next_run_at
+                    ELSE EXCLUDED.next_run_at
+                END
+            RETURNING *
+            ",
+        )
+        .bind(name)
+        .bind(kind)
+        .bind(cron)
+        .bind(payload)
+        .bind(next_run_at)
+        .fetch_one(db)
+        .await?;
+
+        Ok(item)
+    }
+
+    /// Locks the schedules among `names` that are due, skipping those another
+    /// worker is queueing.
+    pub async fn lock_due(
+        conn: &mut PgConnection,
+        names: &[&str],
+    ) -> Result<Vec<Self>, ModelError> {
+        let items = sqlx::query_as::<_, Self>(
+            "
+            SELECT * FROM job_schedules
+            WHERE name = ANY($1) AND next_run_at <= NOW()
+            FOR UPDATE SKIP LOCKED
+            ",
+        )
+        .bind(names)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(items)
+    }
+
+    pub async fn reschedule(
+        conn: &mut PgConnection,
+        name: &str,
+        next_run_at: DateTime<FixedOffset>,
+    ) -> Result<(), ModelError> {
+        sqlx::query("UPDATE job_schedules SET next_run_at = $2 WHERE name = $1")
+            .bind(name)
+            .bind(next_run_at)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, PgPool, Postgres, prelude::FromRow};
+use uuid::Uuid;
+
+use crate::models::labels::{NewLabel, UpdateLabel};
+
+use super::{ModelError, permissions::Permission};
+
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct Label {
+    pub id: i32,
+    pub pid: Uuid,
+    pub user_pid: Uuid,
+    pub name: String,
+    pub colour: String,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+/// Label row joined with the task it is attached to.
+#[derive(Debug, FromRow)]
+struct TaskLabel {
+    task_id: i32,
+    #[sqlx(flatten)]
+    label: Label,
+}
+
+fn map_unique_violation(e: sqlx::Error) -> ModelError {
+    if let sqlx::Error::Database(ref err) = e
+        && err.constraint() == Some("labels_user_pid_name_key")
+    {
+        return ModelError::LabelExists;
+    }
+    e.into()
+}
+
+impl Label {
+    pub async fn create(
+        db: &PgPool,
+        params: &NewLabel,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        sqlx::query_as::<_, Self>(
+            "
+            INSERT INTO labels (user_pid, name, colour)
+            VALUES ($1, $2, COALESCE($3, '#808080')) RETURNING *
+            ",
+        )
+        .bind(user_pid)
+        .bind(params.name.trim())
+        .bind(params.colour.as_deref())
+        .fetch_one(db)
+        .await
+        .map_err(map_unique_violation)
+    }
+
+    /// Loads the user's labels named in `names`, creating the missing ones
+    /// with the default colour.
+    pub async fn find_or_create<'e, C>(
+        db: C,
+        user_pid: Uuid,
+        names: &[String],
+    ) -> Result<Vec<Self>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        // The outer SELECT does not see the rows inserted by the CTE, so each
+        // label is returned once
+        let items = sqlx::query_as::<_, Self>(
+            "
+            WITH created AS (
+                INSERT INTO labels (user_pid, name, colour)
+                SELECT $1, name, '#808080' FROM UNNEST($2::TEXT[]) AS name
+                ON CONFLICT (user_pid, name) DO NOTHING
+                RETURNING *
+            )
+            SELECT * FROM created
+            UNION ALL
+            SELECT * FROM labels WHERE user_pid = $1 AND name = ANY($2)
+            ",
+        )
+        .bind(user_pid)
+        .bind(names)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    pub async fn find_all<'e, C>(db: C, user_pid: Uuid) -> Result<Vec<Self>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let items =
+            sqlx::query_as::<_, Self>("SELECT * FROM labels WHERE user_pid = $1 ORDER BY name")
+                .bind(user_pid)
+                .fetch_all(db)
+                .await?;
+
+        Ok(items)
+    }
+
+    pub async fn find_by_id<'e, C>(db: C, user_pid: Uuid, id: i32) -> Result<Self, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let item =
+            sqlx::query_as::<_, Self>("SELECT * FROM labels WHERE id = $1 AND user_pid = $2")
+                .bind(id)
+                .bind(user_pid)
+                .fetch_optional(db)
+                .await?;
+
+        item.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Renames and/or recolours a label owned by `user_pid`.
+    pub async fn update_by_id(
+        db: &PgPool,
+        params: &UpdateLabel,
+        id: i32,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        let mut txn = db.begin().await?;
+
+        let label = Self::find_by_id(&mut *txn, user_pid, id).await?;
+
+        let name = params
+            .name
+            .as_deref()
+            .map_or_else(|| label.name.clone(), |name| name.trim().to_string());
+        let colour = params
+            .colour
+            .as_ref()
+            .map_or_else(|| label.colour.clone(), ToString::to_string);
+
+        let label = sqlx::query_as::<_, Self>(
+            "
+            UPDATE labels
+            SET name = $3, colour = $4, updated_at = $5
+            WHERE id = $1 AND user_pid = $2
+            RETURNING *",
+        )
+        .bind(id)
+        .bind(user_pid)
+        .bind(name)
+        .bind(colour)
+        .bind(Utc::now().fixed_offset())
+        .fetch_one(&mut *txn)
+        .await
+        .map_err(map_unique_violation)?;
+
+        txn.commit().await?;
+
+        Ok(label)
+    }
+
+    pub async fn delete_by_id(db: &PgPool, id: i32, user_pid: Uuid) -> Result<(), ModelError> {
+        let query = sqlx::query("DELETE FROM labels WHERE id = $1 AND user_pid = $2")
+            .bind(id)
+            .bind(user_pid)
+            .execute(db)
+            .await?;
+
+        if query.rows_affected() == 0 {
+            return Err(ModelError::EntityNotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Attaches one of the user's labels to a task they can edit. Attaching
+    /// a label twice is a no-op.
+    pub async fn attach(
+        db: &PgPool,
+        task_id: i32,
+        label_id: i32,
+        user_pid: Uuid,
+    ) -> Result<(), ModelError> {
+        let mut txn = db.begin().await?;
+
+        Permission::require_task(&mut *txn, task_id, user_pid, Permission::Editor).await?;
+        let label = Self::find_by_id(&mut *txn, user_pid, label_id).await?;
+
+        sqlx::query(
+            "
+            INSERT INTO task_labels (task_id, label_id)
+            VALUES ($1, $2) ON CONFLICT DO NOTHING
+            ",
+        )
+        .bind(task_id)
+        .bind(label.id)
+        .execute(&mut *txn)
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    /// Detaches a label from a task the user can edit, including labels
+    /// other members of a shared task attached.
+    pub async fn detach(
+        db: &PgPool,
+        task_id: i32,
+        label_id: i32,
+        user_pid: Uuid,
+    ) -> Result<(), ModelError> {
+        Permission::require_task(db, task_id, user_pid, Permission::Editor).await?;
+
+        let query = sqlx::query("DELETE FROM task_labels WHERE task_id = $1 AND label_id = $2")
+            .bind(task_id)
+            .bind(label_id)
+            .execute(db)
+            .await?;
+
+        if query.rows_affected() == 0 {
+            return Err(ModelError::EntityNotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Loads the labels of every task in `task_ids`, keyed by task id.
+    pub async fn find_for_tasks<'e, C>(
+        db: C,
+        task_ids: &[i32],
+    ) -> Result<HashMap<i32, Vec<Self>>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let rows = sqlx::query_as::<_, TaskLabel>(
+            "
+            SELECT tl.task_id, l.*
+            FROM task_labels tl
+            JOIN labels l ON l.id = tl.label_id
+            WHERE tl.task_id = ANY($1)
+            ORDER BY l.name
+            ",
+        )
+        .bind(task_ids)
+        .fetch_all(db)
+        .await?;
+
+        let mut labels: HashMap<i32, Vec<Self>> = HashMap::new();
+        for row in rows {
+            labels.entry(row.task_id).or_default().push(row.label);
+        }
+
+        Ok(labels)
+    }
+}
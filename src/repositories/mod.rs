@@ -1,5 +1,20 @@
+pub mod account_exports;
+pub mod attachments;
+pub mod comments;
+pub mod dependencies;
+pub mod history;
+pub mod idempotency;
+pub mod inbound;
+pub mod jobs;
+pub mod labels;
+pub mod notifications;
+pub mod outbox;
+pub mod permissions;
+pub mod projects;
+pub mod shares;
 pub mod tasks;
 pub mod users;
+pub mod webhooks;
 
 use axum::{
     Json,
@@ -8,50 +23,177 @@ use axum::{
 };
 use serde_json::json;
 
+use crate::{models::recurrence::RecurrenceError, storage::StorageError};
+
 #[derive(Debug, thiserror::Error)]
 pub enum ModelError {
+    #[error(transparent)]
+    Archive(#[from] zip::result::ZipError),
     #[error("{0}")]
     Argon2(argon2::Error),
+    #[error("Assignee has no access to the task")]
+    AssigneeNoAccess,
+    #[error("Attachment exceeds the upload size limit")]
+    AttachmentTooLarge,
     #[error("{0}")]
     ArgonPasswordHash(argon2::password_hash::Error),
+    #[error("Task is blocked by {0} unfinished tasks")]
+    Blocked(i64),
     #[error("{0}")]
     Database(String),
+    #[error("Dependency would create a cycle")]
+    DependencyCycle,
     #[error("Account with email already exists")]
     EmailExists,
     #[error("Entity not in the database")]
     EntityNotFound,
+    #[error("Idempotency key is still in use by another request")]
+    IdempotencyKeyInUse,
+    #[error("Idempotency key was used with a different request")]
+    IdempotencyKeyReused,
+    #[error("Idempotency key is not valid")]
+    InvalidIdempotencyKey,
+    #[error("Permission level too low")]
+    Forbidden,
+    #[error("Task has subtasks")]
+    HasSubtasks,
     #[error(transparent)]
     Jwt(#[from] jsonwebtoken::errors::Error),
+    #[error("Label with name already exists")]
+    LabelExists,
+    #[error(transparent)]
+    Mail(#[from] lettre::error::Error),
+    #[error(transparent)]
+    MailAddress(#[from] lettre::address::AddressError),
+    #[error("Entity changed since it was read")]
+    PreconditionFailed,
+    #[error(transparent)]
+    Recurrence(#[from] RecurrenceError),
+    #[error("Rolled back because another operation failed")]
+    RolledBack,
     #[error(transparent)]
     Sqlx(#[from] sqlx::Error),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error("Cannot share with the owner")]
+    ShareWithOwner,
+    #[error(transparent)]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("Task cannot be nested under itself or its subtasks")]
+    TaskCycle,
+    #[error("Task nesting is too deep")]
+    TaskDepthExceeded,
     #[error("Failed to authenticate user")]
     Unauthorised,
+    #[error("File type {0} is not allowed")]
+    UnsupportedMediaType(String),
     #[error("Username already taken")]
     UsernameTaken,
 }
 
 impl ModelError {
-    pub fn response(&self) -> Response {
-        let (status, message) = match self {
+    /// Status code and client facing message describing the error.
+    #[must_use]
+    pub fn status(&self) -> (StatusCode, &'static str) {
+        match self {
+            Self::AssigneeNoAccess => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "The assignee cannot access this task, share it with them first",
+            ),
+            Self::AttachmentTooLarge => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "File exceeds the upload size limit",
+            ),
+            Self::Blocked(_) => (
+                StatusCode::CONFLICT,
+                "Task has unfinished blockers, complete them first or pass force=true",
+            ),
+            Self::DependencyCycle => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "A task cannot block itself or a task that already blocks it",
+            ),
             Self::EmailExists => (
                 StatusCode::CONFLICT,
                 "Email already registered to an account",
             ),
-            Self::EntityNotFound => (StatusCode::NOT_FOUND, "Entity not found"),
+            Self::EntityNotFound | Self::Storage(StorageError::NotFound(_)) => {
+                (StatusCode::NOT_FOUND, "Entity not found")
+            }
+            Self::Forbidden => (
+                StatusCode::FORBIDDEN,
+                "You do not have permission to do that",
+            ),
+            Self::HasSubtasks => (
+                StatusCode::CONFLICT,
+                "Task has subtasks, delete with cascade=true to remove them too",
+            ),
+            Self::IdempotencyKeyInUse => (
+                StatusCode::CONFLICT,
+                "A request with this Idempotency-Key is still being processed",
+            ),
+            Self::IdempotencyKeyReused => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "This Idempotency-Key was already used for a different request",
+            ),
+            Self::InvalidIdempotencyKey => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Idempotency-Key must be between 1 and 255 visible characters",
+            ),
+            Self::LabelExists => (
+                StatusCode::CONFLICT,
+                "A label with that name already exists",
+            ),
+            Self::PreconditionFailed => (
+                StatusCode::PRECONDITION_FAILED,
+                "The task was changed by someone else, fetch it again and retry",
+            ),
+            Self::Recurrence(_) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Recurrence rule is not valid",
+            ),
+            Self::RolledBack => (
+                StatusCode::FAILED_DEPENDENCY,
+                "Not applied because another operation in the batch failed",
+            ),
+            Self::ShareWithOwner => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "An item cannot be shared with its owner",
+            ),
             Self::Sqlx(_)
+            | Self::Archive(_)
+            | Self::Storage(_)
             | Self::Argon2(_)
             | Self::ArgonPasswordHash(_)
             | Self::Jwt(_)
+            | Self::Mail(_)
+            | Self::MailAddress(_)
+            | Self::Smtp(_)
             | Self::Database(_) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Something went wrong on our end",
             ),
+            Self::TaskCycle => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "A task cannot be nested under itself or one of its subtasks",
+            ),
+            Self::TaskDepthExceeded => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Subtasks cannot be nested that deep",
+            ),
+            Self::UnsupportedMediaType(_) => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "File type is not allowed",
+            ),
             Self::UsernameTaken => (
                 StatusCode::CONFLICT,
                 "Username is already taken, please pick another one",
             ),
             Self::Unauthorised => (StatusCode::UNAUTHORIZED, "Invalid email or password"),
-        };
+        }
+    }
+
+    pub fn response(&self) -> Response {
+        let (status, message) = self.status();
 
         let body = Json(json!({
             "message": message
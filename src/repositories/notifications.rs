@@ -0,0 +1,370 @@
+use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, PgConnection, PgPool, Postgres, prelude::FromRow};
+use uuid::Uuid;
+
+use crate::{
+    events::Event,
+    jobs::{self, EmailNotification},
+    models::{
+        notifications::{
+            NewNotification, NotificationKind, NotificationPayload, NotificationQuery,
+            UpdatePreference,
+        },
+        webhooks::WebhookEvent,
+    },
+};
+
+use super::{ModelError, outbox::OutboxEvent, webhooks::WebhookDelivery};
+
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct Notification {
+    pub id: i64,
+    pub user_pid: Uuid,
+    pub kind: String,
+    pub message: String,
+    pub task_id: Option<i32>,
+    pub project_id: Option<i32>,
+    pub comment_id: Option<i32>,
+    pub actor_pid: Option<Uuid>,
+    pub read_at: Option<DateTime<FixedOffset>>,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+/// What a notification refers to, as it is when the notification is sent.
+#[derive(Debug, FromRow)]
+struct Subject {
+    recipient: bool,
+    title: Option<String>,
+    actor: Option<String>,
+    comment_exists: bool,
+}
+
+impl Notification {
+    /// Sends the notification `event` calls for on the channels its
+    /// recipient chose, as part of the transaction relaying the event.
+    /// Returns the in-app notification, if one was stored.
+    ///
+    /// Nothing is sent once the recipient or what the event is about is
+    /// gone, trashed tasks included.
+    pub async fn dispatch(
+        conn: &mut PgConnection,
+        event: &Event,
+    ) -> Result<Option<Self>, ModelError> {
+        let Some(new) = NewNotification::for_event(event) else {
+            return Ok(None);
+        };
+
+        let preference = NotificationPreference::find(&mut *conn, new.user_pid, new.kind).await?;
+        if !(preference.in_app || preference.email || preference.webhook) {
+            return Ok(None);
+        }
+
+        let subject = sqlx::query_as::<_, Subject>(
+            "
+            SELECT
+                EXISTS (SELECT 1 FROM users WHERE pid = $1) AS recipient,
+                CASE
+                    WHEN $2::INTEGER IS NOT NULL
+                    THEN (SELECT title FROM tasks WHERE id = $2 AND deleted_at IS NULL)
+                    ELSE (SELECT name FROM projects WHERE id = $3)
+                END AS title,
+                (SELECT username FROM users WHERE pid = $4) AS actor,
+                EXISTS (SELECT 1 FROM comments WHERE id = $5) AS comment_exists
+            ",
+        )
+        .bind(new.user_pid)
+        .bind(new.task_id)
+        .bind(new.project_id)
+        .bind(new.actor_pid)
+        .bind(new.comment_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let Some(title) = subject.title.filter(|_| subject.recipient) else {
+            return Ok(None);
+        };
+        let message = new.message(&title, subject.actor.as_deref());
+
+        let notification = if preference.in_app {
+            let notification = sqlx::query_as::<_, Self>(
+                "
+                INSERT INTO notifications
+                    (user_pid, kind, message, task_id, project_id, comment_id, actor_pid)
+                VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *
+                ",
+            )
+            .bind(new.user_pid)
+            .bind(new.kind.as_str())
+            .bind(&message)
+            .bind(new.task_id)
+            .bind(new.project_id)
+            .bind(new.comment_id.filter(|_| subject.comment_exists))
+            .bind(new.actor_pid.filter(|_| subject.actor.is_some()))
+            .fetch_one(&mut *conn)
+            .await?;
+
+            let event = Event::Notified {
+                notification_id: notification.id,
+                user_pid: notification.user_pid,
+                task_id: notification.task_id,
+            };
+            OutboxEvent::record(conn, &event).await?;
+
+            Some(notification)
+        } else {
+            None
+        };
+
+        if preference.email {
+            let job = EmailNotification {
+                user_pid: new.user_pid,
+                message: message.clone(),
+            };
+            jobs::enqueue(&mut *conn, &job).await?;
+        }
+
+        if preference.webhook {
+            let payload = NotificationPayload {
+                event: WebhookEvent::Notification,
+                kind: new.kind,
+                message,
+                task_id: new.task_id,
+                project_id: new.project_id,
+                notification_id: notification.as_ref().map(|notification| notification.id),
+                occurred_at: Utc::now().fixed_offset(),
+            };
+            WebhookDelivery::enqueue_notification(conn, new.user_pid, &payload).await?;
+        }
+
+        Ok(notification)
+    }
+
+    /// The inbox of `user_pid`, newest first.
+    pub async fn find_all(
+        db: &PgPool,
+        user_pid: Uuid,
+        query: &NotificationQuery,
+    ) -> Result<Vec<Self>, ModelError> {
+        let items = sqlx::query_as::<_, Self>(
+            "
+            SELECT * FROM notifications
+            WHERE user_pid = $1
+                AND (NOT $2 OR read_at IS NULL)
+                AND ($3::BIGINT IS NULL OR id < $3)
+            ORDER BY id DESC
+            LIMIT $4
+            ",
+        )
+        .bind(user_pid)
+        .bind(query.unread)
+        .bind(query.before)
+        .bind(query.limit())
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    pub async fn unread_count(db: &PgPool, user_pid: Uuid) -> Result<i64, ModelError> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM notifications WHERE user_pid = $1 AND read_at IS NULL",
+        )
+        .bind(user_pid)
+        .fetch_one(db)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Marks a notification of `user_pid` as read, or as unread again. A
+    /// notification already read keeps when it was first read.
+    pub async fn mark(
+        db: &PgPool,
+        id: i64,
+        user_pid: Uuid,
+        read: bool,
+    ) -> Result<Self, ModelError> {
+        sqlx::query_as::<_, Self>(
+            "
+            UPDATE notifications
+            SET read_at = CASE WHEN $3 THEN COALESCE(read_at, NOW()) END
+            WHERE id = $1 AND user_pid = $2
+            RETURNING *
+            ",
+        )
+        .bind(id)
+        .bind(user_pid)
+        .bind(read)
+        .fetch_optional(db)
+        .await?
+        .ok_or(ModelError::EntityNotFound)
+    }
+
+    /// Marks every unread notification of `user_pid` as read, returning how
+    /// many there were.
+    pub async fn mark_all_read(db: &PgPool, user_pid: Uuid) -> Result<u64, ModelError> {
+        let query = sqlx::query(
+            "UPDATE notifications SET read_at = NOW() WHERE user_pid = $1 AND read_at IS NULL",
+        )
+        .bind(user_pid)
+        .execute(db)
+        .await?;
+
+        Ok(query.rows_affected())
+    }
+
+    /// Records a [`Event::TaskDue`] for every open task falling due within
+    /// `lead` that was not reminded of at its current due date, addressed to
+    /// its assignee or else its owner. Returns how many were recorded.
+    pub async fn queue_reminders(db: &PgPool, lead: TimeDelta) -> Result<usize, ModelError> {
+        let mut txn = db.begin().await?;
+
+        let due = sqlx::query_as::<_, (i32, Uuid, DateTime<FixedOffset>)>(
+            "
+            SELECT t.id, COALESCE(t.assignee_pid, t.user_pid), t.due_at
+            FROM tasks t
+            LEFT JOIN task_reminders r ON r.task_id = t.id
+            WHERE NOT t.done AND t.deleted_at IS NULL
+                AND t.due_at > NOW() AND t.due_at <= NOW() + $1
+                AND r.due_at IS DISTINCT FROM t.due_at
+            ORDER BY t.due_at
+            FOR UPDATE OF t SKIP LOCKED
+            ",
+        )
+        .bind(lead)
+        .fetch_all(&mut *txn)
+        .await?;
+
+        for &(task_id, user_pid, due_at) in &due {
+            sqlx::query(
+                "
+                INSERT INTO task_reminders (task_id, due_at) VALUES ($1, $2)
+                ON CONFLICT (task_id) DO UPDATE SET due_at = EXCLUDED.due_at
+                ",
+            )
+            .bind(task_id)
+            .bind(due_at)
+            .execute(&mut *txn)
+            .await?;
+
+            let event = Event::TaskDue {
+                task_id,
+                user_pid,
+                due_at,
+            };
+            OutboxEvent::record(&mut txn, &event).await?;
+        }
+
+        txn.commit().await?;
+
+        Ok(due.len())
+    }
+}
+
+/// Channels `user_pid` gets one kind of notification on.
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct NotificationPreference {
+    pub user_pid: Uuid,
+    pub kind: String,
+    pub in_app: bool,
+    pub email: bool,
+    pub webhook: bool,
+}
+
+impl NotificationPreference {
+    /// The defaults, for kinds the user has not changed.
+    #[must_use]
+    pub fn default_for(user_pid: Uuid, kind: NotificationKind) -> Self {
+        Self {
+            user_pid,
+            kind: kind.as_str().to_string(),
+            in_app: true,
+            email: false,
+            webhook: false,
+        }
+    }
+
+    pub async fn find<'e, C>(
+        db: C,
+        user_pid: Uuid,
+        kind: NotificationKind,
+    ) -> Result<Self, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let item = sqlx::query_as::<_, Self>(
+            "
+            SELECT user_pid, kind, in_app, email, webhook FROM notification_preferences
+            WHERE user_pid = $1 AND kind = $2
+            ",
+        )
+        .bind(user_pid)
+        .bind(kind.as_str())
+        .fetch_optional(db)
+        .await?;
+
+        Ok(item.unwrap_or_else(|| Self::default_for(user_pid, kind)))
+    }
+
+    /// Preferences of `user_pid` for every kind of notification.
+    pub async fn find_all(db: &PgPool, user_pid: Uuid) -> Result<Vec<Self>, ModelError> {
+        let stored = sqlx::query_as::<_, Self>(
+            "
+            SELECT user_pid, kind, in_app, email, webhook FROM notification_preferences
+            WHERE user_pid = $1
+            ",
+        )
+        .bind(user_pid)
+        .fetch_all(db)
+        .await?;
+
+        let items = NotificationKind::ALL
+            .into_iter()
+            .map(|kind| {
+                stored
+                    .iter()
+                    .find(|preference| preference.kind == kind.as_str())
+                    .cloned()
+                    .unwrap_or_else(|| Self::default_for(user_pid, kind))
+            })
+            .collect();
+
+        Ok(items)
+    }
+
+    /// Turns channels of one kind on or off, starting from the defaults.
+    pub async fn update(
+        db: &PgPool,
+        user_pid: Uuid,
+        kind: NotificationKind,
+        params: &UpdatePreference,
+    ) -> Result<Self, ModelError> {
+        let defaults = Self::default_for(user_pid, kind);
+
+        let item = sqlx::query_as::<_, Self>(
+            "
+            INSERT INTO notification_preferences (user_pid, kind, in_app, email, webhook)
+            VALUES ($1, $2, COALESCE($3, $6), COALESCE($4, $7), COALESCE($5, $8))
+            ON CONFLICT (user_pid, kind) DO UPDATE
+            SET in_app = COALESCE($3, notification_preferences.in_app),
+                email = COALESCE($4, notification_preferences.email),
+                webhook = COALESCE($5, notification_preferences.webhook),
+                updated_at = NOW()
+            RETURNING user_pid, kind, in_app, email, webhook
+            ",
+        )
+        .bind(user_pid)
+        .bind(kind.as_str())
+        .bind(params.in_app)
+        .bind(params.email)
+        .bind(params.webhook)
+        .bind(defaults.in_app)
+        .bind(defaults.email)
+        .bind(defaults.webhook)
+        .fetch_one(db)
+        .await?;
+
+        Ok(item)
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Acquire, PgConnection, PgPool, prelude::FromRow};
+
+use crate::{
+    config::OutboxConfig,
+    events::{EVENTS_CHANNEL, Envelope, Event},
+};
+
+use super::{ModelError, notifications::Notification, webhooks::WebhookDelivery};
+
+/// Key of the advisory lock the relay holds for each batch, so that only one
+/// instance publishes at a time and events keep their order.
+const RELAY_LOCK: i64 = 0x6f75_7462_6f78;
+
+/// A domain event recorded in the same transaction as the change it
+/// describes, waiting to be published.
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct OutboxEvent {
+    pub id: i64,
+    pub aggregate_type: String,
+    pub aggregate_id: String,
+    pub payload: Value,
+    pub created_at: DateTime<FixedOffset>,
+    pub published_at: Option<DateTime<FixedOffset>>,
+    /// Failed attempts at publishing the event
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    /// When the relay gave up on the event
+    pub dead_at: Option<DateTime<FixedOffset>>,
+    /// When a failed event is tried again
+    pub next_attempt_at: Option<DateTime<FixedOffset>>,
+}
+
+impl OutboxEvent {
+    /// Records `event` for the relay, which only sees it once the
+    /// transaction commits.
+    pub async fn record(conn: &mut PgConnection, event: &Event) -> Result<Self, ModelError> {
+        let (aggregate_type, aggregate_id) = event.aggregate();
+        let payload =
+            serde_json::to_value(event).map_err(|e| ModelError::Database(e.to_string()))?;
+
+        let item = sqlx::query_as::<_, Self>(
+            "
+            INSERT INTO outbox_events (aggregate_type, aggregate_id, payload)
+            VALUES ($1, $2, $3) RETURNING *
+            ",
+        )
+        .bind(aggregate_type)
+        .bind(aggregate_id)
+        .bind(payload)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(item)
+    }
+
+    /// The recorded event `id`, for listeners told about it on
+    /// [`EVENTS_CHANNEL`].
+    pub async fn find_event(db: &PgPool, id: i64) -> Result<Event, ModelError> {
+        let payload =
+            sqlx::query_scalar::<_, Value>("SELECT payload FROM outbox_events WHERE id = $1")
+                .bind(id)
+                .fetch_optional(db)
+                .await?
+                .ok_or(ModelError::EntityNotFound)?;
+
+        serde_json::from_value(payload).map_err(|e| ModelError::Database(e.to_string()))
+    }
+
+    /// Publishes up to `limit` of the oldest pending events, returning how
+    /// many were published; returns 0 while another relay holds the lock.
+    ///
+    /// Each event queues its webhook deliveries and notifications, is
+    /// announced on [`EVENTS_CHANNEL`] and marked published in a savepoint
+    /// of its own, so that none is lost and one failing does not hold back
+    /// the rest. A failed event is retried after the backoff of `config`,
+    /// and later events about the same aggregate wait for it, until after
+    /// its last attempt it is dead-lettered. The events waiting for a
+    /// dead-lettered one are then published without it, so consumers see a
+    /// gap in the history of that aggregate.
+    pub async fn relay(
+        db: &PgPool,
+        limit: i64,
+        config: &OutboxConfig,
+    ) -> Result<usize, ModelError> {
+        let mut txn = db.begin().await?;
+
+        let locked = sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_xact_lock($1)")
+            .bind(RELAY_LOCK)
+            .fetch_one(&mut *txn)
+            .await?;
+        if !locked {
+            return Ok(0);
+        }
+
+        // Events waiting for a retry hold back the later events of their
+        // aggregate
+        let items = sqlx::query_as::<_, Self>(
+            "
+            SELECT * FROM outbox_events e
+            WHERE published_at IS NULL AND dead_at IS NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM outbox_events w
+                WHERE w.published_at IS NULL AND w.dead_at IS NULL
+                AND w.aggregate_type = e.aggregate_type AND w.aggregate_id = e.aggregate_id
+                AND w.id <= e.id AND w.next_attempt_at > NOW()
+            )
+            ORDER BY id LIMIT $1
+            ",
+        )
+        .bind(limit)
+        .fetch_all(&mut *txn)
+        .await?;
+
+        let mut published = 0;
+        // Aggregates with an event left to retry, whose later events wait
+        let mut held_back = HashSet::new();
+        for item in &items {
+            let aggregate = (item.aggregate_type.as_str(), item.aggregate_id.as_str());
+            if held_back.contains(&aggregate) {
+                continue;
+            }
+
+            let event = match serde_json::from_value::<Event>(item.payload.clone()) {
+                Ok(event) => event,
+                Err(e) => {
+                    tracing::error!(
+                        "Dead-lettering malformed outbox event {} of {} {}: {e}",
+                        item.id,
+                        item.aggregate_type,
+                        item.aggregate_id
+                    );
+                    Self::fail(&mut txn, item.id, &e.to_string(), None).await?;
+                    continue;
+                }
+            };
+
+            let mut savepoint = txn.begin().await?;
+            match item.publish(&mut savepoint, &event).await {
+                Ok(()) => {
+                    savepoint.commit().await?;
+                    published += 1;
+                }
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    let attempts = u32::try_from(item.attempts + 1).unwrap_or_default();
+                    let retry_in = config.backoff(attempts);
+                    if retry_in.is_some() {
+                        tracing::warn!("Failed to publish outbox event {}: {e}", item.id);
+                        held_back.insert(aggregate);
+                    } else {
+                        tracing::error!(
+                            "Dead-lettering outbox event {} of {} {}, its later events go on without it: {e}",
+                            item.id,
+                            item.aggregate_type,
+                            item.aggregate_id
+                        );
+                    }
+                    Self::fail(&mut txn, item.id, &e.to_string(), retry_in).await?;
+                }
+            }
+        }
+
+        txn.commit().await?;
+
+        Ok(published)
+    }
+
+    /// Hands the event on and marks it published. Listeners are only told
+    /// its id and kind and load the rest, which keeps the notification well
+    /// below the payload limit of Postgres.
+    async fn publish(&self, conn: &mut PgConnection, event: &Event) -> Result<(), ModelError> {
+        WebhookDelivery::enqueue(&mut *conn, event).await?;
+        Notification::dispatch(&mut *conn, event).await?;
+
+        let envelope = Envelope {
+            id: self.id,
+            kind: self.payload["type"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        };
+        let envelope =
+            serde_json::to_string(&envelope).map_err(|e| ModelError::Database(e.to_string()))?;
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(EVENTS_CHANNEL)
+            .bind(envelope)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query("UPDATE outbox_events SET published_at = NOW() WHERE id = $1")
+            .bind(self.id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Counts a failed attempt at publishing event `id`, to be tried again
+    /// in `retry_in` or given up on when that is `None`.
+    async fn fail(
+        conn: &mut PgConnection,
+        id: i64,
+        error: &str,
+        retry_in: Option<TimeDelta>,
+    ) -> Result<(), ModelError> {
+        sqlx::query(
+            "
+            UPDATE outbox_events
+            SET attempts = attempts + 1, last_error = $2,
+                next_attempt_at = NOW() + $3,
+                dead_at = CASE WHEN $3::INTERVAL IS NULL THEN NOW() END
+            WHERE id = $1
+            ",
+        )
+        .bind(id)
+        .bind(error)
+        .bind(retry_in)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes the events published before `before`.
+    pub async fn purge(db: &PgPool, before: DateTime<FixedOffset>) -> Result<u64, ModelError> {
+        let query = sqlx::query("DELETE FROM outbox_events WHERE published_at < $1")
+            .bind(before)
+            .execute(db)
+            .await?;
+
+        Ok(query.rows_affected())
+    }
+}
@@ -0,0 +1,130 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::ModelError;
+
+/// Access level a user holds on a task or project, ordered from least to
+/// most privileged.
+///
+/// Owners hold [`Permission::Owner`] implicitly. Everyone else gets access
+/// through a share of the item itself, of a parent task or of the project it
+/// belongs to, and the highest of those applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// Can read the item.
+    Viewer,
+    /// Can also change the item and its tasks.
+    Editor,
+    /// Can also delete and share the item.
+    Owner,
+}
+
+impl Permission {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Viewer => "viewer",
+            Self::Editor => "editor",
+            Self::Owner => "owner",
+        }
+    }
+
+    /// Highest permission `user_pid` holds on a task, `None` without access.
+    pub async fn for_task<'e, C>(
+        db: C,
+        task_id: i32,
+        user_pid: Uuid,
+    ) -> Result<Option<Self>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let permission = sqlx::query_scalar::<_, Option<String>>("SELECT task_permission($1, $2)")
+            .bind(task_id)
+            .bind(user_pid)
+            .fetch_one(db)
+            .await?;
+
+        permission.as_deref().map(str::parse).transpose()
+    }
+
+    /// Highest permission `user_pid` holds on a project, `None` without
+    /// access.
+    pub async fn for_project<'e, C>(
+        db: C,
+        project_id: i32,
+        user_pid: Uuid,
+    ) -> Result<Option<Self>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let permission =
+            sqlx::query_scalar::<_, Option<String>>("SELECT project_permission($1, $2)")
+                .bind(project_id)
+                .bind(user_pid)
+                .fetch_one(db)
+                .await?;
+
+        permission.as_deref().map(str::parse).transpose()
+    }
+
+    /// Fails unless `user_pid` holds at least `required` on the task.
+    ///
+    /// Tasks the user cannot see at all are reported as not found, so their
+    /// existence is not leaked.
+    pub async fn require_task<'e, C>(
+        db: C,
+        task_id: i32,
+        user_pid: Uuid,
+        required: Self,
+    ) -> Result<Self, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        Self::check(Self::for_task(db, task_id, user_pid).await?, required)
+    }
+
+    /// Fails unless `user_pid` holds at least `required` on the project.
+    pub async fn require_project<'e, C>(
+        db: C,
+        project_id: i32,
+        user_pid: Uuid,
+        required: Self,
+    ) -> Result<Self, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        Self::check(Self::for_project(db, project_id, user_pid).await?, required)
+    }
+
+    fn check(granted: Option<Self>, required: Self) -> Result<Self, ModelError> {
+        match granted {
+            None => Err(ModelError::EntityNotFound),
+            Some(granted) if granted < required => Err(ModelError::Forbidden),
+            Some(granted) => Ok(granted),
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Permission {
+    type Err = ModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Self::Viewer),
+            "editor" => Ok(Self::Editor),
+            "owner" => Ok(Self::Owner),
+            other => Err(ModelError::Database(format!("Unknown permission {other}"))),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, PgPool, Postgres, prelude::FromRow};
+use uuid::Uuid;
+
+use crate::models::projects::{NewProject, UpdateProject};
+
+use super::{ModelError, permissions::Permission, tasks::Task};
+
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct Project {
+    pub id: i32,
+    pub pid: Uuid,
+    pub user_pid: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+impl Project {
+    pub async fn create(
+        db: &PgPool,
+        params: &NewProject,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        let item = sqlx::query_as::<_, Self>(
+            "
+            INSERT INTO projects (user_pid, name, description)
+            VALUES ($1, $2, $3) RETURNING *
+            ",
+        )
+        .bind(user_pid)
+        .bind(&params.name)
+        .bind(params.description.as_deref())
+        .fetch_one(db)
+        .await?;
+
+        Ok(item)
+    }
+
+    pub async fn find_all<'e, C>(db: C, user_pid: Uuid) -> Result<Vec<Self>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let items =
+            sqlx::query_as::<_, Self>("SELECT * FROM projects WHERE user_pid = $1 ORDER BY id")
+                .bind(user_pid)
+                .fetch_all(db)
+                .await?;
+
+        Ok(items)
+    }
+
+    /// Projects owned by someone else that were shared with `user_pid`.
+    pub async fn find_shared<'e, C>(db: C, user_pid: Uuid) -> Result<Vec<Self>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let items = sqlx::query_as::<_, Self>(
+            "
+            SELECT p.* FROM projects p
+            JOIN project_shares s ON s.project_id = p.id
+            WHERE s.user_pid = $1
+            ORDER BY p.id
+            ",
+        )
+        .bind(user_pid)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Loads a project `user_pid` can at least view.
+    pub async fn find_by_id<'e, C>(db: C, user_pid: Uuid, id: i32) -> Result<Self, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let item = sqlx::query_as::<_, Self>(
+            "SELECT * FROM projects WHERE id = $1 AND project_permission(id, $2) IS NOT NULL",
+        )
+        .bind(id)
+        .bind(user_pid)
+        .fetch_optional(db)
+        .await?;
+
+        item.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    pub async fn update_by_id(
+        db: &PgPool,
+        params: &UpdateProject,
+        id: i32,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        let mut txn = db.begin().await?;
+
+        Permission::require_project(&mut *txn, id, user_pid, Permission::Editor).await?;
+        let project = Self::find_by_id(&mut *txn, user_pid, id).await?;
+
+        let name = params
+            .name
+            .as_ref()
+            .map_or_else(|| project.name.clone(), ToString::to_string);
+        let description = params.description.clone().or(project.description);
+
+        let project = sqlx::query_as::<_, Self>(
+            "
+            UPDATE projects
+            SET name = $2, description = $3, updated_at = $4
+            WHERE id = $1
+            RETURNING *",
+        )
+        .bind(id)
+        .bind(name)
+        .bind(description)
+        .bind(Utc::now().fixed_offset())
+        .fetch_one(&mut *txn)
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(project)
+    }
+
+    /// Deletes a project, which takes owner permission. Its tasks are kept
+    /// and appended to the end of their owners' inboxes in project order.
+    pub async fn delete_by_id(db: &PgPool, id: i32, user_pid: Uuid) -> Result<(), ModelError> {
+        let mut txn = db.begin().await?;
+
+        Permission::require_project(&mut *txn, id, user_pid, Permission::Owner).await?;
+        let project = Self::find_by_id(&mut *txn, user_pid, id).await?;
+
+        let mut owners = sqlx::query_scalar::<_, Uuid>(
+            "
+            UPDATE tasks t
+            SET project_id = NULL,
+                position = t.position + (
+                    SELECT COUNT(*) FROM tasks i
+                    WHERE i.user_pid = t.user_pid AND i.project_id IS NULL
+                    AND i.deleted_at IS NULL
+                )
+            WHERE t.project_id = $1
+            RETURNING t.user_pid
+            ",
+        )
+        .bind(project.id)
+        .fetch_all(&mut *txn)
+        .await?;
+
+        // Tasks added by other members leave gaps in their owners' inboxes.
+        owners.sort();
+        owners.dedup();
+        for owner_pid in owners {
+            Task::renumber(&mut *txn, owner_pid, None).await?;
+        }
+
+        sqlx::query("DELETE FROM projects WHERE id = $1")
+            .bind(project.id)
+            .execute(&mut *txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    /// Tasks of the project in their manual order.
+    pub async fn tasks(db: &PgPool, user_pid: Uuid, id: i32) -> Result<Vec<Task>, ModelError> {
+        Permission::require_project(db, id, user_pid, Permission::Viewer).await?;
+
+        let items = sqlx::query_as::<_, Task>(
+            "SELECT * FROM tasks WHERE project_id = $1 AND deleted_at IS NULL ORDER BY position",
+        )
+        .bind(id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+}
@@ -0,0 +1,214 @@
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, PgPool, Postgres, prelude::FromRow};
+use uuid::Uuid;
+
+use crate::{events::Event, models::shares::NewShare};
+
+use super::{ModelError, outbox::OutboxEvent, permissions::Permission, users::User};
+
+/// A user's access to a shared task or project.
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct Share {
+    pub user_pid: Uuid,
+    pub username: String,
+    pub permission: String,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+/// The item a [`Share`] grants access to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareTarget {
+    Task(i32),
+    Project(i32),
+}
+
+impl ShareTarget {
+    const fn table(self) -> &'static str {
+        match self {
+            Self::Task(_) => "task_shares",
+            Self::Project(_) => "project_shares",
+        }
+    }
+
+    const fn column(self) -> &'static str {
+        match self {
+            Self::Task(_) => "task_id",
+            Self::Project(_) => "project_id",
+        }
+    }
+
+    const fn id(self) -> i32 {
+        match self {
+            Self::Task(id) | Self::Project(id) => id,
+        }
+    }
+
+    const fn shared_event(self, user_pid: Uuid, shared_by: Uuid) -> Event {
+        match self {
+            Self::Task(task_id) => Event::TaskShared {
+                task_id,
+                user_pid,
+                shared_by,
+            },
+            Self::Project(project_id) => Event::ProjectShared {
+                project_id,
+                user_pid,
+                shared_by,
+            },
+        }
+    }
+
+    async fn require<'e, C>(
+        self,
+        db: C,
+        user_pid: Uuid,
+        required: Permission,
+    ) -> Result<Permission, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        match self {
+            Self::Task(id) => Permission::require_task(db, id, user_pid, required).await,
+            Self::Project(id) => Permission::require_project(db, id, user_pid, required).await,
+        }
+    }
+
+    async fn owner_pid<'e, C>(self, db: C) -> Result<Uuid, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let query = match self {
+            Self::Task(_) => "SELECT user_pid FROM tasks WHERE id = $1",
+            Self::Project(_) => "SELECT user_pid FROM projects WHERE id = $1",
+        };
+
+        let owner = sqlx::query_scalar::<_, Uuid>(query)
+            .bind(self.id())
+            .fetch_optional(db)
+            .await?;
+
+        owner.ok_or_else(|| ModelError::EntityNotFound)
+    }
+}
+
+impl Share {
+    /// Shares the target with `params.username`, or changes the permission
+    /// of an existing share. Only owners can share, and the user is notified
+    /// of new shares.
+    pub async fn grant(
+        db: &PgPool,
+        target: ShareTarget,
+        params: &NewShare,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        let mut txn = db.begin().await?;
+
+        target
+            .require(&mut *txn, user_pid, Permission::Owner)
+            .await?;
+
+        let user = User::find_by_username(&mut *txn, params.username.trim()).await?;
+        if user.pid == target.owner_pid(&mut *txn).await? {
+            return Err(ModelError::ShareWithOwner);
+        }
+
+        let existing = format!(
+            "SELECT EXISTS (SELECT 1 FROM {table} WHERE {column} = $1 AND user_pid = $2)",
+            table = target.table(),
+            column = target.column(),
+        );
+        let existed = sqlx::query_scalar::<_, bool>(&existing)
+            .bind(target.id())
+            .bind(user.pid)
+            .fetch_one(&mut *txn)
+            .await?;
+
+        let query = format!(
+            "
+            INSERT INTO {table} ({column}, user_pid, permission)
+            VALUES ($1, $2, $3)
+            ON CONFLICT ({column}, user_pid) DO UPDATE SET permission = EXCLUDED.permission
+            RETURNING user_pid, $4::TEXT AS username, permission, created_at
+            ",
+            table = target.table(),
+            column = target.column(),
+        );
+
+        let share = sqlx::query_as::<_, Self>(&query)
+            .bind(target.id())
+            .bind(user.pid)
+            .bind(params.permission.as_str())
+            .bind(&user.username)
+            .fetch_one(&mut *txn)
+            .await?;
+
+        if !existed {
+            OutboxEvent::record(&mut txn, &target.shared_event(user.pid, user_pid)).await?;
+        }
+
+        txn.commit().await?;
+
+        Ok(share)
+    }
+
+    /// Removes the share of `username`. Owners can remove any share, other
+    /// users only their own.
+    pub async fn revoke(
+        db: &PgPool,
+        target: ShareTarget,
+        username: &str,
+        user_pid: Uuid,
+    ) -> Result<(), ModelError> {
+        let user = User::find_by_username(db, username).await?;
+        if user.pid != user_pid {
+            target.require(db, user_pid, Permission::Owner).await?;
+        }
+
+        let query = format!(
+            "DELETE FROM {table} WHERE {column} = $1 AND user_pid = $2",
+            table = target.table(),
+            column = target.column(),
+        );
+
+        let query = sqlx::query(&query)
+            .bind(target.id())
+            .bind(user.pid)
+            .execute(db)
+            .await?;
+
+        if query.rows_affected() == 0 {
+            return Err(ModelError::EntityNotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Everyone the target is shared with, visible to anyone with access.
+    pub async fn find_all(
+        db: &PgPool,
+        target: ShareTarget,
+        user_pid: Uuid,
+    ) -> Result<Vec<Self>, ModelError> {
+        target.require(db, user_pid, Permission::Viewer).await?;
+
+        let query = format!(
+            "
+            SELECT s.user_pid, u.username, s.permission, s.created_at
+            FROM {table} s
+            JOIN users u ON u.pid = s.user_pid
+            WHERE s.{column} = $1
+            ORDER BY u.username
+            ",
+            table = target.table(),
+            column = target.column(),
+        );
+
+        let items = sqlx::query_as::<_, Self>(&query)
+            .bind(target.id())
+            .fetch_all(db)
+            .await?;
+
+        Ok(items)
+    }
+}
@@ -1,13 +1,51 @@
+use std::collections::{HashMap, HashSet};
+
 use chrono::{DateTime, FixedOffset, Utc};
+use futures_util::{
+    StreamExt,
+    stream::{self, BoxStream},
+};
 use serde::{Deserialize, Serialize};
-use sqlx::{Decode, Executor, PgPool, Postgres, postgres::PgQueryResult, prelude::FromRow};
+use sqlx::{
+    Acquire, Decode, Executor, PgConnection, PgPool, Postgres, QueryBuilder, Transaction,
+    postgres::PgQueryResult, prelude::FromRow,
+};
 use uuid::Uuid;
 
-use crate::models::tasks::{NewTask, UpdateTask};
+use crate::{
+    events::Event,
+    models::{
+        bulk::{BulkAction, BulkMode, BulkOperation, BulkRequest},
+        import::ImportedTask,
+        labels::LabelResponse,
+        recurrence::Recurrence,
+        sync::{SyncChange, SyncToken},
+        tasks::{
+            Assigned, DeleteOptions, LabelMatch, MoveTask, NewTask, TaskFilter, TaskResponse,
+            UpdateOptions, UpdateTask, VersionMatch,
+        },
+    },
+    storage::BlobStore,
+};
+
+use super::{
+    ModelError,
+    attachments::Attachment,
+    dependencies::TaskDependency,
+    history::{HistoryAction, TaskHistory},
+    labels::Label,
+    outbox::OutboxEvent,
+    permissions::Permission,
+    users::User,
+};
 
-use super::ModelError;
+/// How deep tasks may be nested, counting the top level task as one.
+pub const MAX_TASK_DEPTH: i32 = 5;
 
-#[derive(Debug, Deserialize, Serialize, FromRow, Decode)]
+/// How many tasks an export reads from the database at a time.
+pub const EXPORT_PAGE_SIZE: i64 = 200;
+
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow, Decode)]
 pub struct Task {
     pub id: i32,
     pub pid: Uuid,
@@ -16,6 +54,47 @@ pub struct Task {
     pub done: bool,
     pub created_at: DateTime<FixedOffset>,
     pub updated_at: DateTime<FixedOffset>,
+    pub project_id: Option<i32>,
+    pub position: i32,
+    pub parent_id: Option<i32>,
+    pub due_at: Option<DateTime<FixedOffset>>,
+    pub recurrence: Option<String>,
+    pub series_id: Option<Uuid>,
+    pub assignee_pid: Option<Uuid>,
+    pub deleted_at: Option<DateTime<FixedOffset>>,
+    /// Incremented by the database on every write
+    pub version: i32,
+}
+
+/// What a successful operation of a bulk request did.
+#[derive(Debug)]
+pub enum BulkApplied {
+    Created(Task),
+    Updated(Task),
+    Deleted(i32),
+}
+
+/// Outcome of one operation of a bulk request, with the id of the task it
+/// targeted.
+pub type BulkResult = (Option<i32>, Result<BulkApplied, ModelError>);
+
+/// A page of the changes to a user's tasks, see [`Task::changes_since`].
+#[derive(Debug)]
+pub struct TaskChanges {
+    /// Live tasks, in the order they changed
+    pub changed: Vec<Task>,
+    /// Tasks trashed or deleted for good
+    pub deleted: Vec<i32>,
+    pub next: SyncToken,
+    pub has_more: bool,
+}
+
+/// A [`Task`] together with its distance from the root of a subtree.
+#[derive(Debug, FromRow)]
+pub struct TreeNode {
+    #[sqlx(flatten)]
+    pub task: Task,
+    pub level: i32,
 }
 
 impl Task {
@@ -29,137 +108,1536 @@ impl Task {
         params: &NewTask,
         user_pid: Uuid,
     ) -> Result<Self, ModelError> {
+        let mut txn = db.begin().await?;
+        let task = Self::create_in(&mut txn, params, user_pid).await?;
+        txn.commit().await?;
+
+        Ok(task)
+    }
+
+    /// Saves imported tasks in one transaction, creating any labels they
+    /// name that the user does not have yet. Parents are created before
+    /// their subtasks, the tasks are returned in file order.
+    ///
+    /// The tasks must have passed [`check`](crate::models::import::check).
+    pub async fn import(
+        db: &PgPool,
+        tasks: &[ImportedTask],
+        user_pid: Uuid,
+    ) -> Result<Vec<Self>, ModelError> {
+        let mut txn = db.begin().await?;
+
+        let mut names = tasks
+            .iter()
+            .flat_map(|task| task.labels.iter().cloned())
+            .collect::<Vec<String>>();
+        names.sort();
+        names.dedup();
+        let labels = Label::find_or_create(&mut *txn, user_pid, &names)
+            .await?
+            .into_iter()
+            .map(|label| (label.name, label.id))
+            .collect::<HashMap<String, i32>>();
+
+        let mut created = HashMap::<&str, i32>::new();
+        let mut saved = Vec::with_capacity(tasks.len());
+        let mut pending = tasks.iter().collect::<Vec<&ImportedTask>>();
+        while !pending.is_empty() {
+            let remaining = pending.len();
+            let mut waiting = Vec::new();
+
+            for imported in pending {
+                let parent_id = match imported.parent.as_deref() {
+                    Some(parent) => match created.get(parent) {
+                        Some(&parent_id) => Some(parent_id),
+                        None => {
+                            waiting.push(imported);
+                            continue;
+                        }
+                    },
+                    None => None,
+                };
+
+                let params = NewTask {
+                    parent_id,
+                    ..imported.task.clone()
+                };
+                let task = Self::create_in(&mut txn, &params, user_pid).await?;
+
+                let label_ids = imported
+                    .labels
+                    .iter()
+                    .filter_map(|name| labels.get(name).copied())
+                    .collect::<Vec<i32>>();
+                sqlx::query(
+                    "
+                    INSERT INTO task_labels (task_id, label_id)
+                    SELECT $1, UNNEST($2::INTEGER[])
+                    ",
+                )
+                .bind(task.id)
+                .bind(&label_ids)
+                .execute(&mut *txn)
+                .await?;
+
+                if let Some(key) = imported.key.as_deref() {
+                    created.insert(key, task.id);
+                }
+                saved.push((imported.row, task));
+            }
+
+            // Only reachable when a parent is missing from the import
+            if waiting.len() == remaining {
+                return Err(ModelError::EntityNotFound);
+            }
+            pending = waiting;
+        }
+
+        txn.commit().await?;
+
+        saved.sort_by_key(|(row, _)| *row);
+        Ok(saved.into_iter().map(|(_, task)| task).collect())
+    }
+
+    /// Creates a task within `txn`, for [`Task::create_task`], bulk
+    /// operations and inbound mail.
+    pub(crate) async fn create_in(
+        txn: &mut Transaction<'_, Postgres>,
+        params: &NewTask,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        if let Some(project_id) = params.project_id {
+            Permission::require_project(&mut **txn, project_id, user_pid, Permission::Editor)
+                .await?;
+        }
+
+        if let Some(parent_id) = params.parent_id {
+            Permission::require_task(&mut **txn, parent_id, user_pid, Permission::Editor).await?;
+            let depth = Self::ancestor_ids(&mut **txn, parent_id).await?.len() as i32;
+            if depth + 1 > MAX_TASK_DEPTH {
+                return Err(ModelError::TaskDepthExceeded);
+            }
+        }
+
+        let recurrence = params
+            .recurrence
+            .as_deref()
+            .map(str::parse::<Recurrence>)
+            .transpose()?;
+        let series_id = recurrence.as_ref().map(|_| Uuid::new_v4());
+
+        let position = Self::list_len(&mut **txn, user_pid, params.project_id).await?;
+
         let item = sqlx::query_as::<_, Self>(
             "
-            INSERT INTO tasks (user_pid, title, done)
-            VALUES ($1, $2, $3) RETURNING *
+            INSERT INTO tasks (
+                user_pid, title, done, project_id, position, parent_id,
+                due_at, recurrence, series_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *
             ",
         )
         .bind(user_pid)
         .bind(&params.title)
         .bind(params.done)
-        .fetch_one(db)
+        .bind(params.project_id)
+        .bind(position)
+        .bind(params.parent_id)
+        .bind(params.due_at)
+        .bind(recurrence.map(|rule| rule.to_string()))
+        .bind(series_id)
+        .fetch_one(&mut **txn)
         .await;
 
         match item {
-            Ok(task) => Ok(task),
+            Ok(task) => {
+                Self::record_change(
+                    txn,
+                    HistoryAction::Created,
+                    Some(user_pid),
+                    None,
+                    Some(&task),
+                )
+                .await?;
+                Ok(task)
+            }
             Err(e) => {
-                if let Some(db_err) = e.as_database_error() {
-                    if db_err.is_foreign_key_violation() {
-                        return Err(ModelError::Unauthorised);
-                    }
+                if let Some(db_err) = e.as_database_error()
+                    && db_err.is_foreign_key_violation()
+                {
+                    return Err(ModelError::Unauthorised);
                 }
                 Err(e.into())
             }
         }
     }
 
-    pub async fn find_all<'e, C>(db: C, user_pid: Uuid) -> Result<Vec<Self>, ModelError>
+    pub async fn find_all<'e, C>(
+        db: C,
+        user_pid: Uuid,
+        filter: &TaskFilter,
+    ) -> Result<Vec<Self>, ModelError>
     where
         C: Executor<'e, Database = Postgres>,
     {
-        let items = sqlx::query_as::<_, Self>("SELECT * FROM tasks WHERE user_pid = $1")
-            .bind(user_pid)
-            .fetch_all(db)
-            .await?;
+        let mut query = QueryBuilder::<Postgres>::new("SELECT * FROM tasks WHERE ");
+        match filter.assigned {
+            // Access may have been revoked since the task was assigned
+            Some(Assigned::Me) => {
+                query.push("assignee_pid = ");
+                query.push_bind(user_pid);
+                query.push(" AND task_permission(id, ");
+                query.push_bind(user_pid);
+                query.push(") IS NOT NULL");
+            }
+            None => {
+                query.push("user_pid = ");
+                query.push_bind(user_pid);
+            }
+        }
+
+        if let Some(done) = filter.done {
+            query.push(" AND done = ");
+            query.push_bind(done);
+        }
+
+        let mut labels = filter.labels();
+        labels.sort();
+        labels.dedup();
+
+        if !labels.is_empty() {
+            let label_count = labels.len() as i64;
+            match filter.label_match {
+                LabelMatch::Any => {
+                    query.push(
+                        " AND EXISTS (
+                        SELECT 1 FROM task_labels tl JOIN labels l ON l.id = tl.label_id
+                        WHERE tl.task_id = tasks.id AND l.name = ANY(",
+                    );
+                    query.push_bind(labels);
+                    query.push("))");
+                }
+                LabelMatch::All => {
+                    query.push(
+                        " AND (
+                        SELECT COUNT(DISTINCT l.name) FROM task_labels tl
+                        JOIN labels l ON l.id = tl.label_id
+                        WHERE tl.task_id = tasks.id AND l.name = ANY(",
+                    );
+                    query.push_bind(labels);
+                    query.push(")) = ");
+                    query.push_bind(label_count);
+                }
+            }
+        }
+
+        query.push(" AND deleted_at IS NULL ORDER BY id");
+
+        let items = query.build_query_as::<Self>().fetch_all(db).await?;
+
+        Ok(items)
+    }
+
+    /// Tasks owned by someone else that were shared with `user_pid`,
+    /// directly or through their project.
+    pub async fn find_shared<'e, C>(db: C, user_pid: Uuid) -> Result<Vec<Self>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let items = sqlx::query_as::<_, Self>(
+            "
+            SELECT t.* FROM tasks t
+            WHERE t.user_pid <> $1 AND t.deleted_at IS NULL AND (
+                EXISTS (SELECT 1 FROM task_shares s WHERE s.task_id = t.id AND s.user_pid = $1)
+                OR EXISTS (
+                    SELECT 1 FROM project_shares s
+                    WHERE s.project_id = t.project_id AND s.user_pid = $1
+                )
+            )
+            ORDER BY t.id
+            ",
+        )
+        .bind(user_pid)
+        .fetch_all(db)
+        .await?;
 
         Ok(items)
     }
 
+    /// Loads a task `user_pid` can at least view.
     pub async fn find_by_id<'e, C>(db: C, user_pid: Uuid, id: i32) -> Result<Self, ModelError>
     where
         C: Executor<'e, Database = Postgres>,
     {
-        let item = sqlx::query_as::<_, Self>("SELECT * FROM tasks WHERE id = $1 and user_pid = $2")
-            .bind(id)
-            .bind(user_pid)
-            .fetch_optional(db)
-            .await?;
+        let item = sqlx::query_as::<_, Self>(
+            "
+            SELECT * FROM tasks
+            WHERE id = $1 AND deleted_at IS NULL AND task_permission(id, $2) IS NOT NULL
+            ",
+        )
+        .bind(id)
+        .bind(user_pid)
+        .fetch_optional(db)
+        .await?;
 
         item.ok_or_else(|| ModelError::EntityNotFound)
     }
 
+    /// Moves a task to the trash, which takes owner permission. A task with
+    /// subtasks is only deleted, together with its whole subtree, when
+    /// `options.cascade` is set.
+    ///
+    /// With `options.permanent` the task is removed for good instead, live or
+    /// already trashed, along with attachment files no other task uses.
+    /// With `options.if_match` the task must still be at one of the given
+    /// versions.
     pub async fn delete_by_id(
         db: &PgPool,
+        blobs: &dyn BlobStore,
         id: i32,
         user_pid: Uuid,
+        options: &DeleteOptions,
     ) -> Result<PgQueryResult, ModelError> {
         let mut txn = db.begin().await?;
+        let (query, storage_keys) = Self::delete_in(&mut txn, id, user_pid, options).await?;
+        txn.commit().await?;
+
+        Attachment::release(db, blobs, &storage_keys).await?;
+
+        Ok(query)
+    }
+
+    /// Deletes a task within `txn`, for [`Task::delete_by_id`] and bulk
+    /// operations. Returns the storage keys of attachments to release once
+    /// the transaction is committed.
+    async fn delete_in(
+        txn: &mut Transaction<'_, Postgres>,
+        id: i32,
+        user_pid: Uuid,
+        options: &DeleteOptions,
+    ) -> Result<(PgQueryResult, Vec<String>), ModelError> {
+        let (subtree, lists) = match Self::lock_trashed(&mut **txn, id, user_pid).await? {
+            // Subtasks were trashed together with the task, nothing to renumber
+            Some(task) if options.permanent => {
+                task.check_version(options.if_match.as_ref())?;
+                (Self::trashed_subtree(&mut **txn, &task).await?, Vec::new())
+            }
+            Some(_) => return Err(ModelError::EntityNotFound),
+            None => {
+                Permission::require_task(&mut **txn, id, user_pid, Permission::Owner).await?;
+                let task = Self::lock_by_id(&mut **txn, id).await?;
+                task.check_version(options.if_match.as_ref())?;
+
+                let subtree = Self::subtree(&mut **txn, task.id)
+                    .await?
+                    .into_iter()
+                    .map(|node| node.task)
+                    .collect::<Vec<Self>>();
+                if subtree.len() > 1 && !options.cascade {
+                    return Err(ModelError::HasSubtasks);
+                }
+
+                let lists = Self::lists_of(&subtree);
+                (subtree, lists)
+            }
+        };
+
+        let ids = subtree.iter().map(|task| task.id).collect::<Vec<i32>>();
+        let deleted_at = Utc::now().fixed_offset();
+
+        let (query, storage_keys) = if options.permanent {
+            let storage_keys = Attachment::storage_keys(&mut **txn, &ids).await?;
+            let query = sqlx::query("DELETE FROM tasks WHERE id = ANY($1)")
+                .bind(&ids)
+                .execute(&mut **txn)
+                .await?;
+            (query, storage_keys)
+        } else {
+            let query = sqlx::query("UPDATE tasks SET deleted_at = $2 WHERE id = ANY($1)")
+                .bind(&ids)
+                .bind(deleted_at)
+                .execute(&mut **txn)
+                .await?;
+            (query, Vec::new())
+        };
+
+        if query.rows_affected() > ids.len() as u64 {
+            return Err(ModelError::Database(
+                "Deleted more records than the task tree holds".into(),
+            ));
+        }
+
+        for (owner_pid, project_id) in lists {
+            Self::renumber(&mut **txn, owner_pid, project_id).await?;
+        }
+
+        for before in &subtree {
+            let after = (!options.permanent).then(|| Self {
+                deleted_at: Some(deleted_at),
+                ..before.clone()
+            });
+            Self::record_change(
+                txn,
+                HistoryAction::Deleted,
+                Some(user_pid),
+                Some(before),
+                after.as_ref(),
+            )
+            .await?;
+        }
+
+        Ok((query, storage_keys))
+    }
+
+    /// Runs the operations of a bulk request, followed by the selector's
+    /// action on every task it picks, in one transaction. Each operation
+    /// goes through the same checks as its single task endpoint.
+    ///
+    /// In [`BulkMode::Atomic`] the first failure rolls everything back and
+    /// the other operations report [`ModelError::RolledBack`]. In
+    /// [`BulkMode::BestEffort`] every operation runs in its own savepoint, so
+    /// a failure leaves the others in place.
+    ///
+    /// Returns whether anything was committed, and one result per operation.
+    pub async fn bulk(
+        db: &PgPool,
+        blobs: &dyn BlobStore,
+        request: &BulkRequest,
+        user_pid: Uuid,
+    ) -> Result<(bool, Vec<BulkResult>), ModelError> {
+        let mut txn = db.begin().await?;
+
+        let mut operations = request.operations.clone();
+        if let Some(selector) = &request.selector {
+            let mut selected = Self::find_all(&mut *txn, user_pid, &selector.filter)
+                .await?
+                .into_iter()
+                .map(|task| task.id)
+                .collect::<Vec<i32>>();
+
+            // Cascading deletes of the selected ancestors take these along
+            if let BulkAction::Delete { cascade: true, .. } = selector.action {
+                let nested = Self::nested_ids(&mut *txn, &selected).await?;
+                selected.retain(|id| !nested.contains(id));
+            }
+
+            operations.extend(selected.into_iter().map(|id| selector.action.operation(id)));
+        }
+
+        let mut results = Vec::with_capacity(operations.len());
+        let mut storage_keys = Vec::new();
+        for operation in &operations {
+            let mut savepoint = txn.begin().await?;
+            match Self::apply(&mut savepoint, operation, user_pid).await {
+                Ok((applied, keys)) => {
+                    savepoint.commit().await?;
+                    storage_keys.extend(keys);
+                    results.push((operation.id(), Ok(applied)));
+                }
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    results.push((operation.id(), Err(e)));
+                    if request.mode == BulkMode::Atomic {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let failed = results.iter().any(|(_, result)| result.is_err());
+        if failed && request.mode == BulkMode::Atomic {
+            txn.rollback().await?;
+
+            let mut results = results
+                .into_iter()
+                .map(|(id, result)| (id, result.and(Err(ModelError::RolledBack))))
+                .collect::<Vec<BulkResult>>();
+            for operation in &operations[results.len()..] {
+                results.push((operation.id(), Err(ModelError::RolledBack)));
+            }
+
+            return Ok((false, results));
+        }
+
+        txn.commit().await?;
+
+        Attachment::release(db, blobs, &storage_keys).await?;
+
+        let committed = results.iter().any(|(_, result)| result.is_ok());
+        Ok((committed, results))
+    }
+
+    /// Runs a single bulk operation within `txn`. Returns what it did and
+    /// the storage keys of attachments to release after the commit.
+    async fn apply(
+        txn: &mut Transaction<'_, Postgres>,
+        operation: &BulkOperation,
+        user_pid: Uuid,
+    ) -> Result<(BulkApplied, Vec<String>), ModelError> {
+        match operation {
+            BulkOperation::Create { task } => {
+                let task = Self::create_in(txn, task, user_pid).await?;
+                Ok((BulkApplied::Created(task), Vec::new()))
+            }
+            BulkOperation::Update { id, patch, force } => {
+                let options = UpdateOptions {
+                    force: *force,
+                    ..Default::default()
+                };
+                let task = Self::update_in(txn, patch, *id, user_pid, &options).await?;
+                Ok((BulkApplied::Updated(task), Vec::new()))
+            }
+            BulkOperation::Delete {
+                id,
+                cascade,
+                permanent,
+            } => {
+                let options = DeleteOptions {
+                    cascade: *cascade,
+                    permanent: *permanent,
+                    ..Default::default()
+                };
+                let (_, storage_keys) = Self::delete_in(txn, *id, user_pid, &options).await?;
+                Ok((BulkApplied::Deleted(*id), storage_keys))
+            }
+        }
+    }
+
+    /// Changes to the tasks `user_pid` can see after `since`, at most
+    /// `limit` of them, oldest first. Tasks the user lost access to are
+    /// returned as deleted.
+    ///
+    /// Only changes of transactions that have finished are returned, so a
+    /// transaction committing late cannot slip in behind a token already
+    /// handed out. Any transaction still open on the database, not only
+    /// ones touching tasks, holds back the changes after it started. Changes
+    /// may be returned more than once.
+    pub async fn changes_since(
+        db: &PgPool,
+        user_pid: Uuid,
+        since: SyncToken,
+        limit: usize,
+    ) -> Result<TaskChanges, ModelError> {
+        let mut txn = db.begin().await?;
+        // One snapshot for the page and the tasks in it
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+            .execute(&mut *txn)
+            .await?;
+
+        let settled = sqlx::query_scalar::<_, i64>("SELECT settled_change_seq()")
+            .fetch_one(&mut *txn)
+            .await?;
+
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let mut page = sqlx::query_as::<_, (i64, i32, bool)>(
+            "
+            SELECT change_seq, id, deleted FROM (
+                SELECT change_seq, id, deleted_at IS NOT NULL AS deleted FROM tasks
+                WHERE CASE
+                    WHEN deleted_at IS NULL THEN task_permission(id, $1)
+                    ELSE task_access(id, $1)
+                END IS NOT NULL
+                UNION ALL
+                SELECT change_seq, task_id, TRUE FROM task_tombstones WHERE user_pid = $1
+                UNION ALL
+                SELECT change_seq, task_id, TRUE FROM task_revocations WHERE user_pid = $1
+            ) changes
+            WHERE (change_seq, id) > ($2, $3) AND change_seq < $4
+            ORDER BY change_seq, id
+            LIMIT $5
+            ",
+        )
+        .bind(user_pid)
+        .bind(since.seq)
+        .bind(since.id)
+        .bind(settled)
+        .bind(limit + 1)
+        .fetch_all(&mut *txn)
+        .await?;
+
+        let has_more = page.len() as i64 > limit;
+        page.truncate(usize::try_from(limit).unwrap_or(usize::MAX));
+
+        // Every change below the settled sequence has been seen, and no
+        // task id is 0
+        let next = match page.last() {
+            Some(&(seq, id, _)) if has_more => SyncToken { seq, id },
+            _ => since.max(SyncToken {
+                seq: settled,
+                id: 0,
+            }),
+        };
+
+        // A task can be revoked and shared again within a page, only its
+        // latest change counts
+        let mut seen = HashSet::new();
+        page.reverse();
+        page.retain(|row| seen.insert(row.1));
+        page.reverse();
+
+        let (changed, deleted): (Vec<_>, Vec<_>) = page.into_iter().partition(|row| !row.2);
+        let ids = changed.iter().map(|row| row.1).collect::<Vec<i32>>();
+        let changed = sqlx::query_as::<_, Self>(
+            "SELECT * FROM tasks WHERE id = ANY($1) ORDER BY change_seq, id",
+        )
+        .bind(&ids)
+        .fetch_all(&mut *txn)
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(TaskChanges {
+            changed,
+            deleted: deleted.into_iter().map(|row| row.1).collect(),
+            next,
+            has_more,
+        })
+    }
+
+    /// Applies the changes a client made while offline, each on its own so
+    /// that one failing or conflicting does not hold back the others.
+    /// Updates and deletes only apply while the task is still at the version
+    /// the client based them on.
+    pub async fn sync(
+        db: &PgPool,
+        blobs: &dyn BlobStore,
+        changes: &[SyncChange],
+        user_pid: Uuid,
+    ) -> Result<Vec<Result<BulkApplied, ModelError>>, ModelError> {
+        let mut txn = db.begin().await?;
+
+        let mut results = Vec::with_capacity(changes.len());
+        let mut storage_keys = Vec::new();
+        for change in changes {
+            let mut savepoint = txn.begin().await?;
+            let result = match change {
+                SyncChange::Create { task, .. } => Self::create_in(&mut savepoint, task, user_pid)
+                    .await
+                    .map(BulkApplied::Created),
+                SyncChange::Update {
+                    id,
+                    base_version,
+                    patch,
+                } => {
+                    let options = UpdateOptions {
+                        if_match: Some(VersionMatch::Tags(vec![(false, *base_version)])),
+                        ..Default::default()
+                    };
+                    Self::update_in(&mut savepoint, patch, *id, user_pid, &options)
+                        .await
+                        .map(BulkApplied::Updated)
+                }
+                SyncChange::Delete { id, base_version } => {
+                    let options = DeleteOptions {
+                        cascade: true,
+                        if_match: Some(VersionMatch::Tags(vec![(false, *base_version)])),
+                        ..Default::default()
+                    };
+                    Self::delete_in(&mut savepoint, *id, user_pid, &options)
+                        .await
+                        .map(|(_, keys)| {
+                            storage_keys.extend(keys);
+                            BulkApplied::Deleted(*id)
+                        })
+                }
+            };
+
+            if result.is_ok() {
+                savepoint.commit().await?;
+            } else {
+                savepoint.rollback().await?;
+            }
+            results.push(result);
+        }
+
+        txn.commit().await?;
+
+        Attachment::release(db, blobs, &storage_keys).await?;
 
-        let task = Task::get_by_id(&mut *txn, id)
+        Ok(results)
+    }
+
+    /// Those of `ids` nested, at any depth, under another task of `ids`.
+    async fn nested_ids<'e, C>(db: C, ids: &[i32]) -> Result<Vec<i32>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let nested = sqlx::query_scalar::<_, i32>(
+            "
+            WITH RECURSIVE ancestors AS (
+                SELECT id AS task_id, parent_id FROM tasks WHERE id = ANY($1)
+                UNION ALL
+                SELECT a.task_id, p.parent_id FROM ancestors a
+                JOIN tasks p ON p.id = a.parent_id
+            )
+            SELECT DISTINCT task_id FROM ancestors WHERE parent_id = ANY($1)
+            ",
+        )
+        .bind(ids)
+        .fetch_all(db)
+        .await?;
+
+        Ok(nested)
+    }
+
+    /// Tasks in the trash owned by `user_pid`, most recently deleted first.
+    pub async fn find_trashed<'e, C>(db: C, user_pid: Uuid) -> Result<Vec<Self>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let items = sqlx::query_as::<_, Self>(
+            "
+            SELECT * FROM tasks
+            WHERE user_pid = $1 AND deleted_at IS NOT NULL
+            ORDER BY deleted_at DESC, id
+            ",
+        )
+        .bind(user_pid)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Streams the user's live tasks in pages of [`EXPORT_PAGE_SIZE`], oldest
+    /// first. Pages are fetched as the stream is polled so an export never
+    /// holds every task in memory.
+    pub fn export(
+        db: PgPool,
+        user_pid: Uuid,
+    ) -> BoxStream<'static, Result<Vec<TaskResponse>, ModelError>> {
+        stream::try_unfold(Some(0), move |after| {
+            let db = db.clone();
+            async move {
+                let Some(after) = after else {
+                    return Ok(None);
+                };
+
+                let tasks = sqlx::query_as::<_, Self>(
+                    "
+                    SELECT * FROM tasks
+                    WHERE user_pid = $1 AND deleted_at IS NULL AND id > $2
+                    ORDER BY id
+                    LIMIT $3
+                    ",
+                )
+                .bind(user_pid)
+                .bind(after)
+                .bind(EXPORT_PAGE_SIZE)
+                .fetch_all(&db)
+                .await?;
+
+                let Some(last) = tasks.last().map(|task| task.id) else {
+                    return Ok(None);
+                };
+                let next = (tasks.len() as i64 == EXPORT_PAGE_SIZE).then_some(last);
+
+                Ok(Some((Self::responses(&db, tasks).await?, next)))
+            }
+        })
+        .boxed()
+    }
+
+    /// Takes a task out of the trash together with the subtasks trashed
+    /// with it. Only the owner can restore a task.
+    ///
+    /// Restored tasks go to the end of their lists, and a task whose parent
+    /// is still in the trash becomes a top level task.
+    pub async fn restore(db: &PgPool, id: i32, user_pid: Uuid) -> Result<Self, ModelError> {
+        let mut txn = db.begin().await?;
+
+        let task = Self::lock_trashed(&mut *txn, id, user_pid)
             .await?
             .ok_or_else(|| ModelError::EntityNotFound)?;
+        let subtree = Self::trashed_subtree(&mut *txn, &task).await?;
+        let ids = subtree.iter().map(|task| task.id).collect::<Vec<i32>>();
 
-        if task.user_pid != user_pid {
-            return Err(ModelError::Unauthorised);
+        if let Some(parent_id) = task.parent_id
+            && Self::get_by_id(&mut *txn, parent_id).await?.is_none()
+        {
+            sqlx::query("UPDATE tasks SET parent_id = NULL WHERE id = $1")
+                .bind(task.id)
+                .execute(&mut *txn)
+                .await?;
         }
 
-        let query = sqlx::query("DELETE FROM tasks WHERE id = $1")
-            .bind(id)
+        for (owner_pid, project_id) in Self::lists_of(&subtree) {
+            let len = Self::list_len(&mut *txn, owner_pid, project_id).await?;
+
+            sqlx::query(
+                "
+                UPDATE tasks SET deleted_at = NULL, position = position + $4, updated_at = $5
+                WHERE id = ANY($1)
+                AND project_id IS NOT DISTINCT FROM $3 AND ($3 IS NOT NULL OR user_pid = $2)
+                ",
+            )
+            .bind(&ids)
+            .bind(owner_pid)
+            .bind(project_id)
+            .bind(len)
+            .bind(Utc::now().fixed_offset())
             .execute(&mut *txn)
             .await?;
 
-        if query.rows_affected() > 1 {
-            txn.rollback().await?;
-            return Err(ModelError::Database("Deleted more than one record".into()));
+            Self::renumber(&mut *txn, owner_pid, project_id).await?;
+        }
+
+        let restored = sqlx::query_as::<_, Self>("SELECT * FROM tasks WHERE id = ANY($1)")
+            .bind(&ids)
+            .fetch_all(&mut *txn)
+            .await?;
+        for after in &restored {
+            let before = subtree.iter().find(|before| before.id == after.id);
+            Self::record_change(
+                &mut txn,
+                HistoryAction::Restored,
+                Some(user_pid),
+                before,
+                Some(after),
+            )
+            .await?;
         }
 
+        let task = Self::lock_by_id(&mut *txn, task.id).await?;
+
         txn.commit().await?;
 
-        Ok(query)
+        Ok(task)
     }
 
+    /// Permanently deletes every task trashed before `before`, along with
+    /// attachment files no other task uses. Returns how many were removed.
+    pub async fn purge_trash(
+        db: &PgPool,
+        blobs: &dyn BlobStore,
+        before: DateTime<FixedOffset>,
+    ) -> Result<u64, ModelError> {
+        let mut txn = db.begin().await?;
+
+        let ids = sqlx::query_scalar::<_, i32>(
+            "SELECT id FROM tasks WHERE deleted_at < $1 FOR UPDATE SKIP LOCKED",
+        )
+        .bind(before)
+        .fetch_all(&mut *txn)
+        .await?;
+
+        let storage_keys = Attachment::storage_keys(&mut *txn, &ids).await?;
+
+        let purged = sqlx::query_as::<_, Self>("DELETE FROM tasks WHERE id = ANY($1) RETURNING *")
+            .bind(&ids)
+            .fetch_all(&mut *txn)
+            .await?;
+
+        for before in &purged {
+            Self::record_change(&mut txn, HistoryAction::Purged, None, Some(before), None).await?;
+        }
+
+        txn.commit().await?;
+
+        Attachment::release(db, blobs, &storage_keys).await?;
+
+        Ok(purged.len() as u64)
+    }
+
+    /// Loads a trashed task of `user_pid` and locks its row.
+    async fn lock_trashed<'e, C>(db: C, id: i32, user_pid: Uuid) -> Result<Option<Self>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let item = sqlx::query_as::<_, Self>(
+            "
+            SELECT * FROM tasks
+            WHERE id = $1 AND user_pid = $2 AND deleted_at IS NOT NULL
+            FOR UPDATE
+            ",
+        )
+        .bind(id)
+        .bind(user_pid)
+        .fetch_optional(db)
+        .await?;
+
+        Ok(item)
+    }
+
+    /// A trashed task and the subtasks that were trashed in the same delete.
+    async fn trashed_subtree<'e, C>(db: C, task: &Self) -> Result<Vec<Self>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let items = sqlx::query_as::<_, Self>(
+            "
+            WITH RECURSIVE subtree AS (
+                SELECT tasks.* FROM tasks WHERE id = $1
+                UNION ALL
+                SELECT t.* FROM tasks t JOIN subtree s ON t.parent_id = s.id
+                WHERE t.deleted_at = $2
+            )
+            SELECT * FROM subtree
+            ",
+        )
+        .bind(task.id)
+        .bind(task.deleted_at)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// The distinct lists `tasks` sit in.
+    fn lists_of(tasks: &[Self]) -> Vec<(Uuid, Option<i32>)> {
+        let mut lists = tasks
+            .iter()
+            .map(|task| (task.user_pid, task.project_id))
+            .collect::<Vec<(Uuid, Option<i32>)>>();
+        lists.sort();
+        lists.dedup();
+        lists
+    }
+
+    /// Nests a task under `parent_id`, or makes it a top level task again
+    /// when `parent_id` is `None`.
+    ///
+    /// Rejects parents that would create a cycle or push any part of the
+    /// task's subtree deeper than [`MAX_TASK_DEPTH`].
+    pub async fn set_parent(
+        db: &PgPool,
+        id: i32,
+        parent_id: Option<i32>,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        let mut txn = db.begin().await?;
+
+        let task = Self::lock_by_id(&mut *txn, id).await?;
+        Permission::require_task(&mut *txn, task.id, user_pid, Permission::Editor).await?;
+
+        if let Some(parent_id) = parent_id {
+            Permission::require_task(&mut *txn, parent_id, user_pid, Permission::Editor).await?;
+
+            let ancestors = Self::ancestor_ids(&mut *txn, parent_id).await?;
+            if ancestors.contains(&task.id) {
+                return Err(ModelError::TaskCycle);
+            }
+
+            let height = Self::subtree(&mut *txn, task.id)
+                .await?
+                .iter()
+                .map(|node| node.level)
+                .max()
+                .unwrap_or_default();
+            if ancestors.len() as i32 + 1 + height > MAX_TASK_DEPTH {
+                return Err(ModelError::TaskDepthExceeded);
+            }
+        }
+
+        let updated = sqlx::query_as::<_, Self>(
+            "
+            UPDATE tasks SET parent_id = $2, updated_at = $3
+            WHERE id = $1
+            RETURNING *",
+        )
+        .bind(id)
+        .bind(parent_id)
+        .bind(Utc::now().fixed_offset())
+        .fetch_one(&mut *txn)
+        .await?;
+
+        Self::record_change(
+            &mut txn,
+            HistoryAction::Updated,
+            Some(user_pid),
+            Some(&task),
+            Some(&updated),
+        )
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(updated)
+    }
+
+    /// Ids of the task and each of its ancestors, nearest first.
+    async fn ancestor_ids<'e, C>(db: C, id: i32) -> Result<Vec<i32>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let ids = sqlx::query_scalar::<_, i32>(
+            "
+            WITH RECURSIVE ancestors AS (
+                SELECT id, parent_id, 0 AS level FROM tasks WHERE id = $1
+                UNION ALL
+                SELECT t.id, t.parent_id, a.level + 1
+                FROM tasks t JOIN ancestors a ON t.id = a.parent_id
+            )
+            SELECT id FROM ancestors ORDER BY level
+            ",
+        )
+        .bind(id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// The task followed by all of its descendants, breadth first.
+    pub async fn subtree<'e, C>(db: C, id: i32) -> Result<Vec<TreeNode>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let nodes = sqlx::query_as::<_, TreeNode>(
+            "
+            WITH RECURSIVE subtree AS (
+                SELECT tasks.*, 0 AS level FROM tasks WHERE id = $1 AND deleted_at IS NULL
+                UNION ALL
+                SELECT t.*, s.level + 1
+                FROM tasks t JOIN subtree s ON t.parent_id = s.id
+                WHERE t.deleted_at IS NULL
+            )
+            SELECT * FROM subtree ORDER BY level, position, id
+            ",
+        )
+        .bind(id)
+        .fetch_all(db)
+        .await?;
+
+        Ok(nodes)
+    }
+
+    /// Applies a merge patch to a task, leaving fields the patch does not
+    /// mention unchanged. Completing a recurring task also creates the next
+    /// task of its series, in the same transaction.
+    ///
+    /// A task with unfinished blockers can only be completed with
+    /// `options.force`. With `options.if_match` the task must still be at one
+    /// of the given versions.
     pub async fn update_by_id(
         db: &PgPool,
         params: &UpdateTask,
         id: i32,
         user_pid: Uuid,
+        options: &UpdateOptions,
     ) -> Result<Self, ModelError> {
         let mut txn = db.begin().await?;
+        let task = Self::update_in(&mut txn, params, id, user_pid, options).await?;
+        txn.commit().await?;
 
-        let tast_to_update = Self::get_by_id(&mut *txn, id)
-            .await?
-            .ok_or_else(|| ModelError::EntityNotFound)?;
-        if tast_to_update.user_pid != user_pid {
-            return Err(ModelError::Unauthorised);
+        Ok(task)
+    }
+
+    /// Updates a task within `txn`, for [`Task::update_by_id`] and bulk
+    /// operations.
+    async fn update_in(
+        txn: &mut Transaction<'_, Postgres>,
+        params: &UpdateTask,
+        id: i32,
+        user_pid: Uuid,
+        options: &UpdateOptions,
+    ) -> Result<Self, ModelError> {
+        let tast_to_update = Self::lock_by_id(&mut **txn, id).await?;
+        Permission::require_task(&mut **txn, id, user_pid, Permission::Editor).await?;
+        tast_to_update.check_version(options.if_match.as_ref())?;
+
+        let done = params.done.unwrap_or(tast_to_update.done);
+        if done && !tast_to_update.done && !options.force {
+            let blockers = TaskDependency::unfinished_blockers(&mut **txn, id).await?;
+            if blockers > 0 {
+                return Err(ModelError::Blocked(blockers));
+            }
         }
 
         let title = params.title.as_ref().map_or_else(
             || tast_to_update.title.to_string(),
             |title| title.to_string(),
         );
+        let due_at = params.due_at.unwrap_or(tast_to_update.due_at);
 
         let updated_at = Utc::now().fixed_offset();
 
         let task = sqlx::query_as::<_, Self>(
             "
             UPDATE tasks
-            SET title = $3, done = $4, updated_at = $5
-            WHERE id = $1 AND user_pid = $2
+            SET title = $2, done = $3, updated_at = $4, due_at = $5
+            WHERE id = $1
             RETURNING *",
         )
         .bind(id)
-        .bind(user_pid)
         .bind(title)
-        .bind(params.done)
+        .bind(done)
         .bind(updated_at)
+        .bind(due_at)
+        .fetch_one(&mut **txn)
+        .await?;
+
+        let task = if task.done && !tast_to_update.done && task.recurrence.is_some() {
+            Self::schedule_next(txn, task, user_pid).await?
+        } else {
+            task
+        };
+
+        Self::record_change(
+            txn,
+            HistoryAction::Updated,
+            Some(user_pid),
+            Some(&tast_to_update),
+            Some(&task),
+        )
+        .await?;
+
+        Ok(task)
+    }
+
+    /// Creates the next task of a completed recurring task's series and
+    /// hands the rule over to it, so completing the same task twice never
+    /// generates two follow ups. Returns the completed task.
+    async fn schedule_next(
+        txn: &mut Transaction<'_, Postgres>,
+        completed: Self,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        let Some(rule) = completed.recurrence.as_deref() else {
+            return Ok(completed);
+        };
+        let rule = rule.parse::<Recurrence>()?;
+
+        let now = Utc::now().fixed_offset();
+        let series_id = completed.series_id.unwrap_or(completed.pid);
+
+        let completed = sqlx::query_as::<_, Self>(
+            "UPDATE tasks SET recurrence = NULL, series_id = $2 WHERE id = $1 RETURNING *",
+        )
+        .bind(completed.id)
+        .bind(series_id)
+        .fetch_one(&mut **txn)
+        .await?;
+
+        // The series has ended
+        let Some(due_at) = rule.next_occurrence(completed.due_at.unwrap_or(now), now) else {
+            return Ok(completed);
+        };
+
+        let position = Self::list_len(&mut **txn, completed.user_pid, completed.project_id).await?;
+
+        let next = sqlx::query_as::<_, Self>(
+            "
+            INSERT INTO tasks (
+                user_pid, title, done, project_id, position, parent_id,
+                due_at, recurrence, series_id, assignee_pid
+            )
+            VALUES ($1, $2, FALSE, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            ",
+        )
+        .bind(completed.user_pid)
+        .bind(&completed.title)
+        .bind(completed.project_id)
+        .bind(position)
+        .bind(completed.parent_id)
+        .bind(due_at)
+        .bind(rule.to_string())
+        .bind(series_id)
+        .bind(completed.assignee_pid)
+        .fetch_one(&mut **txn)
+        .await?;
+
+        Self::record_change(
+            txn,
+            HistoryAction::Created,
+            Some(user_pid),
+            None,
+            Some(&next),
+        )
+        .await?;
+
+        Ok(completed)
+    }
+
+    /// Sets, replaces or, with `None`, ends the recurrence of a task.
+    pub async fn set_recurrence(
+        db: &PgPool,
+        id: i32,
+        rule: Option<&Recurrence>,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        let mut txn = db.begin().await?;
+
+        let task = Self::lock_by_id(&mut *txn, id).await?;
+        Permission::require_task(&mut *txn, task.id, user_pid, Permission::Editor).await?;
+        let series_id = rule.map(|_| task.series_id.unwrap_or(task.pid));
+
+        let updated = sqlx::query_as::<_, Self>(
+            "
+            UPDATE tasks
+            SET recurrence = $2, series_id = COALESCE($3, series_id), updated_at = $4
+            WHERE id = $1
+            RETURNING *",
+        )
+        .bind(task.id)
+        .bind(rule.map(ToString::to_string))
+        .bind(series_id)
+        .bind(Utc::now().fixed_offset())
         .fetch_one(&mut *txn)
         .await?;
 
+        Self::record_change(
+            &mut txn,
+            HistoryAction::Updated,
+            Some(user_pid),
+            Some(&task),
+            Some(&updated),
+        )
+        .await?;
+
         txn.commit().await?;
 
-        Ok(task)
+        Ok(updated)
+    }
+
+    /// Assigns a task to the user called `username`, or unassigns it with
+    /// `None`. Takes editor permission, and the assignee must be able to see
+    /// the task.
+    ///
+    /// Returns the task together with its previous assignee, who is notified
+    /// when the assignee changed.
+    pub async fn assign(
+        db: &PgPool,
+        id: i32,
+        username: Option<&str>,
+        user_pid: Uuid,
+    ) -> Result<(Self, Option<Uuid>), ModelError> {
+        let mut txn = db.begin().await?;
+
+        let task = Self::lock_by_id(&mut *txn, id).await?;
+        Permission::require_task(&mut *txn, task.id, user_pid, Permission::Editor).await?;
+
+        let assignee_pid = match username {
+            Some(username) => {
+                let assignee = User::find_by_username(&mut *txn, username.trim()).await?;
+                Permission::for_task(&mut *txn, task.id, assignee.pid)
+                    .await?
+                    .ok_or_else(|| ModelError::AssigneeNoAccess)?;
+                Some(assignee.pid)
+            }
+            None => None,
+        };
+
+        let updated = sqlx::query_as::<_, Self>(
+            "UPDATE tasks SET assignee_pid = $2, updated_at = $3 WHERE id = $1 RETURNING *",
+        )
+        .bind(task.id)
+        .bind(assignee_pid)
+        .bind(Utc::now().fixed_offset())
+        .fetch_one(&mut *txn)
+        .await?;
+
+        Self::record_change(
+            &mut txn,
+            HistoryAction::Updated,
+            Some(user_pid),
+            Some(&task),
+            Some(&updated),
+        )
+        .await?;
+
+        if let Some(assignee_pid) = assignee_pid.filter(|pid| Some(*pid) != task.assignee_pid) {
+            let event = Event::TaskAssigned {
+                task_id: task.id,
+                assignee_pid,
+                assigned_by: user_pid,
+            };
+            OutboxEvent::record(&mut txn, &event).await?;
+        }
+
+        txn.commit().await?;
+
+        Ok((updated, task.assignee_pid))
+    }
+
+    /// Fails unless the task is at one of the versions in `if_match`, so a
+    /// write based on a stale read does not overwrite a newer change.
+    fn check_version(&self, if_match: Option<&VersionMatch>) -> Result<(), ModelError> {
+        match if_match {
+            Some(if_match) if !if_match.matches(self.version) => {
+                Err(ModelError::PreconditionFailed)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Loads a task and locks its row until the transaction ends.
+    async fn lock_by_id<'e, C>(db: C, id: i32) -> Result<Self, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let item = sqlx::query_as::<_, Self>(
+            "SELECT * FROM tasks WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        )
+        .bind(id)
+        .fetch_optional(db)
+        .await?;
+
+        item.ok_or_else(|| ModelError::EntityNotFound)
     }
 
     pub async fn get_by_id<'e, C>(db: C, id: i32) -> Result<Option<Self>, ModelError>
     where
         C: Executor<'e, Database = Postgres>,
     {
-        let item = sqlx::query_as::<_, Self>("SELECT * FROM tasks WHERE id = $1")
-            .bind(id)
-            .fetch_optional(db)
-            .await?;
+        let item =
+            sqlx::query_as::<_, Self>("SELECT * FROM tasks WHERE id = $1 AND deleted_at IS NULL")
+                .bind(id)
+                .fetch_optional(db)
+                .await?;
 
         Ok(item)
     }
+
+    /// Moves a task to another project (or back to the inbox when
+    /// `project_id` is `None`) and/or to another position in that list.
+    ///
+    /// Both the source and destination lists are renumbered in the same
+    /// transaction so positions stay contiguous from zero.
+    pub async fn move_task(
+        db: &PgPool,
+        params: &MoveTask,
+        id: i32,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        let mut txn = db.begin().await?;
+
+        let task = Self::lock_by_id(&mut *txn, id).await?;
+        Permission::require_task(&mut *txn, task.id, user_pid, Permission::Editor).await?;
+
+        if let Some(project_id) = params.project_id {
+            Permission::require_project(&mut *txn, project_id, user_pid, Permission::Editor)
+                .await?;
+        }
+
+        // A task leaving its project lands in its owner's inbox, not the
+        // inbox of whoever moved it.
+        Self::close_gap(&mut *txn, task.user_pid, task.project_id, task.position).await?;
+
+        // The task itself still sits in its old slot, leave it out of the count.
+        let mut len = Self::list_len(&mut *txn, task.user_pid, params.project_id).await?;
+        if task.project_id == params.project_id {
+            len -= 1;
+        }
+        let position = params
+            .position
+            .map_or(len, |position| position.clamp(0, len));
+
+        sqlx::query(
+            "
+            UPDATE tasks SET position = position + 1
+            WHERE project_id IS NOT DISTINCT FROM $2 AND ($2 IS NOT NULL OR user_pid = $1)
+            AND deleted_at IS NULL AND position >= $3 AND id <> $4
+            ",
+        )
+        .bind(task.user_pid)
+        .bind(params.project_id)
+        .bind(position)
+        .bind(id)
+        .execute(&mut *txn)
+        .await?;
+
+        let moved = sqlx::query_as::<_, Self>(
+            "
+            UPDATE tasks
+            SET project_id = $2, position = $3, updated_at = $4
+            WHERE id = $1
+            RETURNING *",
+        )
+        .bind(id)
+        .bind(params.project_id)
+        .bind(position)
+        .bind(Utc::now().fixed_offset())
+        .fetch_one(&mut *txn)
+        .await?;
+
+        Self::record_change(
+            &mut txn,
+            HistoryAction::Updated,
+            Some(user_pid),
+            Some(&task),
+            Some(&moved),
+        )
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(moved)
+    }
+
+    /// Number of tasks in a list, which is also the next free position.
+    ///
+    /// A list is a project, shared by everyone adding tasks to it, or the
+    /// inbox of `user_pid` when `project_id` is `None`.
+    async fn list_len<'e, C>(
+        db: C,
+        user_pid: Uuid,
+        project_id: Option<i32>,
+    ) -> Result<i32, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let len = sqlx::query_scalar::<_, i64>(
+            "
+            SELECT COUNT(*) FROM tasks
+            WHERE project_id IS NOT DISTINCT FROM $2 AND ($2 IS NOT NULL OR user_pid = $1)
+            AND deleted_at IS NULL
+            ",
+        )
+        .bind(user_pid)
+        .bind(project_id)
+        .fetch_one(db)
+        .await?;
+
+        i32::try_from(len).map_err(|e| ModelError::Database(e.to_string()))
+    }
+
+    /// Shifts every task after `position` up by one, filling the hole left
+    /// by a task that was removed from the list.
+    async fn close_gap<'e, C>(
+        db: C,
+        user_pid: Uuid,
+        project_id: Option<i32>,
+        position: i32,
+    ) -> Result<(), ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        sqlx::query(
+            "
+            UPDATE tasks SET position = position - 1
+            WHERE project_id IS NOT DISTINCT FROM $2 AND ($2 IS NOT NULL OR user_pid = $1)
+            AND deleted_at IS NULL AND position > $3
+            ",
+        )
+        .bind(user_pid)
+        .bind(project_id)
+        .bind(position)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Renumbers a list from zero, keeping the current relative order.
+    pub(crate) async fn renumber<'e, C>(
+        db: C,
+        user_pid: Uuid,
+        project_id: Option<i32>,
+    ) -> Result<(), ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        sqlx::query(
+            "
+            UPDATE tasks t SET position = ordered.rn - 1
+            FROM (
+                SELECT id, ROW_NUMBER() OVER (ORDER BY position, id) AS rn FROM tasks
+                WHERE project_id IS NOT DISTINCT FROM $2 AND ($2 IS NOT NULL OR user_pid = $1)
+                AND deleted_at IS NULL
+            ) ordered
+            WHERE t.id = ordered.id AND t.position <> ordered.rn - 1
+            ",
+        )
+        .bind(user_pid)
+        .bind(project_id)
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a change in the task's history and in the outbox, addressed to
+    /// everyone who can see the task. The relay only publishes the event once
+    /// the transaction commits.
+    async fn record_change(
+        conn: &mut PgConnection,
+        action: HistoryAction,
+        actor_pid: Option<Uuid>,
+        before: Option<&Self>,
+        after: Option<&Self>,
+    ) -> Result<(), ModelError> {
+        if !TaskHistory::record(&mut *conn, action, actor_pid, before, after).await? {
+            return Ok(());
+        }
+        let Some(task) = after.or(before) else {
+            return Ok(());
+        };
+
+        // Starts from the parent as well, as a task deleted for good is gone
+        let audience = sqlx::query_scalar::<_, Uuid>(
+            "
+            WITH RECURSIVE ancestors AS (
+                SELECT id, parent_id, user_pid, project_id FROM tasks WHERE id IN ($1, $2)
+                UNION
+                SELECT t.id, t.parent_id, t.user_pid, t.project_id
+                FROM tasks t JOIN ancestors a ON t.id = a.parent_id
+            ),
+            projects_of AS (
+                SELECT project_id AS id FROM ancestors WHERE project_id IS NOT NULL
+                UNION
+                SELECT $3::INTEGER WHERE $3::INTEGER IS NOT NULL
+            )
+            SELECT $4::UUID
+            UNION SELECT $5::UUID WHERE $5::UUID IS NOT NULL
+            UNION SELECT user_pid FROM ancestors
+            UNION SELECT s.user_pid FROM task_shares s JOIN ancestors a ON a.id = s.task_id
+            UNION SELECT user_pid FROM projects WHERE id IN (SELECT id FROM projects_of)
+            UNION SELECT user_pid FROM project_shares WHERE project_id IN (SELECT id FROM projects_of)
+            ",
+        )
+        .bind(task.id)
+        .bind(task.parent_id)
+        .bind(task.project_id)
+        .bind(task.user_pid)
+        .bind(task.assignee_pid)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let task_id = task.id;
+        let event = match action {
+            HistoryAction::Created | HistoryAction::Restored => {
+                Event::TaskCreated { task_id, audience }
+            }
+            HistoryAction::Updated => Event::TaskUpdated { task_id, audience },
+            HistoryAction::Deleted | HistoryAction::Purged => {
+                Event::TaskDeleted { task_id, audience }
+            }
+        };
+        OutboxEvent::record(conn, &event).await?;
+
+        Ok(())
+    }
+
+    /// Builds the API representation of `tasks`, loading their labels and
+    /// dependencies in one query each.
+    pub async fn responses(db: &PgPool, tasks: Vec<Self>) -> Result<Vec<TaskResponse>, ModelError> {
+        let ids = tasks.iter().map(|task| task.id).collect::<Vec<i32>>();
+        let mut labels = Label::find_for_tasks(db, &ids).await?;
+        let mut dependencies = TaskDependency::find_for_tasks(db, &ids).await?;
+
+        let responses = tasks
+            .into_iter()
+            .map(|task| {
+                let task_labels = labels.remove(&task.id).unwrap_or_default();
+                let blocked_by = dependencies.blocked_by.remove(&task.id);
+                let blocking = dependencies.blocking.remove(&task.id);
+
+                let mut response = TaskResponse::from(task);
+                response.labels = task_labels.into_iter().map(LabelResponse::from).collect();
+                response.blocked_by = blocked_by.unwrap_or_default();
+                response.blocking = blocking.unwrap_or_default();
+                response
+            })
+            .collect();
+
+        Ok(responses)
+    }
+
+    pub async fn response(db: &PgPool, task: Self) -> Result<TaskResponse, ModelError> {
+        let mut responses = Self::responses(db, vec![task]).await?;
+
+        responses.pop().ok_or_else(|| ModelError::EntityNotFound)
+    }
 }
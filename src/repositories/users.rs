@@ -10,10 +10,14 @@ use uuid::Uuid;
 
 use crate::{
     context::JwtState,
+    events::Event,
     models::auth::{LoginResponse, LoginUser, RegisterUser, TokenClaims},
+    storage::BlobStore,
 };
 
-use super::ModelError;
+use super::{
+    ModelError, attachments::Attachment, history::OWNED_TASKS, outbox::OutboxEvent, tasks::Task,
+};
 
 #[derive(Debug, Deserialize, Clone, FromRow, Encode)]
 pub struct User {
@@ -31,7 +35,7 @@ impl User {
         let mut txn = db.begin().await?;
 
         let password_hashed = Argon2::default()
-            .hash_password(&dto.password.as_bytes(), &SaltString::generate(&mut OsRng))
+            .hash_password(dto.password.as_bytes(), &SaltString::generate(&mut OsRng))
             .map(|hash| hash.to_string())?;
 
         let result = sqlx::query_as::<_, Self>(
@@ -56,6 +60,14 @@ impl User {
 
         let model = result?;
 
+        OutboxEvent::record(
+            &mut txn,
+            &Event::UserCreated {
+                user_pid: model.pid,
+            },
+        )
+        .await?;
+
         txn.commit().await?;
 
         Ok(model)
@@ -110,14 +122,7 @@ impl User {
             },
         };
 
-        let password_hash = PasswordHash::new(&user.password)?;
-
-        Argon2::default()
-            .verify_password(&dto.password.as_bytes(), &password_hash)
-            .map_err(|e| {
-                tracing::warn!("An error occurred: {e}");
-                ModelError::Unauthorised
-            })?;
+        user.verify_password(&dto.password)?;
 
         let now = Utc::now();
 
@@ -133,4 +138,129 @@ impl User {
 
         Ok(LoginResponse::new(&user, &token))
     }
+
+    /// Checks `password` against the stored hash.
+    pub fn verify_password(&self, password: &str) -> Result<(), ModelError> {
+        let password_hash = PasswordHash::new(&self.password)?;
+
+        Argon2::default()
+            .verify_password(password.as_bytes(), &password_hash)
+            .map_err(|e| {
+                tracing::warn!("An error occurred: {e}");
+                ModelError::Unauthorised
+            })
+    }
+
+    /// Erases the account and everything keyed by it: tasks, projects,
+    /// labels, comments, uploaded files, shares, exports, idempotency keys
+    /// and the history of the user's tasks. Changes the user made to other
+    /// users' tasks stay in their history, with the user anonymised.
+    ///
+    /// Other users' tasks are kept: subtasks of the user's tasks become top
+    /// level tasks and tasks in the user's projects move to their owners'
+    /// inboxes.
+    pub async fn erase(db: &PgPool, blobs: &dyn BlobStore, pid: Uuid) -> Result<(), ModelError> {
+        let mut txn = db.begin().await?;
+
+        sqlx::query("SELECT id FROM users WHERE pid = $1 FOR UPDATE")
+            .bind(pid)
+            .fetch_optional(&mut *txn)
+            .await?
+            .ok_or(ModelError::EntityNotFound)?;
+
+        let storage_keys = sqlx::query_scalar::<_, String>(
+            "
+            SELECT storage_key FROM attachments
+            WHERE uploader_pid = $1 OR task_id IN (SELECT id FROM tasks WHERE user_pid = $1)
+            UNION
+            SELECT storage_key FROM account_exports
+            WHERE user_pid = $1 AND storage_key IS NOT NULL
+            ",
+        )
+        .bind(pid)
+        .fetch_all(&mut *txn)
+        .await?;
+
+        sqlx::query(
+            "
+            UPDATE tasks SET parent_id = NULL
+            WHERE user_pid <> $1 AND parent_id IN (SELECT id FROM tasks WHERE user_pid = $1)
+            ",
+        )
+        .bind(pid)
+        .execute(&mut *txn)
+        .await?;
+
+        let moved_owners = sqlx::query_scalar::<_, Uuid>(
+            "
+            WITH moved AS (
+                UPDATE tasks SET project_id = NULL, position = 2147483647
+                WHERE user_pid <> $1 AND project_id IN (SELECT id FROM projects WHERE user_pid = $1)
+                RETURNING user_pid
+            )
+            SELECT DISTINCT user_pid FROM moved
+            ",
+        )
+        .bind(pid)
+        .fetch_all(&mut *txn)
+        .await?;
+
+        for owner in moved_owners {
+            Task::renumber(&mut *txn, owner, None).await?;
+        }
+
+        // Lifts the immutability of task_history for this transaction
+        sqlx::query("SELECT set_config('app.erasing_user', $1, true)")
+            .bind(pid.to_string())
+            .execute(&mut *txn)
+            .await?;
+
+        sqlx::query(&format!(
+            "
+            {OWNED_TASKS}
+            DELETE FROM task_history WHERE task_id IN (SELECT task_id FROM owned)
+            "
+        ))
+        .bind(pid)
+        .execute(&mut *txn)
+        .await?;
+
+        sqlx::query(
+            "
+            UPDATE task_history SET
+                actor_pid = CASE WHEN actor_pid = $1 THEN NULL ELSE actor_pid END,
+                changes = REPLACE(changes::TEXT, $1::TEXT, $2::TEXT)::JSONB
+            WHERE actor_pid = $1 OR changes::TEXT LIKE '%' || $1::TEXT || '%'
+            ",
+        )
+        .bind(pid)
+        .bind(Uuid::nil())
+        .execute(&mut *txn)
+        .await?;
+
+        // Stored responses to the user's registration go with the user row
+        sqlx::query("DELETE FROM idempotency_keys WHERE user_pid = $1")
+            .bind(pid)
+            .execute(&mut *txn)
+            .await?;
+
+        // Tasks go first so their own cascades run before the user's
+        sqlx::query("DELETE FROM tasks WHERE user_pid = $1")
+            .bind(pid)
+            .execute(&mut *txn)
+            .await?;
+
+        sqlx::query("DELETE FROM users WHERE pid = $1")
+            .bind(pid)
+            .execute(&mut *txn)
+            .await?;
+
+        OutboxEvent::record(&mut txn, &Event::UserDeleted { user_pid: pid }).await?;
+
+        txn.commit().await?;
+
+        Attachment::release(db, blobs, &storage_keys).await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,464 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
+use futures_util::future::join_all;
+use reqwest::{Client, header::CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Executor, PgConnection, PgPool, Postgres, prelude::FromRow};
+use uuid::Uuid;
+
+use crate::{
+    config::WebhookConfig,
+    events::Event,
+    models::{
+        notifications::NotificationPayload,
+        webhooks::{
+            DELIVERY_HEADER, DeliveryQuery, DeliveryStatus, EVENT_HEADER, EndpointResolver,
+            NewWebhook, SIGNATURE_HEADER, TIMESTAMP_HEADER, UpdateWebhook, WebhookEvent,
+            WebhookPayload, sign,
+        },
+    },
+};
+
+use super::ModelError;
+
+/// Longest error message kept from a failed attempt.
+const MAX_ERROR_LEN: usize = 500;
+
+/// Endpoint a user has asked to be sent task events at.
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct Webhook {
+    pub id: i32,
+    pub pid: Uuid,
+    pub user_pid: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+fn event_names(events: &[WebhookEvent]) -> Vec<&'static str> {
+    events.iter().map(|event| event.as_str()).collect()
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0_u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+impl Webhook {
+    pub async fn create(
+        db: &PgPool,
+        params: &NewWebhook,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        let item = sqlx::query_as::<_, Self>(
+            "
+            INSERT INTO webhooks (user_pid, url, secret, events)
+            VALUES ($1, $2, $3, $4) RETURNING *
+            ",
+        )
+        .bind(user_pid)
+        .bind(params.url.trim())
+        .bind(generate_secret())
+        .bind(event_names(&params.events))
+        .fetch_one(db)
+        .await?;
+
+        Ok(item)
+    }
+
+    pub async fn find_all<'e, C>(db: C, user_pid: Uuid) -> Result<Vec<Self>, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let items =
+            sqlx::query_as::<_, Self>("SELECT * FROM webhooks WHERE user_pid = $1 ORDER BY id")
+                .bind(user_pid)
+                .fetch_all(db)
+                .await?;
+
+        Ok(items)
+    }
+
+    pub async fn find_by_id<'e, C>(db: C, user_pid: Uuid, id: i32) -> Result<Self, ModelError>
+    where
+        C: Executor<'e, Database = Postgres>,
+    {
+        let item =
+            sqlx::query_as::<_, Self>("SELECT * FROM webhooks WHERE id = $1 AND user_pid = $2")
+                .bind(id)
+                .bind(user_pid)
+                .fetch_optional(db)
+                .await?;
+
+        item.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Changes the URL, the events or whether a webhook owned by `user_pid`
+    /// is active. Fields missing from `params` are left as they are.
+    pub async fn update_by_id(
+        db: &PgPool,
+        params: &UpdateWebhook,
+        id: i32,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        let item = sqlx::query_as::<_, Self>(
+            "
+            UPDATE webhooks
+            SET url = COALESCE($3, url),
+                events = COALESCE($4, events),
+                active = COALESCE($5, active),
+                updated_at = NOW()
+            WHERE id = $1 AND user_pid = $2
+            RETURNING *
+            ",
+        )
+        .bind(id)
+        .bind(user_pid)
+        .bind(params.url.as_deref().map(str::trim))
+        .bind(params.events.as_deref().map(event_names))
+        .bind(params.active)
+        .fetch_optional(db)
+        .await?;
+
+        item.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Deletes a webhook together with its delivery log.
+    pub async fn delete_by_id(db: &PgPool, id: i32, user_pid: Uuid) -> Result<(), ModelError> {
+        let query = sqlx::query("DELETE FROM webhooks WHERE id = $1 AND user_pid = $2")
+            .bind(id)
+            .bind(user_pid)
+            .execute(db)
+            .await?;
+
+        if query.rows_affected() == 0 {
+            return Err(ModelError::EntityNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+/// One event queued for, or sent to, a webhook.
+#[derive(Debug, Deserialize, Serialize, Clone, FromRow)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub pid: Uuid,
+    pub webhook_id: i32,
+    pub event: String,
+    pub payload: Value,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<FixedOffset>,
+    pub response_status: Option<i16>,
+    pub error: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+    pub delivered_at: Option<DateTime<FixedOffset>>,
+}
+
+/// Delivery claimed for an attempt, with where to send it.
+#[derive(Debug, FromRow)]
+struct ClaimedDelivery {
+    #[sqlx(flatten)]
+    delivery: WebhookDelivery,
+    url: String,
+    secret: String,
+}
+
+/// How an attempt at a delivery went.
+enum Attempt {
+    Delivered(i16),
+    Failed(Option<i16>, String),
+}
+
+impl WebhookDelivery {
+    /// Queues `event` for the active webhooks of its audience that subscribe
+    /// to it, as part of the transaction that caused it.
+    pub async fn enqueue(conn: &mut PgConnection, event: &Event) -> Result<u64, ModelError> {
+        let audience = match event {
+            Event::TaskCreated { audience, .. }
+            | Event::TaskUpdated { audience, .. }
+            | Event::TaskDeleted { audience, .. } => audience,
+            Event::TaskAssigned { .. }
+            | Event::Mentioned { .. }
+            | Event::TaskShared { .. }
+            | Event::ProjectShared { .. }
+            | Event::TaskDue { .. }
+            | Event::Notified { .. }
+            | Event::UserCreated { .. }
+            | Event::UserDeleted { .. } => return Ok(0),
+        };
+        let Some(payload) = WebhookPayload::for_event(event) else {
+            return Ok(0);
+        };
+        let kind = payload.kind;
+        let payload =
+            serde_json::to_value(&payload).map_err(|e| ModelError::Database(e.to_string()))?;
+
+        Self::enqueue_for(conn, audience, kind, &payload).await
+    }
+
+    /// Queues a notification for the active webhooks of `user_pid` that
+    /// subscribe to notifications.
+    pub async fn enqueue_notification(
+        conn: &mut PgConnection,
+        user_pid: Uuid,
+        payload: &NotificationPayload,
+    ) -> Result<u64, ModelError> {
+        let value =
+            serde_json::to_value(payload).map_err(|e| ModelError::Database(e.to_string()))?;
+
+        Self::enqueue_for(conn, &[user_pid], WebhookEvent::Notification, &value).await
+    }
+
+    async fn enqueue_for(
+        conn: &mut PgConnection,
+        audience: &[Uuid],
+        kind: WebhookEvent,
+        payload: &Value,
+    ) -> Result<u64, ModelError> {
+        let query = sqlx::query(
+            "
+            INSERT INTO webhook_deliveries (webhook_id, event, payload)
+            SELECT id, $1::TEXT, $2 FROM webhooks
+            WHERE active AND user_pid = ANY($3) AND (events = '{}' OR $1::TEXT = ANY(events))
+            ",
+        )
+        .bind(kind.as_str())
+        .bind(payload)
+        .bind(audience)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(query.rows_affected())
+    }
+
+    /// Deliveries of a webhook owned by `user_pid`, newest first.
+    pub async fn find_for_webhook(
+        db: &PgPool,
+        webhook_id: i32,
+        user_pid: Uuid,
+        query: &DeliveryQuery,
+    ) -> Result<Vec<Self>, ModelError> {
+        Webhook::find_by_id(db, user_pid, webhook_id).await?;
+
+        let items = sqlx::query_as::<_, Self>(
+            "
+            SELECT * FROM webhook_deliveries
+            WHERE webhook_id = $1
+                AND ($2::TEXT IS NULL OR status = $2)
+                AND ($3::BIGINT IS NULL OR id < $3)
+            ORDER BY id DESC
+            LIMIT $4
+            ",
+        )
+        .bind(webhook_id)
+        .bind(query.status.map(DeliveryStatus::as_str))
+        .bind(query.before)
+        .bind(query.limit())
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Queues a delivery of a webhook owned by `user_pid` again, with a fresh
+    /// set of attempts. Pending deliveries are left as they are.
+    pub async fn redeliver(
+        db: &PgPool,
+        webhook_id: i32,
+        id: i64,
+        user_pid: Uuid,
+    ) -> Result<Self, ModelError> {
+        Webhook::find_by_id(db, user_pid, webhook_id).await?;
+
+        let item = sqlx::query_as::<_, Self>(
+            "
+            WITH requeued AS (
+                UPDATE webhook_deliveries
+                SET status = 'pending', attempts = 0, next_attempt_at = NOW(), error = NULL
+                WHERE id = $1 AND webhook_id = $2 AND status <> 'pending'
+                RETURNING *
+            )
+            SELECT * FROM requeued
+            UNION ALL
+            SELECT * FROM webhook_deliveries
+            WHERE id = $1 AND webhook_id = $2 AND status = 'pending'
+            ",
+        )
+        .bind(id)
+        .bind(webhook_id)
+        .fetch_optional(db)
+        .await?;
+
+        item.ok_or(ModelError::EntityNotFound)
+    }
+
+    /// Claims up to `limit` due deliveries of active webhooks, counting the
+    /// attempt and hiding them from other workers for `lease`.
+    async fn claim(
+        db: &PgPool,
+        limit: i64,
+        lease: TimeDelta,
+    ) -> Result<Vec<ClaimedDelivery>, ModelError> {
+        let items = sqlx::query_as::<_, ClaimedDelivery>(
+            "
+            WITH due AS (
+                SELECT d.id FROM webhook_deliveries d
+                JOIN webhooks w ON w.id = d.webhook_id
+                WHERE d.status = 'pending' AND d.next_attempt_at <= NOW() AND w.active
+                ORDER BY d.next_attempt_at
+                LIMIT $1
+                FOR UPDATE OF d SKIP LOCKED
+            )
+            UPDATE webhook_deliveries d
+            SET attempts = d.attempts + 1, next_attempt_at = NOW() + $2
+            FROM due, webhooks w
+            WHERE d.id = due.id AND w.id = d.webhook_id
+            RETURNING d.*, w.url, w.secret
+            ",
+        )
+        .bind(limit)
+        .bind(lease)
+        .fetch_all(db)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Makes one attempt at a claimed delivery. The endpoint is checked
+    /// again, its host may resolve elsewhere than when it was registered.
+    async fn attempt(
+        client: &Client,
+        resolver: &EndpointResolver,
+        claimed: &ClaimedDelivery,
+    ) -> Attempt {
+        if let Err(e) = resolver.check(&claimed.url).await {
+            return Attempt::Failed(None, e.to_string());
+        }
+
+        let body = match serde_json::to_vec(&claimed.delivery.payload) {
+            Ok(body) => body,
+            Err(e) => return Attempt::Failed(None, e.to_string()),
+        };
+        let timestamp = Utc::now().timestamp();
+
+        let response = client
+            .post(&claimed.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(DELIVERY_HEADER, claimed.delivery.pid.to_string())
+            .header(EVENT_HEADER, &claimed.delivery.event)
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign(&claimed.secret, timestamp, &body))
+            .body(body)
+            .send()
+            .await;
+
+        match response {
+            Ok(response) => {
+                let status = response.status();
+                let code = i16::try_from(status.as_u16()).ok();
+                if status.is_success() {
+                    Attempt::Delivered(code.unwrap_or_default())
+                } else {
+                    Attempt::Failed(code, format!("Endpoint answered {status}"))
+                }
+            }
+            Err(e) => Attempt::Failed(None, e.to_string()),
+        }
+    }
+
+    /// Records how an attempt went: the delivery is done, retried after the
+    /// configured backoff or, once out of attempts, dead-lettered.
+    async fn settle(
+        db: &PgPool,
+        delivery: &Self,
+        attempt: Attempt,
+        config: &WebhookConfig,
+    ) -> Result<(), ModelError> {
+        match attempt {
+            Attempt::Delivered(status) => {
+                sqlx::query(
+                    "
+                    UPDATE webhook_deliveries
+                    SET status = 'delivered', response_status = $2, error = NULL,
+                        delivered_at = NOW()
+                    WHERE id = $1
+                    ",
+                )
+                .bind(delivery.id)
+                .bind(status)
+                .execute(db)
+                .await?;
+            }
+            Attempt::Failed(status, mut error) => {
+                if error.len() > MAX_ERROR_LEN {
+                    let mut end = MAX_ERROR_LEN;
+                    while !error.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    error.truncate(end);
+                }
+                let attempts = u32::try_from(delivery.attempts).unwrap_or_default();
+                let retry_at = config
+                    .backoff(attempts)
+                    .map(|delay| (Utc::now() + delay).fixed_offset());
+
+                sqlx::query(
+                    "
+                    UPDATE webhook_deliveries
+                    SET status = CASE WHEN $4::TIMESTAMPTZ IS NULL THEN 'dead' ELSE 'pending' END,
+                        next_attempt_at = COALESCE($4, next_attempt_at),
+                        response_status = $2, error = $3
+                    WHERE id = $1
+                    ",
+                )
+                .bind(delivery.id)
+                .bind(status)
+                .bind(error)
+                .bind(retry_at)
+                .execute(db)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends a batch of due deliveries and records the outcomes, returning
+    /// how many were attempted. Deliveries are claimed with `SKIP LOCKED`, so
+    /// any number of workers can run this side by side.
+    pub async fn deliver_due(
+        db: &PgPool,
+        client: &Client,
+        config: &WebhookConfig,
+    ) -> Result<usize, ModelError> {
+        let claimed = Self::claim(db, i64::from(config.batch_size), config.lease()).await?;
+
+        let resolver = EndpointResolver::new(config);
+        let attempts = join_all(
+            claimed
+                .iter()
+                .map(|claimed| Self::attempt(client, &resolver, claimed)),
+        )
+        .await;
+
+        for (claimed, attempt) in claimed.iter().zip(attempts) {
+            if let Attempt::Failed(_, error) = &attempt {
+                tracing::warn!(
+                    "Webhook delivery {} failed on attempt {}: {error}",
+                    claimed.delivery.pid,
+                    claimed.delivery.attempts
+                );
+            }
+            Self::settle(db, &claimed.delivery, attempt, config).await?;
+        }
+
+        Ok(claimed.len())
+    }
+}
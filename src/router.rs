@@ -19,7 +19,7 @@ use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
     context::AppState,
-    controllers::{auth, tasks},
+    controllers::{auth, events, labels, notifications, projects, sync, tasks, users, webhooks},
     middlewares::{auth::JwtAuthLayer, trace},
 };
 
@@ -65,10 +65,38 @@ async fn health() -> Response {
 pub fn router(ctx: &AppState) -> Router {
     let app_router: OpenApiRouter = OpenApiRouter::new()
         .with_state(Arc::new(ctx.clone()))
-        .nest("/auth", auth::auth_routes(&ctx))
+        .nest("/auth", auth::auth_routes(ctx))
         .nest(
             "/tasks",
-            tasks::task_routes(&ctx).layer(JwtAuthLayer::new(&ctx)),
+            tasks::task_routes(ctx).layer(JwtAuthLayer::new(ctx)),
+        )
+        .nest(
+            "/labels",
+            labels::label_routes(ctx).layer(JwtAuthLayer::new(ctx)),
+        )
+        .nest(
+            "/projects",
+            projects::project_routes(ctx).layer(JwtAuthLayer::new(ctx)),
+        )
+        .nest(
+            "/events",
+            events::event_routes(ctx).layer(JwtAuthLayer::new(ctx).with_cookie()),
+        )
+        .nest(
+            "/notifications",
+            notifications::notification_routes(ctx).layer(JwtAuthLayer::new(ctx)),
+        )
+        .nest(
+            "/sync",
+            sync::sync_routes(ctx).layer(JwtAuthLayer::new(ctx)),
+        )
+        .nest(
+            "/users",
+            users::user_routes(ctx).layer(JwtAuthLayer::new(ctx)),
+        )
+        .nest(
+            "/webhooks",
+            webhooks::webhook_routes(ctx).layer(JwtAuthLayer::new(ctx)),
         )
         .routes(routes!(health))
         .layer(
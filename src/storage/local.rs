@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use futures_util::{FutureExt, StreamExt, TryStreamExt, future::BoxFuture};
+use tokio::{fs, io::AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use super::{BlobStore, ByteStream, StorageError, check_key, content_key};
+
+/// Stores blobs on the local filesystem under `root`, fanned out into
+/// directories by the first characters of their key.
+#[derive(Debug, Clone)]
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        check_key(key)?;
+
+        Ok(self.root.join(&key[..2]).join(&key[2..4]).join(key))
+    }
+}
+
+impl BlobStore for LocalStore {
+    fn put(&self, data: Bytes) -> BoxFuture<'_, Result<String, StorageError>> {
+        async move {
+            let key = content_key(&data);
+            let path = self.path(&key)?;
+
+            if fs::try_exists(&path).await? {
+                return Ok(key);
+            }
+
+            let dir = path.parent().unwrap_or(&self.root);
+            fs::create_dir_all(dir).await?;
+
+            // Write to a temporary file first so a blob is never visible half
+            // written, even when two uploads of the same file race.
+            let temp = dir.join(format!(".{key}.{}", Uuid::new_v4()));
+            let mut file = fs::File::create(&temp).await?;
+            file.write_all(&data).await?;
+            file.sync_all().await?;
+            fs::rename(&temp, &path).await?;
+
+            Ok(key)
+        }
+        .boxed()
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<ByteStream, StorageError>> {
+        async move {
+            let file = match fs::File::open(self.path(key)?).await {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Err(StorageError::NotFound(key.to_string()));
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let stream: ByteStream = ReaderStream::new(file).map_err(StorageError::from).boxed();
+            Ok(stream)
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            match fs::remove_file(self.path(key)?).await {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e.into()),
+            }
+        }
+        .boxed()
+    }
+}
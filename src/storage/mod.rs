@@ -0,0 +1,69 @@
+mod local;
+mod s3;
+
+pub use self::{local::LocalStore, s3::S3Store};
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures_util::{future::BoxFuture, stream::BoxStream};
+use sha2::{Digest, Sha256};
+
+use crate::config::{StorageBackend, StorageConfig};
+
+/// Contents of a blob, read in chunks.
+pub type ByteStream = BoxStream<'static, Result<Bytes, StorageError>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("Blob {0} does not exist")]
+    NotFound(String),
+    #[error("Invalid blob key {0}")]
+    InvalidKey(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("Storage service responded with {0}")]
+    Status(reqwest::StatusCode),
+}
+
+/// Content addressed blob storage.
+///
+/// A blob's key is the hex encoded SHA-256 digest of its contents, so
+/// storing the same file twice keeps a single copy.
+pub trait BlobStore: Send + Sync {
+    /// Stores `data` and returns its key.
+    fn put(&self, data: Bytes) -> BoxFuture<'_, Result<String, StorageError>>;
+
+    /// Streams the contents of the blob stored under `key`.
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<ByteStream, StorageError>>;
+
+    /// Removes a blob. Removing a missing blob is not an error.
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), StorageError>>;
+}
+
+/// Key under which `data` is stored.
+#[must_use]
+pub fn content_key(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Keys are only ever generated by [`content_key`], reject anything else
+/// before it reaches a path or URL.
+fn check_key(key: &str) -> Result<(), StorageError> {
+    if key.len() == 64 && key.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidKey(key.to_string()))
+    }
+}
+
+/// Builds the [`BlobStore`] selected in the configuration.
+#[must_use]
+pub fn from_config(config: &StorageConfig) -> Arc<dyn BlobStore> {
+    match &config.backend {
+        StorageBackend::Local { root } => Arc::new(LocalStore::new(root)),
+        StorageBackend::S3(s3) => Arc::new(S3Store::new(s3)),
+    }
+}
@@ -0,0 +1,146 @@
+use bytes::Bytes;
+use chrono::Utc;
+use futures_util::{FutureExt, StreamExt, TryStreamExt, future::BoxFuture};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+
+use crate::config::S3Config;
+
+use super::{BlobStore, ByteStream, StorageError, check_key, content_key};
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Stores blobs in a bucket of an S3 compatible service, addressed in path
+/// style (`{endpoint}/{bucket}/{key}`) so self-hosted services work without
+/// wildcard DNS.
+///
+/// Requests are signed with AWS Signature Version 4.
+#[derive(Debug, Clone)]
+pub struct S3Store {
+    client: Client,
+    config: S3Config,
+}
+
+impl S3Store {
+    #[must_use]
+    pub fn new(config: &S3Config) -> Self {
+        Self {
+            client: Client::new(),
+            config: config.clone(),
+        }
+    }
+
+    fn url(&self, key: &str) -> Result<Url, StorageError> {
+        check_key(key)?;
+
+        let endpoint = self.config.endpoint.trim_end_matches('/');
+        Url::parse(&format!("{endpoint}/{}/{key}", self.config.bucket))
+            .map_err(|_| StorageError::InvalidKey(key.to_string()))
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        body: Bytes,
+    ) -> Result<reqwest::Response, StorageError> {
+        let url = self.url(key)?;
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(&body));
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{method}\n{path}\n\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n{signed_headers}\n{payload_hash}",
+            path = url.path(),
+        );
+
+        let scope = format!("{date}/{}/s3/aws4_request", self.config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let signing_key = [self.config.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(
+                hmac(format!("AWS4{}", self.config.secret_key).as_bytes(), &date),
+                |key, part| hmac(&key, part),
+            );
+        let signature = hex::encode(hmac(&signing_key, &string_to_sign));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.config.access_key,
+        );
+
+        let response = self
+            .client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("authorization", authorization)
+            .body(body)
+            .send()
+            .await?;
+
+        Ok(response)
+    }
+}
+
+impl BlobStore for S3Store {
+    fn put(&self, data: Bytes) -> BoxFuture<'_, Result<String, StorageError>> {
+        async move {
+            let key = content_key(&data);
+
+            let response = self.send(Method::PUT, &key, data).await?;
+            if !response.status().is_success() {
+                return Err(StorageError::Status(response.status()));
+            }
+
+            Ok(key)
+        }
+        .boxed()
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<ByteStream, StorageError>> {
+        async move {
+            let response = self.send(Method::GET, key, Bytes::new()).await?;
+            match response.status() {
+                StatusCode::NOT_FOUND => return Err(StorageError::NotFound(key.to_string())),
+                status if !status.is_success() => return Err(StorageError::Status(status)),
+                _ => {}
+            }
+
+            let stream: ByteStream = response.bytes_stream().map_err(StorageError::from).boxed();
+            Ok(stream)
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let response = self.send(Method::DELETE, key, Bytes::new()).await?;
+            let status = response.status();
+            if !status.is_success() && status != StatusCode::NOT_FOUND {
+                return Err(StorageError::Status(status));
+            }
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
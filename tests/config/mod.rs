@@ -25,3 +25,42 @@ fn cannot_build_from_missing_file() {
 
     assert!(result.is_err());
 }
+
+#[test]
+fn backs_off_webhook_retries_exponentially() {
+    let config = AppConfig::from_env(&AppEnvironment::Development).unwrap();
+    let webhooks = config.webhooks();
+
+    let delays = (1..webhooks.max_attempts)
+        .map(|attempts| webhooks.backoff(attempts).unwrap().num_seconds())
+        .collect::<Vec<i64>>();
+
+    assert_eq!(delays[..3], [30, 60, 120]);
+    assert!(delays.iter().all(|delay| *delay <= 21600));
+    assert!(webhooks.backoff(webhooks.max_attempts).is_none());
+}
+
+#[test]
+fn allows_event_sockets_from_configured_origins_only() {
+    let config = AppConfig::from_env(&AppEnvironment::Development).unwrap();
+    let events = config.events();
+
+    assert!(events.allows_origin("http://localhost:5150"));
+    assert!(events.allows_origin("HTTP://LOCALHOST:5150/"));
+    assert!(!events.allows_origin("http://localhost:8080"));
+    assert!(!events.allows_origin("https://evil.example.com"));
+}
+
+#[test]
+fn backs_off_outbox_retries_until_dead_lettered() {
+    let config = AppConfig::from_env(&AppEnvironment::Development).unwrap();
+    let outbox = config.outbox();
+
+    let delays = (1..outbox.max_attempts)
+        .map(|attempts| outbox.backoff(attempts).unwrap().num_seconds())
+        .collect::<Vec<i64>>();
+
+    assert_eq!(delays[..3], [5, 10, 20]);
+    assert!(delays.iter().all(|delay| *delay <= 600));
+    assert!(outbox.backoff(outbox.max_attempts).is_none());
+}
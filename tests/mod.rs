@@ -1,2 +1,4 @@
 mod config;
+mod models;
 mod repositories;
+mod storage;
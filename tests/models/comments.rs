@@ -0,0 +1,15 @@
+use tasks_authenticated::models::comments::parse_mentions;
+
+#[test]
+fn can_parse_mentions() {
+    let mentions = parse_mentions("@alice can you pair with @bob.smith? cc @alice");
+
+    assert_eq!(mentions, vec!["alice", "bob.smith"]);
+}
+
+#[test]
+fn skips_emails_and_bare_at_signs() {
+    let mentions = parse_mentions("Mail alice@example.com @ noon, then ping @carol.");
+
+    assert_eq!(mentions, vec!["carol"]);
+}
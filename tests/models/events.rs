@@ -0,0 +1,64 @@
+use tasks_authenticated::{events::Event, models::events::TaskEventResponse};
+use uuid::Uuid;
+
+#[test]
+fn only_sends_task_changes_to_their_audience() {
+    let owner = Uuid::new_v4();
+    let stranger = Uuid::new_v4();
+    let event = Event::TaskUpdated {
+        task_id: 7,
+        audience: vec![owner],
+    };
+
+    let sent = TaskEventResponse::for_user(&event, owner).unwrap();
+    assert_eq!(sent.kind, "taskUpdated");
+    assert_eq!(sent.task_id, Some(7));
+    assert!(TaskEventResponse::for_user(&event, stranger).is_none());
+}
+
+#[test]
+fn does_not_send_other_events() {
+    let assignee = Uuid::new_v4();
+    let event = Event::TaskAssigned {
+        task_id: 7,
+        assignee_pid: assignee,
+        assigned_by: Uuid::new_v4(),
+    };
+
+    assert!(event.concerns(assignee));
+    assert!(TaskEventResponse::for_user(&event, assignee).is_none());
+}
+
+#[test]
+fn serialises_without_audience() {
+    let event = TaskEventResponse::for_user(
+        &Event::TaskDeleted {
+            task_id: 3,
+            audience: vec![Uuid::nil()],
+        },
+        Uuid::nil(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&event).unwrap(),
+        serde_json::json!({ "type": "taskDeleted", "taskId": 3 })
+    );
+}
+
+#[test]
+fn pushes_notifications_to_their_recipient() {
+    let recipient = Uuid::new_v4();
+    let event = Event::Notified {
+        notification_id: 12,
+        user_pid: recipient,
+        task_id: Some(3),
+    };
+
+    assert!(TaskEventResponse::for_user(&event, Uuid::new_v4()).is_none());
+    let sent = TaskEventResponse::for_user(&event, recipient).unwrap();
+    assert_eq!(
+        serde_json::to_value(&sent).unwrap(),
+        serde_json::json!({ "type": "notification", "taskId": 3, "notificationId": 12 })
+    );
+}
@@ -0,0 +1,112 @@
+use chrono::{TimeZone, Utc};
+use tasks_authenticated::models::{
+    export::{CsvSerializer, ExportFormat, IcsSerializer, MarkdownSerializer, TaskSerializer},
+    labels::LabelResponse,
+    tasks::TaskResponse,
+};
+
+fn task(title: &str, done: bool) -> TaskResponse {
+    TaskResponse {
+        id: 1,
+        pid: "0196a1c4-7b1e-7f00-8000-000000000001".into(),
+        user_pid: "0196a1c4-7b1e-7f00-8000-000000000002".into(),
+        title: title.into(),
+        done,
+        created_at: "20-04-2025 09:00:00".into(),
+        project_id: None,
+        position: 0,
+        parent_id: None,
+        due_at: Some("2025-05-01T12:30:00+02:00".into()),
+        recurrence: None,
+        series_id: None,
+        assignee_pid: None,
+        deleted_at: None,
+        version: 1,
+        labels: vec![LabelResponse {
+            id: 1,
+            pid: "0196a1c4-7b1e-7f00-8000-000000000003".into(),
+            name: "home".into(),
+            colour: "#ffffff".into(),
+        }],
+        blocked_by: vec![2, 3],
+        blocking: Vec::new(),
+    }
+}
+
+fn export(serializer: &mut dyn TaskSerializer, tasks: &[TaskResponse]) -> String {
+    let rows = tasks
+        .iter()
+        .map(|task| serializer.row(task))
+        .collect::<String>();
+    format!("{}{rows}{}", serializer.header(), serializer.footer())
+}
+
+#[test]
+fn json_export_is_an_array_of_tasks() {
+    let tasks = [task("First task", false), task("Second task", true)];
+
+    let output = export(ExportFormat::Json.serializer().as_mut(), &tasks);
+    let parsed: Vec<serde_json::Value> = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(parsed.len(), 2);
+    assert_eq!(parsed[1]["title"], "Second task");
+}
+
+#[test]
+fn csv_export_quotes_fields_that_need_it() {
+    let output = export(
+        &mut CsvSerializer,
+        &[task("Milk, eggs and \"bread\"", false)],
+    );
+    let lines = output.split("\r\n").collect::<Vec<&str>>();
+
+    assert!(lines[0].starts_with("id,pid,title,done"));
+    assert!(lines[1].contains(",\"Milk, eggs and \"\"bread\"\"\",false,"));
+    assert!(lines[1].contains(",home,2;3,"));
+}
+
+#[test]
+fn markdown_export_is_a_task_list() {
+    let output = export(
+        &mut MarkdownSerializer,
+        &[task("Buy *milk*", true), task("Walk dog", false)],
+    );
+
+    assert!(output.contains("- [x] Buy \\*milk\\* (due 2025-05-01) #home\n"));
+    assert!(output.contains("- [ ] Walk dog"));
+}
+
+#[test]
+fn ics_export_holds_a_vtodo_per_task() {
+    let now = Utc.with_ymd_and_hms(2025, 4, 20, 9, 0, 0).unwrap();
+    let mut serializer = IcsSerializer::new(now);
+
+    let output = export(
+        &mut serializer,
+        &[
+            task("Pay rent; call landlord", true),
+            task("Plan trip", false),
+        ],
+    );
+
+    assert!(output.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(output.ends_with("END:VCALENDAR\r\n"));
+    assert_eq!(output.matches("BEGIN:VTODO\r\n").count(), 2);
+    assert!(output.contains("DTSTAMP:20250420T090000Z\r\n"));
+    assert!(output.contains("SUMMARY:Pay rent\\; call landlord\r\n"));
+    assert!(output.contains("DUE:20250501T103000Z\r\n"));
+    assert!(output.contains("STATUS:COMPLETED\r\n"));
+    assert!(output.contains("STATUS:NEEDS-ACTION\r\n"));
+}
+
+#[test]
+fn ics_export_folds_long_lines() {
+    let mut serializer = IcsSerializer::new(Utc::now());
+
+    let output = export(&mut serializer, &[task(&"ä".repeat(100), false)]);
+
+    assert!(output.contains("\r\n ä"));
+    for line in output.split("\r\n") {
+        assert!(line.len() <= 75, "line too long: {line}");
+    }
+}
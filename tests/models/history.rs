@@ -0,0 +1,23 @@
+use serde_json::json;
+use tasks_authenticated::models::history::diff;
+
+#[test]
+fn diff_keeps_only_changed_fields() {
+    let before = json!({ "title": "Draft", "done": false, "updated_at": "2025-04-20T09:00:00Z" });
+    let after = json!({ "title": "Final", "done": false, "updated_at": "2025-04-21T09:00:00Z" });
+
+    let changes = diff(Some(&before), Some(&after));
+
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes["title"], json!({ "from": "Draft", "to": "Final" }));
+}
+
+#[test]
+fn diff_of_created_record_lists_its_values() {
+    let after = json!({ "title": "Draft", "due_at": null });
+
+    let changes = diff(None, Some(&after));
+
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes["title"], json!({ "from": null, "to": "Draft" }));
+}
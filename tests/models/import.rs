@@ -0,0 +1,122 @@
+use serde_json::json;
+use tasks_authenticated::models::import::{ImportQuery, check, parse};
+
+fn query(params: serde_json::Value) -> ImportQuery {
+    serde_json::from_value(params).unwrap()
+}
+
+#[test]
+fn csv_import_uses_column_mapping() {
+    let query = query(json!({ "format": "csv", "titleColumn": "Name", "dueColumn": "Due" }));
+    let body = "Name,done,Due,labels\r\n\"Milk, eggs and \"\"bread\"\"\",yes,2025-05-01,home;shop\r\nWalk the dog,,,\r\n";
+
+    let tasks = parse(&query, body.as_bytes()).unwrap();
+
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].row, 2);
+    assert_eq!(tasks[0].task.title, "Milk, eggs and \"bread\"");
+    assert!(tasks[0].task.done);
+    assert_eq!(
+        tasks[0].task.due_at.unwrap().to_rfc3339(),
+        "2025-05-01T00:00:00+00:00"
+    );
+    assert_eq!(tasks[0].labels, ["home", "shop"]);
+    assert!(!tasks[1].task.done);
+    assert!(check(&tasks).is_empty());
+}
+
+#[test]
+fn csv_import_requires_title_column() {
+    let query = query(json!({ "format": "csv" }));
+
+    assert!(parse(&query, b"name,done\nWalk the dog,false\n").is_err());
+}
+
+#[test]
+fn check_reports_every_invalid_row() {
+    let query = query(json!({ "format": "csv" }));
+    let body = "title,done,dueAt\nShop,maybe,tomorrow\nA valid title,false,\n";
+
+    let tasks = parse(&query, body.as_bytes()).unwrap();
+    let errors = check(&tasks);
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].row, 2);
+    assert_eq!(
+        errors[0].errors["title"],
+        "Title must be between 5 to 255 characters"
+    );
+    assert!(errors[0].errors.contains_key("done"));
+    assert!(errors[0].errors.contains_key("dueAt"));
+}
+
+#[test]
+fn todo_txt_import_reads_completion_dates_and_tags() {
+    let query = query(json!({ "format": "todotxt" }));
+    let body = "x 2025-04-20 2025-04-18 Call the plumber +house @phone\n\n(A) 2025-04-18 Pay the rent due:2025-05-01\n";
+
+    let tasks = parse(&query, body.as_bytes()).unwrap();
+
+    assert_eq!(tasks.len(), 2);
+    assert!(tasks[0].task.done);
+    assert_eq!(tasks[0].task.title, "Call the plumber");
+    assert_eq!(tasks[0].labels, ["house", "phone"]);
+    assert_eq!(tasks[1].row, 3);
+    assert!(!tasks[1].task.done);
+    assert_eq!(tasks[1].task.title, "Pay the rent");
+    assert!(tasks[1].task.due_at.is_some());
+}
+
+#[test]
+fn todoist_import_keeps_subtasks() {
+    let query = query(json!({ "format": "todoist" }));
+    let body = json!({
+        "items": [
+            { "id": "100", "content": "Plan the trip", "checked": false, "labels": ["travel"] },
+            {
+                "id": "101", "parent_id": "100", "content": "Book the flights",
+                "checked": true, "due": { "date": "2025-05-01" }
+            }
+        ]
+    });
+
+    let tasks = parse(&query, body.to_string().as_bytes()).unwrap();
+
+    assert_eq!(tasks[1].parent.as_deref(), Some("100"));
+    assert!(tasks[1].task.done);
+    assert!(check(&tasks).is_empty());
+}
+
+#[test]
+fn trello_import_turns_checklists_into_subtasks() {
+    let query = query(json!({ "format": "trello" }));
+    let body = json!({
+        "labels": [{ "id": "l1", "name": "Urgent" }],
+        "cards": [
+            { "id": "c1", "name": "Launch website", "closed": false, "dueComplete": false, "due": "2025-05-01T09:00:00.000Z", "idLabels": ["l1"] },
+            { "id": "c2", "name": "Archived card", "closed": true, "dueComplete": false, "due": null, "idLabels": [] }
+        ],
+        "checklists": [
+            { "idCard": "c1", "checkItems": [{ "name": "Write the copy", "state": "complete" }] },
+            { "idCard": "c2", "checkItems": [{ "name": "Never imported", "state": "incomplete" }] }
+        ]
+    });
+
+    let tasks = parse(&query, body.to_string().as_bytes()).unwrap();
+
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].labels, ["Urgent"]);
+    assert_eq!(tasks[1].parent.as_deref(), Some("c1"));
+    assert!(tasks[1].task.done);
+}
+
+#[test]
+fn check_rejects_unknown_parents() {
+    let query = query(json!({ "format": "json" }));
+    let body = json!([{ "id": 2, "parentId": 1, "title": "Orphaned subtask" }]);
+
+    let tasks = parse(&query, body.to_string().as_bytes()).unwrap();
+    let errors = check(&tasks);
+
+    assert!(errors[0].errors.contains_key("parentId"));
+}
@@ -0,0 +1,91 @@
+use tasks_authenticated::{
+    Error,
+    models::inbound::{parse, recipient_token},
+};
+
+const FORWARDED: &[u8] = include_bytes!("../fixtures/forwarded.eml");
+
+fn email(subject: &str, body: &str) -> Vec<u8> {
+    format!("From: alice@example.com\r\nSubject: {subject}\r\n\r\n{body}").into_bytes()
+}
+
+#[test]
+fn reads_the_task_from_subject_and_body() {
+    let mail = parse(FORWARDED).unwrap();
+
+    assert_eq!(mail.task.title, "Book the flights for May");
+    assert_eq!(mail.message_id.as_deref(), Some("flights-2025@example.com"));
+    assert!(!mail.task.done);
+    assert_eq!(
+        mail.task.due_at.unwrap().to_rfc3339(),
+        "2025-05-01T00:00:00+00:00"
+    );
+    assert_eq!(mail.task.recurrence.as_deref(), Some("FREQ=WEEKLY"));
+    // The signature is left out
+    assert_eq!(
+        mail.note.as_deref(),
+        Some("Window seats if we can.\nBudget is in the PDF.")
+    );
+
+    assert_eq!(mail.attachments.len(), 2);
+    assert_eq!(mail.attachments[0].filename, "budget.pdf");
+    assert_eq!(mail.attachments[0].content_type, "application/pdf");
+    assert_eq!(
+        mail.attachments[0].data.as_ref(),
+        b"%PDF-1.7 not really a pdf"
+    );
+    assert_eq!(mail.attachments[1].filename, "setup.exe");
+}
+
+#[test]
+fn body_without_directives_is_the_note() {
+    let mail = parse(&email("Water the plants", "\r\nDue soon: the ferns\r\n")).unwrap();
+
+    assert!(mail.task.due_at.is_none());
+    assert_eq!(mail.note.as_deref(), Some("Due soon: the ferns"));
+    assert!(mail.attachments.is_empty());
+
+    let mail = parse(&email("Water the plants", "Due: 2025-05-01 09:30:00\r\n")).unwrap();
+    assert_eq!(
+        mail.task.due_at.unwrap().to_rfc3339(),
+        "2025-05-01T09:30:00+00:00"
+    );
+    assert!(mail.note.is_none());
+}
+
+#[test]
+fn rejects_mail_that_cannot_become_a_task() {
+    let rejected = |raw: Vec<u8>, field: &str| match parse(&raw) {
+        Err(Error::Validation(details)) => assert!(details.contains(field), "{details}"),
+        other => panic!("expected a validation error, got {other:?}"),
+    };
+
+    rejected(email("Re: Hi", "Short subject"), "title");
+    rejected(email("Water the plants", "Due: tomorrow\r\n"), "due");
+    rejected(
+        email("Water the plants", "Repeat: FREQ=HOURLY\r\n"),
+        "recurrence",
+    );
+}
+
+#[test]
+fn recognises_inbound_addresses() {
+    let domain = "inbound.localhost";
+    let token = "0123456789abcdef0123456789abcdef";
+
+    assert_eq!(
+        recipient_token(&format!("<{token}@inbound.localhost>"), domain).as_deref(),
+        Some(token)
+    );
+    assert_eq!(
+        recipient_token(
+            &format!("{}@Inbound.Localhost", token.to_uppercase()),
+            domain
+        )
+        .as_deref(),
+        Some(token)
+    );
+    assert!(recipient_token(&format!("{token}@example.com"), domain).is_none());
+    assert!(recipient_token("postmaster@inbound.localhost", domain).is_none());
+    assert!(recipient_token("<>", domain).is_none());
+}
@@ -0,0 +1,11 @@
+mod comments;
+mod events;
+mod export;
+mod history;
+mod import;
+mod inbound;
+mod notifications;
+mod recurrence;
+mod sync;
+mod tasks;
+mod webhooks;
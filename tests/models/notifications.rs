@@ -0,0 +1,71 @@
+use chrono::DateTime;
+use tasks_authenticated::{
+    events::Event,
+    models::notifications::{NewNotification, NotificationKind},
+};
+use uuid::Uuid;
+
+#[test]
+fn notifies_the_recipient_of_an_event() {
+    let assignee = Uuid::new_v4();
+    let owner = Uuid::new_v4();
+    let event = Event::TaskAssigned {
+        task_id: 7,
+        assignee_pid: assignee,
+        assigned_by: owner,
+    };
+
+    let new = NewNotification::for_event(&event).unwrap();
+    assert_eq!(new.user_pid, assignee);
+    assert_eq!(new.kind, NotificationKind::Assigned);
+    assert_eq!(new.task_id, Some(7));
+    assert_eq!(new.actor_pid, Some(owner));
+
+    let event = Event::TaskUpdated {
+        task_id: 7,
+        audience: vec![assignee],
+    };
+    assert!(NewNotification::for_event(&event).is_none());
+}
+
+#[test]
+fn words_messages_by_kind() {
+    let user_pid = Uuid::new_v4();
+    let event = Event::ProjectShared {
+        project_id: 3,
+        user_pid,
+        shared_by: Uuid::new_v4(),
+    };
+    let new = NewNotification::for_event(&event).unwrap();
+    assert_eq!(new.kind, NotificationKind::Shared);
+    assert_eq!(
+        new.message("Holidays", Some("ana")),
+        "ana shared \"Holidays\" with you"
+    );
+    assert_eq!(
+        new.message("Holidays", None),
+        "Someone shared \"Holidays\" with you"
+    );
+
+    let event = Event::TaskDue {
+        task_id: 7,
+        user_pid,
+        due_at: DateTime::parse_from_rfc3339("2025-05-01T09:30:00+02:00").unwrap(),
+    };
+    let new = NewNotification::for_event(&event).unwrap();
+    assert_eq!(
+        new.message("Pay rent", None),
+        "\"Pay rent\" is due 2025-05-01 09:30 +02:00"
+    );
+}
+
+#[test]
+fn parses_every_kind_it_prints() {
+    for kind in NotificationKind::ALL {
+        assert_eq!(kind.as_str().parse::<NotificationKind>(), Ok(kind));
+        assert_eq!(
+            serde_json::to_value(kind).unwrap(),
+            serde_json::json!(kind.as_str())
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use chrono::{DateTime, FixedOffset};
+use tasks_authenticated::models::recurrence::{Frequency, Recurrence};
+
+fn at(datetime: &str) -> DateTime<FixedOffset> {
+    DateTime::parse_from_rfc3339(datetime).unwrap()
+}
+
+#[test]
+fn can_parse_and_print_rule() {
+    let rule = "RRULE:freq=weekly;interval=2;byday=TH,MO"
+        .parse::<Recurrence>()
+        .unwrap();
+
+    assert_eq!(rule.frequency, Frequency::Weekly);
+    assert_eq!(rule.to_string(), "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH");
+}
+
+#[test]
+fn cannot_parse_invalid_rules() {
+    assert!("INTERVAL=2".parse::<Recurrence>().is_err());
+    assert!("FREQ=YEARLY".parse::<Recurrence>().is_err());
+    assert!("FREQ=DAILY;BYDAY=MO".parse::<Recurrence>().is_err());
+    assert!("FREQ=MONTHLY;BYMONTHDAY=32".parse::<Recurrence>().is_err());
+}
+
+#[test]
+fn can_step_daily_with_interval() {
+    let rule = "FREQ=DAILY;INTERVAL=3".parse::<Recurrence>().unwrap();
+
+    let next = rule.next_after(at("2025-04-28T09:00:00+03:00"));
+
+    assert_eq!(next, Some(at("2025-05-01T09:00:00+03:00")));
+}
+
+#[test]
+fn can_step_weekly_on_weekdays() {
+    let rule = "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH"
+        .parse::<Recurrence>()
+        .unwrap();
+
+    // Monday -> Thursday of the same week
+    let thursday = rule.next_after(at("2025-04-14T08:00:00Z")).unwrap();
+    assert_eq!(thursday, at("2025-04-17T08:00:00Z"));
+
+    // Thursday -> Monday two weeks on
+    let monday = rule.next_after(thursday).unwrap();
+    assert_eq!(monday, at("2025-04-28T08:00:00Z"));
+}
+
+#[test]
+fn can_step_monthly_by_day_clamping_short_months() {
+    let rule = "FREQ=MONTHLY;BYMONTHDAY=31".parse::<Recurrence>().unwrap();
+
+    let february = rule.next_after(at("2025-01-31T12:00:00Z")).unwrap();
+    assert_eq!(february, at("2025-02-28T12:00:00Z"));
+
+    let march = rule.next_after(february).unwrap();
+    assert_eq!(march, at("2025-03-31T12:00:00Z"));
+}
+
+#[test]
+fn can_end_series_with_until() {
+    let rule = "FREQ=DAILY;UNTIL=20250502".parse::<Recurrence>().unwrap();
+
+    assert!(rule.next_after(at("2025-05-01T10:00:00Z")).is_some());
+    assert!(rule.next_after(at("2025-05-02T10:00:00Z")).is_none());
+}
+
+#[test]
+fn can_skip_missed_occurrences() {
+    let rule = "FREQ=DAILY".parse::<Recurrence>().unwrap();
+
+    let next = rule.next_occurrence(at("2025-04-01T07:00:00Z"), at("2025-04-10T12:00:00Z"));
+
+    assert_eq!(next, Some(at("2025-04-11T07:00:00Z")));
+}
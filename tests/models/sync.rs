@@ -0,0 +1,43 @@
+use tasks_authenticated::models::sync::{MAX_SYNC_CHANGES, SyncQuery, SyncToken};
+
+#[test]
+fn sync_token_round_trips() {
+    let token = SyncToken { seq: 1234, id: 56 };
+
+    assert_eq!(token.to_string(), "1234-56");
+    assert_eq!("1234-56".parse::<SyncToken>().unwrap(), token);
+}
+
+#[test]
+fn rejects_malformed_sync_token() {
+    for since in ["", "1234", "abc-1", "1-2-3"] {
+        let query = SyncQuery {
+            since: Some(since.into()),
+            limit: None,
+        };
+        assert!(query.token().is_err(), "{since:?} was accepted");
+    }
+}
+
+#[test]
+fn syncs_from_the_start_without_token() {
+    let query = SyncQuery::default();
+
+    assert_eq!(query.token().unwrap(), SyncToken::default());
+    assert_eq!(query.limit(), MAX_SYNC_CHANGES);
+}
+
+#[test]
+fn clamps_sync_limit() {
+    let query = SyncQuery {
+        since: None,
+        limit: Some(0),
+    };
+    assert_eq!(query.limit(), 1);
+
+    let query = SyncQuery {
+        since: None,
+        limit: Some(MAX_SYNC_CHANGES + 1),
+    };
+    assert_eq!(query.limit(), MAX_SYNC_CHANGES);
+}
@@ -0,0 +1,46 @@
+use tasks_authenticated::models::tasks::{UpdateTask, VersionMatch, etag};
+use validator::Validate;
+
+#[test]
+fn can_parse_version_tags() {
+    let tags = "\"3\", W/\"4\", \"other\"".parse::<VersionMatch>().unwrap();
+
+    assert_eq!(tags, VersionMatch::Tags(vec![(false, 3), (true, 4)]));
+    assert!(tags.matches(3));
+    assert!(!tags.matches(4));
+    assert!(tags.matches_weak(4));
+    assert!(!tags.matches_weak(5));
+}
+
+#[test]
+fn wildcard_matches_any_version() {
+    let tags = "*".parse::<VersionMatch>().unwrap();
+
+    assert!(tags.matches(7));
+    assert!(etag(7).parse::<VersionMatch>().unwrap().matches(7));
+}
+
+#[test]
+fn merge_patch_tells_absent_from_null() {
+    let absent = serde_json::from_str::<UpdateTask>(r#"{"title": "Renamed"}"#).unwrap();
+    assert_eq!(absent.title.as_deref(), Some("Renamed"));
+    assert_eq!(absent.done, None);
+    assert_eq!(absent.due_at, None);
+
+    let cleared = serde_json::from_str::<UpdateTask>(r#"{"dueAt": null}"#).unwrap();
+    assert_eq!(cleared.due_at, Some(None));
+}
+
+#[test]
+fn merge_patch_rejects_null_for_required_fields() {
+    let patch = serde_json::from_str::<UpdateTask>(r#"{"title": null, "done": null}"#).unwrap();
+    assert_eq!(patch.title, None);
+
+    let errors = patch.validate().unwrap_err();
+    let fields = errors.field_errors();
+    assert!(fields.contains_key("title"));
+    assert!(fields.contains_key("done"));
+
+    let patch = serde_json::from_str::<UpdateTask>(r#"{"dueAt": null, "done": true}"#).unwrap();
+    assert!(patch.validate().is_ok());
+}
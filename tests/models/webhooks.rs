@@ -0,0 +1,122 @@
+use tasks_authenticated::{
+    config::WebhookConfig,
+    events::Event,
+    models::webhooks::{
+        EndpointError, EndpointResolver, WebhookEvent, WebhookPayload, is_public_address, sign,
+    },
+};
+use uuid::Uuid;
+
+#[test]
+fn signs_timestamp_and_body() {
+    let signature = sign("whsec_test", 1_700_000_000, br#"{"type":"taskCreated"}"#);
+
+    assert_eq!(
+        signature,
+        "sha256=8ce24079332e5a57bf365f087c3f1dde861d79da0aa246b26930dd486390f4d8"
+    );
+    assert_ne!(
+        sign("whsec_test", 1_700_000_001, br#"{"type":"taskCreated"}"#),
+        signature
+    );
+}
+
+#[test]
+fn only_delivers_task_changes() {
+    let event = Event::TaskDeleted {
+        task_id: 7,
+        audience: vec![Uuid::nil()],
+    };
+    let payload = WebhookPayload::for_event(&event).unwrap();
+    assert_eq!(payload.kind, WebhookEvent::TaskDeleted);
+    assert_eq!(payload.task_id, 7);
+
+    let event = Event::TaskAssigned {
+        task_id: 7,
+        assignee_pid: Uuid::nil(),
+        assigned_by: Uuid::nil(),
+    };
+    assert!(WebhookPayload::for_event(&event).is_none());
+}
+
+#[test]
+fn refuses_internal_addresses() {
+    for internal in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "0.0.0.0",
+        "::1",
+        "::",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:127.0.0.1",
+        "100.64.0.1",
+        "100.127.255.254",
+        "0.1.2.3",
+        "192.0.0.8",
+        "198.18.0.1",
+        "198.19.255.255",
+        "240.0.0.1",
+        "255.255.255.255",
+        "224.0.0.1",
+        "239.255.255.250",
+        "192.0.2.1",
+        "64:ff9b::a00:1",
+        "64:ff9b::7f00:1",
+        "64:ff9b:1::1",
+        "2002:a00:1::1",
+        "2002:c0a8:101::1",
+        "::10.0.0.1",
+        "::127.0.0.1",
+        "ff02::1",
+        "ff0e::1",
+        "fec0::1",
+        "2001:db8::1",
+        "2001::1",
+        "100::1",
+    ] {
+        assert!(!is_public_address(internal.parse().unwrap()), "{internal}");
+    }
+
+    for public in [
+        "93.184.216.34",
+        "100.128.0.1",
+        "198.20.0.1",
+        "2606:2800:220:1:248:1893:25c8:1946",
+        "::ffff:93.184.216.34",
+        "64:ff9b::5db8:d822",
+        "2002:5db8:d822::1",
+    ] {
+        assert!(is_public_address(public.parse().unwrap()), "{public}");
+    }
+}
+
+#[tokio::test]
+async fn only_allowed_hosts_may_be_internal() {
+    let mut config = WebhookConfig {
+        max_attempts: 3,
+        retry_base: 60,
+        retry_max: 3600,
+        timeout: 5,
+        poll_interval: 1,
+        batch_size: 20,
+        allowed_hosts: Vec::new(),
+    };
+
+    let resolver = EndpointResolver::new(&config);
+    assert_eq!(
+        resolver.check("http://127.0.0.1:8080/hook").await,
+        Err(EndpointError::Internal("127.0.0.1".into()))
+    );
+    assert_eq!(
+        resolver.check("http://[::1]/hook").await,
+        Err(EndpointError::Internal("::1".into()))
+    );
+
+    config.allowed_hosts = vec!["127.0.0.1".into()];
+    let resolver = EndpointResolver::new(&config);
+    assert!(resolver.check("http://127.0.0.1:8080/hook").await.is_ok());
+}
@@ -0,0 +1,106 @@
+use serial_test::serial;
+use tasks_authenticated::{
+    models::attachments::Upload,
+    repositories::attachments::Attachment,
+    storage::{BlobStore, LocalStore, StorageError},
+};
+use uuid::Uuid;
+
+use super::common::{fresh_db, seed_task, seed_user};
+
+fn upload(filename: &str, data: &'static [u8]) -> Upload {
+    Upload {
+        filename: filename.into(),
+        content_type: "text/plain".into(),
+        data: data.into(),
+    }
+}
+
+fn blob_store() -> LocalStore {
+    LocalStore::new(std::env::temp_dir().join(format!("blobs-{}", Uuid::new_v4())))
+}
+
+#[tokio::test]
+#[serial]
+async fn stores_identical_uploads_once() {
+    let (_, db) = fresh_db().await;
+    let blobs = blob_store();
+    let user = seed_user(&db, "tasker").await;
+    let first = seed_task(&db, &user, "First task").await;
+    let second = seed_task(&db, &user, "Second task").await;
+
+    let a = Attachment::create(&db, &blobs, first.id, upload("a.txt", b"same"), user.pid)
+        .await
+        .unwrap();
+    let b = Attachment::create(&db, &blobs, second.id, upload("b.txt", b"same"), user.pid)
+        .await
+        .unwrap();
+    let other = Attachment::create(&db, &blobs, first.id, upload("c.txt", b"other"), user.pid)
+        .await
+        .unwrap();
+
+    assert_eq!(a.storage_key, b.storage_key);
+    assert_ne!(a.storage_key, other.storage_key);
+    assert_eq!(
+        (a.filename.as_str(), b.filename.as_str()),
+        ("a.txt", "b.txt")
+    );
+    assert_eq!(a.size, 4);
+}
+
+#[tokio::test]
+#[serial]
+async fn deletes_the_blob_with_its_last_reference() {
+    let (_, db) = fresh_db().await;
+    let blobs = blob_store();
+    let user = seed_user(&db, "tasker").await;
+    let task = seed_task(&db, &user, "Attached twice").await;
+
+    let first = Attachment::create(&db, &blobs, task.id, upload("a.txt", b"shared"), user.pid)
+        .await
+        .unwrap();
+    let second = Attachment::create(&db, &blobs, task.id, upload("b.txt", b"shared"), user.pid)
+        .await
+        .unwrap();
+
+    Attachment::delete_by_id(&db, &blobs, task.id, first.id, user.pid)
+        .await
+        .unwrap();
+    assert!(blobs.get(&second.storage_key).await.is_ok());
+
+    Attachment::delete_by_id(&db, &blobs, task.id, second.id, user.pid)
+        .await
+        .unwrap();
+    assert!(matches!(
+        blobs.get(&second.storage_key).await,
+        Err(StorageError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn keeps_blobs_attached_while_their_last_reference_goes() {
+    let (_, db) = fresh_db().await;
+    let blobs = blob_store();
+    let user = seed_user(&db, "tasker").await;
+    let task = seed_task(&db, &user, "Attached and detached").await;
+
+    for _ in 0..5 {
+        let old = Attachment::create(&db, &blobs, task.id, upload("old.txt", b"racy"), user.pid)
+            .await
+            .unwrap();
+
+        let (created, deleted) = tokio::join!(
+            Attachment::create(&db, &blobs, task.id, upload("new.txt", b"racy"), user.pid),
+            Attachment::delete_by_id(&db, &blobs, task.id, old.id, user.pid),
+        );
+        let created = created.unwrap();
+        deleted.unwrap();
+
+        // The new attachment still finds its file
+        assert!(blobs.get(&created.storage_key).await.is_ok());
+        Attachment::delete_by_id(&db, &blobs, task.id, created.id, user.pid)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,67 @@
+use sqlx::PgPool;
+use tasks_authenticated::{
+    AppConfig, AppEnvironment,
+    config::OutboxConfig,
+    models::{auth::RegisterUser, tasks::NewTask},
+    repositories::{tasks::Task, users::User},
+};
+
+/// Recreates the development database, returning its config and a pool.
+pub async fn fresh_db() -> (AppConfig, PgPool) {
+    let config = AppConfig::from_env(&AppEnvironment::Development).unwrap();
+    config.db().recreate().await.unwrap();
+    let db = config.db().connection_pool().unwrap();
+
+    (config, db)
+}
+
+/// Outbox settings of the development config, for relaying by hand.
+pub fn outbox() -> OutboxConfig {
+    let config = AppConfig::from_env(&AppEnvironment::Development).unwrap();
+
+    config.outbox().clone()
+}
+
+/// Registration of `username` at `<username>@mail.com` with the password
+/// `Password`.
+pub fn register(username: &str) -> RegisterUser {
+    RegisterUser {
+        username: username.into(),
+        email: format!("{username}@mail.com"),
+        password: "Password".into(),
+        confirm_password: "Password".into(),
+    }
+}
+
+pub async fn seed_user(db: &PgPool, username: &str) -> User {
+    User::create_with_password(db, &register(username))
+        .await
+        .unwrap()
+}
+
+/// An open top-level task titled `title`, without a project or due date.
+pub fn new_task(title: &str) -> NewTask {
+    NewTask {
+        title: title.into(),
+        done: false,
+        project_id: None,
+        parent_id: None,
+        due_at: None,
+        recurrence: None,
+    }
+}
+
+pub async fn seed_task(db: &PgPool, user: &User, title: &str) -> Task {
+    Task::create_task(db, &new_task(title), user.pid)
+        .await
+        .unwrap()
+}
+
+pub async fn seed_subtask(db: &PgPool, user: &User, title: &str, parent_id: i32) -> Task {
+    let params = NewTask {
+        parent_id: Some(parent_id),
+        ..new_task(title)
+    };
+
+    Task::create_task(db, &params, user.pid).await.unwrap()
+}
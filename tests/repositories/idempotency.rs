@@ -0,0 +1,107 @@
+use axum::http::Method;
+use chrono::{TimeDelta, Utc};
+use serial_test::serial;
+use tasks_authenticated::{
+    middlewares::idempotency::request_hash, repositories::idempotency::IdempotencyKey,
+};
+use uuid::Uuid;
+
+use super::common::fresh_db;
+
+#[tokio::test]
+#[serial]
+async fn replays_key_only_within_window() {
+    let (_, db) = fresh_db().await;
+    let user_pid = Uuid::nil();
+    let since = (Utc::now() - TimeDelta::hours(1)).fixed_offset();
+    let stale_before = (Utc::now() - TimeDelta::minutes(1)).fixed_offset();
+
+    let reserved = IdempotencyKey::reserve(&db, user_pid, "retry-1", "hash", since, stale_before)
+        .await
+        .unwrap();
+    assert!(reserved);
+
+    let taken = IdempotencyKey::reserve(&db, user_pid, "retry-1", "hash", since, stale_before)
+        .await
+        .unwrap();
+    assert!(!taken);
+
+    IdempotencyKey::complete(&db, user_pid, "retry-1", 201, Vec::new(), b"{}", None)
+        .await
+        .unwrap();
+    let record = IdempotencyKey::find(&db, user_pid, "retry-1", since)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record.status, Some(201));
+    assert_eq!(record.body.as_deref(), Some(&b"{}"[..]));
+
+    // Once the window has passed the key can be used again
+    let later = (Utc::now() + TimeDelta::seconds(1)).fixed_offset();
+    let reused = IdempotencyKey::reserve(&db, user_pid, "retry-1", "other", later, stale_before)
+        .await
+        .unwrap();
+    assert!(reused);
+}
+
+#[tokio::test]
+#[serial]
+async fn takes_over_unanswered_key_after_lock_timeout() {
+    let (_, db) = fresh_db().await;
+    let user_pid = Uuid::nil();
+    let since = (Utc::now() - TimeDelta::hours(1)).fixed_offset();
+    let stale_before = (Utc::now() - TimeDelta::minutes(1)).fixed_offset();
+
+    let reserved = IdempotencyKey::reserve(&db, user_pid, "crashed", "hash", since, stale_before)
+        .await
+        .unwrap();
+    assert!(reserved);
+
+    // The request holding the key never answered
+    let later = (Utc::now() + TimeDelta::seconds(1)).fixed_offset();
+    let taken_over = IdempotencyKey::reserve(&db, user_pid, "crashed", "hash", since, later)
+        .await
+        .unwrap();
+    assert!(taken_over);
+
+    // Answered keys are kept for the whole window
+    IdempotencyKey::complete(&db, user_pid, "crashed", 201, Vec::new(), b"{}", None)
+        .await
+        .unwrap();
+    let taken = IdempotencyKey::reserve(&db, user_pid, "crashed", "hash", since, later)
+        .await
+        .unwrap();
+    assert!(!taken);
+}
+
+#[tokio::test]
+#[serial]
+async fn stores_no_hash_of_the_password() {
+    let (_, db) = fresh_db().await;
+    let since = (Utc::now() - TimeDelta::hours(1)).fixed_offset();
+    let stale_before = (Utc::now() - TimeDelta::minutes(1)).fixed_offset();
+    let register = |email: &str, password: &str| {
+        let body = serde_json::json!({
+            "email": email,
+            "username": "alice",
+            "password": password,
+            "confirm_password": password,
+        });
+        request_hash(&Method::POST, "/auth/register", body.to_string().as_bytes())
+    };
+
+    let hash = register("alice@mail.com", "Password");
+    IdempotencyKey::reserve(&db, Uuid::nil(), "signup", &hash, since, stale_before)
+        .await
+        .unwrap();
+    let record = IdempotencyKey::find(&db, Uuid::nil(), "signup", since)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        record.request_hash,
+        register("alice@mail.com", "Other password")
+    );
+    assert_ne!(record.request_hash, register("bob@mail.com", "Password"));
+}
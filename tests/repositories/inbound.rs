@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use serial_test::serial;
+use sqlx::PgPool;
+use tasks_authenticated::{
+    AppConfig,
+    inbound::{Inbound, smtp},
+    models::tasks::TaskFilter,
+    repositories::{
+        attachments::Attachment, comments::Comment, inbound::InboundAddress, tasks::Task,
+        users::User,
+    },
+    storage::{BlobStore, LocalStore},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use uuid::Uuid;
+
+use super::common::{fresh_db, seed_user};
+
+const FORWARDED: &[u8] = include_bytes!("../fixtures/forwarded.eml");
+
+async fn setup() -> (AppConfig, PgPool, User, Inbound) {
+    let (config, db) = fresh_db().await;
+
+    let user = seed_user(&db, "alice").await;
+
+    let blobs: Arc<dyn BlobStore> = Arc::new(LocalStore::new(
+        std::env::temp_dir().join(format!("blobs-{}", Uuid::new_v4())),
+    ));
+    let inbound = Inbound::new(db.clone(), blobs, &config);
+
+    (config, db, user, inbound)
+}
+
+#[tokio::test]
+#[serial]
+async fn gives_each_user_a_secret_address_that_can_be_replaced() {
+    let (config, db, user, inbound) = setup().await;
+
+    let address = InboundAddress::find_or_create(&db, user.pid).await.unwrap();
+    let again = InboundAddress::find_or_create(&db, user.pid).await.unwrap();
+    assert_eq!(address.token, again.token);
+    let mailbox = config.inbound().mailbox(&address.token);
+    assert_eq!(inbound.recipient(&mailbox).await.unwrap(), Some(user.pid));
+
+    let rotated = InboundAddress::rotate(&db, user.pid).await.unwrap();
+    assert_ne!(rotated.token, address.token);
+    assert_eq!(inbound.recipient(&mailbox).await.unwrap(), None);
+    let mailbox = config.inbound().mailbox(&rotated.token);
+    assert_eq!(inbound.recipient(&mailbox).await.unwrap(), Some(user.pid));
+}
+
+#[tokio::test]
+#[serial]
+async fn turns_an_email_into_a_task() {
+    let (config, db, user, inbound) = setup().await;
+    let address = InboundAddress::find_or_create(&db, user.pid).await.unwrap();
+
+    let mailbox = config.inbound().mailbox(&address.token);
+    let task = inbound.ingest(&mailbox, FORWARDED).await.unwrap().unwrap();
+    assert_eq!(task.title, "Book the flights for May");
+    assert_eq!(task.user_pid, user.pid);
+    assert_eq!(task.recurrence.as_deref(), Some("FREQ=WEEKLY"));
+
+    let comments = Comment::find_all(&db, task.id, user.pid).await.unwrap();
+    assert_eq!(comments.len(), 1);
+    assert_eq!(
+        comments[0].body,
+        "Window seats if we can.\nBudget is in the PDF."
+    );
+
+    // The executable is not an allowed file type
+    let attachments = Attachment::find_all(&db, task.id, user.pid).await.unwrap();
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0].filename, "budget.pdf");
+
+    // The same message delivered again is skipped
+    assert!(inbound.ingest(&mailbox, FORWARDED).await.unwrap().is_none());
+
+    let unknown = config.inbound().mailbox("0123456789abcdef0123456789abcdef");
+    assert!(inbound.ingest(&unknown, FORWARDED).await.is_err());
+    assert_eq!(
+        Task::find_all(&db, user.pid, &TaskFilter::default())
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+/// Sends `command` and returns the code of the reply, skipping the lines of
+/// multiline replies.
+async fn send(stream: &mut BufReader<TcpStream>, command: &str) -> String {
+    stream
+        .get_mut()
+        .write_all(format!("{command}\r\n").as_bytes())
+        .await
+        .unwrap();
+    read_reply(stream).await
+}
+
+async fn read_reply(stream: &mut BufReader<TcpStream>) -> String {
+    loop {
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return line[..3].to_string();
+        }
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn receives_mail_over_smtp() {
+    let (config, db, user, inbound) = setup().await;
+    let address = InboundAddress::find_or_create(&db, user.pid).await.unwrap();
+    let mailbox = config.inbound().mailbox(&address.token);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let local = listener.local_addr().unwrap();
+    tokio::spawn(smtp::serve(listener, inbound));
+
+    let mut stream = BufReader::new(TcpStream::connect(local).await.unwrap());
+    assert_eq!(read_reply(&mut stream).await, "220");
+    assert_eq!(send(&mut stream, "EHLO mail.example.com").await, "250");
+    assert_eq!(send(&mut stream, "RCPT TO:<x@y>").await, "503");
+    assert_eq!(
+        send(
+            &mut stream,
+            "MAIL FROM:<alice@example.com> SIZE=999999999999"
+        )
+        .await,
+        "552"
+    );
+    assert_eq!(
+        send(&mut stream, "MAIL FROM:<alice@example.com>").await,
+        "250"
+    );
+    assert_eq!(
+        send(&mut stream, "RCPT TO:<bob@inbound.localhost>").await,
+        "550"
+    );
+    assert_eq!(send(&mut stream, "DATA").await, "503");
+    assert_eq!(
+        send(&mut stream, &format!("RCPT TO:<{mailbox}>")).await,
+        "250"
+    );
+    assert_eq!(send(&mut stream, "DATA").await, "354");
+    let message = "Subject: Renew the passport\r\n\r\nDue: 2025-06-01\r\n\r\n..and the visa\r\n.";
+    assert_eq!(send(&mut stream, message).await, "250");
+
+    // A subject too short for a title is refused
+    assert_eq!(
+        send(&mut stream, "MAIL FROM:<alice@example.com>").await,
+        "250"
+    );
+    assert_eq!(
+        send(&mut stream, &format!("RCPT TO:<{mailbox}>")).await,
+        "250"
+    );
+    assert_eq!(send(&mut stream, "DATA").await, "354");
+    assert_eq!(
+        send(&mut stream, "Subject: Hi\r\n\r\nHello\r\n.").await,
+        "550"
+    );
+    assert_eq!(send(&mut stream, "QUIT").await, "221");
+
+    let tasks = Task::find_all(&db, user.pid, &TaskFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].title, "Renew the passport");
+    assert_eq!(
+        tasks[0].due_at.unwrap().to_rfc3339(),
+        "2025-06-01T00:00:00+00:00"
+    );
+    let comments = Comment::find_all(&db, tasks[0].id, user.pid).await.unwrap();
+    assert_eq!(comments[0].body, ".and the visa");
+}
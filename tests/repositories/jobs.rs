@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use chrono::TimeDelta;
+
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serial_test::serial;
+use sqlx::PgPool;
+use tasks_authenticated::{
+    AppConfig,
+    jobs::{self, Job, JobContext, Worker},
+    repositories::{ModelError, jobs::QueuedJob},
+    storage::{BlobStore, LocalStore},
+};
+
+use super::common::fresh_db;
+
+/// Fails its first `failures` attempts.
+#[derive(Debug, Deserialize, Serialize)]
+struct Flaky {
+    failures: i32,
+}
+
+impl Job for Flaky {
+    const KIND: &'static str = "test.flaky";
+    const MAX_ATTEMPTS: i32 = 2;
+
+    fn run<'a>(&'a self, ctx: &'a JobContext) -> BoxFuture<'a, Result<(), ModelError>> {
+        Box::pin(async move {
+            if ctx.attempt <= self.failures {
+                return Err(ModelError::Database("Flaky job failed".into()));
+            }
+            Ok(())
+        })
+    }
+}
+
+async fn setup() -> (AppConfig, PgPool, Arc<dyn BlobStore>) {
+    let (config, db) = fresh_db().await;
+    let blobs: Arc<dyn BlobStore> = Arc::new(LocalStore::new(std::env::temp_dir()));
+
+    (config, db, blobs)
+}
+
+async fn make_due(db: &PgPool) {
+    sqlx::query("UPDATE jobs SET run_at = NOW() WHERE status = 'queued'")
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn retries_failed_jobs_until_out_of_attempts() {
+    let (config, db, blobs) = setup().await;
+    let worker = Worker::new(db.clone(), blobs, &config).register::<Flaky>();
+
+    let recovers = jobs::enqueue(&db, &Flaky { failures: 1 }).await.unwrap();
+    let gives_up = jobs::enqueue(&db, &Flaky { failures: 5 }).await.unwrap();
+    let unknown = QueuedJob::enqueue(&db, "test.unknown", &json!({}), 1, recovers.run_at)
+        .await
+        .unwrap();
+
+    assert_eq!(worker.run_once().await.unwrap(), 2);
+    let job = QueuedJob::find_by_id(&db, recovers.id).await.unwrap();
+    assert_eq!(job.status, "queued");
+    assert_eq!(job.attempts, 1);
+    assert_eq!(job.last_error.as_deref(), Some("Flaky job failed"));
+    assert!(job.run_at > recovers.run_at);
+
+    // Nothing is due until the backoff has passed
+    assert_eq!(worker.run_once().await.unwrap(), 0);
+
+    make_due(&db).await;
+    assert_eq!(worker.run_once().await.unwrap(), 2);
+    let job = QueuedJob::find_by_id(&db, recovers.id).await.unwrap();
+    assert_eq!(job.status, "completed");
+    let job = QueuedJob::find_by_id(&db, gives_up.id).await.unwrap();
+    assert_eq!(job.status, "failed");
+    assert_eq!(job.attempts, 2);
+
+    // Jobs without a handler are left for a worker that has one
+    let job = QueuedJob::find_by_id(&db, unknown.id).await.unwrap();
+    assert_eq!(job.status, "queued");
+}
+
+#[tokio::test]
+#[serial]
+async fn workers_do_not_run_the_same_job_twice() {
+    let (config, db, blobs) = setup().await;
+    let first = Worker::new(db.clone(), blobs.clone(), &config).register::<Flaky>();
+    let second = Worker::new(db.clone(), blobs, &config).register::<Flaky>();
+
+    for _ in 0..6 {
+        jobs::enqueue(&db, &Flaky { failures: 0 }).await.unwrap();
+    }
+
+    let (a, b) = tokio::join!(first.run_once(), second.run_once());
+    let (a, b) = (a.unwrap(), b.unwrap());
+    let rest = first.run_once().await.unwrap();
+    assert_eq!(a + b + rest, 6);
+
+    let completed = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM jobs WHERE status = 'completed' AND attempts = 1",
+    )
+    .fetch_one(&db)
+    .await
+    .unwrap();
+    assert_eq!(completed, 6);
+}
+
+#[tokio::test]
+#[serial]
+async fn attempts_that_lost_their_lease_record_nothing() {
+    let (_, db, _) = setup().await;
+    let job = jobs::enqueue(&db, &Flaky { failures: 0 }).await.unwrap();
+    let kinds = [Flaky::KIND];
+
+    let first = QueuedJob::claim(&db, &kinds, 10, TimeDelta::zero())
+        .await
+        .unwrap();
+    assert_eq!(first.len(), 1);
+
+    // The first lease ran out and another worker took the job over
+    let second = QueuedJob::claim(&db, &kinds, 10, TimeDelta::minutes(10))
+        .await
+        .unwrap();
+    assert_eq!(second[0].attempts, 2);
+
+    let lease = TimeDelta::minutes(10);
+    assert!(!QueuedJob::renew(&db, job.id, 1, lease).await.unwrap());
+    assert!(!QueuedJob::complete(&db, job.id, 1).await.unwrap());
+    assert!(QueuedJob::renew(&db, job.id, 2, lease).await.unwrap());
+    assert!(
+        QueuedJob::fail(&db, job.id, 2, "Failed", None)
+            .await
+            .unwrap()
+    );
+    assert!(!QueuedJob::fail(&db, job.id, 1, "Late", None).await.unwrap());
+
+    let job = QueuedJob::find_by_id(&db, job.id).await.unwrap();
+    assert_eq!(job.status, "failed");
+    assert_eq!(job.last_error.as_deref(), Some("Failed"));
+}
+
+#[tokio::test]
+#[serial]
+async fn queues_scheduled_jobs_once_per_run() {
+    let (config, db, blobs) = setup().await;
+    let worker = Worker::new(db.clone(), blobs.clone(), &config)
+        .schedule("yearly", "0 0 0 1 1 *", &Flaky { failures: 0 })
+        .unwrap();
+    worker.sync_schedules().await.unwrap();
+
+    assert_eq!(worker.queue_scheduled().await.unwrap(), 0);
+
+    sqlx::query("UPDATE job_schedules SET next_run_at = NOW() - INTERVAL '1 minute'")
+        .execute(&db)
+        .await
+        .unwrap();
+    let other = Worker::new(db.clone(), blobs, &config)
+        .schedule("yearly", "0 0 0 1 1 *", &Flaky { failures: 0 })
+        .unwrap();
+    let (a, b) = tokio::join!(worker.queue_scheduled(), other.queue_scheduled());
+    assert_eq!(a.unwrap() + b.unwrap(), 1);
+    assert_eq!(worker.queue_scheduled().await.unwrap(), 0);
+
+    assert_eq!(worker.run_once().await.unwrap(), 1);
+}
+
+#[tokio::test]
+#[serial]
+async fn configured_schedules_are_valid() {
+    let (config, db, blobs) = setup().await;
+
+    let worker = jobs::worker(db, blobs, &config).unwrap();
+
+    worker.sync_schedules().await.unwrap();
+}
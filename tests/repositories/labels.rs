@@ -0,0 +1,81 @@
+use serial_test::serial;
+use sqlx::PgPool;
+use tasks_authenticated::{
+    models::{
+        labels::NewLabel,
+        tasks::{LabelMatch, TaskFilter},
+    },
+    repositories::{labels::Label, tasks::Task, users::User},
+};
+
+use super::common::{fresh_db, seed_task, seed_user};
+
+async fn seed_label(db: &PgPool, user: &User, name: &str) -> Label {
+    let params = NewLabel {
+        name: name.into(),
+        colour: None,
+    };
+
+    Label::create(db, &params, user.pid).await.unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_handle_redundant_label_name() {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "labeller").await;
+    seed_label(&db, &user, "work").await;
+
+    let params = NewLabel {
+        name: "work".into(),
+        colour: Some("#ffffff".into()),
+    };
+
+    let result = Label::create(&db, &params, user.pid).await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn can_filter_tasks_by_labels() {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "labeller").await;
+
+    let both = seed_task(&db, &user, "Both labels").await;
+    let work_only = seed_task(&db, &user, "Work label only").await;
+    seed_task(&db, &user, "No labels at all").await;
+
+    let work = seed_label(&db, &user, "work").await;
+    let urgent = seed_label(&db, &user, "urgent").await;
+
+    Label::attach(&db, both.id, work.id, user.pid)
+        .await
+        .unwrap();
+    Label::attach(&db, both.id, urgent.id, user.pid)
+        .await
+        .unwrap();
+    Label::attach(&db, work_only.id, work.id, user.pid)
+        .await
+        .unwrap();
+
+    let all = TaskFilter {
+        label: Some("work, urgent".into()),
+        label_match: LabelMatch::All,
+        assigned: None,
+        done: None,
+    };
+    let any = TaskFilter {
+        label_match: LabelMatch::Any,
+        ..all.clone()
+    };
+
+    let all = Task::find_all(&db, user.pid, &all).await.unwrap();
+    let any = Task::find_all(&db, user.pid, &any).await.unwrap();
+
+    assert_eq!(all.iter().map(|t| t.id).collect::<Vec<_>>(), vec![both.id]);
+    assert_eq!(
+        any.iter().map(|t| t.id).collect::<Vec<_>>(),
+        vec![both.id, work_only.id]
+    );
+}
@@ -1 +1,13 @@
+mod attachments;
+mod common;
+mod idempotency;
+mod inbound;
+mod jobs;
+mod labels;
+mod notifications;
+mod outbox;
+mod projects;
+mod shares;
+mod tasks;
 mod users;
+mod webhooks;
@@ -0,0 +1,305 @@
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use serial_test::serial;
+use sqlx::PgPool;
+use tasks_authenticated::{
+    events::{self, Event, EventBus},
+    models::{
+        comments::NewComment,
+        notifications::{NotificationKind, NotificationQuery, UpdatePreference},
+        shares::NewShare,
+        tasks::{DeleteOptions, UpdateOptions, UpdateTask},
+        webhooks::{NewWebhook, WebhookEvent},
+    },
+    repositories::{
+        comments::Comment,
+        notifications::{Notification, NotificationPreference},
+        outbox::OutboxEvent,
+        permissions::Permission,
+        shares::{Share, ShareTarget},
+        tasks::Task,
+        users::User,
+        webhooks::Webhook,
+    },
+    storage::LocalStore,
+};
+
+use super::common::{fresh_db, outbox, seed_task, seed_user};
+
+/// Relays the outbox until it is empty, including the events relaying adds.
+async fn relay_all(db: &PgPool) {
+    while OutboxEvent::relay(db, 100, &outbox()).await.unwrap() > 0 {}
+}
+
+async fn setup() -> (PgPool, User, User, Task) {
+    let (_, db) = fresh_db().await;
+    let owner = seed_user(&db, "owner").await;
+    let helper = seed_user(&db, "helper").await;
+    let task = seed_task(&db, &owner, "Plan the trip").await;
+
+    let share = NewShare {
+        username: "helper".into(),
+        permission: Permission::Editor,
+    };
+    Share::grant(&db, ShareTarget::Task(task.id), &share, owner.pid)
+        .await
+        .unwrap();
+
+    (db, owner, helper, task)
+}
+
+#[tokio::test]
+#[serial]
+async fn notifies_shares_assignments_and_mentions() {
+    let (db, owner, helper, task) = setup().await;
+    let bus = EventBus::new();
+    let mut receiver = bus.subscribe();
+    events::listen(&db, bus).await.unwrap();
+
+    Task::assign(&db, task.id, Some("helper"), owner.pid)
+        .await
+        .unwrap();
+    let comment = NewComment {
+        body: "@helper can you book it?".into(),
+    };
+    Comment::create(&db, task.id, &comment, owner.pid)
+        .await
+        .unwrap();
+    relay_all(&db).await;
+
+    let query = NotificationQuery::default();
+    let inbox = Notification::find_all(&db, helper.pid, &query)
+        .await
+        .unwrap();
+    let kinds = inbox
+        .iter()
+        .map(|notification| notification.kind.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(kinds, ["mentioned", "assigned", "shared"]);
+    assert_eq!(inbox[1].message, "owner assigned \"Plan the trip\" to you");
+    assert_eq!(inbox[1].actor_pid, Some(owner.pid));
+    assert!(
+        Notification::find_all(&db, owner.pid, &query)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // Each in-app notification is pushed to the recipient
+    let mut pushed = Vec::new();
+    while pushed.len() < 3 {
+        let event = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        if let Event::Notified {
+            notification_id,
+            user_pid,
+            ..
+        } = event
+        {
+            assert_eq!(user_pid, helper.pid);
+            pushed.push(notification_id);
+        }
+    }
+    pushed.sort_unstable();
+    assert_eq!(pushed, [inbox[2].id, inbox[1].id, inbox[0].id]);
+}
+
+#[tokio::test]
+#[serial]
+async fn sends_nothing_about_trashed_tasks() {
+    let (db, owner, helper, task) = setup().await;
+    let blobs = LocalStore::new(std::env::temp_dir());
+
+    Task::assign(&db, task.id, Some("helper"), owner.pid)
+        .await
+        .unwrap();
+    Task::delete_by_id(&db, &blobs, task.id, owner.pid, &DeleteOptions::default())
+        .await
+        .unwrap();
+    relay_all(&db).await;
+
+    let inbox = Notification::find_all(&db, helper.pid, &NotificationQuery::default())
+        .await
+        .unwrap();
+    assert!(inbox.is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn marks_notifications_read_and_pages_the_inbox() {
+    let (db, owner, helper, task) = setup().await;
+    for i in 0..3 {
+        let comment = NewComment {
+            body: format!("@helper ping {i}"),
+        };
+        Comment::create(&db, task.id, &comment, owner.pid)
+            .await
+            .unwrap();
+    }
+    relay_all(&db).await;
+    assert_eq!(
+        Notification::unread_count(&db, helper.pid).await.unwrap(),
+        4
+    );
+
+    let query = NotificationQuery {
+        limit: Some(3),
+        ..Default::default()
+    };
+    let first = Notification::find_all(&db, helper.pid, &query)
+        .await
+        .unwrap();
+    assert_eq!(first.len(), 3);
+    let query = NotificationQuery {
+        before: Some(first[2].id),
+        limit: Some(3),
+        ..Default::default()
+    };
+    let second = Notification::find_all(&db, helper.pid, &query)
+        .await
+        .unwrap();
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0].kind, "shared");
+
+    let read = Notification::mark(&db, first[0].id, helper.pid, true)
+        .await
+        .unwrap();
+    assert!(read.read_at.is_some());
+    assert_eq!(
+        Notification::unread_count(&db, helper.pid).await.unwrap(),
+        3
+    );
+    let query = NotificationQuery {
+        unread: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        Notification::find_all(&db, helper.pid, &query)
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+
+    let unread = Notification::mark(&db, first[0].id, helper.pid, false)
+        .await
+        .unwrap();
+    assert!(unread.read_at.is_none());
+    assert!(
+        Notification::mark(&db, first[0].id, owner.pid, true)
+            .await
+            .is_err()
+    );
+
+    assert_eq!(
+        Notification::mark_all_read(&db, helper.pid).await.unwrap(),
+        4
+    );
+    assert_eq!(
+        Notification::unread_count(&db, helper.pid).await.unwrap(),
+        0
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn sends_notifications_on_the_chosen_channels() {
+    let (db, owner, helper, task) = setup().await;
+    let params = NewWebhook {
+        url: "https://example.com/hook".into(),
+        events: vec![WebhookEvent::Notification],
+    };
+    Webhook::create(&db, &params, helper.pid).await.unwrap();
+
+    let params = UpdatePreference {
+        in_app: Some(false),
+        email: Some(true),
+        webhook: Some(true),
+    };
+    let preference =
+        NotificationPreference::update(&db, helper.pid, NotificationKind::Assigned, &params)
+            .await
+            .unwrap();
+    assert!(!preference.in_app && preference.email && preference.webhook);
+
+    let preferences = NotificationPreference::find_all(&db, helper.pid)
+        .await
+        .unwrap();
+    assert_eq!(preferences.len(), NotificationKind::ALL.len());
+    let shared = preferences.iter().find(|p| p.kind == "shared").unwrap();
+    assert!(shared.in_app && !shared.email && !shared.webhook);
+
+    Task::assign(&db, task.id, Some("helper"), owner.pid)
+        .await
+        .unwrap();
+    relay_all(&db).await;
+
+    let inbox = Notification::find_all(&db, helper.pid, &NotificationQuery::default())
+        .await
+        .unwrap();
+    assert_eq!(inbox.len(), 1);
+    assert_eq!(inbox[0].kind, "shared");
+
+    let emails = sqlx::query_scalar::<_, serde_json::Value>(
+        "SELECT payload FROM jobs WHERE kind = 'notifications.email'",
+    )
+    .fetch_all(&db)
+    .await
+    .unwrap();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(
+        emails[0]["message"],
+        "owner assigned \"Plan the trip\" to you"
+    );
+
+    let deliveries = sqlx::query_scalar::<_, serde_json::Value>(
+        "SELECT payload FROM webhook_deliveries WHERE event = 'notification'",
+    )
+    .fetch_all(&db)
+    .await
+    .unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0]["kind"], "assigned");
+    assert_eq!(deliveries[0]["notificationId"], serde_json::Value::Null);
+}
+
+#[tokio::test]
+#[serial]
+async fn reminds_of_tasks_due_soon_once_per_due_date() {
+    let (db, owner, helper, task) = setup().await;
+    let lead = TimeDelta::hours(1);
+    Task::assign(&db, task.id, Some("helper"), owner.pid)
+        .await
+        .unwrap();
+    seed_task(&db, &owner, "Not due").await;
+
+    let due = |minutes| UpdateTask {
+        due_at: Some(Some(
+            (Utc::now() + TimeDelta::minutes(minutes)).fixed_offset(),
+        )),
+        ..Default::default()
+    };
+    Task::update_by_id(&db, &due(30), task.id, owner.pid, &UpdateOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(Notification::queue_reminders(&db, lead).await.unwrap(), 1);
+    assert_eq!(Notification::queue_reminders(&db, lead).await.unwrap(), 0);
+    relay_all(&db).await;
+
+    let query = NotificationQuery::default();
+    let inbox = Notification::find_all(&db, helper.pid, &query)
+        .await
+        .unwrap();
+    assert_eq!(inbox[0].kind, "reminder");
+    assert!(inbox[0].message.starts_with("\"Plan the trip\" is due"));
+
+    // Moving the due date reminds again
+    Task::update_by_id(&db, &due(45), task.id, owner.pid, &UpdateOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(Notification::queue_reminders(&db, lead).await.unwrap(), 1);
+}
@@ -0,0 +1,169 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use serial_test::serial;
+use sqlx::{PgPool, postgres::PgListener};
+use tasks_authenticated::{
+    events::{EVENTS_CHANNEL, Envelope, Event},
+    models::tasks::{UpdateOptions, UpdateTask},
+    repositories::{ModelError, outbox::OutboxEvent, tasks::Task, users::User},
+};
+
+use super::common::{fresh_db, outbox, register, seed_task, seed_user};
+
+async fn setup() -> (PgPool, User) {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "tasker").await;
+
+    (db, user)
+}
+
+async fn unpublished(db: &PgPool) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM outbox_events WHERE published_at IS NULL")
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn relays_recorded_events_in_order_once() {
+    let (db, user) = setup().await;
+    let mut listener = PgListener::connect_with(&db).await.unwrap();
+    listener.listen(EVENTS_CHANNEL).await.unwrap();
+
+    let task = seed_task(&db, &user, "Draft").await;
+    let params = UpdateTask {
+        done: Some(true),
+        ..Default::default()
+    };
+    Task::update_by_id(&db, &params, task.id, user.pid, &UpdateOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(unpublished(&db).await, 3);
+
+    assert_eq!(OutboxEvent::relay(&db, 2, &outbox()).await.unwrap(), 2);
+    assert_eq!(OutboxEvent::relay(&db, 2, &outbox()).await.unwrap(), 1);
+    assert_eq!(OutboxEvent::relay(&db, 2, &outbox()).await.unwrap(), 0);
+    assert_eq!(unpublished(&db).await, 0);
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        let notification = tokio::time::timeout(Duration::from_secs(5), listener.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        received.push(serde_json::from_str::<Envelope>(notification.payload()).unwrap());
+    }
+    assert!(received.windows(2).all(|pair| pair[0].id < pair[1].id));
+    let kinds = received
+        .iter()
+        .map(|envelope| envelope.kind.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(kinds, ["userCreated", "taskCreated", "taskUpdated"]);
+
+    // Listeners load the events by id
+    let event = OutboxEvent::find_event(&db, received[2].id).await.unwrap();
+    assert!(matches!(event, Event::TaskUpdated { task_id, .. } if task_id == task.id));
+}
+
+#[tokio::test]
+#[serial]
+async fn dead_letters_events_that_cannot_be_published() {
+    let (db, user) = setup().await;
+    sqlx::query(
+        "
+        INSERT INTO outbox_events (aggregate_type, aggregate_id, payload)
+        VALUES ('task', '0', '{\"type\": \"unknown\"}')
+        ",
+    )
+    .execute(&db)
+    .await
+    .unwrap();
+    seed_task(&db, &user, "Published anyway").await;
+
+    // The broken event does not hold back the others
+    assert_eq!(OutboxEvent::relay(&db, 100, &outbox()).await.unwrap(), 2);
+    assert_eq!(unpublished(&db).await, 1);
+
+    let (attempts, dead) = sqlx::query_as::<_, (i32, bool)>(
+        "SELECT attempts, dead_at IS NOT NULL FROM outbox_events WHERE published_at IS NULL",
+    )
+    .fetch_one(&db)
+    .await
+    .unwrap();
+    assert_eq!(attempts, 1);
+    assert!(dead);
+    assert_eq!(OutboxEvent::relay(&db, 100, &outbox()).await.unwrap(), 0);
+}
+
+#[tokio::test]
+#[serial]
+async fn events_waiting_for_a_retry_hold_back_their_aggregate() {
+    let (db, user) = setup().await;
+    let task = seed_task(&db, &user, "Failed before").await;
+    sqlx::query(
+        "
+        UPDATE outbox_events SET attempts = 1, next_attempt_at = NOW() + INTERVAL '1 minute'
+        WHERE payload->>'type' = 'taskCreated'
+        ",
+    )
+    .execute(&db)
+    .await
+    .unwrap();
+    let params = UpdateTask {
+        done: Some(true),
+        ..Default::default()
+    };
+    Task::update_by_id(&db, &params, task.id, user.pid, &UpdateOptions::default())
+        .await
+        .unwrap();
+    seed_task(&db, &user, "Unrelated").await;
+
+    // Only the user and the unrelated task are published
+    assert_eq!(OutboxEvent::relay(&db, 100, &outbox()).await.unwrap(), 2);
+    assert_eq!(unpublished(&db).await, 2);
+
+    sqlx::query("UPDATE outbox_events SET next_attempt_at = NOW() - INTERVAL '1 second'")
+        .execute(&db)
+        .await
+        .unwrap();
+    assert_eq!(OutboxEvent::relay(&db, 100, &outbox()).await.unwrap(), 2);
+    assert_eq!(unpublished(&db).await, 0);
+}
+
+#[tokio::test]
+#[serial]
+async fn records_nothing_for_changes_rolled_back() {
+    let (db, user) = setup().await;
+
+    let result = User::create_with_password(&db, &register("tasker")).await;
+    assert!(matches!(result, Err(ModelError::UsernameTaken)));
+
+    let result = Task::assign(&db, 0, None, user.pid).await;
+    assert!(result.is_err());
+
+    assert_eq!(unpublished(&db).await, 1);
+}
+
+#[tokio::test]
+#[serial]
+async fn concurrent_relays_publish_each_event_once() {
+    let (db, user) = setup().await;
+    for i in 0..10 {
+        seed_task(&db, &user, &format!("Task {i}")).await;
+    }
+
+    let config = outbox();
+    let (a, b) = tokio::join!(
+        OutboxEvent::relay(&db, 100, &config),
+        OutboxEvent::relay(&db, 100, &config)
+    );
+    assert_eq!(a.unwrap() + b.unwrap(), 11);
+    assert_eq!(unpublished(&db).await, 0);
+
+    let purged = OutboxEvent::purge(&db, Utc::now().fixed_offset())
+        .await
+        .unwrap();
+    assert_eq!(purged, 11);
+}
@@ -0,0 +1,74 @@
+use serial_test::serial;
+use tasks_authenticated::{
+    models::{
+        projects::NewProject,
+        tasks::{MoveTask, NewTask},
+    },
+    repositories::{projects::Project, tasks::Task},
+};
+
+use super::common::{fresh_db, seed_user};
+
+#[tokio::test]
+#[serial]
+async fn can_move_task_between_projects_without_gaps() {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "planner").await;
+
+    let params = NewProject {
+        name: "Chores".into(),
+        description: None,
+    };
+    let chores = Project::create(&db, &params, user.pid).await.unwrap();
+
+    let mut tasks = Vec::new();
+    for title in ["First task", "Second task", "Third task"] {
+        let params = NewTask {
+            title: title.into(),
+            done: false,
+            project_id: Some(chores.id),
+            parent_id: None,
+            due_at: None,
+            recurrence: None,
+        };
+        tasks.push(Task::create_task(&db, &params, user.pid).await.unwrap());
+    }
+
+    // Third to the top of the same project
+    let params = MoveTask {
+        project_id: Some(chores.id),
+        position: Some(0),
+    };
+    Task::move_task(&db, &params, tasks[2].id, user.pid)
+        .await
+        .unwrap();
+
+    let ordered = Project::tasks(&db, user.pid, chores.id).await.unwrap();
+    assert_eq!(
+        ordered
+            .iter()
+            .map(|t| (t.id, t.position))
+            .collect::<Vec<_>>(),
+        vec![(tasks[2].id, 0), (tasks[0].id, 1), (tasks[1].id, 2)]
+    );
+
+    // First out to the inbox
+    let params = MoveTask {
+        project_id: None,
+        position: None,
+    };
+    let moved = Task::move_task(&db, &params, tasks[0].id, user.pid)
+        .await
+        .unwrap();
+    assert_eq!(moved.project_id, None);
+    assert_eq!(moved.position, 0);
+
+    let ordered = Project::tasks(&db, user.pid, chores.id).await.unwrap();
+    assert_eq!(
+        ordered
+            .iter()
+            .map(|t| (t.id, t.position))
+            .collect::<Vec<_>>(),
+        vec![(tasks[2].id, 0), (tasks[1].id, 1)]
+    );
+}
@@ -0,0 +1,120 @@
+use serial_test::serial;
+use tasks_authenticated::{
+    models::{
+        shares::NewShare,
+        tasks::{Assigned, DeleteOptions, TaskFilter, UpdateOptions, UpdateTask},
+    },
+    repositories::{
+        ModelError,
+        permissions::Permission,
+        shares::{Share, ShareTarget},
+        tasks::Task,
+    },
+    storage::LocalStore,
+};
+
+use super::common::{fresh_db, seed_task, seed_user};
+
+fn update(title: &str) -> UpdateTask {
+    UpdateTask {
+        title: Some(title.into()),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn permission_level_limits_what_a_share_allows() {
+    let (_, db) = fresh_db().await;
+
+    let owner = seed_user(&db, "owner").await;
+    let friend = seed_user(&db, "friend").await;
+    let task = seed_task(&db, &owner, "Shared task").await;
+
+    // Without a share the task does not exist for the friend
+    let result = Task::find_by_id(&db, friend.pid, task.id).await;
+    assert!(matches!(result, Err(ModelError::EntityNotFound)));
+
+    let params = NewShare {
+        username: "friend".into(),
+        permission: Permission::Viewer,
+    };
+    Share::grant(&db, ShareTarget::Task(task.id), &params, owner.pid)
+        .await
+        .unwrap();
+
+    assert!(Task::find_by_id(&db, friend.pid, task.id).await.is_ok());
+    let result = Task::update_by_id(
+        &db,
+        &update("Renamed"),
+        task.id,
+        friend.pid,
+        &UpdateOptions::default(),
+    )
+    .await;
+    assert!(matches!(result, Err(ModelError::Forbidden)));
+
+    let params = NewShare {
+        username: "friend".into(),
+        permission: Permission::Editor,
+    };
+    Share::grant(&db, ShareTarget::Task(task.id), &params, owner.pid)
+        .await
+        .unwrap();
+
+    let updated = Task::update_by_id(
+        &db,
+        &update("Renamed"),
+        task.id,
+        friend.pid,
+        &UpdateOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(updated.title, "Renamed");
+
+    // Editors can neither delete nor reshare
+    let blobs = LocalStore::new(std::env::temp_dir());
+    let result =
+        Task::delete_by_id(&db, &blobs, task.id, friend.pid, &DeleteOptions::default()).await;
+    assert!(matches!(result, Err(ModelError::Forbidden)));
+
+    let shared = Task::find_shared(&db, friend.pid).await.unwrap();
+    assert_eq!(shared.len(), 1);
+    assert_eq!(shared[0].id, task.id);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_only_assign_users_with_access() {
+    let (_, db) = fresh_db().await;
+
+    let owner = seed_user(&db, "owner").await;
+    let friend = seed_user(&db, "friend").await;
+    let task = seed_task(&db, &owner, "Shared task").await;
+
+    let result = Task::assign(&db, task.id, Some("friend"), owner.pid).await;
+    assert!(matches!(result, Err(ModelError::AssigneeNoAccess)));
+
+    let params = NewShare {
+        username: "friend".into(),
+        permission: Permission::Viewer,
+    };
+    Share::grant(&db, ShareTarget::Task(task.id), &params, owner.pid)
+        .await
+        .unwrap();
+
+    let (assigned, previous) = Task::assign(&db, task.id, Some("friend"), owner.pid)
+        .await
+        .unwrap();
+    assert_eq!(assigned.assignee_pid, Some(friend.pid));
+    assert_eq!(previous, None);
+
+    let filter = TaskFilter {
+        assigned: Some(Assigned::Me),
+        ..TaskFilter::default()
+    };
+    let mine = Task::find_all(&db, friend.pid, &filter).await.unwrap();
+    assert_eq!(mine.len(), 1);
+    assert_eq!(mine[0].id, task.id);
+}
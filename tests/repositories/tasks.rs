@@ -0,0 +1,559 @@
+use std::time::Duration;
+
+use serde_json::json;
+use serial_test::serial;
+use tasks_authenticated::{
+    events::{self, Event, EventBus},
+    models::{
+        bulk::{BulkMode, BulkOperation, BulkRequest},
+        import::{self, ImportQuery},
+        projects::NewProject,
+        shares::NewShare,
+        sync::{SyncChange, SyncToken},
+        tasks::{DeleteOptions, MoveTask, NewTask, UpdateOptions, UpdateTask, VersionMatch},
+    },
+    repositories::{
+        ModelError,
+        dependencies::TaskDependency,
+        history::TaskHistory,
+        labels::Label,
+        permissions::Permission,
+        projects::Project,
+        shares::{Share, ShareTarget},
+        tasks::{BulkApplied, Task},
+    },
+    storage::LocalStore,
+};
+
+use super::common::{fresh_db, new_task, seed_subtask, seed_task, seed_user};
+
+#[tokio::test]
+#[serial]
+async fn cannot_nest_task_under_its_subtask() {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "tasker").await;
+
+    let root = seed_task(&db, &user, "Root task").await;
+    let child = seed_subtask(&db, &user, "Child task", root.id).await;
+
+    let result = Task::set_parent(&db, root.id, Some(child.id), user.pid).await;
+
+    assert!(matches!(result, Err(ModelError::TaskCycle)));
+}
+
+#[tokio::test]
+#[serial]
+async fn can_delete_subtree_only_with_cascade() {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "tasker").await;
+
+    let root = seed_task(&db, &user, "Root task").await;
+    let child = seed_subtask(&db, &user, "Child task", root.id).await;
+    seed_subtask(&db, &user, "Grandchild task", child.id).await;
+
+    let blobs = LocalStore::new(std::env::temp_dir());
+    let refused =
+        Task::delete_by_id(&db, &blobs, root.id, user.pid, &DeleteOptions::default()).await;
+    assert!(matches!(refused, Err(ModelError::HasSubtasks)));
+
+    let options = DeleteOptions {
+        cascade: true,
+        ..Default::default()
+    };
+    let deleted = Task::delete_by_id(&db, &blobs, root.id, user.pid, &options)
+        .await
+        .unwrap();
+    assert_eq!(deleted.rows_affected(), 3);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_restore_trashed_subtree() {
+    let (_, db) = fresh_db().await;
+    let blobs = LocalStore::new(std::env::temp_dir());
+    let user = seed_user(&db, "tasker").await;
+
+    let root = seed_task(&db, &user, "Root task").await;
+    let child = seed_subtask(&db, &user, "Child task", root.id).await;
+    seed_task(&db, &user, "Other task").await;
+
+    let options = DeleteOptions {
+        cascade: true,
+        ..Default::default()
+    };
+    Task::delete_by_id(&db, &blobs, root.id, user.pid, &options)
+        .await
+        .unwrap();
+
+    let result = Task::find_by_id(&db, user.pid, child.id).await;
+    assert!(matches!(result, Err(ModelError::EntityNotFound)));
+
+    let trashed = Task::find_trashed(&db, user.pid).await.unwrap();
+    assert_eq!(trashed.len(), 2);
+
+    let restored = Task::restore(&db, root.id, user.pid).await.unwrap();
+    assert!(restored.deleted_at.is_none());
+    assert_eq!(restored.position, 1);
+
+    let child = Task::find_by_id(&db, user.pid, child.id).await.unwrap();
+    assert_eq!(child.parent_id, Some(root.id));
+    assert!(Task::find_trashed(&db, user.pid).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_add_cyclic_dependency() {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "tasker").await;
+
+    let design = seed_task(&db, &user, "Design schema").await;
+    let build = seed_task(&db, &user, "Build service").await;
+    let ship = seed_task(&db, &user, "Ship release").await;
+
+    TaskDependency::add(&db, build.id, design.id, user.pid)
+        .await
+        .unwrap();
+    TaskDependency::add(&db, ship.id, build.id, user.pid)
+        .await
+        .unwrap();
+
+    let result = TaskDependency::add(&db, design.id, ship.id, user.pid).await;
+
+    assert!(matches!(result, Err(ModelError::DependencyCycle)));
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_complete_blocked_task_unless_forced() {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "tasker").await;
+
+    let design = seed_task(&db, &user, "Design schema").await;
+    let build = seed_task(&db, &user, "Build service").await;
+    TaskDependency::add(&db, build.id, design.id, user.pid)
+        .await
+        .unwrap();
+
+    let params = UpdateTask {
+        done: Some(true),
+        ..Default::default()
+    };
+
+    let blocked =
+        Task::update_by_id(&db, &params, build.id, user.pid, &UpdateOptions::default()).await;
+    assert!(matches!(blocked, Err(ModelError::Blocked(1))));
+
+    let options = UpdateOptions {
+        force: true,
+        ..Default::default()
+    };
+    let forced = Task::update_by_id(&db, &params, build.id, user.pid, &options)
+        .await
+        .unwrap();
+    assert!(forced.done);
+}
+
+#[tokio::test]
+#[serial]
+async fn records_history_of_task_changes() {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "tasker").await;
+
+    let task = seed_task(&db, &user, "Draft").await;
+    let params = UpdateTask {
+        title: Some("Final".into()),
+        ..Default::default()
+    };
+    Task::update_by_id(&db, &params, task.id, user.pid, &UpdateOptions::default())
+        .await
+        .unwrap();
+
+    let history = TaskHistory::find_all(&db, task.id, user.pid).await.unwrap();
+
+    let actions = history
+        .iter()
+        .map(|entry| entry.action.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(actions, ["created", "updated"]);
+    assert_eq!(history[1].actor.as_deref(), Some("tasker"));
+    assert_eq!(history[1].changes.0["title"]["to"], "Final");
+    assert!(history[1].changes.0.get("done").is_none());
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_update_stale_version() {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "tasker").await;
+
+    let task = seed_task(&db, &user, "Draft").await;
+    let params = UpdateTask {
+        title: Some("Final".into()),
+        ..Default::default()
+    };
+    let options = UpdateOptions {
+        if_match: Some(VersionMatch::Tags(vec![(false, task.version)])),
+        ..Default::default()
+    };
+
+    let updated = Task::update_by_id(&db, &params, task.id, user.pid, &options)
+        .await
+        .unwrap();
+    assert_eq!(updated.version, task.version + 1);
+
+    let stale = Task::update_by_id(&db, &params, task.id, user.pid, &options).await;
+    assert!(matches!(stale, Err(ModelError::PreconditionFailed)));
+}
+
+#[tokio::test]
+#[serial]
+async fn bulk_rolls_back_atomic_batches_only() {
+    let (_, db) = fresh_db().await;
+    let blobs = LocalStore::new(std::env::temp_dir());
+    let user = seed_user(&db, "tasker").await;
+
+    let task = seed_task(&db, &user, "Finished task").await;
+    let operations = vec![
+        BulkOperation::Delete {
+            id: task.id,
+            cascade: false,
+            permanent: false,
+        },
+        BulkOperation::Delete {
+            id: task.id + 1000,
+            cascade: false,
+            permanent: false,
+        },
+    ];
+
+    let atomic = BulkRequest {
+        mode: BulkMode::Atomic,
+        operations: operations.clone(),
+        selector: None,
+    };
+    let (committed, results) = Task::bulk(&db, &blobs, &atomic, user.pid).await.unwrap();
+    assert!(!committed);
+    assert!(matches!(results[0].1, Err(ModelError::RolledBack)));
+    assert!(matches!(results[1].1, Err(ModelError::EntityNotFound)));
+    assert!(Task::find_by_id(&db, user.pid, task.id).await.is_ok());
+
+    let best_effort = BulkRequest {
+        mode: BulkMode::BestEffort,
+        operations,
+        selector: None,
+    };
+    let (committed, results) = Task::bulk(&db, &blobs, &best_effort, user.pid)
+        .await
+        .unwrap();
+    assert!(committed);
+    assert!(matches!(results[0].1, Ok(BulkApplied::Deleted(id)) if id == task.id));
+    assert!(matches!(results[1].1, Err(ModelError::EntityNotFound)));
+    assert!(Task::find_by_id(&db, user.pid, task.id).await.is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn import_creates_subtasks_and_labels() {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "tasker").await;
+
+    let query = serde_json::from_value::<ImportQuery>(json!({ "format": "json" })).unwrap();
+    // Subtasks may come before their parent
+    let body = json!([
+        { "id": 2, "parentId": 1, "title": "Book the flights", "labels": [{ "name": "travel" }] },
+        { "id": 1, "title": "Plan the trip", "done": true, "labels": [{ "name": "travel" }] }
+    ]);
+    let tasks = import::parse(&query, body.to_string().as_bytes()).unwrap();
+    assert!(import::check(&tasks).is_empty());
+
+    let created = Task::import(&db, &tasks, user.pid).await.unwrap();
+
+    assert_eq!(created.len(), 2);
+    assert_eq!(created[0].parent_id, Some(created[1].id));
+    assert!(created[1].done);
+    let labels = Label::find_all(&db, user.pid).await.unwrap();
+    assert_eq!(labels.len(), 1);
+    assert_eq!(labels[0].name, "travel");
+}
+
+#[tokio::test]
+#[serial]
+async fn sends_task_changes_to_listeners() {
+    let (config, db) = fresh_db().await;
+    let user = seed_user(&db, "tasker").await;
+
+    let bus = EventBus::new();
+    let mut receiver = bus.subscribe();
+    events::listen(&db, bus).await.unwrap();
+    events::relay(&db, config.outbox()).await.unwrap();
+    let mut next = async || {
+        tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    };
+
+    // Recorded before anyone listened, the sign up is still published
+    assert_eq!(next().await, Event::UserCreated { user_pid: user.pid });
+
+    let task = seed_task(&db, &user, "Draft").await;
+    assert_eq!(
+        next().await,
+        Event::TaskCreated {
+            task_id: task.id,
+            audience: vec![user.pid]
+        }
+    );
+
+    let params = UpdateTask {
+        title: Some("Final".into()),
+        ..Default::default()
+    };
+    for _ in 0..2 {
+        Task::update_by_id(&db, &params, task.id, user.pid, &UpdateOptions::default())
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        next().await,
+        Event::TaskUpdated {
+            task_id: task.id,
+            audience: vec![user.pid]
+        }
+    );
+
+    // The second update changed nothing, so the next event is the delete
+    let blobs = LocalStore::new(std::env::temp_dir());
+    let options = DeleteOptions {
+        permanent: true,
+        ..Default::default()
+    };
+    Task::delete_by_id(&db, &blobs, task.id, user.pid, &options)
+        .await
+        .unwrap();
+    assert_eq!(
+        next().await,
+        Event::TaskDeleted {
+            task_id: task.id,
+            audience: vec![user.pid]
+        }
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn syncs_changes_and_deletes_since_token() {
+    let (_, db) = fresh_db().await;
+    let blobs = LocalStore::new(std::env::temp_dir());
+    let user = seed_user(&db, "tasker").await;
+
+    let kept = seed_task(&db, &user, "Kept").await;
+    let trashed = seed_task(&db, &user, "Trashed").await;
+    let purged = seed_task(&db, &user, "Purged").await;
+
+    // A first page, then the rest
+    let first = Task::changes_since(&db, user.pid, SyncToken::default(), 2)
+        .await
+        .unwrap();
+    assert!(first.has_more);
+    assert_eq!(first.changed.len(), 2);
+    let rest = Task::changes_since(&db, user.pid, first.next, 2)
+        .await
+        .unwrap();
+    assert!(!rest.has_more);
+    assert_eq!(rest.changed.len(), 1);
+    assert_eq!(rest.changed[0].id, purged.id);
+
+    let params = UpdateTask {
+        title: Some("Kept and renamed".into()),
+        ..Default::default()
+    };
+    Task::update_by_id(&db, &params, kept.id, user.pid, &UpdateOptions::default())
+        .await
+        .unwrap();
+    Task::delete_by_id(&db, &blobs, trashed.id, user.pid, &DeleteOptions::default())
+        .await
+        .unwrap();
+    let options = DeleteOptions {
+        permanent: true,
+        ..Default::default()
+    };
+    Task::delete_by_id(&db, &blobs, purged.id, user.pid, &options)
+        .await
+        .unwrap();
+
+    let changes = Task::changes_since(&db, user.pid, rest.next, 500)
+        .await
+        .unwrap();
+    let changed = changes
+        .changed
+        .iter()
+        .map(|task| task.title.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(changed, ["Kept and renamed"]);
+    let mut deleted = changes.deleted.clone();
+    deleted.sort_unstable();
+    assert_eq!(deleted, [trashed.id, purged.id]);
+
+    let caught_up = Task::changes_since(&db, user.pid, changes.next, 500)
+        .await
+        .unwrap();
+    assert!(caught_up.changed.is_empty() && caught_up.deleted.is_empty());
+    assert_eq!(caught_up.next, changes.next);
+}
+
+#[tokio::test]
+#[serial]
+async fn syncs_shared_tasks_until_access_is_revoked() {
+    let (_, db) = fresh_db().await;
+    let owner = seed_user(&db, "owner").await;
+    let friend = seed_user(&db, "friend").await;
+
+    let shared = seed_task(&db, &owner, "Shared").await;
+    let subtask = seed_subtask(&db, &owner, "Shared subtask", shared.id).await;
+    seed_task(&db, &owner, "Private").await;
+
+    let params = NewShare {
+        username: "friend".into(),
+        permission: Permission::Viewer,
+    };
+    Share::grant(&db, ShareTarget::Task(shared.id), &params, owner.pid)
+        .await
+        .unwrap();
+
+    let changes = Task::changes_since(&db, friend.pid, SyncToken::default(), 500)
+        .await
+        .unwrap();
+    let mut changed = changes
+        .changed
+        .iter()
+        .map(|task| task.id)
+        .collect::<Vec<i32>>();
+    changed.sort_unstable();
+    assert_eq!(changed, [shared.id, subtask.id]);
+    assert!(changes.deleted.is_empty());
+
+    Share::revoke(&db, ShareTarget::Task(shared.id), "friend", owner.pid)
+        .await
+        .unwrap();
+
+    let revoked = Task::changes_since(&db, friend.pid, changes.next, 500)
+        .await
+        .unwrap();
+    assert!(revoked.changed.is_empty());
+    let mut deleted = revoked.deleted.clone();
+    deleted.sort_unstable();
+    assert_eq!(deleted, [shared.id, subtask.id]);
+
+    // Sharing again brings the tasks back
+    Share::grant(&db, ShareTarget::Task(shared.id), &params, owner.pid)
+        .await
+        .unwrap();
+    let regained = Task::changes_since(&db, friend.pid, SyncToken::default(), 500)
+        .await
+        .unwrap();
+    assert_eq!(regained.changed.len(), 2);
+    assert!(regained.deleted.is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn syncs_tasks_moved_away_from_shared_projects_as_deletes() {
+    let (_, db) = fresh_db().await;
+    let owner = seed_user(&db, "owner").await;
+    let friend = seed_user(&db, "friend").await;
+
+    let params = NewProject {
+        name: "Shared".into(),
+        description: None,
+    };
+    let project = Project::create(&db, &params, owner.pid).await.unwrap();
+    let shared = seed_task(&db, &owner, "Shared parent").await;
+    let params = NewShare {
+        username: "friend".into(),
+        permission: Permission::Viewer,
+    };
+    Share::grant(&db, ShareTarget::Project(project.id), &params, owner.pid)
+        .await
+        .unwrap();
+    Share::grant(&db, ShareTarget::Task(shared.id), &params, owner.pid)
+        .await
+        .unwrap();
+
+    let in_project = |title: &str| NewTask {
+        project_id: Some(project.id),
+        ..new_task(title)
+    };
+    let moved = Task::create_task(&db, &in_project("Moved out"), owner.pid)
+        .await
+        .unwrap();
+    let kept = Task::create_task(&db, &in_project("Left when deleted"), owner.pid)
+        .await
+        .unwrap();
+    let nested = seed_subtask(&db, &owner, "Nested", moved.id).await;
+    let detached = seed_subtask(&db, &owner, "Detached", shared.id).await;
+
+    let changes = Task::changes_since(&db, friend.pid, SyncToken::default(), 500)
+        .await
+        .unwrap();
+    assert_eq!(changes.changed.len(), 5);
+
+    let params = MoveTask {
+        project_id: None,
+        position: None,
+    };
+    Task::move_task(&db, &params, moved.id, owner.pid)
+        .await
+        .unwrap();
+    Task::set_parent(&db, detached.id, None, owner.pid)
+        .await
+        .unwrap();
+    Project::delete_by_id(&db, project.id, owner.pid)
+        .await
+        .unwrap();
+
+    let revoked = Task::changes_since(&db, friend.pid, changes.next, 500)
+        .await
+        .unwrap();
+    assert!(revoked.changed.is_empty());
+    let mut deleted = revoked.deleted.clone();
+    deleted.sort_unstable();
+    assert_eq!(deleted, [moved.id, kept.id, nested.id, detached.id]);
+}
+
+#[tokio::test]
+#[serial]
+async fn sync_reports_stale_changes() {
+    let (_, db) = fresh_db().await;
+    let blobs = LocalStore::new(std::env::temp_dir());
+    let user = seed_user(&db, "tasker").await;
+
+    let edited = seed_task(&db, &user, "Edited on the server").await;
+    let removed = seed_task(&db, &user, "Removed offline").await;
+    let params = UpdateTask {
+        title: Some("Edited again".into()),
+        ..Default::default()
+    };
+    Task::update_by_id(&db, &params, edited.id, user.pid, &UpdateOptions::default())
+        .await
+        .unwrap();
+
+    let changes = serde_json::from_value::<Vec<SyncChange>>(json!([
+        { "op": "create", "clientId": "local-1", "task": { "title": "Made offline", "done": false } },
+        { "op": "update", "id": edited.id, "baseVersion": edited.version, "patch": { "done": true } },
+        { "op": "delete", "id": removed.id, "baseVersion": removed.version },
+        { "op": "update", "id": removed.id + 1000, "baseVersion": 1, "patch": { "done": true } },
+    ]))
+    .unwrap();
+
+    let results = Task::sync(&db, &blobs, &changes, user.pid).await.unwrap();
+
+    assert!(matches!(&results[0], Ok(BulkApplied::Created(task)) if task.title == "Made offline"));
+    assert!(matches!(results[1], Err(ModelError::PreconditionFailed)));
+    assert!(matches!(results[2], Ok(BulkApplied::Deleted(id)) if id == removed.id));
+    assert!(matches!(results[3], Err(ModelError::EntityNotFound)));
+
+    // The conflicting change was not applied
+    let edited = Task::find_by_id(&db, user.pid, edited.id).await.unwrap();
+    assert!(!edited.done);
+}
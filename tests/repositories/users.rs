@@ -1,10 +1,26 @@
+use chrono::{TimeDelta, Utc};
 use serial_test::serial;
 use tasks_authenticated::{
     AppConfig, AppEnvironment,
     context::JwtState,
-    models::auth::{LoginUser, RegisterUser},
-    repositories::users::User,
+    models::{
+        auth::{LoginUser, RegisterUser},
+        shares::NewShare,
+        tasks::{NewTask, UpdateOptions, UpdateTask},
+    },
+    repositories::{
+        ModelError,
+        account_exports::AccountExport,
+        history::TaskHistory,
+        idempotency::IdempotencyKey,
+        permissions::Permission,
+        shares::{Share, ShareTarget},
+        tasks::Task,
+        users::User,
+    },
+    storage::{BlobStore, LocalStore, StorageError},
 };
+use uuid::Uuid;
 
 async fn seed_data(config: &AppConfig) {
     config.db().recreate().await.unwrap();
@@ -107,3 +123,128 @@ async fn can_login_user() {
 
     assert!(result.is_ok());
 }
+
+#[tokio::test]
+#[serial]
+async fn can_erase_user_and_keep_other_users_tasks() {
+    let config = AppConfig::from_env(&AppEnvironment::Development).unwrap();
+    let db = config.db().connection_pool().unwrap();
+    seed_data(&config).await;
+    let blobs = LocalStore::new(std::env::temp_dir().join(format!("blobs-{}", Uuid::new_v4())));
+
+    let user1 = User::find_by_username(&db, "user1").await.unwrap();
+    let user2 = User::find_by_username(&db, "user2").await.unwrap();
+
+    let new_task = |title: &str| NewTask {
+        title: title.into(),
+        done: false,
+        project_id: None,
+        parent_id: None,
+        due_at: None,
+        recurrence: None,
+    };
+    let own = Task::create_task(&db, &new_task("Own task"), user1.pid)
+        .await
+        .unwrap();
+    let other = Task::create_task(&db, &new_task("Other task"), user2.pid)
+        .await
+        .unwrap();
+
+    // user1 edits a task of user2's, which shows up in its history
+    let share = NewShare {
+        username: "user1".into(),
+        permission: Permission::Editor,
+    };
+    Share::grant(&db, ShareTarget::Task(other.id), &share, user2.pid)
+        .await
+        .unwrap();
+    let update = UpdateTask {
+        title: Some("Edited by user1".into()),
+        ..Default::default()
+    };
+    Task::update_by_id(&db, &update, other.id, user1.pid, &UpdateOptions::default())
+        .await
+        .unwrap();
+
+    let export = AccountExport::create(&db, user1.pid).await.unwrap();
+    let export = export
+        .build(
+            &db,
+            &blobs,
+            (Utc::now() + TimeDelta::days(1)).fixed_offset(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(export.status, "ready");
+    let storage_key = export.storage_key.unwrap();
+
+    // Stored responses to user1's own requests and to its registration
+    let since = (Utc::now() - TimeDelta::hours(1)).fixed_offset();
+    for (user_pid, key) in [(user1.pid, "own"), (Uuid::nil(), "signup")] {
+        IdempotencyKey::reserve(&db, user_pid, key, "hash", since, since)
+            .await
+            .unwrap();
+        IdempotencyKey::complete(&db, user_pid, key, 201, Vec::new(), b"{}", Some(user1.pid))
+            .await
+            .unwrap();
+    }
+
+    User::erase(&db, &blobs, user1.pid).await.unwrap();
+
+    assert!(matches!(
+        User::find_by_pid(&db, user1.pid).await,
+        Err(ModelError::EntityNotFound)
+    ));
+    assert!(matches!(
+        blobs.get(&storage_key).await,
+        Err(StorageError::NotFound(_))
+    ));
+    for (user_pid, key) in [(user1.pid, "own"), (Uuid::nil(), "signup")] {
+        let record = IdempotencyKey::find(&db, user_pid, key, since)
+            .await
+            .unwrap();
+        assert!(record.is_none());
+    }
+
+    let owned =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM task_history WHERE task_id = $1")
+            .bind(own.id)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert_eq!(owned, 0);
+
+    let task = Task::find_by_id(&db, user2.pid, other.id).await.unwrap();
+    assert_eq!(task.title, "Edited by user1");
+
+    let history = TaskHistory::find_all(&db, other.id, user2.pid)
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 2);
+    assert!(
+        history
+            .iter()
+            .all(|entry| entry.actor_pid != Some(user1.pid))
+    );
+    assert_eq!(history[1].actor_pid, None);
+
+    // The history stays immutable outside of an erasure
+    let result = sqlx::query("DELETE FROM task_history WHERE task_id = $1")
+        .bind(other.id)
+        .execute(&db)
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn can_not_erase_unknown_user() {
+    let config = AppConfig::from_env(&AppEnvironment::Development).unwrap();
+    let db = config.db().connection_pool().unwrap();
+    seed_data(&config).await;
+    let blobs = LocalStore::new(std::env::temp_dir().join(format!("blobs-{}", Uuid::new_v4())));
+
+    let result = User::erase(&db, &blobs, Uuid::new_v4()).await;
+
+    assert!(matches!(result, Err(ModelError::EntityNotFound)));
+}
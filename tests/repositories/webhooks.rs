@@ -0,0 +1,190 @@
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicU16, Ordering},
+};
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+};
+use reqwest::Client;
+use serial_test::serial;
+use tasks_authenticated::{
+    config::WebhookConfig,
+    models::{
+        tasks::{UpdateOptions, UpdateTask},
+        webhooks::{
+            DeliveryQuery, NewWebhook, SIGNATURE_HEADER, TIMESTAMP_HEADER, WebhookEvent, sign,
+        },
+    },
+    repositories::{
+        outbox::OutboxEvent,
+        tasks::Task,
+        webhooks::{Webhook, WebhookDelivery},
+    },
+};
+use tokio::net::TcpListener;
+
+use super::common::{fresh_db, outbox, seed_task, seed_user};
+
+/// Local HTTP endpoint recording the requests it is sent.
+#[derive(Clone, Default)]
+struct Receiver {
+    requests: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+    status: Arc<AtomicU16>,
+}
+
+impl Receiver {
+    async fn spawn(status: StatusCode) -> (Self, String) {
+        let receiver = Self::default();
+        receiver.status.store(status.as_u16(), Ordering::SeqCst);
+
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(receiver.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (receiver, url)
+    }
+
+    fn requests(&self) -> Vec<(HeaderMap, Bytes)> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn receive(State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    receiver.requests.lock().unwrap().push((headers, body));
+    StatusCode::from_u16(receiver.status.load(Ordering::SeqCst)).unwrap()
+}
+
+fn webhook_config(max_attempts: u32) -> WebhookConfig {
+    WebhookConfig {
+        max_attempts,
+        retry_base: 60,
+        retry_max: 3600,
+        timeout: 5,
+        poll_interval: 1,
+        batch_size: 20,
+        allowed_hosts: vec!["127.0.0.1".into()],
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn delivers_signed_task_events() {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "tasker").await;
+    let (receiver, url) = Receiver::spawn(StatusCode::NO_CONTENT).await;
+
+    let params = NewWebhook {
+        url,
+        events: vec![WebhookEvent::TaskUpdated],
+    };
+    let webhook = Webhook::create(&db, &params, user.pid).await.unwrap();
+
+    // Only the update is subscribed to
+    let task = seed_task(&db, &user, "Watched").await;
+    let params = UpdateTask {
+        done: Some(true),
+        ..Default::default()
+    };
+    Task::update_by_id(&db, &params, task.id, user.pid, &UpdateOptions::default())
+        .await
+        .unwrap();
+
+    OutboxEvent::relay(&db, 100, &outbox()).await.unwrap();
+    let sent = WebhookDelivery::deliver_due(&db, &Client::new(), &webhook_config(3))
+        .await
+        .unwrap();
+    assert_eq!(sent, 1);
+
+    let requests = receiver.requests();
+    assert_eq!(requests.len(), 1);
+    let (headers, body) = &requests[0];
+    let timestamp = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+    assert_eq!(
+        headers[SIGNATURE_HEADER].to_str().unwrap(),
+        sign(&webhook.secret, timestamp, body)
+    );
+    let payload = serde_json::from_slice::<serde_json::Value>(body).unwrap();
+    assert_eq!(payload["type"], "taskUpdated");
+    assert_eq!(payload["taskId"], task.id);
+
+    let log =
+        WebhookDelivery::find_for_webhook(&db, webhook.id, user.pid, &DeliveryQuery::default())
+            .await
+            .unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].status, "delivered");
+    assert_eq!(log[0].response_status, Some(204));
+}
+
+#[tokio::test]
+#[serial]
+async fn retries_and_dead_letters_failed_deliveries() {
+    let (_, db) = fresh_db().await;
+    let user = seed_user(&db, "tasker").await;
+    let (receiver, url) = Receiver::spawn(StatusCode::INTERNAL_SERVER_ERROR).await;
+    let client = Client::new();
+    let webhook_config = webhook_config(2);
+
+    let params = NewWebhook {
+        url,
+        events: Vec::new(),
+    };
+    let webhook = Webhook::create(&db, &params, user.pid).await.unwrap();
+    seed_task(&db, &user, "Unlucky").await;
+    OutboxEvent::relay(&db, 100, &outbox()).await.unwrap();
+
+    WebhookDelivery::deliver_due(&db, &client, &webhook_config)
+        .await
+        .unwrap();
+    let delivery =
+        &WebhookDelivery::find_for_webhook(&db, webhook.id, user.pid, &DeliveryQuery::default())
+            .await
+            .unwrap()[0];
+    assert_eq!(delivery.status, "pending");
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.response_status, Some(500));
+
+    // Not due again until the backoff has passed
+    let sent = WebhookDelivery::deliver_due(&db, &client, &webhook_config)
+        .await
+        .unwrap();
+    assert_eq!(sent, 0);
+
+    sqlx::query("UPDATE webhook_deliveries SET next_attempt_at = NOW()")
+        .execute(&db)
+        .await
+        .unwrap();
+    WebhookDelivery::deliver_due(&db, &client, &webhook_config)
+        .await
+        .unwrap();
+    let delivery =
+        &WebhookDelivery::find_for_webhook(&db, webhook.id, user.pid, &DeliveryQuery::default())
+            .await
+            .unwrap()[0];
+    assert_eq!(delivery.status, "dead");
+    assert_eq!(delivery.attempts, 2);
+    assert_eq!(receiver.requests().len(), 2);
+
+    // A redelivery starts over once the endpoint is back
+    receiver.status.store(200, Ordering::SeqCst);
+    WebhookDelivery::redeliver(&db, webhook.id, delivery.id, user.pid)
+        .await
+        .unwrap();
+    WebhookDelivery::deliver_due(&db, &client, &webhook_config)
+        .await
+        .unwrap();
+    let delivery =
+        &WebhookDelivery::find_for_webhook(&db, webhook.id, user.pid, &DeliveryQuery::default())
+            .await
+            .unwrap()[0];
+    assert_eq!(delivery.status, "delivered");
+    assert_eq!(delivery.attempts, 1);
+}
@@ -0,0 +1,43 @@
+use bytes::Bytes;
+use futures_util::TryStreamExt;
+use tasks_authenticated::storage::{BlobStore, LocalStore, StorageError, content_key};
+use uuid::Uuid;
+
+#[tokio::test]
+async fn can_store_read_and_delete_blobs() {
+    let root = std::env::temp_dir().join(format!("blobs-{}", Uuid::new_v4()));
+    let store = LocalStore::new(&root);
+    let data = Bytes::from_static(b"%PDF-1.7 not really a pdf");
+
+    let key = store.put(data.clone()).await.unwrap();
+    assert_eq!(key, content_key(&data));
+
+    // Same contents, same blob
+    assert_eq!(store.put(data.clone()).await.unwrap(), key);
+
+    let chunks = store
+        .get(&key)
+        .await
+        .unwrap()
+        .try_collect::<Vec<Bytes>>()
+        .await
+        .unwrap();
+    assert_eq!(chunks.concat(), data.to_vec());
+
+    store.delete(&key).await.unwrap();
+    assert!(matches!(
+        store.get(&key).await,
+        Err(StorageError::NotFound(_))
+    ));
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[tokio::test]
+async fn rejects_keys_that_are_not_content_hashes() {
+    let store = LocalStore::new(std::env::temp_dir());
+
+    let result = store.get("../../etc/passwd").await;
+
+    assert!(matches!(result, Err(StorageError::InvalidKey(_))));
+}
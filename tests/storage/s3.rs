@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::put,
+};
+use futures_util::TryStreamExt;
+use tasks_authenticated::{
+    config::S3Config,
+    storage::{BlobStore, S3Store, StorageError},
+};
+use tokio::net::TcpListener;
+
+type Objects = Arc<Mutex<HashMap<String, Bytes>>>;
+
+/// Minimal stand-in for an S3 bucket that only accepts signed requests.
+fn signed(headers: &HeaderMap) -> bool {
+    headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value.starts_with("AWS4-HMAC-SHA256 Credential=access/")
+                && value.contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date")
+        })
+        && headers.contains_key("x-amz-date")
+}
+
+async fn put_object(
+    State(objects): State<Objects>,
+    Path((_, key)): Path<(String, String)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if !signed(&headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    objects.lock().unwrap().insert(key, body);
+    StatusCode::OK.into_response()
+}
+
+async fn get_object(
+    State(objects): State<Objects>,
+    Path((_, key)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Response {
+    if !signed(&headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    match objects.lock().unwrap().get(&key) {
+        Some(body) => body.clone().into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn delete_object(
+    State(objects): State<Objects>,
+    Path((_, key)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Response {
+    if !signed(&headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    objects.lock().unwrap().remove(&key);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn spawn_bucket() -> S3Config {
+    let objects = Objects::default();
+    let app = Router::new()
+        .route(
+            "/{bucket}/{key}",
+            put(put_object).get(get_object).delete(delete_object),
+        )
+        .with_state(objects);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    S3Config {
+        endpoint: format!("http://{address}"),
+        bucket: "attachments".into(),
+        region: "us-east-1".into(),
+        access_key: "access".into(),
+        secret_key: "secret".into(),
+    }
+}
+
+#[tokio::test]
+async fn can_store_read_and_delete_blobs_in_a_bucket() {
+    let store = S3Store::new(&spawn_bucket().await);
+    let data = Bytes::from_static(b"\x89PNG\r\n\x1a\n fake image");
+
+    let key = store.put(data.clone()).await.unwrap();
+
+    let chunks = store
+        .get(&key)
+        .await
+        .unwrap()
+        .try_collect::<Vec<Bytes>>()
+        .await
+        .unwrap();
+    assert_eq!(chunks.concat(), data.to_vec());
+
+    store.delete(&key).await.unwrap();
+    assert!(matches!(
+        store.get(&key).await,
+        Err(StorageError::NotFound(_))
+    ));
+}